@@ -1,32 +1,520 @@
-use clap::{ArgAction, Parser};
+use std::{
+    ffi::OsStr,
+    fmt::{self, Display, Write},
+    path::PathBuf,
+    str::FromStr,
+    thread,
+};
 
-use crate::clients::beacon::types::BlockId;
+use clap::{
+    builder::TypedValueParser, error::ErrorKind, Arg, ArgAction, CommandFactory, Parser, Subcommand,
+};
+use ethers::types::H256;
+
+use crate::{
+    clients::beacon::types::BlockId,
+    env::ENV_SETTINGS,
+    profile::Profile,
+    slots_processor::{EmptyResponsePolicy, IndexMode, ReindexField},
+};
 
 /// Blobscan's indexer for the EIP-4844 upgrade.
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, mut_args = bound_flags)]
 pub struct Args {
-    /// Slot to start indexing from
-    #[arg(short, long)]
+    /// What to run. Defaults to `sync`
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Slot to start indexing from. Also accepts head, finalized, justified or genesis, resolved at startup
+    #[arg(short, long, env = "INDEXER_FROM_SLOT")]
     pub from_slot: Option<BlockId>,
 
-    /// Slot to stop indexing at
-    #[arg(short, long)]
+    /// Slot to stop indexing at. The indexer exits once every slot up to it is synced. Bounded runs don't update blobscan's sync checkpoints
+    #[arg(short, long, env = "INDEXER_TO_SLOT")]
     pub to_slot: Option<BlockId>,
 
-    /// Number of threads used for parallel indexing
-    #[arg(short, long)]
+    /// Maximum number of slot batches indexed concurrently
+    #[arg(long, env = "INDEXER_CONCURRENCY", default_value_t = available_cores())]
+    pub concurrency: u32,
+
+    /// Deprecated alias of --concurrency
+    #[arg(
+        short,
+        long,
+        env = "INDEXER_NUM_THREADS",
+        hide = true,
+        conflicts_with = "concurrency"
+    )]
     pub num_threads: Option<u32>,
 
     /// Amount of slots to be processed before saving latest slot in the database
-    #[arg(short, long)]
+    #[arg(short, long, env = "INDEXER_SLOTS_PER_SAVE")]
     pub slots_per_save: Option<u32>,
 
     /// Disable slot checkpoint saving when syncing
-    #[arg(short = 'c', long, action = ArgAction::SetTrue)]
+    #[arg(short = 'c', long, env = "INDEXER_DISABLE_SYNC_CHECKPOINT_SAVE", action = ArgAction::SetTrue)]
     pub disable_sync_checkpoint_save: bool,
 
     /// Disable historical synchronization
-    #[arg(short = 'd', long, action = ArgAction::SetTrue)]
+    #[arg(short = 'd', long, env = "INDEXER_DISABLE_SYNC_HISTORICAL", action = ArgAction::SetTrue)]
     pub disable_sync_historical: bool,
+
+    /// Disable pushing the chain config (spec and genesis) to blobscan at startup
+    #[arg(long, env = "INDEXER_DISABLE_CHAIN_CONFIG_PUSH", action = ArgAction::SetTrue)]
+    pub disable_chain_config_push: bool,
+
+    /// Re-index already indexed slots by patching only the given entities (block, transactions, blob_metadata)
+    #[arg(
+        short = 'r',
+        long,
+        env = "INDEXER_REINDEX_FIELDS",
+        value_delimiter = ','
+    )]
+    pub reindex_fields: Option<Vec<ReindexField>>,
+
+    /// Fail at startup when a setting is given under a deprecated environment variable name
+    #[arg(long, env = "INDEXER_FAIL_ON_DEPRECATED", action = ArgAction::SetTrue)]
+    pub fail_on_deprecated: bool,
+
+    /// Validate configuration and connectivity, print a report and exit without indexing
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub check: bool,
+
+    /// Print an env file template documenting every setting and its default, then exit
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub print_config_template: bool,
+
+    /// Index blocks without transactions instead of skipping them
+    #[arg(long, env = "INDEXER_INDEX_EMPTY_BLOCKS", action = ArgAction::SetTrue)]
+    pub index_empty_blocks: bool,
+
+    /// Send blobscan a slot heartbeat for skipped empty blocks, at most once every this many
+    /// seconds, so its freshness tracking keeps moving through runs of empty blocks
+    #[arg(long, env = "INDEXER_EMPTY_BLOCK_HEARTBEAT_SECS")]
+    pub empty_block_heartbeat_secs: Option<u64>,
+
+    /// Only index N evenly spread slots of every epoch when backfilling historical slots
+    #[arg(long, env = "INDEXER_SAMPLE_RATE")]
+    pub sample_rate: Option<u32>,
+
+    /// Report intentionally skipped slots and why they were skipped to blobscan
+    #[arg(long, env = "INDEXER_RECORD_SKIPPED_SLOTS", action = ArgAction::SetTrue)]
+    pub record_skipped_slots: bool,
+
+    /// Log, record and skip slots that fail instead of stopping the sync. Blobscan rejecting a submission still stops it
+    #[arg(long, env = "INDEXER_CONTINUE_ON_ERROR", action = ArgAction::SetTrue)]
+    pub continue_on_error: bool,
+
+    /// Share of a synced range's slots that may fail with --continue-on-error before the range fails as a whole
+    #[arg(long, env = "INDEXER_MAX_FAILURE_RATE", default_value_t = 0.05)]
+    pub max_failure_rate: f64,
+
+    /// Operating profile setting the defaults of the validation flags below
+    #[arg(long, env = "INDEXER_PROFILE", value_enum, default_value_t = Profile::Lenient)]
+    pub profile: Profile,
+
+    /// Whether to fail or skip a slot when the beacon node keeps returning unexpectedly empty data. Overrides the profile
+    #[arg(long, env = "INDEXER_EMPTY_RESPONSE_POLICY", value_enum)]
+    pub empty_response_policy: Option<EmptyResponsePolicy>,
+
+    /// Which blocks to index: all, blobs-only or blocks-and-blobs
+    #[arg(long, env = "INDEXER_INDEX_MODE", value_enum, default_value_t = IndexMode::All)]
+    pub index_mode: IndexMode,
+
+    /// Fail slots whose execution block timestamp doesn't advance past its parent's. Overrides the profile
+    #[arg(long, env = "INDEXER_VERIFY_PARENT_TIMESTAMP", num_args = 0..=1, default_missing_value = "true")]
+    pub verify_parent_timestamp: Option<bool>,
+
+    /// Fail slots whose execution block number doesn't follow its parent's. Overrides the profile
+    #[arg(long, env = "INDEXER_VERIFY_PARENT_NUMBER", num_args = 0..=1, default_missing_value = "true")]
+    pub verify_parent_number: Option<bool>,
+
+    /// Report indexing latency for blocks indexed within this many seconds of their slot time (0 disables it)
+    #[arg(long, env = "INDEXER_LATENCY_FRESHNESS_WINDOW", default_value_t = 60)]
+    pub latency_freshness_window: u64,
+
+    /// Slots whose beacon block, execution block and columns are fetched ahead of the one being indexed, per batch. At most this many slots are prefetched at once across batches (0 disables prefetching)
+    #[arg(long, env = "INDEXER_PREFETCH_SLOTS", default_value_t = 4)]
+    pub prefetch_slots: u32,
+
+    /// Deepest reorg followed back when a block doesn't build on the previously processed one (0 disables the check)
+    #[arg(long, env = "INDEXER_MAX_REORG_DEPTH", default_value_t = 64)]
+    pub max_reorg_depth: u32,
+
+    /// Report a stalled head after this many slot intervals without beacon events (0 disables it)
+    #[arg(long, env = "INDEXER_STALL_SLOTS", default_value_t = 5)]
+    pub stall_slots: u32,
+
+    /// Seconds between head polls while the beacon event stream is unavailable
+    #[arg(long, env = "INDEXER_POLL_FALLBACK_INTERVAL", default_value_t = 10)]
+    pub poll_fallback_interval: u64,
+
+    /// Seconds to let in-flight slots finish and the checkpoint be saved after SIGINT or SIGTERM before exiting anyway
+    #[arg(long, env = "INDEXER_SHUTDOWN_GRACE_PERIOD", default_value_t = 30)]
+    pub shutdown_grace_period: u64,
+
+    /// Keep realtime indexing this many slots behind the head, so shallow reorgs never reach the index (0 follows the head)
+    #[arg(long, env = "INDEXER_FOLLOW_DISTANCE", default_value_t = 0)]
+    pub follow_distance: u32,
+
+    /// Sync up to the realtime target once, close the gap it moved while syncing, then exit
+    #[arg(long, env = "INDEXER_ONCE", action = ArgAction::SetTrue)]
+    pub once: bool,
+
+    /// File listing slots or start-end ranges to never index, each followed by the reason. Reloaded on SIGHUP
+    #[arg(long, env = "INDEXER_SKIP_LIST")]
+    pub skip_list: Option<PathBuf>,
+
+    /// File mirroring the synced slots checkpoints, used on startup when it's ahead of blobscan or blobscan is unreachable
+    #[arg(long, env = "INDEXER_CHECKPOINT_FILE")]
+    pub checkpoint_file: Option<PathBuf>,
+
+    /// Serve Prometheus metrics on this port
+    #[arg(long, env = "INDEXER_METRICS_PORT")]
+    pub metrics_port: Option<u16>,
+
+    /// Serve a /healthz endpoint on this port. May be the same as --metrics-port
+    #[arg(long, env = "INDEXER_HEALTH_PORT")]
+    pub health_port: Option<u16>,
+
+    /// Report unhealthy when the last processed slot is more than this many slots behind the realtime target
+    #[arg(long, env = "INDEXER_HEALTH_MAX_LAG", default_value_t = 64)]
+    pub health_max_lag: u32,
+
+    /// Report unhealthy after this many head fetches fail in a row
+    #[arg(long, env = "INDEXER_HEALTH_MAX_HEAD_FAILURES", default_value_t = 3)]
+    pub health_max_head_failures: u32,
+
+    /// Only index finalized slots, syncing up to every new finalized checkpoint instead of the head
+    #[arg(long, env = "INDEXER_FINALIZED_ONLY", action = ArgAction::SetTrue)]
+    pub finalized_only: bool,
+
+    /// Also save the slots checkpoint when this many seconds have elapsed since the last save
+    #[arg(long, env = "INDEXER_CHECKPOINT_INTERVAL_SECS")]
+    pub checkpoint_interval_secs: Option<u64>,
+
+    /// Slots per epoch to use when the beacon spec endpoint is unavailable
+    #[arg(long, env = "INDEXER_SLOTS_PER_EPOCH")]
+    pub slots_per_epoch: Option<u32>,
+
+    /// Seconds per slot to use when the beacon spec endpoint is unavailable
+    #[arg(long, env = "INDEXER_SECONDS_PER_SLOT")]
+    pub seconds_per_slot: Option<u32>,
+}
+
+/// Options above apply to every command and go before it.
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Follow the head and backfill history
+    Sync,
+    /// Sync a bounded slot range and exit
+    Backfill {
+        /// First slot of the range
+        #[arg(long)]
+        from: BlockId,
+
+        /// Last slot of the range
+        #[arg(long)]
+        to: BlockId,
+    },
+    /// Print how far blobscan lags behind the beacon head and exit
+    Status,
+    /// Compare what blobscan stores for a slot range against the chain, printing every
+    /// discrepancy as a JSON line. Exits with an error when any is found
+    Verify {
+        /// First slot to verify
+        #[arg(long)]
+        from: u32,
+
+        /// Last slot to verify
+        #[arg(long)]
+        to: u32,
+    },
+    /// Rebuild a blob from the beacon node and upload it again, replacing the data blobscan
+    /// stores for it
+    ReuploadBlob {
+        /// Versioned hash of the blob
+        versioned_hash: H256,
+    },
+}
+
+/// Bounds of the numeric flags that don't accept their type's whole range. Clap's ranged value
+/// parsers don't expose their bounds, so they're set here along with the help sentence stating
+/// them, which `--help` and the config template render.
+fn bound_flags(arg: Arg) -> Arg {
+    match arg.get_id().as_str() {
+        "concurrency"
+        | "num_threads"
+        | "sample_rate"
+        | "health_max_head_failures"
+        | "slots_per_epoch"
+        | "seconds_per_slot" => bounded(arg, Bounds::at_least(1u32)),
+        "empty_block_heartbeat_secs" | "poll_fallback_interval" => {
+            bounded(arg, Bounds::at_least(1u64))
+        }
+        "max_failure_rate" => bounded(arg, Bounds::between(0.0, 1.0)),
+        _ => arg,
+    }
+}
+
+fn bounded<T>(arg: Arg, bounds: Bounds<T>) -> Arg
+where
+    Bounds<T>: TypedValueParser,
+    T: Display,
+{
+    let help = match arg.get_help() {
+        Some(help) => format!("{help}. Must be {bounds}"),
+        None => format!("Must be {bounds}"),
+    };
+
+    arg.help(help).value_parser(bounds)
+}
+
+/// Accepted range of a numeric flag
+#[derive(Debug, Clone, Copy)]
+struct Bounds<T> {
+    min: T,
+    max: Option<T>,
+}
+
+impl<T> Bounds<T> {
+    fn at_least(min: T) -> Self {
+        Self { min, max: None }
+    }
+
+    fn between(min: T, max: T) -> Self {
+        Self {
+            min,
+            max: Some(max),
+        }
+    }
+}
+
+impl<T: PartialOrd> Bounds<T> {
+    /// Rejects NaN, as it compares to nothing
+    fn contains(&self, value: &T) -> bool {
+        self.min <= *value && !self.max.as_ref().is_some_and(|max| value > max)
+    }
+}
+
+impl<T: Display> Display for Bounds<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.max {
+            Some(max) => write!(f, "between {} and {max}", self.min),
+            None => write!(f, "at least {}", self.min),
+        }
+    }
+}
+
+impl<T> TypedValueParser for Bounds<T>
+where
+    T: FromStr + PartialOrd + Display + Clone + Send + Sync + 'static,
+{
+    type Value = T;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&Arg>,
+        value: &OsStr,
+    ) -> Result<T, clap::Error> {
+        let flag = arg.map_or_else(|| "value".to_string(), |arg| arg.to_string());
+        let invalid = |reason: String| {
+            clap::Error::raw(
+                ErrorKind::ValueValidation,
+                format!(
+                    "invalid value '{}' for '{flag}': {reason}\n",
+                    value.to_string_lossy()
+                ),
+            )
+            .with_cmd(cmd)
+        };
+        let parsed = value
+            .to_str()
+            .and_then(|value| value.parse::<T>().ok())
+            .ok_or_else(|| invalid("not a number".to_string()))?;
+
+        if !self.contains(&parsed) {
+            return Err(invalid(format!("must be {self}")));
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// Default concurrency. Falls back to a single batch when the available parallelism is unknown
+fn available_cores() -> u32 {
+    thread::available_parallelism().map_or(1, |cores| cores.get() as u32)
+}
+
+impl Args {
+    /// --concurrency, or its deprecated --num-threads alias
+    pub fn concurrency(&self) -> u32 {
+        self.num_threads.unwrap_or(self.concurrency)
+    }
+
+    /// Slots to sync: the backfill range, or --from-slot and --to-slot otherwise
+    pub fn slot_bounds(&self) -> (Option<BlockId>, Option<BlockId>) {
+        match &self.command {
+            Some(Command::Backfill { from, to }) => (Some(from.clone()), Some(to.clone())),
+            _ => (self.from_slot.clone(), self.to_slot.clone()),
+        }
+    }
+
+    /// Renders an env file template listing every setting with its description and default.
+    /// Flags are generated from the clap metadata so the template can't drift from them, and
+    /// read `INDEXER_`-prefixed variables so they can't collide with other programs' settings.
+    pub fn config_template() -> String {
+        let mut template = String::from("# Environment settings\n\n");
+
+        for (name, default, description) in ENV_SETTINGS {
+            let _ = writeln!(template, "# {description}\n#{name}={default}\n");
+        }
+
+        template.push_str("# Flags (command line arguments take precedence)\n\n");
+
+        for arg in Args::command().get_arguments() {
+            let Some(env) = arg.get_env() else {
+                continue;
+            };
+
+            if let Some(help) = arg.get_help() {
+                let _ = writeln!(template, "# {help}");
+            }
+
+            let possible_values = arg
+                .get_possible_values()
+                .iter()
+                .filter(|value| !value.is_hide_set())
+                .map(|value| value.get_name().to_string())
+                .collect::<Vec<_>>();
+
+            if !possible_values.is_empty() {
+                let _ = writeln!(
+                    template,
+                    "# Possible values: {}",
+                    possible_values.join(", ")
+                );
+            }
+
+            let default = arg
+                .get_default_values()
+                .iter()
+                .map(|value| value.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(",");
+
+            let _ = writeln!(template, "#{}={default}\n", env.to_string_lossy());
+        }
+
+        template
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{error::ErrorKind, CommandFactory, Parser};
+
+    use super::Args;
+
+    fn parse(flags: &[&str]) -> Result<Args, clap::Error> {
+        Args::try_parse_from(std::iter::once("blobscan-indexer").chain(flags.iter().copied()))
+    }
+
+    /// Flags that only make sense on the command line
+    const COMMAND_LINE_ONLY: &[&str] = &["check", "print_config_template", "help", "version"];
+
+    #[test]
+    fn test_args_are_consistent() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn test_every_flag_reads_a_prefixed_env_var() {
+        for arg in Args::command().get_arguments() {
+            let id = arg.get_id().as_str();
+
+            if COMMAND_LINE_ONLY.contains(&id) {
+                continue;
+            }
+
+            assert_eq!(
+                arg.get_env().map(|env| env.to_string_lossy().to_string()),
+                Some(format!("INDEXER_{}", id.to_uppercase())),
+                "--{id}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_bounded_flags_reject_values_out_of_range() {
+        for flag in [
+            "--concurrency=0",
+            "--sample-rate=0",
+            "--poll-fallback-interval=0",
+            "--max-failure-rate=1.5",
+            "--max-failure-rate=-0.1",
+            "--max-failure-rate=NaN",
+        ] {
+            assert_eq!(
+                parse(&[flag]).unwrap_err().kind(),
+                ErrorKind::ValueValidation,
+                "{flag}"
+            );
+        }
+
+        let args = parse(&[
+            "--concurrency",
+            "1",
+            "--poll-fallback-interval",
+            "1",
+            "--max-failure-rate",
+            "1",
+        ])
+        .unwrap();
+
+        assert_eq!(args.concurrency(), 1);
+        assert_eq!(args.poll_fallback_interval, 1);
+        assert_eq!(args.max_failure_rate, 1.0);
+    }
+
+    #[test]
+    fn test_num_threads_is_an_alias_of_concurrency() {
+        assert_eq!(parse(&["--num-threads", "3"]).unwrap().concurrency(), 3);
+        assert_eq!(
+            parse(&["--concurrency", "4", "--num-threads", "2"])
+                .unwrap_err()
+                .kind(),
+            ErrorKind::ArgumentConflict
+        );
+    }
+
+    #[test]
+    fn test_template_renders_bounds_and_defaults_from_the_flags() {
+        let template = Args::config_template();
+
+        assert!(template.contains(
+            "# Maximum number of slot batches indexed concurrently. Must be at least 1\n#INDEXER_CONCURRENCY="
+        ));
+        assert!(template.contains(&format!(
+            "#INDEXER_CONCURRENCY={}\n",
+            super::available_cores()
+        )));
+        assert!(template.contains("Must be at least 1\n#INDEXER_POLL_FALLBACK_INTERVAL=10\n"));
+        assert!(template.contains("Must be between 0 and 1\n#INDEXER_MAX_FAILURE_RATE=0.05\n"));
+    }
+
+    #[test]
+    fn test_every_flag_appears_in_the_template() {
+        let template = Args::config_template();
+
+        for arg in Args::command().get_arguments() {
+            if let Some(env) = arg.get_env() {
+                let env = env.to_string_lossy();
+
+                assert!(template.contains(&format!("\n#{env}=")), "{env}");
+            }
+        }
+    }
 }
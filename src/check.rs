@@ -0,0 +1,129 @@
+use anyhow::{anyhow, Context as AnyhowContext, Result as AnyhowResult};
+use ethers::providers::Middleware;
+
+use crate::{
+    context::{Config as ContextConfig, Context},
+    env::Environment,
+};
+
+fn report<T>(name: &str, result: &AnyhowResult<T>, describe: impl Fn(&T) -> String) -> bool {
+    match result {
+        Ok(value) => {
+            println!("[PASS] {name}: {}", describe(value));
+
+            true
+        }
+        Err(error) => {
+            println!("[FAIL] {name}: {error:#}");
+
+            false
+        }
+    }
+}
+
+/// Runs the startup sequence against every configured endpoint without writing any data
+/// and prints a pass/fail line per check. Fails if any of the checks failed.
+pub async fn run_checks(env: &Environment) -> AnyhowResult<()> {
+    let mut failed_checks = 0;
+    let mut check = |passed: bool| {
+        if !passed {
+            failed_checks += 1;
+        }
+    };
+
+    check(report("Environment", &Ok(()), |_| {
+        "variables loaded and validated".to_string()
+    }));
+
+    let context = Context::try_new(ContextConfig {
+        disable_retries: true,
+        ..ContextConfig::from(env)
+    });
+
+    check(report("Context", &context, |_| {
+        "clients created".to_string()
+    }));
+
+    if let Ok(context) = context {
+        let beacon_client = context.beacon_client();
+
+        let chain_id = match context.provider() {
+            Some(provider) => {
+                let chain_id = provider
+                    .get_chainid()
+                    .await
+                    .map(|chain_id| chain_id.as_u64())
+                    .map_err(anyhow::Error::from);
+
+                check(report("Execution node", &chain_id, |chain_id| {
+                    format!("chain id {chain_id}")
+                }));
+
+                Some(chain_id)
+            }
+            None => {
+                println!("[SKIP] Execution node: CONSENSUS_ONLY is set");
+
+                None
+            }
+        };
+
+        let spec = beacon_client
+            .get_spec()
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|spec| spec.with_context(|| "Beacon spec not found"));
+
+        check(report("Beacon spec", &spec, |spec| {
+            format!(
+                "{} seconds per slot, {} slots per epoch",
+                spec.seconds_per_slot, spec.slots_per_epoch
+            )
+        }));
+
+        let genesis = beacon_client
+            .get_genesis()
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|genesis| genesis.with_context(|| "Beacon genesis not found"));
+
+        check(report("Beacon genesis", &genesis, |genesis| {
+            format!("genesis time {}", genesis.genesis_time)
+        }));
+
+        if let (Some(Ok(chain_id)), Ok(spec)) = (&chain_id, &spec) {
+            let chain_id_match = match spec.deposit_chain_id {
+                Some(deposit_chain_id) if deposit_chain_id != *chain_id => Err(anyhow!(
+                    "execution chain id {chain_id} doesn't match beacon deposit chain id {deposit_chain_id}"
+                )),
+                Some(_) => Ok("execution and beacon chain ids match".to_string()),
+                None => Ok("beacon spec has no deposit chain id, skipped".to_string()),
+            };
+
+            check(report("Chain id cross-check", &chain_id_match, |details| {
+                details.clone()
+            }));
+        }
+
+        let auth = context
+            .blobscan_client()
+            .probe_auth()
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|probe| {
+                probe.ok_or_else(|| anyhow!("blobscan has no indexer endpoints at this URL"))
+            });
+
+        check(report("Blobscan auth", &auth, |_| {
+            "authenticated read succeeded".to_string()
+        }));
+    }
+
+    if failed_checks > 0 {
+        return Err(anyhow!("{failed_checks} preflight check(s) failed"));
+    }
+
+    println!("All preflight checks passed");
+
+    Ok(())
+}
@@ -0,0 +1,355 @@
+use std::str::FromStr;
+
+use ethers::types::Bytes;
+
+use super::types::{Blob, Column, ColumnsResponse};
+
+/// How blobs are spread over data columns. Columns past `columns_total / extension_factor` hold
+/// the erasure-coded extension, so only the leading ones are needed to rebuild a blob.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnLayout {
+    pub columns_total: u32,
+    pub blobs_per_column_cell: u32,
+    pub extension_factor: u32,
+}
+
+/// Used when the spec gives the number of columns but not how blobs are spread over them
+pub const DEFAULT_BLOBS_PER_COLUMN_CELL: u32 = 1;
+pub const DEFAULT_COLUMN_EXTENSION_FACTOR: u32 = 2;
+
+impl ColumnLayout {
+    pub fn data_columns(&self) -> u32 {
+        self.columns_total / self.extension_factor
+    }
+
+    /// Cells each column carries for a block with `blobs` blobs
+    pub fn cells_per_column(&self, blobs: usize) -> usize {
+        blobs.div_ceil(self.blobs_per_column_cell as usize)
+    }
+
+    pub fn validate(&self) -> Result<(), ColumnReconstructionError> {
+        if self.columns_total == 0
+            || self.blobs_per_column_cell == 0
+            || self.extension_factor == 0
+            || self.columns_total % self.extension_factor != 0
+        {
+            return Err(ColumnReconstructionError::InvalidLayout(*self));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ColumnReconstructionError {
+    #[error("invalid column layout {0:?}")]
+    InvalidLayout(ColumnLayout),
+    #[error("received no columns")]
+    NoColumns,
+    #[error("column {0} is required to rebuild the blobs but wasn't received")]
+    MissingColumn(u32),
+    #[error("invalid column index {0}")]
+    InvalidColumnIndex(String),
+    #[error("column {column} has {received} cells but {expected} were expected for {blobs} blobs")]
+    CellCountMismatch {
+        column: u32,
+        blobs: usize,
+        expected: usize,
+        received: usize,
+    },
+    #[error("cell {cell} of column {column} is {length} bytes long, which can't be split into {blobs_per_cell} blob chunks")]
+    UnevenCell {
+        column: u32,
+        cell: usize,
+        length: usize,
+        blobs_per_cell: u32,
+    },
+    #[error("cell {cell} of column {column} isn't valid hex")]
+    InvalidCell { column: u32, cell: usize },
+    #[error("commitment {0} isn't valid hex")]
+    InvalidCommitment(String),
+}
+
+/// Blobs to index for a block. They're only rebuilt from the columns when the layout is known and
+/// the beacon node serves every data column, as nodes that don't custody all of them are common.
+/// Otherwise each blob holds its commitment in place of its data, as before layouts were
+/// supported.
+pub fn blobs_from_columns(
+    columns: ColumnsResponse,
+    layout: Option<&ColumnLayout>,
+) -> Result<Vec<Blob>, ColumnReconstructionError> {
+    match layout {
+        Some(layout) if serves_data_columns(&columns.data, layout) => {
+            reconstruct_blobs(columns, layout)
+        }
+        _ => commitment_blobs(columns),
+    }
+}
+
+/// Whether every data column of the layout was received
+pub fn serves_data_columns(columns: &[Column], layout: &ColumnLayout) -> bool {
+    (0..layout.data_columns()).all(|index| {
+        columns
+            .iter()
+            .any(|column| column.index.parse::<u32>() == Ok(index))
+    })
+}
+
+/// Blobs carrying their commitment in place of their data
+fn commitment_blobs(columns: ColumnsResponse) -> Result<Vec<Blob>, ColumnReconstructionError> {
+    let commitments = match columns.data.into_iter().next() {
+        Some(column) => column.blob_kzg_commitments,
+        None => return Err(ColumnReconstructionError::NoColumns),
+    };
+
+    commitments
+        .into_iter()
+        .enumerate()
+        .map(|(index, kzg_commitment)| {
+            let blob = Bytes::from_str(&kzg_commitment).map_err(|_| {
+                ColumnReconstructionError::InvalidCommitment(kzg_commitment.clone())
+            })?;
+
+            Ok(Blob {
+                index: index.to_string(),
+                kzg_commitment,
+                kzg_proof: String::new(),
+                blob,
+            })
+        })
+        .collect()
+}
+
+/// Rebuilds the block's blobs from the leading data columns, every one of which is required.
+/// Blob `i` is the concatenation of its chunk in every data column, in column order. Commitments are taken from the first column,
+/// proofs aren't carried by columns and are left empty.
+pub fn reconstruct_blobs(
+    columns: ColumnsResponse,
+    layout: &ColumnLayout,
+) -> Result<Vec<Blob>, ColumnReconstructionError> {
+    layout.validate()?;
+
+    let commitments = match columns.data.first() {
+        Some(column) => column.blob_kzg_commitments.clone(),
+        None => return Err(ColumnReconstructionError::NoColumns),
+    };
+    let data_columns = ordered_data_columns(&columns.data, layout)?;
+    let expected_cells = layout.cells_per_column(commitments.len());
+    let blobs_per_cell = layout.blobs_per_column_cell as usize;
+    let mut blobs_data = vec![Vec::new(); commitments.len()];
+
+    for (column_index, column) in data_columns.iter().enumerate() {
+        let column_index = column_index as u32;
+
+        if column.segments.len() != expected_cells {
+            return Err(ColumnReconstructionError::CellCountMismatch {
+                column: column_index,
+                blobs: commitments.len(),
+                expected: expected_cells,
+                received: column.segments.len(),
+            });
+        }
+
+        for (cell_index, cell) in column.segments.iter().enumerate() {
+            let cell_bytes =
+                Bytes::from_str(cell).map_err(|_| ColumnReconstructionError::InvalidCell {
+                    column: column_index,
+                    cell: cell_index,
+                })?;
+
+            if cell_bytes.len() % blobs_per_cell != 0 {
+                return Err(ColumnReconstructionError::UnevenCell {
+                    column: column_index,
+                    cell: cell_index,
+                    length: cell_bytes.len(),
+                    blobs_per_cell: layout.blobs_per_column_cell,
+                });
+            }
+
+            let chunk_len = cell_bytes.len() / blobs_per_cell;
+
+            // The last cell may be padded with chunks past the block's last blob
+            for (offset, chunk) in cell_bytes.chunks(chunk_len.max(1)).enumerate() {
+                if let Some(blob_data) = blobs_data.get_mut(cell_index * blobs_per_cell + offset) {
+                    blob_data.extend_from_slice(chunk);
+                }
+            }
+        }
+    }
+
+    Ok(commitments
+        .into_iter()
+        .zip(blobs_data)
+        .enumerate()
+        .map(|(index, (kzg_commitment, data))| Blob {
+            index: index.to_string(),
+            kzg_commitment,
+            kzg_proof: String::new(),
+            blob: Bytes::from(data),
+        })
+        .collect())
+}
+
+/// Data columns sorted by index. Every one of them must be present
+fn ordered_data_columns<'a>(
+    columns: &'a [Column],
+    layout: &ColumnLayout,
+) -> Result<Vec<&'a Column>, ColumnReconstructionError> {
+    let mut data_columns: Vec<Option<&Column>> = vec![None; layout.data_columns() as usize];
+
+    for column in columns {
+        let index = column
+            .index
+            .parse::<u32>()
+            .map_err(|_| ColumnReconstructionError::InvalidColumnIndex(column.index.clone()))?;
+
+        if index >= layout.columns_total {
+            return Err(ColumnReconstructionError::InvalidColumnIndex(
+                column.index.clone(),
+            ));
+        }
+
+        if let Some(slot) = data_columns.get_mut(index as usize) {
+            *slot = Some(column);
+        }
+    }
+
+    data_columns
+        .into_iter()
+        .enumerate()
+        .map(|(index, column)| column.ok_or(ColumnReconstructionError::MissingColumn(index as u32)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(index: u32, commitments: &[&str], segments: &[&str]) -> Column {
+        Column {
+            index: index.to_string(),
+            blob_kzg_commitments: commitments.iter().map(|c| c.to_string()).collect(),
+            segment_kzg_proofs: vec![],
+            segments: segments.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn blob_data(blobs: &[Blob]) -> Vec<Vec<u8>> {
+        blobs.iter().map(|blob| blob.blob.to_vec()).collect()
+    }
+
+    #[test]
+    fn rebuilds_blobs_with_one_blob_per_cell() {
+        let layout = ColumnLayout {
+            columns_total: 4,
+            blobs_per_column_cell: 1,
+            extension_factor: 2,
+        };
+        let commitments = ["0xaa", "0xbb"];
+        // Unordered, with an extension column that must be ignored
+        let columns = ColumnsResponse {
+            data: vec![
+                column(1, &commitments, &["0x0304", "0x0708"]),
+                column(3, &commitments, &["0xffff", "0xffff"]),
+                column(0, &commitments, &["0x0102", "0x0506"]),
+            ],
+        };
+
+        let blobs = reconstruct_blobs(columns, &layout).unwrap();
+
+        assert_eq!(blob_data(&blobs), vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]]);
+        assert_eq!(blobs[1].index, "1");
+        assert_eq!(blobs[1].kzg_commitment, "0xbb");
+    }
+
+    #[test]
+    fn rebuilds_blobs_with_several_blobs_per_cell() {
+        let layout = ColumnLayout {
+            columns_total: 6,
+            blobs_per_column_cell: 2,
+            extension_factor: 3,
+        };
+        let commitments = ["0xaa", "0xbb", "0xcc"];
+        // The last cell is padded past the third blob
+        let columns = ColumnsResponse {
+            data: vec![
+                column(0, &commitments, &["0x0a0b", "0x0c00"]),
+                column(1, &commitments, &["0x1a1b", "0x1c00"]),
+            ],
+        };
+
+        let blobs = reconstruct_blobs(columns, &layout).unwrap();
+
+        assert_eq!(
+            blob_data(&blobs),
+            vec![vec![0x0a, 0x1a], vec![0x0b, 0x1b], vec![0x0c, 0x1c]]
+        );
+    }
+
+    #[test]
+    fn requires_every_data_column_to_rebuild_blobs() {
+        let layout = ColumnLayout {
+            columns_total: 4,
+            blobs_per_column_cell: 1,
+            extension_factor: 2,
+        };
+        let columns = ColumnsResponse {
+            data: vec![column(0, &["0xaa"], &["0x01"])],
+        };
+
+        assert!(matches!(
+            reconstruct_blobs(columns, &layout),
+            Err(ColumnReconstructionError::MissingColumn(1))
+        ));
+    }
+
+    #[test]
+    fn rejects_columns_with_a_wrong_cell_count() {
+        let layout = ColumnLayout {
+            columns_total: 2,
+            blobs_per_column_cell: 1,
+            extension_factor: 2,
+        };
+        let columns = ColumnsResponse {
+            data: vec![column(0, &["0xaa", "0xbb"], &["0x01"])],
+        };
+
+        assert!(matches!(
+            reconstruct_blobs(columns, &layout),
+            Err(ColumnReconstructionError::CellCountMismatch {
+                expected: 2,
+                received: 1,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn falls_back_to_commitments_without_every_data_column_or_a_layout() {
+        let layout = ColumnLayout {
+            columns_total: 4,
+            blobs_per_column_cell: 1,
+            extension_factor: 2,
+        };
+        let partial_columns = || ColumnsResponse {
+            data: vec![column(0, &["0xaa", "0xbb"], &["0x01", "0x02"])],
+        };
+
+        let with_layout = blobs_from_columns(partial_columns(), Some(&layout)).unwrap();
+        let without_layout = blobs_from_columns(partial_columns(), None).unwrap();
+
+        assert_eq!(blob_data(&with_layout), vec![vec![0xaa], vec![0xbb]]);
+        assert_eq!(blob_data(&without_layout), vec![vec![0xaa], vec![0xbb]]);
+    }
+
+    #[test]
+    fn rejects_invalid_layouts() {
+        let layout = ColumnLayout {
+            columns_total: 5,
+            blobs_per_column_cell: 1,
+            extension_factor: 2,
+        };
+
+        assert!(layout.validate().is_err());
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use reqwest::header::HeaderMap;
+
+use crate::clients::common::ResponseObserver;
+
+/// Consensus forks in activation order, named as in the `Eth-Consensus-Version` header
+const FORKS: [&str; 8] = [
+    "phase0",
+    "altair",
+    "bellatrix",
+    "capella",
+    "deneb",
+    "electra",
+    "fulu",
+    "gloas",
+];
+
+/// Activation epoch of every fork of the chain, as listed by the beacon spec
+#[derive(Debug, Clone)]
+pub struct ForkSchedule {
+    slots_per_epoch: u32,
+    fork_epochs: BTreeMap<String, u64>,
+}
+
+impl ForkSchedule {
+    pub fn new(slots_per_epoch: u32, fork_epochs: BTreeMap<String, u64>) -> Self {
+        Self {
+            slots_per_epoch,
+            fork_epochs,
+        }
+    }
+
+    /// Fork active at the slot. `None` when a fork missing from [`FORKS`] is already active, as
+    /// there's no telling whether it comes after the known ones
+    pub fn fork_at(&self, slot: u32) -> Option<&'static str> {
+        let epoch = slot as u64 / self.slots_per_epoch.max(1) as u64;
+
+        self.fork_epochs
+            .iter()
+            .filter(|(_, activation_epoch)| **activation_epoch <= epoch)
+            .map(|(fork, _)| FORKS.iter().position(|known| known == fork))
+            .try_fold(0, |latest, position| {
+                position.map(|position| latest.max(position))
+            })
+            .map(|latest| FORKS[latest])
+    }
+}
+
+/// Keeps the fork a response declares in its `Eth-Consensus-Version` header. Nodes that don't
+/// send it leave it unset
+#[derive(Debug, Default)]
+pub struct ConsensusVersionHeader(OnceLock<String>);
+
+impl ConsensusVersionHeader {
+    pub fn get(&self) -> Option<&str> {
+        self.0.get().map(String::as_str)
+    }
+}
+
+impl ResponseObserver for ConsensusVersionHeader {
+    fn observe(&self, _server_timing: Option<&str>, _elapsed: Duration) {}
+
+    fn observe_headers(&self, headers: &HeaderMap) {
+        if let Some(version) = headers
+            .get("eth-consensus-version")
+            .and_then(|value| value.to_str().ok())
+        {
+            let _ = self.0.set(version.trim().to_ascii_lowercase());
+        }
+    }
+}
+
+/// Blocks a beacon node declared to belong to another fork than their slot's, by redacted
+/// endpoint URL
+#[derive(Debug, Default)]
+pub struct ForkMismatches(Mutex<BTreeMap<String, u64>>);
+
+impl ForkMismatches {
+    pub fn record(&self, endpoint: &str) {
+        let mut mismatches = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        *mismatches.entry(endpoint.to_string()).or_default() += 1;
+    }
+
+    pub fn counts(&self) -> BTreeMap<String, u64> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::ForkSchedule;
+
+    fn schedule(fork_epochs: &[(&str, u64)]) -> ForkSchedule {
+        ForkSchedule::new(
+            32,
+            fork_epochs
+                .iter()
+                .map(|(fork, epoch)| (fork.to_string(), *epoch))
+                .collect::<BTreeMap<_, _>>(),
+        )
+    }
+
+    #[test]
+    fn fork_at_picks_the_latest_active_fork() {
+        let schedule = schedule(&[
+            ("altair", 0),
+            ("bellatrix", 0),
+            ("capella", 0),
+            ("deneb", 10),
+            ("electra", u64::MAX),
+        ]);
+
+        assert_eq!(schedule.fork_at(10 * 32 - 1), Some("capella"));
+        assert_eq!(schedule.fork_at(10 * 32), Some("deneb"));
+        assert_eq!(schedule.fork_at(u32::MAX), Some("deneb"));
+    }
+
+    #[test]
+    fn fork_at_defaults_to_phase0() {
+        assert_eq!(schedule(&[("altair", 5)]).fork_at(0), Some("phase0"));
+    }
+
+    #[test]
+    fn fork_at_is_unknown_once_an_unknown_fork_is_active() {
+        let schedule = schedule(&[("deneb", 0), ("eip9999", 10)]);
+
+        assert_eq!(schedule.fork_at(0), Some("deneb"));
+        assert_eq!(schedule.fork_at(10 * 32), None);
+    }
+}
@@ -1,71 +1,151 @@
-use anyhow::Context as AnyhowContext;
+use std::sync::{Arc, OnceLock};
+
 use backoff::ExponentialBackoff;
-use reqwest::{Client, Url};
+use reqwest::Client;
 use reqwest_eventsource::EventSource;
+use tracing::warn;
 
 use crate::{
     clients::{beacon::types::BlockHeaderResponse, common::ClientResult},
     json_get,
+    utils::{body_trace::BodyTrace, clock::Clock, redaction::redact_url, url::EndpointUrl},
 };
 
 // use self::types::{Blob, BlobsResponse, Block, BlockHeader, BlockId, BlockResponse, Topic};
-use self::types::{Block, BlockHeader, BlockId, BlockResponse, ColumnsResponse, Topic, ValidatorsResponse, Validator};
+use self::fork::{ConsensusVersionHeader, ForkMismatches, ForkSchedule};
+use self::types::{Block, BlockHeader, BlockId, BlockResponse, ColumnsResponse, Genesis, GenesisResponse, ProposerDutiesResponse, ProposerDuty, Spec, SpecResponse, Topic};
+pub mod columns;
+pub mod fork;
 pub mod types;
 
 #[derive(Debug, Clone)]
 pub struct BeaconClient {
-    base_url: Url,
+    base_url: EndpointUrl,
     client: Client,
     exp_backoff: Option<ExponentialBackoff>,
+    clock: Arc<dyn Clock>,
+    body_trace: BodyTrace,
+    /// Known once the spec is fetched. Blocks aren't checked against it before
+    fork_schedule: OnceLock<ForkSchedule>,
+    fork_mismatches: Arc<ForkMismatches>,
 }
 
 pub struct Config {
-    pub base_url: String,
+    pub base_url: EndpointUrl,
     pub exp_backoff: Option<ExponentialBackoff>,
+    /// Times the requests and sleeps between their retries
+    pub clock: Arc<dyn Clock>,
+    pub body_trace: BodyTrace,
+    /// Counts the blocks served from another fork than their slot's
+    pub fork_mismatches: Arc<ForkMismatches>,
 }
 
 impl BeaconClient {
     pub fn try_with_client(client: Client, config: Config) -> ClientResult<Self> {
-        let base_url = Url::parse(&format!("{}/eth/", config.base_url))
-            .with_context(|| "Failed to parse base URL")?;
+        let base_url = config.base_url;
         let exp_backoff = config.exp_backoff;
 
         Ok(Self {
             base_url,
             client,
             exp_backoff,
+            clock: config.clock,
+            body_trace: config.body_trace,
+            fork_schedule: OnceLock::new(),
+            fork_mismatches: config.fork_mismatches,
         })
     }
 
+    /// Sets the fork schedule blocks are checked against. Only the first call has an effect
+    pub fn set_fork_schedule(&self, fork_schedule: ForkSchedule) {
+        let _ = self.fork_schedule.set(fork_schedule);
+    }
+
     pub async fn get_block(&self, block_id: &BlockId) -> ClientResult<Option<Block>> {
-        let path = format!("v2/beacon/blocks/{}", { block_id.to_detailed_string() });
-        let url = self.base_url.join(path.as_str())?;
+        let url = self.base_url.join_path(&[
+            "eth/v2/beacon/blocks",
+            block_id.to_detailed_string().as_str(),
+        ]);
 
-        json_get!(&self.client, url, BlockResponse, self.exp_backoff.clone()).map(|res| match res {
-            Some(r) => Some(r.data),
-            None => None,
-        })
+        let consensus_version = ConsensusVersionHeader::default();
+        let block = json_get!(
+            @observed consensus_version,
+            &self.client,
+            url,
+            BlockResponse,
+            self.exp_backoff.clone(),
+            self.clock.as_ref(),
+            self.body_trace
+        )?;
+
+        if let (BlockId::Slot(slot), Some(block)) = (block_id, &block) {
+            // The header is authoritative, the envelope field is for nodes that don't send it
+            let version = consensus_version.get().map(str::to_string).or_else(|| {
+                block
+                    .version
+                    .as_deref()
+                    .map(|version| version.trim().to_ascii_lowercase())
+            });
+
+            if let Some(version) = version {
+                self.check_fork(*slot, &version);
+            }
+        }
+
+        Ok(block.map(|res| res.data))
+    }
+
+    /// Warns when the node declares the block to belong to another fork than the slot's, as a
+    /// node lagging behind a fork transition does
+    fn check_fork(&self, slot: u32, version: &str) {
+        let Some(expected) = self
+            .fork_schedule
+            .get()
+            .and_then(|fork_schedule| fork_schedule.fork_at(slot))
+        else {
+            return;
+        };
+
+        if version != expected {
+            let endpoint = redact_url(&self.base_url.join_path(&[]));
+
+            self.fork_mismatches.record(&endpoint);
+
+            warn!(
+                endpoint,
+                slot,
+                expected,
+                declared = version,
+                "Beacon node served a block from another fork than the slot's"
+            );
+        }
     }
 
-    pub async fn get_validators(&self, block_id: &BlockId) -> ClientResult<Option<Vec<Validator>>> {
-        let path = format!("v1/validator/duties/proposer/{}", { block_id.to_detailed_string() });
-        let url = self.base_url.join(path.as_str())?;
+    pub async fn get_proposer_duties(&self, epoch: u64) -> ClientResult<Option<Vec<ProposerDuty>>> {
+        let url = self.base_url.join_path(&[
+            "eth/v1/validator/duties/proposer",
+            epoch.to_string().as_str(),
+        ]);
 
-        json_get!(&self.client, url, ValidatorsResponse, self.exp_backoff.clone()).map(|res| match res {
+        json_get!(&self.client, url, ProposerDutiesResponse, self.exp_backoff.clone(), self.clock.as_ref(), self.body_trace).map(|res| match res {
             Some(r) => Some(r.data),
             None => None,
         })
     }
 
     pub async fn get_block_header(&self, block_id: &BlockId) -> ClientResult<Option<BlockHeader>> {
-        let path = format!("v1/beacon/headers/{}", { block_id.to_detailed_string() });
-        let url = self.base_url.join(path.as_str())?;
+        let url = self.base_url.join_path(&[
+            "eth/v1/beacon/headers",
+            block_id.to_detailed_string().as_str(),
+        ]);
 
         json_get!(
             &self.client,
             url,
             BlockHeaderResponse,
-            self.exp_backoff.clone()
+            self.exp_backoff.clone(),
+            self.clock.as_ref(),
+            self.body_trace
         )
         .map(|res| match res {
             Some(r) => Some(r.data),
@@ -86,26 +166,259 @@ impl BeaconClient {
     // }
 
     pub async fn get_columns(&self, block_id: &BlockId) -> ClientResult<Option<ColumnsResponse>> {
-        let path = format!("v1/beacon/column_sidecars/{}", {
-            block_id.to_detailed_string()
-        });
-        let url = self.base_url.join(path.as_str())?;
+        let url = self.base_url.join_path(&[
+            "eth/v1/beacon/column_sidecars",
+            block_id.to_detailed_string().as_str(),
+        ]);
 
-        json_get!(&self.client, url, ColumnsResponse, self.exp_backoff.clone()).map(|res| match res {
+        json_get!(&self.client, url, ColumnsResponse, self.exp_backoff.clone(), self.clock.as_ref(), self.body_trace).map(|res| match res {
             Some(r) => Some(r),
             None => None,
         })
     }
 
+    pub async fn get_spec(&self) -> ClientResult<Option<Spec>> {
+        let url = self.base_url.join_path(&["eth/v1/config/spec"]);
+
+        match json_get!(
+            &self.client,
+            url,
+            SpecResponse,
+            self.exp_backoff.clone(),
+            self.clock.as_ref(),
+            self.body_trace
+        )? {
+            Some(r) => Ok(Some(Spec::try_from(r.data)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_genesis(&self) -> ClientResult<Option<Genesis>> {
+        let url = self.base_url.join_path(&["eth/v1/beacon/genesis"]);
+
+        json_get!(
+            &self.client,
+            url,
+            GenesisResponse,
+            self.exp_backoff.clone(),
+            self.clock.as_ref(),
+            self.body_trace
+        )
+        .map(|res| match res {
+            Some(r) => Some(r.data),
+            None => None,
+        })
+    }
+
     pub fn subscribe_to_events(&self, topics: &[Topic]) -> ClientResult<EventSource> {
         let topics = topics
             .iter()
             .map(|topic| topic.into())
             .collect::<Vec<String>>()
             .join(",");
-        let path = format!("v1/events?topics={topics}");
-        let url = self.base_url.join(&path)?;
+        let mut url = self.base_url.join_path(&["eth/v1/events"]);
+        let query = match url.query() {
+            Some(query) => format!("{query}&topics={topics}"),
+            None => format!("topics={topics}"),
+        };
+
+        url.set_query(Some(&query));
 
         Ok(EventSource::get(url))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, sync::Arc};
+
+    use super::{fork::ForkSchedule, types::BlockId, BeaconClient, Config};
+    use crate::{
+        clients::test_server::{self, Reply},
+        utils::{clock::SystemClock, url::EndpointUrl},
+    };
+
+    fn client(base_url: EndpointUrl) -> BeaconClient {
+        BeaconClient::try_with_client(
+            reqwest::Client::new(),
+            Config {
+                base_url,
+                exp_backoff: None,
+                clock: Arc::new(SystemClock),
+                body_trace: Default::default(),
+                fork_mismatches: Default::default(),
+            },
+        )
+        .unwrap()
+    }
+
+    const DENEB_BLOCK: &str = include_str!("fixtures/deneb_block.json");
+
+    /// Client of a chain where the Deneb fixture's slot is a Deneb one
+    fn deneb_client(base_url: EndpointUrl) -> BeaconClient {
+        let client = client(base_url);
+
+        client.set_fork_schedule(ForkSchedule::new(
+            32,
+            BTreeMap::from([("deneb".to_string(), 0), ("electra".to_string(), u64::MAX)]),
+        ));
+
+        client
+    }
+
+    async fn fork_mismatches_for(reply: Reply) -> u64 {
+        let (base_url, server) = test_server::serve(vec![reply]).await;
+        let client = deneb_client(base_url);
+
+        let block = client
+            .get_block(&BlockId::Slot(9_000_000))
+            .await
+            .unwrap()
+            .unwrap();
+
+        server.await.unwrap();
+        assert_eq!(block.message.slot, 9_000_000);
+
+        client.fork_mismatches.counts().values().sum()
+    }
+
+    /// The Deneb fixture, labeled as a block from another fork
+    fn deneb_block_labeled(version: &str) -> String {
+        let mut block: serde_json::Value = serde_json::from_str(DENEB_BLOCK).unwrap();
+
+        block["version"] = version.into();
+
+        block.to_string()
+    }
+
+    #[tokio::test]
+    async fn test_blocks_declared_from_the_slots_fork_arent_counted() {
+        let reply = Reply::RespondWithHeaders(
+            "200 OK",
+            vec![("Eth-Consensus-Version", "deneb".to_string())],
+            DENEB_BLOCK.to_string(),
+        );
+
+        assert_eq!(fork_mismatches_for(reply).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_blocks_declared_from_another_fork_are_counted() {
+        let reply = Reply::RespondWithHeaders(
+            "200 OK",
+            vec![("Eth-Consensus-Version", "Electra".to_string())],
+            DENEB_BLOCK.to_string(),
+        );
+
+        assert_eq!(fork_mismatches_for(reply).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_blocks_without_consensus_version_header_are_accepted() {
+        let reply = Reply::Respond("200 OK", DENEB_BLOCK.to_string());
+
+        assert_eq!(fork_mismatches_for(reply).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_blocks_labeled_from_another_fork_are_counted_by_endpoint() {
+        let (base_url, server) = test_server::serve(vec![
+            Reply::Respond("200 OK", deneb_block_labeled("capella")),
+            Reply::Respond("200 OK", deneb_block_labeled("electra")),
+        ])
+        .await;
+        let endpoint = base_url.to_string();
+        let client = deneb_client(base_url);
+
+        for _ in 0..2 {
+            client
+                .get_block(&BlockId::Slot(9_000_000))
+                .await
+                .unwrap()
+                .unwrap();
+        }
+
+        server.await.unwrap();
+        assert_eq!(
+            client.fork_mismatches.counts(),
+            BTreeMap::from([(endpoint, 2)])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_the_consensus_version_header_overrides_the_envelope_version() {
+        let reply = Reply::RespondWithHeaders(
+            "200 OK",
+            vec![("Eth-Consensus-Version", "deneb".to_string())],
+            deneb_block_labeled("electra"),
+        );
+
+        assert_eq!(fork_mismatches_for(reply).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_blocks_requested_by_root_arent_checked() {
+        let (base_url, server) = test_server::serve(vec![Reply::Respond(
+            "200 OK",
+            deneb_block_labeled("electra"),
+        )])
+        .await;
+        let client = deneb_client(base_url);
+
+        client.get_block(&BlockId::Head).await.unwrap().unwrap();
+
+        server.await.unwrap();
+        assert!(client.fork_mismatches.counts().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_proposer_duties_are_requested_by_epoch() {
+        let (base_url, server) = test_server::serve(vec![Reply::Respond(
+            "200 OK",
+            serde_json::json!({
+                "dependent_root": format!("0x{}", "00".repeat(32)),
+                "execution_optimistic": false,
+                "data": [
+                    { "pubkey": "0xa0", "validator_index": "7", "slot": "320" },
+                    { "pubkey": "0xa1", "validator_index": "8", "slot": "321" }
+                ]
+            })
+            .to_string(),
+        )])
+        .await;
+
+        let duties = client(base_url)
+            .get_proposer_duties(10)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            server.await.unwrap()[0].path,
+            "/eth/v1/validator/duties/proposer/10"
+        );
+        assert_eq!(duties.len(), 2);
+        assert_eq!(duties[1].pubkey, "0xa1");
+        assert_eq!(duties[1].slot, 321);
+    }
+
+    #[tokio::test]
+    async fn test_epochs_beyond_u32_are_requested_as_is() {
+        let (base_url, server) = test_server::serve(vec![Reply::Respond(
+            "404 Not Found",
+            r#"{"code":404,"message":"not found"}"#.to_string(),
+        )])
+        .await;
+        let epoch = u32::MAX as u64 + 1;
+
+        assert!(client(base_url)
+            .get_proposer_duties(epoch)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            server.await.unwrap()[0].path,
+            format!("/eth/v1/validator/duties/proposer/{epoch}")
+        );
+    }
+}
@@ -1,14 +1,28 @@
-use std::{fmt, str::FromStr};
+use std::{collections::BTreeMap, collections::HashMap, fmt, str::FromStr};
 
-use ethers::types::{Bytes, H256};
+use anyhow::Context;
+
+use ethers::{
+    types::{Bytes, H256},
+    utils::keccak256,
+};
 use serde::{Deserialize, Serialize};
 
-use crate::slots_processor::BlockData;
+use crate::{
+    slots_processor::BlockData,
+    utils::web3::{CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION, PRAGUE_BLOB_BASE_FEE_UPDATE_FRACTION},
+};
+
+use super::columns::{
+    ColumnLayout, DEFAULT_BLOBS_PER_COLUMN_CELL, DEFAULT_COLUMN_EXTENSION_FACTOR,
+};
 
 #[derive(Serialize, Debug, Clone)]
 pub enum BlockId {
     Head,
     Finalized,
+    Justified,
+    Genesis,
     Slot(u32),
     Hash(H256),
 }
@@ -21,16 +35,28 @@ pub enum Topic {
     ChainReorg,
 }
 
+// Beacon clients don't agree on field casing, so the fields we consume also accept camelCase
 #[derive(Deserialize, Debug)]
 pub struct ExecutionPayload {
+    #[serde(alias = "blockHash", deserialize_with = "deserialize_h256")]
     pub block_hash: H256,
-    #[serde(deserialize_with = "deserialize_number")]
+    #[serde(alias = "parentHash", deserialize_with = "deserialize_h256")]
+    pub parent_hash: H256,
+    #[serde(alias = "blockNumber", deserialize_with = "deserialize_number")]
     pub block_number: u32,
+    #[serde(deserialize_with = "deserialize_number")]
+    pub timestamp: u64,
+    #[serde(default, alias = "blobGasUsed")]
+    pub blob_gas_used: Option<String>,
+    #[serde(default, alias = "excessBlobGas")]
+    pub excess_blob_gas: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct BlockBody {
+    #[serde(alias = "executionPayload")]
     pub execution_payload: Option<ExecutionPayload>,
+    #[serde(alias = "blobKzgCommitments")]
     pub blob_kzg_commitments: Option<Vec<String>>,
 }
 #[derive(Deserialize, Debug)]
@@ -38,6 +64,7 @@ pub struct BlockMessage {
     #[serde(deserialize_with = "deserialize_number")]
     pub slot: u32,
     pub body: BlockBody,
+    #[serde(alias = "parentRoot", deserialize_with = "deserialize_h256")]
     pub parent_root: H256,
 }
 
@@ -48,16 +75,19 @@ pub struct Block {
 
 #[derive(Deserialize, Debug)]
 pub struct BlockResponse {
+    /// Fork the node declares the block to belong to. Older nodes leave it out
+    #[serde(default)]
+    pub version: Option<String>,
     pub data: Block,
 }
 
 #[derive(Deserialize, Debug)]
-pub struct ValidatorsResponse {
-    pub data: Vec<Validator>,
+pub struct ProposerDutiesResponse {
+    pub data: Vec<ProposerDuty>,
 }
 
 #[derive(Deserialize, Debug)]
-pub struct Validator {
+pub struct ProposerDuty {
     pub pubkey: String,
     pub validator_index: String,
     #[serde(deserialize_with = "deserialize_number")]
@@ -72,11 +102,6 @@ pub struct Blob {
     pub blob: Bytes,
 }
 
-#[derive(Deserialize, Debug)]
-pub struct BlobsResponse {
-    pub data: Vec<Blob>,
-}
-
 #[derive(Deserialize, Debug)]
 pub struct Column {
     pub index: String,
@@ -90,6 +115,43 @@ pub struct ColumnsResponse {
     pub data: Vec<Column>,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct SpecResponse {
+    pub data: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Spec {
+    pub seconds_per_slot: u32,
+    pub slots_per_epoch: u32,
+    pub max_blobs_per_block: Option<u32>,
+    pub deposit_chain_id: Option<u64>,
+    /// Fork activation epochs keyed by fork name (e.g. "deneb")
+    pub fork_epochs: BTreeMap<String, u64>,
+    /// Column layouts keyed by activation epoch
+    pub column_layouts: BTreeMap<u64, ColumnLayout>,
+    /// Blob base fee update fractions keyed by activation epoch
+    pub blob_base_fee_update_fractions: BTreeMap<u64, u64>,
+}
+
+/// Blob base fee update fraction introduced by each consensus fork, paired with its execution
+/// fork
+const FORK_BLOB_BASE_FEE_UPDATE_FRACTIONS: [(&str, u64); 2] = [
+    ("deneb", CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION),
+    ("electra", PRAGUE_BLOB_BASE_FEE_UPDATE_FRACTION),
+];
+
+#[derive(Deserialize, Debug)]
+pub struct GenesisResponse {
+    pub data: Genesis,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Genesis {
+    #[serde(deserialize_with = "deserialize_number")]
+    pub genesis_time: u64,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct BlockHeaderResponse {
     pub data: BlockHeader,
@@ -122,6 +184,19 @@ pub struct ChainReorgEventData {
     pub depth: u32,
 }
 
+impl ChainReorgEventData {
+    /// Deterministic id of the reorg, so the same reorg reported twice can be recognized
+    pub fn reorg_id(&self) -> H256 {
+        let mut preimage = Vec::with_capacity(68);
+
+        preimage.extend_from_slice(self.old_head_block.as_bytes());
+        preimage.extend_from_slice(self.new_head_block.as_bytes());
+        preimage.extend_from_slice(&self.depth.to_be_bytes());
+
+        H256::from(keccak256(preimage))
+    }
+}
+
 #[derive(Deserialize, Debug)]
 pub struct HeadEventData {
     #[serde(deserialize_with = "deserialize_number")]
@@ -134,13 +209,37 @@ pub struct FinalizedCheckpointEventData {
     pub block: H256,
 }
 
-fn deserialize_number<'de, D>(deserializer: D) -> Result<u32, D::Error>
+fn deserialize_number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
 where
     D: serde::Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
 {
     let value = String::deserialize(deserializer)?;
 
-    value.parse::<u32>().map_err(serde::de::Error::custom)
+    value.parse::<T>().map_err(serde::de::Error::custom)
+}
+
+/// Deserializes a 32-byte hash given as hex, with or without the 0x prefix and in any case.
+fn deserialize_h256<'de, D>(deserializer: D) -> Result<H256, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    let hex_value = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(&value);
+    let bytes = hex::decode(hex_value).map_err(serde::de::Error::custom)?;
+
+    if bytes.len() != H256::len_bytes() {
+        return Err(serde::de::Error::custom(format!(
+            "invalid hash length for {value}: expected 32 bytes, got {}",
+            bytes.len()
+        )));
+    }
+
+    Ok(H256::from_slice(&bytes))
 }
 
 impl BlockId {
@@ -148,6 +247,8 @@ impl BlockId {
         match self {
             BlockId::Head => String::from("head"),
             BlockId::Finalized => String::from("finalized"),
+            BlockId::Justified => String::from("justified"),
+            BlockId::Genesis => String::from("genesis"),
             BlockId::Slot(slot) => slot.to_string(),
             BlockId::Hash(hash) => format!("0x{:x}", hash),
         }
@@ -159,6 +260,8 @@ impl fmt::Display for BlockId {
         match self {
             BlockId::Head => write!(f, "head"),
             BlockId::Finalized => write!(f, "finalized"),
+            BlockId::Justified => write!(f, "justified"),
+            BlockId::Genesis => write!(f, "genesis"),
             BlockId::Slot(slot) => write!(f, "{}", slot),
             BlockId::Hash(hash) => write!(f, "{}", hash),
         }
@@ -172,6 +275,8 @@ impl FromStr for BlockId {
         match s {
             "head" => Ok(BlockId::Head),
             "finalized" => Ok(BlockId::Finalized),
+            "justified" => Ok(BlockId::Justified),
+            "genesis" => Ok(BlockId::Genesis),
             _ => match s.parse::<u32>() {
                 Ok(num) => Ok(BlockId::Slot(num)),
                 Err(_) => {
@@ -182,7 +287,7 @@ impl FromStr for BlockId {
                         }
                     } else {
                         Err(
-                            format!("Invalid block ID: {s}. Expected 'head', 'finalized', a hash or a number."),
+                            format!("Invalid block ID: {s}. Expected 'head', 'finalized', 'justified', 'genesis', a hash or a number."),
                         )
                     }
                 }
@@ -191,6 +296,109 @@ impl FromStr for BlockId {
     }
 }
 
+impl TryFrom<HashMap<String, serde_json::Value>> for Spec {
+    type Error = anyhow::Error;
+
+    fn try_from(values: HashMap<String, serde_json::Value>) -> Result<Self, Self::Error> {
+        let get_number = |key: &str| -> Result<Option<u64>, anyhow::Error> {
+            match values.get(key).and_then(|value| value.as_str()) {
+                Some(value) => {
+                    Ok(Some(value.parse::<u64>().with_context(|| {
+                        format!("Invalid spec value for {key}: {value}")
+                    })?))
+                }
+                None => Ok(None),
+            }
+        };
+
+        let seconds_per_slot =
+            get_number("SECONDS_PER_SLOT")?.with_context(|| "Missing SECONDS_PER_SLOT in spec")?;
+        let slots_per_epoch =
+            get_number("SLOTS_PER_EPOCH")?.with_context(|| "Missing SLOTS_PER_EPOCH in spec")?;
+        let max_blobs_per_block = get_number("MAX_BLOBS_PER_BLOCK")?;
+        let deposit_chain_id = get_number("DEPOSIT_CHAIN_ID")?;
+        let mut fork_epochs = BTreeMap::new();
+
+        for key in values.keys() {
+            if let Some(fork_name) = key.strip_suffix("_FORK_EPOCH") {
+                if let Some(epoch) = get_number(key)? {
+                    fork_epochs.insert(fork_name.to_lowercase(), epoch);
+                }
+            }
+        }
+
+        // Unprefixed keys describe the layout since genesis, `<FORK>_`-prefixed ones the layout
+        // from that fork's activation epoch onwards
+        let mut column_layouts = BTreeMap::new();
+        let layout_forks = values
+            .keys()
+            .filter_map(|key| key.strip_suffix("NUMBER_OF_COLUMNS"))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        for prefix in layout_forks {
+            let activation_epoch = match prefix.strip_suffix('_') {
+                Some(fork_name) => match fork_epochs.get(&fork_name.to_lowercase()) {
+                    Some(epoch) => *epoch,
+                    None => continue,
+                },
+                None => 0,
+            };
+            let layout = ColumnLayout {
+                columns_total: get_number(&format!("{prefix}NUMBER_OF_COLUMNS"))?
+                    .map_or(0, |value| value as u32),
+                blobs_per_column_cell: get_number(&format!("{prefix}BLOBS_PER_COLUMN_CELL"))?
+                    .map_or(DEFAULT_BLOBS_PER_COLUMN_CELL, |value| value as u32),
+                extension_factor: get_number(&format!("{prefix}COLUMN_EXTENSION_FACTOR"))?
+                    .map_or(DEFAULT_COLUMN_EXTENSION_FACTOR, |value| value as u32),
+            };
+
+            layout
+                .validate()
+                .with_context(|| format!("Invalid {prefix}NUMBER_OF_COLUMNS column layout"))?;
+
+            column_layouts.insert(activation_epoch, layout);
+        }
+
+        // Known forks come with their fraction. Chains that change it on other forks (e.g. BPO
+        // forks) declare it as `<FORK>_BLOB_BASE_FEE_UPDATE_FRACTION`, which takes precedence
+        let mut blob_base_fee_update_fractions = BTreeMap::new();
+
+        for (fork_name, update_fraction) in FORK_BLOB_BASE_FEE_UPDATE_FRACTIONS {
+            if let Some(epoch) = fork_epochs.get(fork_name) {
+                blob_base_fee_update_fractions.insert(*epoch, update_fraction);
+            }
+        }
+
+        for key in values.keys() {
+            let Some(prefix) = key.strip_suffix("BLOB_BASE_FEE_UPDATE_FRACTION") else {
+                continue;
+            };
+            let activation_epoch = match prefix.strip_suffix('_') {
+                Some(fork_name) => match fork_epochs.get(&fork_name.to_lowercase()) {
+                    Some(epoch) => *epoch,
+                    None => continue,
+                },
+                None => 0,
+            };
+
+            if let Some(update_fraction) = get_number(key)? {
+                blob_base_fee_update_fractions.insert(activation_epoch, update_fraction);
+            }
+        }
+
+        Ok(Self {
+            seconds_per_slot: seconds_per_slot as u32,
+            slots_per_epoch: slots_per_epoch as u32,
+            max_blobs_per_block: max_blobs_per_block.map(|max_blobs| max_blobs as u32),
+            deposit_chain_id,
+            fork_epochs,
+            column_layouts,
+            blob_base_fee_update_fractions,
+        })
+    }
+}
+
 impl From<&Topic> for String {
     fn from(value: &Topic) -> Self {
         match value {
@@ -210,23 +418,370 @@ impl From<HeadEventData> for BlockData {
     }
 }
 
-impl From<ColumnsResponse> for BlobsResponse {
-    fn from(columns_res: ColumnsResponse) -> Self {
-        let mut blobs = Vec::new();
-        let kzg_commitments = columns_res.data[0].blob_kzg_commitments.clone();
-        
-        //每个blob的index对应columns_res.data的每个column的index
-        //第i个blob的kzg_commitment对应columns_res.data的第0号column的blob_kzg_commitments的第i个元素
-        //每个blob的kzg_proof为空字符串
-        //每个blob的kzg_proof为空bytes
-        for (i, comm) in kzg_commitments.iter().enumerate() {
-            blobs.push(Blob {
-                index: i.to_string(),
-                kzg_commitment: comm.clone(),
-                kzg_proof: String::new(),
-                blob: Bytes::from_str(comm).unwrap(),
-            });
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use ethers::{types::H256, utils::keccak256};
+
+    use super::{BlockResponse, ChainReorgEventData, Genesis, Spec};
+    use crate::utils::web3::{
+        CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION, PRAGUE_BLOB_BASE_FEE_UPDATE_FRACTION,
+    };
+
+    fn try_spec(values: &[(&str, &str)]) -> Result<Spec, anyhow::Error> {
+        let values = values
+            .iter()
+            .map(|(key, value)| (key.to_string(), serde_json::Value::from(*value)))
+            .collect::<HashMap<_, _>>();
+
+        Spec::try_from(values)
+    }
+
+    fn spec(values: &[(&str, &str)]) -> Spec {
+        try_spec(values).unwrap()
+    }
+
+    #[test]
+    fn spec_reads_required_and_optional_values() {
+        let spec = spec(&[
+            ("SECONDS_PER_SLOT", "12"),
+            ("SLOTS_PER_EPOCH", "32"),
+            ("MAX_BLOBS_PER_BLOCK", "9"),
+            ("DEPOSIT_CHAIN_ID", "17000"),
+            ("CONFIG_NAME", "holesky"),
+        ]);
+
+        assert_eq!(spec.seconds_per_slot, 12);
+        assert_eq!(spec.slots_per_epoch, 32);
+        assert_eq!(spec.max_blobs_per_block, Some(9));
+        assert_eq!(spec.deposit_chain_id, Some(17000));
+        assert!(spec.fork_epochs.is_empty());
+    }
+
+    #[test]
+    fn spec_leaves_missing_optional_values_unset() {
+        let spec = spec(&[("SECONDS_PER_SLOT", "12"), ("SLOTS_PER_EPOCH", "32")]);
+
+        assert_eq!(spec.max_blobs_per_block, None);
+        assert_eq!(spec.deposit_chain_id, None);
+    }
+
+    #[test]
+    fn spec_collects_fork_epochs_under_lowercase_names() {
+        let spec = spec(&[
+            ("SECONDS_PER_SLOT", "12"),
+            ("SLOTS_PER_EPOCH", "32"),
+            ("DENEB_FORK_EPOCH", "269568"),
+            ("ELECTRA_FORK_EPOCH", "364032"),
+            ("DENEB_FORK_VERSION", "0x04000000"),
+        ]);
+
+        assert_eq!(
+            spec.fork_epochs,
+            BTreeMap::from([
+                ("deneb".to_string(), 269568),
+                ("electra".to_string(), 364032)
+            ])
+        );
+    }
+
+    #[test]
+    fn spec_requires_slot_timing() {
+        let error = try_spec(&[("SLOTS_PER_EPOCH", "32")]).unwrap_err();
+
+        assert!(error.to_string().contains("SECONDS_PER_SLOT"), "{error}");
+
+        let error = try_spec(&[("SECONDS_PER_SLOT", "12")]).unwrap_err();
+
+        assert!(error.to_string().contains("SLOTS_PER_EPOCH"), "{error}");
+    }
+
+    #[test]
+    fn spec_rejects_non_numeric_values() {
+        let error = try_spec(&[
+            ("SECONDS_PER_SLOT", "12"),
+            ("SLOTS_PER_EPOCH", "32"),
+            ("DENEB_FORK_EPOCH", "soon"),
+        ])
+        .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Invalid spec value for DENEB_FORK_EPOCH: soon"
+        );
+    }
+
+    const BLOCK_HASH: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+    const PARENT_HASH: &str = "0x2222222222222222222222222222222222222222222222222222222222222222";
+    const PARENT_ROOT: &str = "0x3333333333333333333333333333333333333333333333333333333333333333";
+
+    #[test]
+    fn block_reads_snake_case_fields() {
+        let block: BlockResponse = serde_json::from_value(serde_json::json!({
+            "data": { "message": {
+                "slot": "10",
+                "parent_root": PARENT_ROOT,
+                "body": {
+                    "execution_payload": {
+                        "block_hash": BLOCK_HASH,
+                        "parent_hash": PARENT_HASH,
+                        "block_number": "20",
+                        "timestamp": "1700000000",
+                        "blob_gas_used": "131072",
+                        "excess_blob_gas": "0"
+                    },
+                    "blob_kzg_commitments": ["0xc0"]
+                }
+            }}
+        }))
+        .unwrap();
+        let message = block.data.message;
+        let execution_payload = message.body.execution_payload.unwrap();
+
+        assert_eq!(message.slot, 10);
+        assert_eq!(message.parent_root, H256::repeat_byte(0x33));
+        assert_eq!(execution_payload.block_hash, H256::repeat_byte(0x11));
+        assert_eq!(execution_payload.parent_hash, H256::repeat_byte(0x22));
+        assert_eq!(execution_payload.block_number, 20);
+        assert_eq!(execution_payload.timestamp, 1700000000);
+        assert_eq!(execution_payload.blob_gas_used.as_deref(), Some("131072"));
+        assert_eq!(
+            message.body.blob_kzg_commitments,
+            Some(vec!["0xc0".to_string()])
+        );
+    }
+
+    #[test]
+    fn block_reads_camel_case_fields_and_unprefixed_hashes() {
+        let block: BlockResponse = serde_json::from_value(serde_json::json!({
+            "data": { "message": {
+                "slot": "10",
+                "parentRoot": PARENT_ROOT.trim_start_matches("0x"),
+                "body": {
+                    "executionPayload": {
+                        "blockHash": BLOCK_HASH.trim_start_matches("0x"),
+                        "parentHash": PARENT_HASH.to_uppercase().replace("0X", "0x"),
+                        "blockNumber": "20",
+                        "timestamp": "1700000000",
+                        "blobGasUsed": "131072",
+                        "excessBlobGas": "0"
+                    },
+                    "blobKzgCommitments": []
+                }
+            }}
+        }))
+        .unwrap();
+        let message = block.data.message;
+        let execution_payload = message.body.execution_payload.unwrap();
+
+        assert_eq!(message.parent_root, H256::repeat_byte(0x33));
+        assert_eq!(execution_payload.block_hash, H256::repeat_byte(0x11));
+        assert_eq!(execution_payload.parent_hash, H256::repeat_byte(0x22));
+        assert_eq!(execution_payload.block_number, 20);
+        assert_eq!(execution_payload.excess_blob_gas.as_deref(), Some("0"));
+        assert_eq!(message.body.blob_kzg_commitments, Some(vec![]));
+    }
+
+    #[test]
+    fn block_hashes_accept_an_uppercase_prefix() {
+        let block: BlockResponse = serde_json::from_value(serde_json::json!({
+            "data": { "message": {
+                "slot": "10",
+                "parent_root": PARENT_ROOT.replace("0x", "0X"),
+                "body": {}
+            }}
+        }))
+        .unwrap();
+
+        assert_eq!(block.data.message.parent_root, H256::repeat_byte(0x33));
+        assert!(block.data.message.body.execution_payload.is_none());
+    }
+
+    #[test]
+    fn block_hashes_must_be_32_bytes_of_hex() {
+        for parent_root in ["0x1234", "0xzz", ""] {
+            let block = serde_json::from_value::<BlockResponse>(serde_json::json!({
+                "data": { "message": {
+                    "slot": "10",
+                    "parent_root": parent_root,
+                    "body": {}
+                }}
+            }));
+
+            assert!(block.is_err(), "{parent_root}");
         }
-        Self { data: blobs }
+
+        let error = serde_json::from_value::<BlockResponse>(serde_json::json!({
+            "data": { "message": {
+                "slot": "10",
+                "parent_root": "0x1234",
+                "body": {}
+            }}
+        }))
+        .unwrap_err();
+
+        assert!(
+            error
+                .to_string()
+                .contains("invalid hash length for 0x1234: expected 32 bytes, got 2"),
+            "{error}"
+        );
+    }
+
+    // Full `eth/v2/beacon/blocks` responses, with every consensus field the types don't declare
+    const DENEB_BLOCK: &str = include_str!("fixtures/deneb_block.json");
+    const ELECTRA_BLOCK: &str = include_str!("fixtures/electra_block.json");
+
+    #[test]
+    fn block_reads_a_full_deneb_block() {
+        let block: BlockResponse = serde_json::from_str(DENEB_BLOCK).unwrap();
+        let message = block.data.message;
+        let execution_payload = message.body.execution_payload.unwrap();
+
+        assert_eq!(message.slot, 9000000);
+        assert_eq!(message.parent_root, H256::repeat_byte(0x33));
+        assert_eq!(execution_payload.block_hash, H256::repeat_byte(0x11));
+        assert_eq!(execution_payload.parent_hash, H256::repeat_byte(0x22));
+        assert_eq!(execution_payload.block_number, 19784112);
+        assert_eq!(execution_payload.timestamp, 1714824047);
+        assert_eq!(execution_payload.blob_gas_used.as_deref(), Some("393216"));
+        assert_eq!(
+            execution_payload.excess_blob_gas.as_deref(),
+            Some("79429632")
+        );
+        assert_eq!(
+            message.body.blob_kzg_commitments,
+            Some(
+                ["a1", "a2", "a3"]
+                    .map(|byte| format!("0x{}", byte.repeat(48)))
+                    .to_vec()
+            )
+        );
+    }
+
+    #[test]
+    fn block_reads_a_full_electra_block() {
+        let block: BlockResponse = serde_json::from_str(ELECTRA_BLOCK).unwrap();
+        let message = block.data.message;
+        let execution_payload = message.body.execution_payload.unwrap();
+
+        assert_eq!(message.slot, 11650000);
+        assert_eq!(message.parent_root, H256::repeat_byte(0x36));
+        assert_eq!(execution_payload.block_hash, H256::repeat_byte(0x14));
+        assert_eq!(execution_payload.parent_hash, H256::repeat_byte(0x25));
+        assert_eq!(execution_payload.block_number, 22431084);
+        assert_eq!(execution_payload.timestamp, 1747799807);
+        assert_eq!(execution_payload.blob_gas_used.as_deref(), Some("786432"));
+        assert_eq!(message.body.blob_kzg_commitments.unwrap().len(), 6);
+    }
+
+    #[test]
+    fn genesis_reads_a_quoted_genesis_time() {
+        let genesis: Genesis = serde_json::from_value(serde_json::json!({
+            "genesis_time": "1695902400",
+            "genesis_validators_root": H256::zero(),
+            "genesis_fork_version": "0x01017000"
+        }))
+        .unwrap();
+
+        assert_eq!(genesis.genesis_time, 1695902400);
+    }
+
+    #[test]
+    fn blob_base_fee_update_fractions_follow_the_fork_schedule() {
+        let spec = spec(&[
+            ("SECONDS_PER_SLOT", "12"),
+            ("SLOTS_PER_EPOCH", "32"),
+            ("DENEB_FORK_EPOCH", "269568"),
+            ("ELECTRA_FORK_EPOCH", "364032"),
+            ("FULU_FORK_EPOCH", "18446744073709551615"),
+        ]);
+
+        assert_eq!(
+            spec.blob_base_fee_update_fractions,
+            BTreeMap::from([
+                (269568, CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION),
+                (364032, PRAGUE_BLOB_BASE_FEE_UPDATE_FRACTION),
+            ])
+        );
+    }
+
+    #[test]
+    fn declared_blob_base_fee_update_fractions_take_precedence() {
+        let spec = spec(&[
+            ("SECONDS_PER_SLOT", "12"),
+            ("SLOTS_PER_EPOCH", "32"),
+            ("DENEB_FORK_EPOCH", "0"),
+            ("ELECTRA_FORK_EPOCH", "10"),
+            ("BPO1_FORK_EPOCH", "20"),
+            ("BPO1_BLOB_BASE_FEE_UPDATE_FRACTION", "8346193"),
+            ("ELECTRA_BLOB_BASE_FEE_UPDATE_FRACTION", "4000000"),
+            ("UNKNOWN_BLOB_BASE_FEE_UPDATE_FRACTION", "1"),
+        ]);
+
+        assert_eq!(
+            spec.blob_base_fee_update_fractions,
+            BTreeMap::from([
+                (0, CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION),
+                (10, 4000000),
+                (20, 8346193),
+            ])
+        );
+    }
+
+    fn reorg_event(old_head_block: H256, new_head_block: H256, depth: u32) -> ChainReorgEventData {
+        serde_json::from_value(serde_json::json!({
+            "slot": "200",
+            "depth": depth.to_string(),
+            "old_head_block": old_head_block,
+            "new_head_block": new_head_block,
+            "old_head_state": H256::zero(),
+            "new_head_state": H256::zero(),
+            "epoch": "6",
+            "execution_optimistic": false
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_reorg_id_hashes_heads_and_depth() {
+        let event = reorg_event(H256::repeat_byte(1), H256::repeat_byte(2), 3);
+
+        let mut preimage = [0u8; 68];
+
+        preimage[..32].copy_from_slice(&[1; 32]);
+        preimage[32..64].copy_from_slice(&[2; 32]);
+        preimage[64..].copy_from_slice(&[0, 0, 0, 3]);
+
+        assert_eq!(event.slot, 200);
+        assert_eq!(event.reorg_id(), H256::from(keccak256(preimage)));
+    }
+
+    #[test]
+    fn test_reorg_id_changes_with_every_input() {
+        let reorg_id = reorg_event(H256::repeat_byte(1), H256::repeat_byte(2), 3).reorg_id();
+
+        assert_eq!(
+            reorg_event(H256::repeat_byte(1), H256::repeat_byte(2), 3).reorg_id(),
+            reorg_id
+        );
+        assert_ne!(
+            reorg_event(H256::repeat_byte(9), H256::repeat_byte(2), 3).reorg_id(),
+            reorg_id
+        );
+        assert_ne!(
+            reorg_event(H256::repeat_byte(1), H256::repeat_byte(9), 3).reorg_id(),
+            reorg_id
+        );
+        assert_ne!(
+            reorg_event(H256::repeat_byte(1), H256::repeat_byte(2), 4).reorg_id(),
+            reorg_id
+        );
+        // The heads aren't interchangeable
+        assert_ne!(
+            reorg_event(H256::repeat_byte(2), H256::repeat_byte(1), 3).reorg_id(),
+            reorg_id
+        );
     }
 }
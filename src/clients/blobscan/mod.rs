@@ -1,40 +1,58 @@
+use std::sync::Arc;
+
 use backoff::ExponentialBackoff;
 use chrono::TimeDelta;
-use reqwest::{Client, Url};
+use ethers::types::H256;
+use reqwest::Client;
 
 use crate::{
     clients::{blobscan::types::ReorgedSlotsResponse, common::ClientResult},
-    json_get, json_put,
+    json_get, json_put, json_send,
+    utils::{body_trace::BodyTrace, clock::Clock, url::EndpointUrl},
 };
 
 use self::{
     jwt_manager::{Config as JWTManagerConfig, JWTManager},
+    server_timing::BlobscanLatency,
     types::{
-        Blob, Block, BlockchainSyncState, BlockchainSyncStateRequest, BlockchainSyncStateResponse,
-        IndexRequest, ReorgedSlotsRequest, Transaction,
+        Blob, BlobMetadata, Block, BlockchainSyncState, BlockchainSyncStateRequest,
+        BlockchainSyncStateResponse, ChainConfig, FailedSlotsChunk, FailedSlotsChunksResponse,
+        IndexPatchRequest, IndexRequest, IndexedBlobDetails, IndexedBlock, PutBlobRequest,
+        RemoveBlocksRequest, RemoveBlocksResponse, RemoveFailedSlotsChunksRequest,
+        ReorgedSlotsRequest, SkippedSlotRange, SkippedSlotsRequest, SlotHeartbeatRequest,
+        Transaction,
     },
 };
 
 mod jwt_manager;
 
+pub mod server_timing;
 pub mod types;
 #[derive(Debug, Clone)]
 pub struct BlobscanClient {
-    base_url: Url,
+    base_url: EndpointUrl,
     client: reqwest::Client,
     jwt_manager: JWTManager,
     exp_backoff: Option<ExponentialBackoff>,
+    clock: Arc<dyn Clock>,
+    body_trace: BodyTrace,
+    latency: Arc<BlobscanLatency>,
 }
 
 pub struct Config {
-    pub base_url: String,
+    pub base_url: EndpointUrl,
     pub secret_key: String,
     pub exp_backoff: Option<ExponentialBackoff>,
+    /// Times the requests and sleeps between their retries
+    pub clock: Arc<dyn Clock>,
+    pub body_trace: BodyTrace,
+    /// Records the response times of every request
+    pub latency: Arc<BlobscanLatency>,
 }
 
 impl BlobscanClient {
     pub fn try_with_client(client: Client, config: Config) -> ClientResult<Self> {
-        let base_url = Url::parse(&format!("{}/", config.base_url))?;
+        let base_url = config.base_url;
         let jwt_manager = JWTManager::new(JWTManagerConfig {
             secret_key: config.secret_key,
             refresh_interval: TimeDelta::try_hours(1).unwrap(),
@@ -47,6 +65,9 @@ impl BlobscanClient {
             client,
             jwt_manager,
             exp_backoff,
+            clock: config.clock,
+            body_trace: config.body_trace,
+            latency: config.latency,
         })
     }
 
@@ -56,44 +77,488 @@ impl BlobscanClient {
         transactions: Vec<Transaction>,
         blobs: Vec<Blob>,
     ) -> ClientResult<()> {
-        let url = self.base_url.join("indexer/block-txs-blobs")?;
+        let url = self.base_url.join_path(&["indexer/block-txs-blobs"]);
+        let token = self.jwt_manager.get_token()?;
+        let req = IndexRequest {
+            block,
+            transactions,
+            blobs,
+            partial: None,
+        };
+
+        json_put!(@observed self.latency, &self.client, url, token, &req, self.exp_backoff.clone(), self.clock.as_ref(), self.body_trace)
+            .map(|_: Option<()>| ())
+    }
+
+    /// Indexes a block without transactions and blobs. The request is flagged as partial so the
+    /// empty child arrays aren't treated as an error.
+    pub async fn index_block(&self, block: Block) -> ClientResult<()> {
+        let url = self.base_url.join_path(&["indexer/block-txs-blobs"]);
         let token = self.jwt_manager.get_token()?;
         let req = IndexRequest {
+            block,
+            transactions: vec![],
+            blobs: vec![],
+            partial: Some(true),
+        };
+
+        json_put!(@observed self.latency, &self.client, url, token, &req, self.exp_backoff.clone(), self.clock.as_ref(), self.body_trace)
+            .map(|_: Option<()>| ())
+    }
+
+    pub async fn patch(
+        &self,
+        block: Option<Block>,
+        transactions: Option<Vec<Transaction>>,
+        blobs: Option<Vec<BlobMetadata>>,
+    ) -> ClientResult<()> {
+        let mut url = self.base_url.join_path(&["indexer/block-txs-blobs"]);
+        url.query_pairs_mut().append_pair("mode", "patch");
+        let token = self.jwt_manager.get_token()?;
+        let req = IndexPatchRequest {
             block,
             transactions,
             blobs,
         };
 
-        json_put!(&self.client, url, token, &req).map(|_: Option<()>| ())
+        json_put!(@observed self.latency, &self.client, url, token, &req, self.exp_backoff.clone(), self.clock.as_ref(), self.body_trace)
+            .map(|_: Option<()>| ())
     }
 
-    pub async fn handle_reorged_slots(&self, slots: &[u32]) -> ClientResult<u32> {
-        let url = self.base_url.join("indexer/reorged-slots")?;
+    /// Replaces the stored data of an already indexed blob
+    pub async fn put_blob(&self, blob: Blob) -> ClientResult<()> {
+        let url = self.base_url.join_path(&["indexer/blobs"]);
+        let token = self.jwt_manager.get_token()?;
+        let req = PutBlobRequest { blob };
+
+        json_put!(@observed self.latency, &self.client, url, token, &req, self.exp_backoff.clone(), self.clock.as_ref(), self.body_trace)
+            .map(|_: Option<()>| ())
+    }
+
+    pub async fn handle_reorged_slots(&self, reorg_id: H256, slots: &[u32]) -> ClientResult<u32> {
+        let url = self.base_url.join_path(&["indexer/reorged-slots"]);
         let token = self.jwt_manager.get_token()?;
         let req = ReorgedSlotsRequest {
+            reorg_id,
             reorged_slots: slots.to_owned(),
         };
 
-        json_put!(&self.client, url, ReorgedSlotsResponse, token, &req)
-            .map(|res: Option<ReorgedSlotsResponse>| res.unwrap().total_updated_slots)
+        json_put!(
+            @observed self.latency,
+            &self.client,
+            url,
+            ReorgedSlotsResponse,
+            token,
+            &req,
+            self.exp_backoff.clone(),
+            self.clock.as_ref(),
+            self.body_trace
+        )
+        .map(|res: Option<ReorgedSlotsResponse>| res.unwrap().total_updated_slots)
+    }
+
+    /// Deletes the blocks indexed for the given slots along with their transactions and blobs.
+    /// Slots that were never indexed are ignored. Returns how many blocks were removed
+    pub async fn remove_blocks(&self, slots: &[u32]) -> ClientResult<u32> {
+        let url = self.base_url.join_path(&["indexer/remove-blocks"]);
+        let token = self.jwt_manager.get_token()?;
+        let req = RemoveBlocksRequest {
+            slots: slots.to_owned(),
+        };
+
+        json_put!(
+            @observed self.latency,
+            &self.client,
+            url,
+            RemoveBlocksResponse,
+            token,
+            &req,
+            self.exp_backoff.clone(),
+            self.clock.as_ref(),
+            self.body_trace
+        )
+        .map(|res: Option<RemoveBlocksResponse>| res.map_or(0, |res| res.total_removed_blocks))
     }
 
     pub async fn update_sync_state(&self, sync_state: BlockchainSyncState) -> ClientResult<()> {
-        let url = self.base_url.join("blockchain-sync-state")?;
+        let url = self.base_url.join_path(&["blockchain-sync-state"]);
         let token = self.jwt_manager.get_token()?;
         let req: BlockchainSyncStateRequest = sync_state.into();
 
-        json_put!(&self.client, url, token, &req).map(|_: Option<()>| ())
+        json_put!(@observed self.latency, &self.client, url, token, &req, self.exp_backoff.clone(), self.clock.as_ref(), self.body_trace)
+            .map(|_: Option<()>| ())
+    }
+
+    /// Reports a processed slot that had nothing to index so freshness tracking keeps moving
+    pub async fn update_slot(&self, slot: u32) -> ClientResult<()> {
+        let url = self.base_url.join_path(&["indexer/slot"]);
+        let token = self.jwt_manager.get_token()?;
+        let req = SlotHeartbeatRequest { slot };
+
+        json_put!(@observed self.latency, &self.client, url, token, &req, self.exp_backoff.clone(), self.clock.as_ref(), self.body_trace)
+            .map(|_: Option<()>| ())
+    }
+
+    pub async fn record_skipped_slots(
+        &self,
+        skipped_slots: Vec<SkippedSlotRange>,
+    ) -> ClientResult<()> {
+        let url = self.base_url.join_path(&["indexer/skipped-slots"]);
+        let token = self.jwt_manager.get_token()?;
+        let req = SkippedSlotsRequest { skipped_slots };
+
+        json_put!(@observed self.latency, &self.client, url, token, &req, self.exp_backoff.clone(), self.clock.as_ref(), self.body_trace)
+            .map(|_: Option<()>| ())
+    }
+
+    pub async fn add_failed_slots_chunk(&self, chunk: FailedSlotsChunk) -> ClientResult<()> {
+        let url = self.base_url.join_path(&["indexer/failed-slots-chunks"]);
+        let token = self.jwt_manager.get_token()?;
+
+        json_send!(
+            @observed self.latency,
+            &self.client,
+            reqwest::Method::POST,
+            url,
+            (),
+            token,
+            &chunk,
+            self.exp_backoff.clone(),
+            self.clock.as_ref(),
+            self.body_trace
+        )
+        .map(|_: Option<()>| ())
+    }
+
+    pub async fn get_failed_slots_chunks(&self) -> ClientResult<Option<Vec<FailedSlotsChunk>>> {
+        let url = self.base_url.join_path(&["indexer/failed-slots-chunks"]);
+        let token = self.jwt_manager.get_token()?;
+
+        json_get!(
+            @observed self.latency,
+            &self.client,
+            url,
+            FailedSlotsChunksResponse,
+            token,
+            self.exp_backoff.clone(),
+            self.clock.as_ref(),
+            self.body_trace
+        )
+        .map(|res: Option<FailedSlotsChunksResponse>| res.map(|res| res.chunks))
+    }
+
+    pub async fn remove_failed_slots_chunks(&self, chunk_ids: Vec<u32>) -> ClientResult<()> {
+        let url = self.base_url.join_path(&["indexer/failed-slots-chunks"]);
+        let token = self.jwt_manager.get_token()?;
+        let req = RemoveFailedSlotsChunksRequest { chunk_ids };
+
+        json_send!(
+            @observed self.latency,
+            &self.client,
+            reqwest::Method::DELETE,
+            url,
+            (),
+            token,
+            &req,
+            self.exp_backoff.clone(),
+            self.clock.as_ref(),
+            self.body_trace
+        )
+        .map(|_: Option<()>| ())
+    }
+
+    pub async fn update_chain_config(&self, chain_config: ChainConfig) -> ClientResult<()> {
+        let url = self.base_url.join_path(&["chain-config"]);
+        let token = self.jwt_manager.get_token()?;
+
+        json_put!(@observed self.latency, &self.client, url, token, &chain_config, self.exp_backoff.clone(), self.clock.as_ref(), self.body_trace)
+            .map(|_: Option<()>| ())
+    }
+
+    /// Performs an authenticated read on an indexer endpoint, so the JWT is checked by the same
+    /// auth as the index calls without writing any data
+    pub async fn probe_auth(&self) -> ClientResult<Option<()>> {
+        let url = self.base_url.join_path(&["indexer/failed-slots-chunks"]);
+        let token = self.jwt_manager.get_token()?;
+
+        json_get!(
+            @observed self.latency,
+            &self.client,
+            url,
+            FailedSlotsChunksResponse,
+            token,
+            self.exp_backoff.clone(),
+            self.clock.as_ref(),
+            self.body_trace
+        )
+        .map(|res| res.map(|_| ()))
     }
 
     pub async fn get_sync_state(&self) -> ClientResult<Option<BlockchainSyncState>> {
-        let url = self.base_url.join("blockchain-sync-state")?;
+        let url = self.base_url.join_path(&["blockchain-sync-state"]);
         json_get!(
+            @observed self.latency,
             &self.client,
             url,
             BlockchainSyncStateResponse,
-            self.exp_backoff.clone()
+            self.exp_backoff.clone(),
+            self.clock.as_ref(),
+            self.body_trace
         )
         .map(|res: Option<BlockchainSyncStateResponse>| Some(res.unwrap().into()))
     }
+
+    pub async fn get_block(&self, block_number: u32) -> ClientResult<Option<IndexedBlock>> {
+        let url = self
+            .base_url
+            .join_path(&["blocks", block_number.to_string().as_str()]);
+
+        json_get!(
+            @observed self.latency,
+            &self.client,
+            url,
+            IndexedBlock,
+            self.exp_backoff.clone(),
+            self.clock.as_ref(),
+            self.body_trace
+        )
+    }
+
+    pub async fn get_blob(&self, versioned_hash: H256) -> ClientResult<Option<IndexedBlobDetails>> {
+        let url = self
+            .base_url
+            .join_path(&["blobs", format!("{versioned_hash:?}").as_str()]);
+
+        json_get!(
+            @observed self.latency,
+            &self.client,
+            url,
+            IndexedBlobDetails,
+            self.exp_backoff.clone(),
+            self.clock.as_ref(),
+            self.body_trace
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use backoff::{ExponentialBackoff, ExponentialBackoffBuilder};
+    use ethers::types::H256;
+
+    use super::{
+        types::{Block, ChainConfig, SkippedSlotRange, SlotSkipReason},
+        BlobscanClient, Config,
+    };
+    use crate::{
+        clients::{
+            common::ClientError,
+            test_server::{self, Reply},
+        },
+        utils::{
+            clock::{Clock, MockClock, SystemClock},
+            url::EndpointUrl,
+        },
+    };
+
+    fn client_with(
+        base_url: EndpointUrl,
+        exp_backoff: Option<ExponentialBackoff>,
+        clock: Arc<dyn Clock>,
+    ) -> BlobscanClient {
+        BlobscanClient::try_with_client(
+            reqwest::Client::new(),
+            Config {
+                base_url,
+                secret_key: "test-secret".to_string(),
+                exp_backoff,
+                clock,
+                body_trace: Default::default(),
+                latency: Arc::default(),
+            },
+        )
+        .unwrap()
+    }
+
+    fn client(base_url: EndpointUrl) -> BlobscanClient {
+        client_with(base_url, None, Arc::new(SystemClock))
+    }
+
+    #[tokio::test]
+    async fn test_probe_auth_reads_an_authenticated_indexer_endpoint() {
+        let (base_url, server) = test_server::serve(vec![Reply::Respond(
+            "200 OK",
+            r#"{"chunks":[]}"#.to_string(),
+        )])
+        .await;
+
+        assert!(client(base_url).probe_auth().await.unwrap().is_some());
+
+        let requests = server.await.unwrap();
+
+        assert_eq!(requests[0].method, "GET");
+        assert_eq!(requests[0].path, "/indexer/failed-slots-chunks");
+        assert!(requests[0]
+            .header("authorization")
+            .is_some_and(|value| value.starts_with("Bearer ")));
+    }
+
+    #[tokio::test]
+    async fn test_probe_auth_fails_on_rejected_token() {
+        let (base_url, server) = test_server::serve(vec![Reply::Respond(
+            "401 Unauthorized",
+            r#"{"code":"UNAUTHORIZED","message":"Unauthorized"}"#.to_string(),
+        )])
+        .await;
+
+        assert!(client(base_url).probe_auth().await.is_err());
+
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_index_block_submits_a_partial_block() {
+        let (base_url, server) =
+            test_server::serve(vec![Reply::Respond("200 OK", String::new())]).await;
+        let block: Block = serde_json::from_value(serde_json::json!({
+            "number": "0x64",
+            "hash": format!("0x{}", "bb".repeat(32)),
+            "timestamp": "0x6553f100",
+            "slot": 200,
+            "blobGasUsed": null,
+            "excessBlobGas": null,
+            "validatorPubkey": "0xa0",
+            "transactionsCount": 2,
+            "blobsCount": 0
+        }))
+        .unwrap();
+
+        client(base_url).index_block(block).await.unwrap();
+
+        let requests = server.await.unwrap();
+        let body = serde_json::from_str::<serde_json::Value>(&requests[0].body).unwrap();
+
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].path, "/indexer/block-txs-blobs");
+        assert_eq!(body["partial"], true);
+        assert_eq!(body["transactions"], serde_json::json!([]));
+        assert_eq!(body["blobs"], serde_json::json!([]));
+        assert_eq!(body["block"]["transactionsCount"], 2);
+        assert_eq!(body["block"]["blobsCount"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_update_chain_config_puts_the_chain_config() {
+        let (base_url, server) =
+            test_server::serve(vec![Reply::Respond("200 OK", String::new())]).await;
+        let chain_config = ChainConfig {
+            chain_id: 1,
+            genesis_time: 1606824023,
+            seconds_per_slot: 12,
+            slots_per_epoch: 32,
+            max_blobs_per_block: None,
+            fork_epochs: Default::default(),
+            column_layouts: Default::default(),
+            blob_base_fee_update_fractions: Default::default(),
+        };
+
+        client(base_url)
+            .update_chain_config(chain_config)
+            .await
+            .unwrap();
+
+        let requests = server.await.unwrap();
+        let body = serde_json::from_str::<serde_json::Value>(&requests[0].body).unwrap();
+
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].path, "/chain-config");
+        assert!(requests[0]
+            .header("authorization")
+            .is_some_and(|value| value.starts_with("Bearer ")));
+        assert_eq!(body["chainId"], 1);
+        assert_eq!(body["genesisTime"], 1606824023);
+    }
+
+    #[tokio::test]
+    async fn test_write_retries_record_every_failed_attempt_in_order() {
+        // Gone before the first attempt, so every connection is refused
+        let (base_url, server) = test_server::serve(vec![]).await;
+
+        server.await.unwrap();
+
+        let clock = Arc::new(MockClock::new(
+            UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        ));
+        let exp_backoff = ExponentialBackoffBuilder::new()
+            .with_initial_interval(Duration::from_secs(1))
+            .with_randomization_factor(0.0)
+            .with_max_elapsed_time(Some(Duration::from_secs(10)))
+            .build();
+        let client = client_with(base_url, Some(exp_backoff), clock.clone());
+
+        let error = client
+            .record_skipped_slots(vec![SkippedSlotRange::new(10, SlotSkipReason::MissedSlot)])
+            .await
+            .unwrap_err();
+
+        let ClientError::RetriesExhausted { attempts, .. } = error else {
+            panic!("expected exhausted retries, got {error:?}");
+        };
+
+        assert!(attempts.len() > 1, "only {} attempts", attempts.len());
+        assert!(attempts
+            .iter()
+            .all(|attempt| attempt.error_class == "connect"));
+        assert!(attempts
+            .windows(2)
+            .all(|pair| pair[0].timestamp < pair[1].timestamp));
+        assert!(attempts
+            .iter()
+            .all(|attempt| attempt.endpoint.ends_with("/indexer/skipped-slots")));
+        assert_eq!(
+            attempts.last().unwrap().timestamp,
+            chrono::DateTime::<chrono::Utc>::from(clock.now())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_writes_that_reached_the_server_are_not_retried() {
+        let (base_url, server) = test_server::serve(vec![Reply::Close]).await;
+        let exp_backoff = ExponentialBackoffBuilder::new()
+            .with_initial_interval(Duration::from_secs(1))
+            .build();
+        let clock = Arc::new(MockClock::new(
+            UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        ));
+        let client = client_with(base_url, Some(exp_backoff), clock.clone());
+
+        let error = client
+            .handle_reorged_slots(H256::zero(), &[10, 11])
+            .await
+            .unwrap_err();
+
+        let ClientError::RetriesExhausted { attempts, .. } = error else {
+            panic!("expected the attempt log, got {error:?}");
+        };
+
+        assert_eq!(attempts.len(), 1);
+        assert_eq!(attempts[0].error_class, "request");
+        assert!(attempts[0].endpoint.ends_with("/indexer/reorged-slots"));
+        assert_eq!(server.await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_write_without_backoff_is_not_retried() {
+        let (base_url, server) = test_server::serve(vec![Reply::Close]).await;
+
+        let error = client(base_url).update_slot(10).await.unwrap_err();
+
+        assert!(matches!(error, ClientError::Reqwest(_)));
+        assert_eq!(server.await.unwrap().len(), 1);
+    }
 }
@@ -0,0 +1,224 @@
+use std::{sync::Mutex, time::Duration};
+
+use tracing::warn;
+
+use crate::{clients::common::ResponseObserver, metrics::LatencyHistogram};
+
+/// Requests per window the server time p95 is compared over
+const REGRESSION_WINDOW: usize = 200;
+/// Growth of the p95 between two windows reported as a regression
+const REGRESSION_FACTOR: f64 = 2.0;
+
+/// Server-side processing time from a `Server-Timing` header, e.g.
+/// `db;dur=53.2, app;desc="render";dur=47.1`. A `total` metric is taken as is. Otherwise the
+/// longest metric is used, as metrics may be nested (`db` inside `app`) and adding them up would
+/// count the same time twice. Returns `None` when no metric has a usable duration.
+pub fn parse_server_timing(header: &str) -> Option<Duration> {
+    let mut total = None;
+    let mut longest: Option<Duration> = None;
+
+    for metric in header.split(',') {
+        let mut params = metric.split(';').map(str::trim);
+        let name = params.next().unwrap_or_default();
+        // Durations that don't fit a `Duration` come from a misbehaving server and are dropped
+        let duration = params
+            .filter_map(|param| param.split_once('='))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case("dur"))
+            .and_then(|(_, value)| value.trim().trim_matches('"').parse::<f64>().ok())
+            .and_then(|duration_ms| Duration::try_from_secs_f64(duration_ms / 1000.0).ok());
+
+        let Some(duration) = duration else {
+            continue;
+        };
+
+        if name.eq_ignore_ascii_case("total") {
+            total = Some(duration);
+        }
+
+        longest = longest.max(Some(duration));
+    }
+
+    total.or(longest)
+}
+
+/// Response times of blobscan requests, split into server and network time when blobscan sends
+/// a `Server-Timing` header
+#[derive(Debug, Default)]
+pub struct BlobscanLatency {
+    pub total: LatencyHistogram,
+    pub server: LatencyHistogram,
+    pub network: LatencyHistogram,
+    regression_detector: Mutex<RegressionDetector>,
+}
+
+impl ResponseObserver for BlobscanLatency {
+    fn observe(&self, server_timing: Option<&str>, elapsed: Duration) {
+        self.total.observe(elapsed);
+
+        let Some(server_time) = server_timing.and_then(parse_server_timing) else {
+            return;
+        };
+
+        self.server.observe(server_time);
+        self.network.observe(elapsed.saturating_sub(server_time));
+
+        let regression = self
+            .regression_detector
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .record(server_time);
+
+        if let Some((previous_p95, current_p95)) = regression {
+            warn!(
+                previous_p95_ms = previous_p95.as_millis() as u64,
+                current_p95_ms = current_p95.as_millis() as u64,
+                "Blobscan server time p95 at least doubled over the last {REGRESSION_WINDOW} requests"
+            );
+        }
+    }
+}
+
+/// Compares the p95 of consecutive, non-overlapping windows of samples
+#[derive(Debug)]
+struct RegressionDetector {
+    window_size: usize,
+    samples: Vec<Duration>,
+    previous_p95: Option<Duration>,
+}
+
+impl Default for RegressionDetector {
+    fn default() -> Self {
+        Self::new(REGRESSION_WINDOW)
+    }
+}
+
+impl RegressionDetector {
+    fn new(window_size: usize) -> Self {
+        Self {
+            window_size,
+            samples: Vec::with_capacity(window_size),
+            previous_p95: None,
+        }
+    }
+
+    /// Returns the previous and current p95 when a window closes with a p95 `REGRESSION_FACTOR`
+    /// times the previous one
+    fn record(&mut self, sample: Duration) -> Option<(Duration, Duration)> {
+        self.samples.push(sample);
+
+        if self.samples.len() < self.window_size {
+            return None;
+        }
+
+        let current_p95 = p95(&mut self.samples);
+        let previous_p95 = self.previous_p95.replace(current_p95);
+
+        self.samples.clear();
+
+        previous_p95
+            .filter(|previous_p95| {
+                !previous_p95.is_zero()
+                    && current_p95.as_secs_f64() >= previous_p95.as_secs_f64() * REGRESSION_FACTOR
+            })
+            .map(|previous_p95| (previous_p95, current_p95))
+    }
+}
+
+/// Nearest-rank 95th percentile. `samples` must not be empty
+fn p95(samples: &mut [Duration]) -> Duration {
+    samples.sort_unstable();
+
+    let rank = (samples.len() as f64 * 0.95).ceil() as usize;
+
+    samples[rank.clamp(1, samples.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn parses_a_single_metric() {
+        assert_eq!(parse_server_timing("app;dur=47"), Some(ms(47)));
+        assert_eq!(
+            parse_server_timing("app;desc=\"render\";dur=\"12.5\""),
+            Some(Duration::from_micros(12_500))
+        );
+    }
+
+    #[test]
+    fn prefers_the_total_metric() {
+        assert_eq!(
+            parse_server_timing("db;dur=53, total;dur=60, app;dur=120"),
+            Some(ms(60))
+        );
+    }
+
+    #[test]
+    fn takes_the_longest_of_nested_metrics() {
+        assert_eq!(parse_server_timing("db;dur=53, app;dur=80"), Some(ms(80)));
+    }
+
+    #[test]
+    fn ignores_metrics_without_a_usable_duration() {
+        assert_eq!(parse_server_timing("cache;desc=hit"), None);
+        assert_eq!(parse_server_timing("app;dur=abc"), None);
+        assert_eq!(parse_server_timing("app;dur=-5"), None);
+        assert_eq!(parse_server_timing("app;dur=1e300"), None);
+        assert_eq!(parse_server_timing("app;dur=NaN"), None);
+        assert_eq!(parse_server_timing("app;dur=inf, db;dur=10"), Some(ms(10)));
+        assert_eq!(parse_server_timing(""), None);
+    }
+
+    #[test]
+    fn p95_uses_the_nearest_rank() {
+        let mut samples = (1..=100).rev().map(ms).collect::<Vec<_>>();
+
+        assert_eq!(p95(&mut samples), ms(95));
+        assert_eq!(p95(&mut [ms(7)]), ms(7));
+    }
+
+    #[test]
+    fn reports_a_regression_when_a_window_p95_doubles() {
+        let mut detector = RegressionDetector::new(4);
+
+        for _ in 0..4 {
+            assert_eq!(detector.record(ms(10)), None);
+        }
+
+        for _ in 0..3 {
+            assert_eq!(detector.record(ms(20)), None);
+        }
+
+        assert_eq!(detector.record(ms(20)), Some((ms(10), ms(20))));
+    }
+
+    #[test]
+    fn doesnt_report_growth_under_the_factor() {
+        let mut detector = RegressionDetector::new(2);
+
+        detector.record(ms(10));
+        detector.record(ms(10));
+        detector.record(ms(19));
+
+        assert_eq!(detector.record(ms(19)), None);
+    }
+
+    #[test]
+    fn compares_non_overlapping_windows() {
+        let mut detector = RegressionDetector::new(2);
+
+        detector.record(ms(10));
+        detector.record(ms(10));
+        detector.record(ms(30));
+        assert_eq!(detector.record(ms(30)), Some((ms(10), ms(30))));
+
+        // The next window compares against the 30ms one, not the first
+        detector.record(ms(40));
+        assert_eq!(detector.record(ms(40)), None);
+    }
+}
@@ -1,4 +1,5 @@
 use core::fmt;
+use std::collections::BTreeMap;
 
 use anyhow::{Context, Result};
 use ethers::types::{
@@ -6,7 +7,13 @@ use ethers::types::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{clients::beacon::types::Blob as BeaconBlob, utils::web3::calculate_versioned_hash};
+use crate::{
+    clients::beacon::{
+        columns::ColumnLayout,
+        types::{Blob as BeaconBlob, ExecutionPayload},
+    },
+    utils::web3::calculate_blob_fee_burned,
+};
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -15,9 +22,24 @@ pub struct Block {
     pub hash: H256,
     pub timestamp: U256,
     pub slot: u32,
-    pub blob_gas_used: U256,
-    pub excess_blob_gas: U256,
+    /// `None` for blocks before the blob fork
+    pub blob_gas_used: Option<U256>,
+    /// `None` for blocks before the blob fork
+    pub excess_blob_gas: Option<U256>,
     pub validator_pubkey: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transactions_count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blobs_count: Option<u32>,
+    /// Milliseconds between the slot's expected time and submission. Only set near the head
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indexing_latency_ms: Option<u64>,
+    /// Set when the block was indexed as part of a sampled range
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampled: Option<bool>,
+    /// `blob_gas_used * blob_gas_price`. Not set for pre-EIP-4844 blocks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob_fee_burned: Option<U256>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -39,6 +61,25 @@ pub struct Blob {
     pub commitment: String,
     pub proof: String,
     pub data: Bytes,
+    /// `None` in consensus-only mode, where transactions aren't fetched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<H256>,
+    pub index: u32,
+}
+
+/// Position of a blob among the blobs of its transaction. This is the index blobscan stores
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlobTxIndex(pub u32);
+
+/// Position of a blob among the commitments of its beacon block, which is also its sidecar index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlobBlockIndex(pub u32);
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobMetadata {
+    pub versioned_hash: H256,
+    pub commitment: String,
     pub tx_hash: H256,
     pub index: u32,
 }
@@ -50,6 +91,23 @@ pub struct FailedSlotsChunk {
     pub id: Option<u32>,
     pub initial_slot: u32,
     pub final_slot: u32,
+    /// Slot the chunk stopped at, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed_slot: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedSlotsChunksResponse {
+    pub chunks: Vec<FailedSlotsChunk>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveFailedSlotsChunksRequest {
+    pub chunk_ids: Vec<u32>,
 }
 
 #[derive(Serialize, Debug)]
@@ -84,11 +142,194 @@ pub struct IndexRequest {
     pub block: Block,
     pub transactions: Vec<Transaction>,
     pub blobs: Vec<Blob>,
+    /// Marks a block submitted without its child entities on purpose
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partial: Option<bool>,
+}
+
+/// Why a slot was intentionally not indexed. The serialized names are part of the blobscan API
+/// and must not change.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SlotSkipReason {
+    /// The genesis slot never has a block
+    #[serde(rename = "genesis")]
+    Genesis,
+    /// No beacon block was proposed for the slot
+    #[serde(rename = "missed_slot")]
+    MissedSlot,
+    /// The beacon block has no execution payload
+    #[serde(rename = "no_execution_payload")]
+    NoExecutionPayload,
+    /// The execution block has no transactions
+    #[serde(rename = "empty_block")]
+    EmptyBlock,
+    /// The block carries no blobs and only blob-carrying blocks are indexed
+    #[serde(rename = "no_blobs")]
+    NoBlobs,
+    /// The slot isn't part of the configured sample
+    #[serde(rename = "not_sampled")]
+    NotSampled,
+    /// The slot's proposer couldn't be resolved
+    #[serde(rename = "missing_proposer")]
+    MissingProposer,
+    /// The beacon node didn't return the blob data for a block carrying commitments
+    #[serde(rename = "missing_blob_data")]
+    MissingBlobData,
+    /// The beacon block carries blob commitments but the execution block has no blob transactions
+    #[serde(rename = "blocks_mismatch")]
+    BlocksMismatch,
+    /// The slot is listed in the operator's skip list
+    #[serde(rename = "operator_exclusion")]
+    OperatorExclusion,
+}
+
+impl SlotSkipReason {
+    /// The serialized name, also used as the metrics label
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Genesis => "genesis",
+            Self::MissedSlot => "missed_slot",
+            Self::NoExecutionPayload => "no_execution_payload",
+            Self::EmptyBlock => "empty_block",
+            Self::NoBlobs => "no_blobs",
+            Self::NotSampled => "not_sampled",
+            Self::MissingProposer => "missing_proposer",
+            Self::MissingBlobData => "missing_blob_data",
+            Self::BlocksMismatch => "blocks_mismatch",
+            Self::OperatorExclusion => "operator_exclusion",
+        }
+    }
+}
+
+/// Consecutive slots skipped for the same reason. A single slot has the same first and last slot
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedSlotRange {
+    pub from_slot: u32,
+    pub to_slot: u32,
+    pub reason: SlotSkipReason,
+}
+
+impl SkippedSlotRange {
+    pub fn new(slot: u32, reason: SlotSkipReason) -> Self {
+        Self {
+            from_slot: slot,
+            to_slot: slot,
+            reason,
+        }
+    }
+
+    /// Grows the range by `slot` when it's adjacent and skipped for the same reason. Ranges grow
+    /// both ways, as slots are walked backwards by historical syncs
+    pub fn try_extend(&mut self, slot: u32, reason: SlotSkipReason) -> bool {
+        if reason != self.reason {
+            return false;
+        }
+
+        if self.to_slot.checked_add(1) == Some(slot) {
+            self.to_slot = slot;
+        } else if self.from_slot.checked_sub(1) == Some(slot) {
+            self.from_slot = slot;
+        } else {
+            return false;
+        }
+
+        true
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedSlotsRequest {
+    pub skipped_slots: Vec<SkippedSlotRange>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SlotHeartbeatRequest {
+    pub slot: u32,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    pub genesis_time: u64,
+    pub seconds_per_slot: u32,
+    pub slots_per_epoch: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_blobs_per_block: Option<u32>,
+    pub fork_epochs: BTreeMap<String, u64>,
+    /// Kept for blob reconstruction, blobscan doesn't need it
+    #[serde(skip)]
+    pub column_layouts: BTreeMap<u64, ColumnLayout>,
+    /// Kept for blob fee accounting, blobscan doesn't need it
+    #[serde(skip)]
+    pub blob_base_fee_update_fractions: BTreeMap<u64, u64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexPatchRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block: Option<Block>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transactions: Option<Vec<Transaction>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blobs: Option<Vec<BlobMetadata>>,
+}
+
+/// A block as stored by blobscan, limited to what's compared against the chain
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedBlock {
+    pub hash: H256,
+    #[serde(default)]
+    pub slot: Option<u32>,
+    #[serde(default)]
+    pub transactions: Vec<IndexedTransaction>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedTransaction {
+    pub hash: H256,
+    #[serde(default)]
+    pub blobs: Vec<IndexedBlob>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedBlob {
+    pub versioned_hash: H256,
+}
+
+/// A blob as stored by blobscan, with the transactions carrying it
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedBlobDetails {
+    pub commitment: String,
+    #[serde(default)]
+    pub transactions: Vec<IndexedBlobTransaction>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedBlobTransaction {
+    pub tx_hash: H256,
+    pub index: u32,
+    pub block_number: u32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PutBlobRequest {
+    pub blob: Blob,
 }
 
 #[derive(Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ReorgedSlotsRequest {
+    /// Lets blobscan ignore a reorg it already handled
+    pub reorg_id: H256,
     pub reorged_slots: Vec<u32>,
 }
 
@@ -98,11 +339,23 @@ pub struct ReorgedSlotsResponse {
     pub total_updated_slots: u32,
 }
 
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveBlocksRequest {
+    pub slots: Vec<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveBlocksResponse {
+    pub total_removed_blocks: u32,
+}
+
 impl fmt::Debug for Blob {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Blob {{ versioned_hash: {}, commitment: {}, tx_hash: {}, index: {}, data: [omitted] }}",
+            "Blob {{ versioned_hash: {}, commitment: {}, tx_hash: {:?}, index: {}, data: [omitted] }}",
             self.versioned_hash, self.commitment, self.tx_hash, self.index
         )
     }
@@ -114,10 +367,34 @@ impl From<(u32, u32)> for FailedSlotsChunk {
             id: None,
             initial_slot,
             final_slot,
+            failed_slot: None,
+            error: None,
         }
     }
 }
 
+impl Block {
+    /// Nulls the blob fields of a block that predates the blob fork
+    pub fn clear_blob_fields(&mut self) {
+        self.blob_gas_used = None;
+        self.excess_blob_gas = None;
+        self.blob_fee_burned = None;
+    }
+
+    /// Derives the blob fee burned from the block's blob gas. The update fraction is the one of
+    /// the fork the block belongs to
+    pub fn set_blob_fee_burned(&mut self, blob_base_fee_update_fraction: u64) {
+        self.blob_fee_burned = match (self.blob_gas_used, self.excess_blob_gas) {
+            (Some(blob_gas_used), Some(excess_blob_gas)) => Some(calculate_blob_fee_burned(
+                blob_gas_used,
+                excess_blob_gas,
+                blob_base_fee_update_fraction,
+            )),
+            _ => None,
+        };
+    }
+}
+
 impl<'a> TryFrom<(&'a EthersBlock<EthersTransaction>, u32, String)> for Block {
     type Error = anyhow::Error;
 
@@ -135,7 +412,7 @@ impl<'a> TryFrom<(&'a EthersBlock<EthersTransaction>, u32, String)> for Block {
                 .with_context(|| format!("Missing block hash field in execution block {number}"))?,
             timestamp: ethers_block.timestamp,
             slot,
-            blob_gas_used: match ethers_block.other.get("blobGasUsed") {
+            blob_gas_used: Some(match ethers_block.other.get("blobGasUsed") {
                 Some(blob_gas_used) => {
                     let blob_gas_used = blob_gas_used.as_str().with_context(|| {
                         format!("Failed to convert `blobGasUsed` field in execution block {number}")
@@ -149,8 +426,8 @@ impl<'a> TryFrom<(&'a EthersBlock<EthersTransaction>, u32, String)> for Block {
                     // ))
                     U256::zero()
                 }
-            },
-            excess_blob_gas: match ethers_block.other.get("excessBlobGas") {
+            }),
+            excess_blob_gas: Some(match ethers_block.other.get("excessBlobGas") {
                 Some(excess_gas_gas) => {
                     let excess_blob_gas = excess_gas_gas.as_str().with_context(|| {
                         format!(
@@ -166,8 +443,51 @@ impl<'a> TryFrom<(&'a EthersBlock<EthersTransaction>, u32, String)> for Block {
                     // ))
                     U256::zero()
                 }
-            },
+            }),
             validator_pubkey,
+            transactions_count: None,
+            blobs_count: None,
+            indexing_latency_ms: None,
+            sampled: None,
+            blob_fee_burned: None,
+        })
+    }
+}
+
+impl<'a> TryFrom<(&'a ExecutionPayload, u32, String)> for Block {
+    type Error = anyhow::Error;
+
+    fn try_from(
+        (execution_payload, slot, validator_pubkey): (&'a ExecutionPayload, u32, String),
+    ) -> Result<Self, Self::Error> {
+        let number = execution_payload.block_number;
+        // Beacon APIs encode payload quantities as decimal strings
+        let parse_quantity = |value: &Option<String>, field: &str| match value {
+            Some(value) => U256::from_dec_str(value).with_context(|| {
+                format!("Failed to convert `{field}` field in execution payload {number}")
+            }),
+            None => Ok(U256::zero()),
+        };
+
+        Ok(Self {
+            number: U64::from(number),
+            hash: execution_payload.block_hash,
+            timestamp: U256::from(execution_payload.timestamp),
+            slot,
+            blob_gas_used: Some(parse_quantity(
+                &execution_payload.blob_gas_used,
+                "blob_gas_used",
+            )?),
+            excess_blob_gas: Some(parse_quantity(
+                &execution_payload.excess_blob_gas,
+                "excess_blob_gas",
+            )?),
+            validator_pubkey,
+            transactions_count: None,
+            blobs_count: None,
+            indexing_latency_ms: None,
+            sampled: None,
+            blob_fee_burned: None,
         })
     }
 }
@@ -215,34 +535,52 @@ impl<'a> TryFrom<(&'a EthersTransaction, &'a EthersBlock<EthersTransaction>)> fo
     }
 }
 
-impl<'a> TryFrom<(&'a BeaconBlob, u32, H256)> for Blob {
-    type Error = anyhow::Error;
+impl Blob {
+    /// `tx_index` is what gets stored, `block_index` is only checked against the sidecar, so a
+    /// blob whose sidecar sits at another position than its commitment is rejected instead of
+    /// being stored under the wrong index
+    pub fn new(
+        blob_data: &BeaconBlob,
+        versioned_hash: H256,
+        tx_hash: Option<H256>,
+        tx_index: BlobTxIndex,
+        block_index: BlobBlockIndex,
+    ) -> Result<Self> {
+        let sidecar_index = blob_data
+            .index
+            .parse::<u32>()
+            .with_context(|| format!("Invalid blob sidecar index {}", blob_data.index))?;
+
+        if sidecar_index != block_index.0 {
+            return Err(anyhow::anyhow!(
+                "Blob {versioned_hash:?} sidecar index {sidecar_index} doesn't match its commitment's position {} in the block",
+                block_index.0
+            ));
+        }
 
-    fn try_from(
-        (blob_data, index, tx_hash): (&'a BeaconBlob, u32, H256),
-    ) -> Result<Self, Self::Error> {
         Ok(Self {
             tx_hash,
-            index,
+            index: tx_index.0,
             commitment: blob_data.kzg_commitment.clone(),
             proof: blob_data.kzg_proof.clone(),
             data: blob_data.blob.clone(),
-            versioned_hash: calculate_versioned_hash(&blob_data.kzg_commitment)?,
+            versioned_hash,
         })
     }
 }
 
-impl<'a> From<(&'a BeaconBlob, &'a H256, usize, &'a H256)> for Blob {
-    fn from(
-        (blob_data, versioned_hash, index, tx_hash): (&'a BeaconBlob, &'a H256, usize, &'a H256),
+impl BlobMetadata {
+    pub fn new(
+        commitment: &str,
+        versioned_hash: H256,
+        tx_hash: H256,
+        tx_index: BlobTxIndex,
     ) -> Self {
         Self {
-            tx_hash: *tx_hash,
-            index: index as u32,
-            commitment: blob_data.kzg_commitment.clone(),
-            proof: blob_data.kzg_proof.clone(),
-            data: blob_data.blob.clone(),
-            versioned_hash: *versioned_hash,
+            tx_hash,
+            index: tx_index.0,
+            commitment: commitment.to_string(),
+            versioned_hash,
         }
     }
 }
@@ -266,3 +604,284 @@ impl From<BlockchainSyncState> for BlockchainSyncStateRequest {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use ethers::types::{Bytes, H256, U256, U64};
+
+    use super::{
+        Blob, BlobBlockIndex, BlobTxIndex, Block, ChainConfig, IndexRequest, ReorgedSlotsRequest,
+        SkippedSlotRange, SkippedSlotsRequest, SlotSkipReason,
+    };
+    use crate::{
+        clients::beacon::{columns::ColumnLayout, types::Blob as BeaconBlob},
+        utils::web3::CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION,
+    };
+
+    fn beacon_blob() -> BeaconBlob {
+        BeaconBlob {
+            index: "1".to_string(),
+            kzg_commitment: "0xc0".to_string(),
+            kzg_proof: "0xc1".to_string(),
+            blob: Bytes::from(vec![0u8; 4]),
+        }
+    }
+
+    #[test]
+    fn blob_without_transaction_omits_the_tx_hash() {
+        let blob = Blob::new(
+            &beacon_blob(),
+            H256::repeat_byte(1),
+            None,
+            BlobTxIndex(1),
+            BlobBlockIndex(1),
+        )
+        .unwrap();
+        let json = serde_json::to_value(&blob).unwrap();
+
+        assert!(json.get("txHash").is_none());
+        assert_eq!(json["index"], 1);
+    }
+
+    #[test]
+    fn blob_with_transaction_serializes_the_tx_hash() {
+        let tx_hash = H256::repeat_byte(2);
+        let blob = Blob::new(
+            &beacon_blob(),
+            H256::repeat_byte(1),
+            Some(tx_hash),
+            BlobTxIndex(0),
+            BlobBlockIndex(1),
+        )
+        .unwrap();
+        let json = serde_json::to_value(&blob).unwrap();
+
+        assert_eq!(json["txHash"], serde_json::to_value(tx_hash).unwrap());
+        assert_eq!(json["index"], 0);
+    }
+
+    #[test]
+    fn test_reorged_slots_request_wire_format() {
+        let request = ReorgedSlotsRequest {
+            reorg_id: H256::repeat_byte(0xab),
+            reorged_slots: vec![10, 11],
+        };
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "reorgId": format!("0x{}", "ab".repeat(32)),
+                "reorgedSlots": [10, 11],
+            })
+        );
+    }
+
+    fn block() -> Block {
+        Block {
+            number: U64::from(100),
+            hash: H256::repeat_byte(0xbb),
+            timestamp: U256::from(1_700_000_000),
+            slot: 200,
+            blob_gas_used: Some(U256::zero()),
+            excess_blob_gas: Some(U256::zero()),
+            validator_pubkey: "0xa0".to_string(),
+            transactions_count: None,
+            blobs_count: None,
+            indexing_latency_ms: None,
+            sampled: None,
+            blob_fee_burned: None,
+        }
+    }
+
+    #[test]
+    fn test_block_omits_unset_entity_counts() {
+        let json = serde_json::to_value(block()).unwrap();
+
+        assert!(json.get("transactionsCount").is_none());
+        assert!(json.get("blobsCount").is_none());
+
+        let mut block = block();
+
+        block.transactions_count = Some(3);
+        block.blobs_count = Some(0);
+
+        let json = serde_json::to_value(block).unwrap();
+
+        assert_eq!(json["transactionsCount"], 3);
+        assert_eq!(json["blobsCount"], 0);
+    }
+
+    #[test]
+    fn test_pre_fork_block_submits_null_blob_fields() {
+        let mut block = block();
+
+        block.set_blob_fee_burned(CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION);
+        block.clear_blob_fields();
+
+        let json = serde_json::to_value(block).unwrap();
+
+        assert_eq!(json["blobGasUsed"], serde_json::Value::Null);
+        assert_eq!(json["excessBlobGas"], serde_json::Value::Null);
+        assert!(json.get("blobFeeBurned").is_none());
+    }
+
+    #[test]
+    fn test_post_fork_block_submits_its_blob_fields() {
+        let mut block = block();
+
+        block.blob_gas_used = Some(U256::from(131072));
+        block.set_blob_fee_burned(CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION);
+
+        let json = serde_json::to_value(block).unwrap();
+
+        assert_eq!(json["blobGasUsed"], "0x20000");
+        assert_eq!(json["excessBlobGas"], "0x0");
+        // One blob at the minimum blob gas price of 1 wei
+        assert_eq!(json["blobFeeBurned"], "0x20000");
+    }
+
+    #[test]
+    fn test_blob_fee_burned_needs_both_blob_gas_fields() {
+        let mut block = block();
+
+        block.excess_blob_gas = None;
+        block.set_blob_fee_burned(CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION);
+
+        assert_eq!(block.blob_fee_burned, None);
+    }
+
+    #[test]
+    fn test_index_request_is_only_flagged_partial_when_set() {
+        let request = IndexRequest {
+            block: block(),
+            transactions: vec![],
+            blobs: vec![],
+            partial: None,
+        };
+
+        assert!(serde_json::to_value(&request)
+            .unwrap()
+            .get("partial")
+            .is_none());
+
+        let request = IndexRequest {
+            partial: Some(true),
+            ..request
+        };
+        let json = serde_json::to_value(&request).unwrap();
+
+        assert_eq!(json["partial"], true);
+        assert_eq!(json["transactions"], serde_json::json!([]));
+        assert_eq!(json["blobs"], serde_json::json!([]));
+    }
+
+    fn chain_config(max_blobs_per_block: Option<u32>) -> ChainConfig {
+        ChainConfig {
+            chain_id: 17000,
+            genesis_time: 1695902400,
+            seconds_per_slot: 12,
+            slots_per_epoch: 32,
+            max_blobs_per_block,
+            fork_epochs: BTreeMap::from([
+                ("deneb".to_string(), 29696),
+                ("electra".to_string(), 115968),
+            ]),
+            column_layouts: BTreeMap::from([(
+                0,
+                ColumnLayout {
+                    columns_total: 128,
+                    blobs_per_column_cell: 1,
+                    extension_factor: 2,
+                },
+            )]),
+            blob_base_fee_update_fractions: BTreeMap::from([(29696, 3338477)]),
+        }
+    }
+
+    #[test]
+    fn test_chain_config_wire_format() {
+        assert_eq!(
+            serde_json::to_value(chain_config(Some(9))).unwrap(),
+            serde_json::json!({
+                "chainId": 17000,
+                "genesisTime": 1695902400,
+                "secondsPerSlot": 12,
+                "slotsPerEpoch": 32,
+                "maxBlobsPerBlock": 9,
+                "forkEpochs": { "deneb": 29696, "electra": 115968 },
+            })
+        );
+    }
+
+    #[test]
+    fn test_chain_config_omits_unknown_max_blobs_per_block() {
+        let json = serde_json::to_value(chain_config(None)).unwrap();
+
+        assert!(json.get("maxBlobsPerBlock").is_none());
+        assert_eq!(json["chainId"], 17000);
+    }
+
+    #[test]
+    fn test_slot_skip_reason_wire_form() {
+        let reasons = [
+            (SlotSkipReason::Genesis, "genesis"),
+            (SlotSkipReason::MissedSlot, "missed_slot"),
+            (SlotSkipReason::NoExecutionPayload, "no_execution_payload"),
+            (SlotSkipReason::EmptyBlock, "empty_block"),
+            (SlotSkipReason::NoBlobs, "no_blobs"),
+            (SlotSkipReason::NotSampled, "not_sampled"),
+            (SlotSkipReason::MissingProposer, "missing_proposer"),
+            (SlotSkipReason::MissingBlobData, "missing_blob_data"),
+            (SlotSkipReason::BlocksMismatch, "blocks_mismatch"),
+            (SlotSkipReason::OperatorExclusion, "operator_exclusion"),
+        ];
+
+        for (reason, wire_form) in reasons {
+            assert_eq!(
+                serde_json::to_value(reason).unwrap(),
+                serde_json::json!(wire_form)
+            );
+            assert_eq!(reason.as_str(), wire_form);
+        }
+    }
+
+    #[test]
+    fn test_skipped_slots_request_wire_format() {
+        let request = SkippedSlotsRequest {
+            skipped_slots: vec![
+                SkippedSlotRange {
+                    from_slot: 10,
+                    to_slot: 40,
+                    reason: SlotSkipReason::NotSampled,
+                },
+                SkippedSlotRange::new(41, SlotSkipReason::MissedSlot),
+            ],
+        };
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "skippedSlots": [
+                    { "fromSlot": 10, "toSlot": 40, "reason": "not_sampled" },
+                    { "fromSlot": 41, "toSlot": 41, "reason": "missed_slot" },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_skipped_slot_ranges_grow_both_ways() {
+        let mut range = SkippedSlotRange::new(10, SlotSkipReason::NotSampled);
+
+        assert!(range.try_extend(11, SlotSkipReason::NotSampled));
+        assert!(range.try_extend(9, SlotSkipReason::NotSampled));
+        assert_eq!((range.from_slot, range.to_slot), (9, 11));
+
+        assert!(!range.try_extend(12, SlotSkipReason::MissedSlot));
+        assert!(!range.try_extend(13, SlotSkipReason::NotSampled));
+        assert!(!range.try_extend(10, SlotSkipReason::NotSampled));
+        assert_eq!((range.from_slot, range.to_slot), (9, 11));
+    }
+}
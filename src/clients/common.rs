@@ -1,7 +1,29 @@
-use std::{fmt::Display, str::FromStr};
+use std::{
+    fmt::Display,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use backoff::{backoff::Backoff, ExponentialBackoff};
+use chrono::{DateTime, Utc};
 
 use serde::Deserialize;
 
+use crate::utils::clock::Clock;
+
+/// HTTP protocol used by a client
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpVersion {
+    /// Negotiated with the server (HTTP/1.1, or HTTP/2 through TLS ALPN)
+    #[default]
+    Auto,
+    /// Force HTTP/1.1
+    Http1,
+    /// Force HTTP/2, using prior knowledge so it also works over cleartext
+    Http2,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 #[serde(untagged)]
 pub enum NumericOrTextCode {
@@ -39,6 +61,200 @@ pub enum ClientError {
     /// Serde Json deser Error
     #[error("{0}")]
     SerdeError(#[from] serde_json::Error),
+
+    /// Every retry attempt failed
+    #[error("{source} after {} attempts ({})", attempts.len(), format_attempts(attempts))]
+    RetriesExhausted {
+        attempts: Vec<AttemptRecord>,
+        source: reqwest::Error,
+    },
+}
+
+impl ClientError {
+    /// Whether the server couldn't be reached at all, as opposed to a request it rejected
+    pub fn is_connectivity_error(&self) -> bool {
+        match self {
+            Self::Reqwest(error) | Self::RetriesExhausted { source: error, .. } => {
+                error.is_connect() || error.is_timeout()
+            }
+            _ => false,
+        }
+    }
+
+    /// Every failed attempt, in order. Empty unless retries ran out
+    pub fn attempts(&self) -> &[AttemptRecord] {
+        match self {
+            Self::RetriesExhausted { attempts, .. } => attempts,
+            _ => &[],
+        }
+    }
+}
+
+/// A failed request attempt, kept so exhausted retries can be inspected after the fact
+#[derive(Debug, Clone)]
+pub struct AttemptRecord {
+    pub endpoint: String,
+    pub timestamp: DateTime<Utc>,
+    pub duration: Duration,
+    pub error_class: &'static str,
+    pub status: Option<u16>,
+}
+
+impl AttemptRecord {
+    pub fn new(
+        endpoint: &str,
+        started_at: SystemTime,
+        failed_at: SystemTime,
+        error: &reqwest::Error,
+    ) -> Self {
+        let error_class = if error.is_timeout() {
+            "timeout"
+        } else if error.is_connect() {
+            "connect"
+        } else if error.is_status() {
+            "status"
+        } else if error.is_decode() {
+            "decode"
+        } else if error.is_body() {
+            "body"
+        } else {
+            "request"
+        };
+
+        Self {
+            endpoint: endpoint.to_string(),
+            timestamp: failed_at.into(),
+            duration: failed_at.duration_since(started_at).unwrap_or_default(),
+            error_class,
+            status: error.status().map(|status| status.as_u16()),
+        }
+    }
+}
+
+fn format_attempts(attempts: &[AttemptRecord]) -> String {
+    attempts
+        .iter()
+        .enumerate()
+        .map(|(i, attempt)| {
+            let status = attempt
+                .status
+                .map(|status| format!(" {status}"))
+                .unwrap_or_default();
+
+            format!(
+                "#{} {}{status} in {}ms",
+                i + 1,
+                attempt.error_class,
+                attempt.duration.as_millis()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Every field of every attempt, for the logs that reach Sentry. `Display` keeps the compact form
+pub fn describe_attempts(attempts: &[AttemptRecord]) -> String {
+    attempts
+        .iter()
+        .enumerate()
+        .map(|(i, attempt)| {
+            let status = attempt
+                .status
+                .map(|status| status.to_string())
+                .unwrap_or_else(|| "none".to_string());
+
+            format!(
+                "#{} {} at {}: {} (status {status}) after {}ms",
+                i + 1,
+                attempt.endpoint,
+                attempt.timestamp.to_rfc3339(),
+                attempt.error_class,
+                attempt.duration.as_millis()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Which failed requests are sent again
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RetryPolicy {
+    /// Every request that failed before a response arrived. Only safe for reads
+    AnyFailure,
+    /// Only requests that never reached the server, so a write is never applied twice
+    ConnectFailure,
+}
+
+impl RetryPolicy {
+    fn retries(self, error: &reqwest::Error) -> bool {
+        match self {
+            Self::AnyFailure => true,
+            Self::ConnectFailure => error.is_connect(),
+        }
+    }
+}
+
+/// Sends the request, retrying it with the backoff while the policy allows. The backoff delays
+/// and attempt timings go through `clock`. When the request isn't sent, the error carries every
+/// failed attempt in order.
+pub async fn send_with_retries(
+    req: reqwest::RequestBuilder,
+    mut exp_backoff: ExponentialBackoff,
+    policy: RetryPolicy,
+    clock: &dyn Clock,
+    endpoint: &str,
+    mut on_retry: impl FnMut(&reqwest::Error, Duration),
+) -> ClientResult<reqwest::Response> {
+    let mut attempts = vec![];
+    let started_at = clock.now();
+
+    exp_backoff.reset();
+
+    loop {
+        let attempt_started_at = clock.now();
+        let error = match req
+            .try_clone()
+            .expect("request bodies are buffered")
+            .send()
+            .await
+        {
+            Ok(resp) => return Ok(resp),
+            Err(error) => error,
+        };
+
+        attempts.push(AttemptRecord::new(
+            endpoint,
+            attempt_started_at,
+            clock.now(),
+            &error,
+        ));
+
+        // The backoff's own elapsed time runs on the system clock, so its limit is checked here
+        let delay = policy
+            .retries(&error)
+            .then(|| exp_backoff.next_backoff())
+            .flatten()
+            .filter(|delay| match exp_backoff.max_elapsed_time {
+                Some(max_elapsed_time) => {
+                    clock.elapsed_since(started_at) + *delay <= max_elapsed_time
+                }
+                None => true,
+            });
+
+        match delay {
+            Some(delay) => {
+                on_retry(&error, delay);
+
+                clock.sleep(delay).await;
+            }
+            None => {
+                return Err(ClientError::RetriesExhausted {
+                    attempts,
+                    source: error,
+                })
+            }
+        }
+    }
 }
 
 /// API Response
@@ -55,6 +271,51 @@ pub enum ClientResponse<T> {
 
 pub type ClientResult<T> = Result<T, ClientError>;
 
+/// Notified by the request macros once a response's headers arrive
+pub trait ResponseObserver {
+    /// `server_timing` is the raw `Server-Timing` header, when the server sent one
+    fn observe(&self, server_timing: Option<&str>, elapsed: Duration);
+
+    /// For observers that read other headers than `Server-Timing`
+    fn observe_headers(&self, _headers: &reqwest::header::HeaderMap) {}
+}
+
+/// Observes nothing, for clients that don't track their response times
+impl ResponseObserver for () {
+    fn observe(&self, _server_timing: Option<&str>, _elapsed: Duration) {}
+}
+
+impl<T: ResponseObserver> ResponseObserver for std::sync::Arc<T> {
+    fn observe(&self, server_timing: Option<&str>, elapsed: Duration) {
+        T::observe(self, server_timing, elapsed)
+    }
+
+    fn observe_headers(&self, headers: &reqwest::header::HeaderMap) {
+        T::observe_headers(self, headers)
+    }
+}
+
+/// Builds a reqwest client for the given protocol. HTTP/2 clients use an adaptive flow-control
+/// window and keep-alive pings so many multiplexed streams can share a single connection.
+pub fn build_http_client(
+    http_version: HttpVersion,
+    timeout: Duration,
+) -> reqwest::Result<reqwest::Client> {
+    let builder = reqwest::Client::builder().timeout(timeout);
+
+    let builder = match http_version {
+        HttpVersion::Auto => builder,
+        HttpVersion::Http1 => builder.http1_only(),
+        HttpVersion::Http2 => builder
+            .http2_prior_knowledge()
+            .http2_adaptive_window(true)
+            .http2_keep_alive_interval(Duration::from_secs(30))
+            .http2_keep_alive_while_idle(true),
+    };
+
+    builder.build()
+}
+
 impl<T> ClientResponse<T> {
     pub(crate) fn into_client_result(self) -> ClientResult<Option<T>> {
         match self {
@@ -107,3 +368,156 @@ impl Display for ErrorResponse {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use backoff::ExponentialBackoffBuilder;
+    use chrono::{TimeZone, Utc};
+
+    use super::{
+        build_http_client, describe_attempts, format_attempts, send_with_retries, AttemptRecord,
+        ClientError, HttpVersion, RetryPolicy,
+    };
+    use crate::{
+        clients::test_server::{self, Reply},
+        utils::clock::{Clock, MockClock},
+    };
+
+    fn attempts() -> Vec<AttemptRecord> {
+        vec![
+            AttemptRecord {
+                endpoint: "http://blobscan/indexer/blobs".to_string(),
+                timestamp: Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+                duration: Duration::from_millis(12),
+                error_class: "connect",
+                status: None,
+            },
+            AttemptRecord {
+                endpoint: "http://blobscan/indexer/blobs".to_string(),
+                timestamp: Utc.timestamp_opt(1_700_000_001, 0).unwrap(),
+                duration: Duration::from_millis(3000),
+                error_class: "status",
+                status: Some(503),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_format_attempts_is_compact() {
+        assert_eq!(
+            format_attempts(&attempts()),
+            "#1 connect in 12ms, #2 status 503 in 3000ms"
+        );
+    }
+
+    #[test]
+    fn test_describe_attempts_has_every_field() {
+        assert_eq!(
+            describe_attempts(&attempts()),
+            "#1 http://blobscan/indexer/blobs at 2023-11-14T22:13:20+00:00: connect (status none) after 12ms; #2 http://blobscan/indexer/blobs at 2023-11-14T22:13:21+00:00: status (status 503) after 3000ms"
+        );
+    }
+
+    #[test]
+    fn test_http_version_names() {
+        let versions = [
+            ("auto", HttpVersion::Auto),
+            ("http1", HttpVersion::Http1),
+            ("http2", HttpVersion::Http2),
+        ];
+
+        for (name, version) in versions {
+            assert_eq!(
+                serde_json::from_value::<HttpVersion>(serde_json::json!(name)).unwrap(),
+                version
+            );
+        }
+
+        assert!(serde_json::from_value::<HttpVersion>(serde_json::json!("http3")).is_err());
+        assert_eq!(HttpVersion::default(), HttpVersion::Auto);
+    }
+
+    async fn response_version(http_version: HttpVersion) -> reqwest::Version {
+        let (base_url, server) =
+            test_server::serve(vec![Reply::Respond("200 OK", "{}".to_string())]).await;
+        let client = build_http_client(http_version, Duration::from_secs(5)).unwrap();
+
+        let response = client.get(base_url.as_str()).send().await.unwrap();
+
+        server.await.unwrap();
+
+        response.version()
+    }
+
+    #[tokio::test]
+    async fn test_auto_and_http1_clients_speak_http1_over_cleartext() {
+        assert_eq!(
+            response_version(HttpVersion::Auto).await,
+            reqwest::Version::HTTP_11
+        );
+        assert_eq!(
+            response_version(HttpVersion::Http1).await,
+            reqwest::Version::HTTP_11
+        );
+    }
+
+    #[tokio::test]
+    async fn test_http2_client_uses_prior_knowledge() {
+        let (base_url, server) = test_server::serve(vec![Reply::Close]).await;
+        let client = build_http_client(HttpVersion::Http2, Duration::from_secs(5)).unwrap();
+
+        assert!(client.get(base_url.as_str()).send().await.is_err());
+
+        // The HTTP/2 connection preface starts with `PRI * HTTP/2.0`
+        let requests = server.await.unwrap();
+
+        assert_eq!(requests[0].method, "PRI");
+        assert_eq!(requests[0].path, "*");
+    }
+
+    #[tokio::test]
+    async fn test_retries_sleep_and_time_attempts_on_the_clock() {
+        // Gone before the first attempt, so every connection is refused
+        let (base_url, server) = test_server::serve(vec![]).await;
+
+        server.await.unwrap();
+
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let clock = MockClock::new(start);
+        let exp_backoff = ExponentialBackoffBuilder::new()
+            .with_initial_interval(Duration::from_secs(1))
+            .with_multiplier(2.0)
+            .with_randomization_factor(0.0)
+            .with_max_elapsed_time(Some(Duration::from_secs(10)))
+            .build();
+        let req = reqwest::Client::new().get(base_url.as_str());
+
+        let error = send_with_retries(
+            req,
+            exp_backoff,
+            RetryPolicy::ConnectFailure,
+            &clock,
+            "endpoint",
+            |_, _| {},
+        )
+        .await
+        .unwrap_err();
+
+        let ClientError::RetriesExhausted { attempts, .. } = &error else {
+            panic!("expected exhausted retries, got {error:?}");
+        };
+        let timestamps = attempts
+            .iter()
+            .map(|attempt| attempt.timestamp.timestamp() - 1_700_000_000)
+            .collect::<Vec<_>>();
+
+        // Waits of 1, 2 and 4 seconds, as the next 8 would run past the 10 seconds allowed
+        assert_eq!(timestamps, vec![0, 1, 3, 7]);
+        assert_eq!(clock.now(), start + Duration::from_secs(7));
+        assert!(attempts
+            .iter()
+            .all(|attempt| attempt.error_class == "connect" && attempt.endpoint == "endpoint"));
+    }
+}
@@ -2,13 +2,18 @@
 /// Make a GET request sending and expecting JSON.
 /// if JSON deser fails, emit a `WARN` level tracing event
 macro_rules! json_get {
-    ($client:expr, $url:expr, $expected:ty, $exp_backoff:expr) => {
-        json_get!($client, $url, $expected, "", $exp_backoff)
+    (@observed $observer:expr, $client:expr, $url:expr, $expected:ty, $exp_backoff:expr, $clock:expr, $body_trace:expr) => {
+        json_get!(@observed $observer, $client, $url, $expected, "", $exp_backoff, $clock, $body_trace)
     };
-    ($client:expr, $url:expr, $expected:ty, $auth_token:expr, $exp_backoff: expr) => {{
+    (@observed $observer:expr, $client:expr, $url:expr, $expected:ty, $auth_token:expr, $exp_backoff: expr, $clock:expr, $body_trace:expr) => {{
         let url = $url.clone();
+        let clock: &dyn $crate::utils::clock::Clock = $clock;
 
-        tracing::trace!(method = "GET", url = url.as_str(), "Dispatching API request");
+        tracing::trace!(
+            method = "GET",
+            url = %$crate::utils::redaction::redact_url(&url),
+            "Dispatching API request"
+        );
 
         let mut req = $client.get($url);
 
@@ -16,21 +21,23 @@ macro_rules! json_get {
           req = req.bearer_auth($auth_token);
         }
 
-        let resp = if $exp_backoff.is_some() {
-            match backoff::future::retry_notify(
-                $exp_backoff.unwrap(),
-                || {
-                    let req = req.try_clone().unwrap();
+        let started_at = clock.now();
+        let exp_backoff: Option<backoff::ExponentialBackoff> = $exp_backoff;
 
-                    async move { req.send().await.map_err(|err| err.into()) }
-                },
-                |error, duration: std::time::Duration| {
+        let resp = if let Some(exp_backoff) = exp_backoff {
+            match $crate::clients::common::send_with_retries(
+                req,
+                exp_backoff,
+                $crate::clients::common::RetryPolicy::AnyFailure,
+                clock,
+                &$crate::utils::redaction::redact_url(&url),
+                |error, duration| {
                     let duration = duration.as_secs();
 
                     tracing::warn!(
                         method = "GET",
-                        url = %url,
-                        ?error,
+                        url = %$crate::utils::redaction::redact_url(&url),
+                        error = %$crate::utils::redaction::redact_error(error),
                         "Failed to send request. Retrying in {duration} seconds…"
                     );
                 },
@@ -40,12 +47,13 @@ macro_rules! json_get {
                 Err(error) => {
                     tracing::warn!(
                         method = "GET",
-                        url = %url,
-                        ?error,
+                        url = %$crate::utils::redaction::redact_url(&url),
+                        error = %$crate::utils::redaction::redact_error(&error),
+                        attempts = %$crate::clients::common::describe_attempts(error.attempts()),
                         "Failed to send request. All retries failed"
                     );
 
-                    return Err(error.into())
+                    return Err(error)
                 }
             }
         } else {
@@ -53,8 +61,8 @@ macro_rules! json_get {
                 Err(error) => {
                     tracing::warn!(
                         method = "GET",
-                        url = %url,
-                        ?error,
+                        url = %$crate::utils::redaction::redact_url(&url),
+                        error = %$crate::utils::redaction::redact_error(&error),
                         "Failed to send request"
                     );
 
@@ -66,19 +74,47 @@ macro_rules! json_get {
 
         let status = resp.status();
 
+        $crate::clients::common::ResponseObserver::observe(
+            &$observer,
+            resp.headers()
+                .get("server-timing")
+                .and_then(|value| value.to_str().ok()),
+            clock.elapsed_since(started_at),
+        );
+        $crate::clients::common::ResponseObserver::observe_headers(&$observer, resp.headers());
+
+        tracing::debug!(
+            method = "GET",
+            url = %$crate::utils::redaction::redact_url(&url),
+            status = status.as_u16(),
+            http_version = ?resp.version(),
+            elapsed_ms = clock.elapsed_since(started_at).as_millis() as u64,
+            "Received API response"
+        );
+
         if status.as_u16() == 404 {
           return Ok(None)
         };
 
         let text = resp.text().await?;
+
+        if let Some(body) = $body_trace.render(text.as_bytes()) {
+            tracing::trace!(
+                method = "GET",
+                url = %$crate::utils::redaction::redact_url(&url),
+                body,
+                "API response body"
+            );
+        }
+
         let result: Result<$crate::clients::common::ClientResponse<$expected>, _> = serde_json::from_str(&text);
 
         match result {
             Err(e) => {
                 tracing::warn!(
                     method = "GET",
-                    url = %url,
-                    response = text.as_str(),
+                    url = %$crate::utils::redaction::redact_url(&url),
+                    response = $body_trace.excerpt(text.as_bytes()),
                     "Unexpected response from server"
                 );
 
@@ -89,55 +125,157 @@ macro_rules! json_get {
             }
         }
     }};
+    ($client:expr, $url:expr, $expected:ty, $exp_backoff:expr, $clock:expr, $body_trace:expr) => {
+        json_get!(@observed (), $client, $url, $expected, "", $exp_backoff, $clock, $body_trace)
+    };
+    ($client:expr, $url:expr, $expected:ty, $auth_token:expr, $exp_backoff: expr, $clock:expr, $body_trace:expr) => {
+        json_get!(@observed (), $client, $url, $expected, $auth_token, $exp_backoff, $clock, $body_trace)
+    };
 }
 
 #[macro_export]
 /// Make a PUT request sending JSON.
 /// if JSON deser fails, emit a `WARN` level tracing event
 macro_rules! json_put {
-    ($client:expr, $url:expr, $auth_token:expr, $body:expr) => {
-        json_put!($client, $url, (), $auth_token, $body)
+    (@observed $observer:expr, $client:expr, $url:expr, $auth_token:expr, $body:expr, $exp_backoff:expr, $clock:expr, $body_trace:expr) => {
+        json_put!(@observed $observer, $client, $url, (), $auth_token, $body, $exp_backoff, $clock, $body_trace)
     };
-    ($client:expr, $url:expr, $expected:ty, $auth_token:expr, $body:expr) => {{
-        let url = $url.clone();
-        let body = format!("{:?}", $body);
+    (@observed $observer:expr, $client:expr, $url:expr, $expected:ty, $auth_token:expr, $body:expr, $exp_backoff:expr, $clock:expr, $body_trace:expr) => {
+        $crate::json_send!(@observed $observer, $client, reqwest::Method::PUT, $url, $expected, $auth_token, $body, $exp_backoff, $clock, $body_trace)
+    };
+    ($client:expr, $url:expr, $auth_token:expr, $body:expr, $exp_backoff:expr, $clock:expr, $body_trace:expr) => {
+        json_put!(@observed (), $client, $url, (), $auth_token, $body, $exp_backoff, $clock, $body_trace)
+    };
+    ($client:expr, $url:expr, $expected:ty, $auth_token:expr, $body:expr, $exp_backoff:expr, $clock:expr, $body_trace:expr) => {
+        json_put!(@observed (), $client, $url, $expected, $auth_token, $body, $exp_backoff, $clock, $body_trace)
+    };
+}
 
-        tracing::trace!(method = "PUT", url = url.as_str(), body, "Dispatching API client request");
+#[macro_export]
+/// Make an authenticated request with the given method, sending JSON. Unlike `json_get!`, only
+/// requests that never reached the server are retried with the given backoff, as a write that
+/// timed out may still have been applied. Every failed attempt is recorded either way.
+/// if JSON deser fails, emit a `WARN` level tracing event
+macro_rules! json_send {
+    (@observed $observer:expr, $client:expr, $method:expr, $url:expr, $expected:ty, $auth_token:expr, $body:expr, $exp_backoff:expr, $clock:expr, $body_trace:expr) => {{
+        let method: reqwest::Method = $method;
+        let url = $url.clone();
+        let clock: &dyn $crate::utils::clock::Clock = $clock;
+        // Serialized once, so the traced body is taken from the bytes that are sent
+        let body_bytes = serde_json::to_vec($body)?;
+        let body = $body_trace.render(&body_bytes);
 
+        tracing::trace!(
+            method = %method,
+            url = %$crate::utils::redaction::redact_url(&url),
+            body = body.as_deref(),
+            "Dispatching API client request"
+        );
 
-        let resp = match $client
-            .put($url)
+        let req = $client
+            .request(method.clone(), $url)
             .bearer_auth($auth_token)
-            .json($body)
-            .send()
-            .await {
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body_bytes);
+        let exp_backoff: Option<backoff::ExponentialBackoff> = $exp_backoff;
+
+        let started_at = clock.now();
+
+        let resp = match exp_backoff {
+            Some(exp_backoff) => {
+                match $crate::clients::common::send_with_retries(
+                    req,
+                    exp_backoff,
+                    $crate::clients::common::RetryPolicy::ConnectFailure,
+                    clock,
+                    &$crate::utils::redaction::redact_url(&url),
+                    |error, duration| {
+                        let duration = duration.as_secs();
+
+                        tracing::warn!(
+                            method = %method,
+                            url = %$crate::utils::redaction::redact_url(&url),
+                            error = %$crate::utils::redaction::redact_error(error),
+                            "Failed to send request. Retrying in {duration} seconds…"
+                        );
+                    },
+                )
+                .await {
+                    Ok(resp) => resp,
+                    Err(error) => {
+                        tracing::warn!(
+                            method = %method,
+                            url = %$crate::utils::redaction::redact_url(&url),
+                            body = body.as_deref(),
+                            error = %$crate::utils::redaction::redact_error(&error),
+                            attempts = %$crate::clients::common::describe_attempts(error.attempts()),
+                            "Failed to send request. All retries failed"
+                        );
+
+                        return Err(error)
+                    }
+                }
+            }
+            None => match req.send().await {
                 Err(error) => {
                     tracing::warn!(
-                        method = "PUT",
-                        url = %url,
-                        body = body,
-                        ?error,
+                        method = %method,
+                        url = %$crate::utils::redaction::redact_url(&url),
+                        body = body.as_deref(),
+                        error = %$crate::utils::redaction::redact_error(&error),
                         "Failed to send request"
                     );
 
                     return Err(error.into())
                 },
                 Ok(resp) => resp
-            };
+            },
+        };
+
+        $crate::clients::common::ResponseObserver::observe(
+            &$observer,
+            resp.headers()
+                .get("server-timing")
+                .and_then(|value| value.to_str().ok()),
+            clock.elapsed_since(started_at),
+        );
+        $crate::clients::common::ResponseObserver::observe_headers(&$observer, resp.headers());
+
+        tracing::debug!(
+            method = %method,
+            url = %$crate::utils::redaction::redact_url(&url),
+            status = resp.status().as_u16(),
+            http_version = ?resp.version(),
+            elapsed_ms = clock.elapsed_since(started_at).as_millis() as u64,
+            "Received API response"
+        );
 
         let text = resp.text().await?;
+
+        if let Some(body) = $body_trace.render(text.as_bytes()) {
+            tracing::trace!(
+                method = %method,
+                url = %$crate::utils::redaction::redact_url(&url),
+                body,
+                "API response body"
+            );
+        }
+
         let result: $crate::clients::common::ClientResponse<$expected> = text.parse()?;
 
         if result.is_err() {
             tracing::warn!(
-                method = "PUT",
-                url = %url,
-                body,
-                response = text.as_str(),
+                method = %method,
+                url = %$crate::utils::redaction::redact_url(&url),
+                body = body.as_deref(),
+                response = $body_trace.excerpt(text.as_bytes()),
                 "Unexpected response from server"
             );
         }
 
         result.into_client_result()
     }};
+    ($client:expr, $method:expr, $url:expr, $expected:ty, $auth_token:expr, $body:expr, $exp_backoff:expr, $clock:expr, $body_trace:expr) => {
+        $crate::json_send!(@observed (), $client, $method, $url, $expected, $auth_token, $body, $exp_backoff, $clock, $body_trace)
+    };
 }
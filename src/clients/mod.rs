@@ -3,3 +3,5 @@ mod macros;
 pub mod beacon;
 pub mod blobscan;
 pub mod common;
+#[cfg(test)]
+pub mod test_server;
@@ -0,0 +1,154 @@
+use std::net::SocketAddr;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::utils::url::EndpointUrl;
+
+/// A raw request received by the test server
+#[derive(Debug, Clone)]
+pub struct ReceivedRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl ReceivedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// How the test server answers a connection
+#[derive(Debug, Clone)]
+pub enum Reply {
+    /// Responds with a status line such as `"200 OK"` and a JSON body
+    Respond(&'static str, String),
+    /// Like [`Reply::Respond`], with extra headers
+    RespondWithHeaders(&'static str, Vec<(&'static str, String)>, String),
+    /// Closes the connection without responding
+    Close,
+}
+
+/// Answers one connection per reply, in order, and returns the requests received. The listener
+/// is dropped after the last reply, so any later connection is refused
+pub async fn serve(replies: Vec<Reply>) -> (EndpointUrl, JoinHandle<Vec<ReceivedRequest>>) {
+    serve_until(replies, CancellationToken::new()).await
+}
+
+/// Like [`serve`], but also stops once `stop` is cancelled, for tests where some of the replies
+/// may never be asked for. Requests are recorded before they're answered, so cancelling after
+/// the client got its responses returns every request it sent
+pub async fn serve_until(
+    replies: Vec<Reply>,
+    stop: CancellationToken,
+) -> (EndpointUrl, JoinHandle<Vec<ReceivedRequest>>) {
+    let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+        .await
+        .unwrap();
+    let base_url =
+        EndpointUrl::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+
+    let handle = tokio::spawn(async move {
+        let mut requests = vec![];
+
+        for reply in replies {
+            let (mut stream, _) = tokio::select! {
+                biased;
+                _ = stop.cancelled() => break,
+                accepted = listener.accept() => accepted.unwrap(),
+            };
+
+            requests.push(read_request(&mut stream).await);
+
+            match reply {
+                Reply::Respond(status, body) => {
+                    respond(&mut stream, status, &[], &body).await;
+                }
+                Reply::RespondWithHeaders(status, headers, body) => {
+                    respond(&mut stream, status, &headers, &body).await;
+                }
+                Reply::Close => drop(stream),
+            }
+        }
+
+        requests
+    });
+
+    (base_url, handle)
+}
+
+async fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    headers: &[(&'static str, String)],
+    body: &str,
+) {
+    let headers = headers
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}\r\n"))
+        .collect::<String>();
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{headers}Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+
+    stream.write_all(response.as_bytes()).await.unwrap();
+    stream.shutdown().await.unwrap();
+}
+
+async fn read_request(stream: &mut TcpStream) -> ReceivedRequest {
+    let mut data = vec![];
+    let mut buffer = [0; 1024];
+
+    let head_end = loop {
+        let read = stream.read(&mut buffer).await.unwrap();
+
+        data.extend_from_slice(&buffer[..read]);
+
+        if let Some(position) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position;
+        }
+
+        assert!(read > 0, "connection closed before the request head ended");
+    };
+
+    let head = String::from_utf8_lossy(&data[..head_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+    let content_length = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    while data.len() < head_end + 4 + content_length {
+        let read = stream.read(&mut buffer).await.unwrap();
+
+        assert!(read > 0, "connection closed before the request body ended");
+
+        data.extend_from_slice(&buffer[..read]);
+    }
+
+    ReceivedRequest {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&data[head_end + 4..head_end + 4 + content_length])
+            .to_string(),
+    }
+}
@@ -0,0 +1,596 @@
+use anyhow::{anyhow, Result as AnyhowResult};
+
+use crate::{
+    args::{Args, Command},
+    clients::{beacon::types::BlockId, blobscan::types::BlockchainSyncState},
+    env::Environment,
+    slots_processor::IndexMode,
+};
+
+type Rule = fn(&Args, &Environment) -> Option<String>;
+
+const RULES: &[Rule] = &[
+    reindexing_requires_execution_node,
+    reindexing_requires_bounded_run,
+    parent_verification_requires_execution_node,
+    empty_blocks_are_never_indexed_in_blobs_only_mode,
+    empty_block_heartbeat_requires_skipped_empty_blocks,
+    sampling_requires_historical_sync,
+    sample_rate_below_slots_per_epoch,
+    checkpoint_options_require_checkpoint_saving,
+    follow_distance_conflicts_with_finalized_only,
+    to_slot_not_below_from_slot,
+    backfill_range_replaces_slot_flags,
+    once_requires_unbounded_run,
+    deprecated_env_vars_fail_when_disallowed,
+    metrics_port_requires_metrics_feature,
+    health_port_requires_health_feature,
+];
+
+/// Checks options that are valid on their own but can't work together, reporting every
+/// violation at once. Runs before any network activity.
+pub fn validate_config(args: &Args, env: &Environment) -> AnyhowResult<()> {
+    let violations = RULES
+        .iter()
+        .filter_map(|rule| rule(args, env))
+        .collect::<Vec<_>>();
+
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    Err(anyhow!(
+        "Incompatible configuration:\n{}",
+        violations
+            .iter()
+            .map(|violation| format!("  - {violation}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    ))
+}
+
+/// Checks a bounded run against blobscan's sync state, which is only known once the indexer
+/// starts. Without a start slot the run resumes right after the last synced slot, so an end slot
+/// at or below it would have the run walk backwards
+pub fn bounded_run_resumes_below_to_slot(
+    start_block_id: Option<&BlockId>,
+    end_block_id: Option<&BlockId>,
+    sync_state: Option<&BlockchainSyncState>,
+) -> Option<String> {
+    let (None, Some(BlockId::Slot(to_slot)), Some(sync_state)) =
+        (start_block_id, end_block_id, sync_state)
+    else {
+        return None;
+    };
+    let last_synced_slot = sync_state
+        .last_upper_synced_slot
+        .or(sync_state.last_lower_synced_slot)?;
+
+    (*to_slot <= last_synced_slot).then(|| {
+        format!(
+            "--to-slot {to_slot} is at or below the last slot blobscan synced ({last_synced_slot}), so a run resuming after it would walk backwards. Set --from-slot to backfill a range below it"
+        )
+    })
+}
+
+fn reindexing_requires_execution_node(args: &Args, env: &Environment) -> Option<String> {
+    (args.reindex_fields.is_some() && env.consensus_only).then(|| {
+        "--reindex-fields rebuilds entities from execution blocks, which consensus-only mode doesn't fetch. Unset CONSENSUS_ONLY or drop --reindex-fields".to_string()
+    })
+}
+
+fn reindexing_requires_bounded_run(args: &Args, _env: &Environment) -> Option<String> {
+    (args.reindex_fields.is_some() && args.slot_bounds().1.is_none()).then(|| {
+        "--reindex-fields only patches already indexed slots, so new head slots would never be fully indexed. Set --to-slot or use backfill to bound the run".to_string()
+    })
+}
+
+fn parent_verification_requires_execution_node(args: &Args, env: &Environment) -> Option<String> {
+    let verifies_parent =
+        args.verify_parent_timestamp == Some(true) || args.verify_parent_number == Some(true);
+
+    (verifies_parent && env.consensus_only).then(|| {
+        "--verify-parent-timestamp and --verify-parent-number compare execution blocks, which consensus-only mode doesn't fetch. Unset CONSENSUS_ONLY or drop the flags".to_string()
+    })
+}
+
+fn empty_blocks_are_never_indexed_in_blobs_only_mode(
+    args: &Args,
+    _env: &Environment,
+) -> Option<String> {
+    (args.index_empty_blocks && args.index_mode == IndexMode::BlobsOnly).then(|| {
+        "--index-empty-blocks has no effect with --index-mode blobs-only, as empty blocks carry no blobs. Use --index-mode all or drop --index-empty-blocks".to_string()
+    })
+}
+
+fn empty_block_heartbeat_requires_skipped_empty_blocks(
+    args: &Args,
+    _env: &Environment,
+) -> Option<String> {
+    let indexes_empty_blocks =
+        args.index_empty_blocks || args.index_mode == IndexMode::BlocksAndBlobs;
+
+    (args.empty_block_heartbeat_secs.is_some() && indexes_empty_blocks).then(|| {
+        "--empty-block-heartbeat-secs only applies to skipped empty blocks, which --index-empty-blocks and --index-mode blocks-and-blobs index instead. Drop one of them".to_string()
+    })
+}
+
+fn sampling_requires_historical_sync(args: &Args, _env: &Environment) -> Option<String> {
+    (args.sample_rate.is_some() && args.disable_sync_historical).then(|| {
+        "--sample-rate only applies to historical backfill, which --disable-sync-historical turns off. Drop one of them".to_string()
+    })
+}
+
+fn sample_rate_below_slots_per_epoch(args: &Args, _env: &Environment) -> Option<String> {
+    match (args.sample_rate, args.slots_per_epoch) {
+        (Some(sample_rate), Some(slots_per_epoch)) if sample_rate >= slots_per_epoch => Some(format!(
+            "--sample-rate {sample_rate} keeps every slot of a {slots_per_epoch}-slot epoch, so nothing is sampled out. Lower --sample-rate or drop it"
+        )),
+        _ => None,
+    }
+}
+
+fn checkpoint_options_require_checkpoint_saving(args: &Args, _env: &Environment) -> Option<String> {
+    let has_checkpoint_options =
+        args.slots_per_save.is_some() || args.checkpoint_interval_secs.is_some();
+
+    (has_checkpoint_options && args.disable_sync_checkpoint_save).then(|| {
+        "--slots-per-save and --checkpoint-interval-secs tune checkpoint saving, which --disable-sync-checkpoint-save turns off. Drop one of them".to_string()
+    })
+}
+
+fn follow_distance_conflicts_with_finalized_only(
+    args: &Args,
+    _env: &Environment,
+) -> Option<String> {
+    (args.follow_distance > 0 && args.finalized_only).then(|| {
+        "--follow-distance and --finalized-only both pick the slot realtime indexing stops at. Drop one of them".to_string()
+    })
+}
+
+fn to_slot_not_below_from_slot(args: &Args, _env: &Environment) -> Option<String> {
+    match args.slot_bounds() {
+        (Some(BlockId::Slot(from_slot)), Some(BlockId::Slot(to_slot))) if to_slot < from_slot => {
+            Some(format!(
+                "End slot {to_slot} is below start slot {from_slot}. Bounded runs sync forward, so swap them"
+            ))
+        }
+        _ => None,
+    }
+}
+
+fn backfill_range_replaces_slot_flags(args: &Args, _env: &Environment) -> Option<String> {
+    let sets_slot_flags = args.from_slot.is_some() || args.to_slot.is_some();
+
+    (matches!(args.command, Some(Command::Backfill { .. })) && sets_slot_flags).then(|| {
+        "backfill takes its range from --from and --to, so --from-slot and --to-slot would be ignored. Drop them".to_string()
+    })
+}
+
+fn once_requires_unbounded_run(args: &Args, _env: &Environment) -> Option<String> {
+    (args.once && args.slot_bounds().1.is_some()).then(|| {
+        "--once catches up to the realtime target, while --to-slot and backfill already stop at a given slot. Drop one of them".to_string()
+    })
+}
+
+fn deprecated_env_vars_fail_when_disallowed(args: &Args, env: &Environment) -> Option<String> {
+    (args.fail_on_deprecated && !env.deprecated_vars.is_empty()).then(|| {
+        format!(
+            "--fail-on-deprecated is set but deprecated environment variables are in use. Rename {}",
+            env.deprecated_vars
+                .iter()
+                .map(|deprecated_var| format!(
+                    "{} to {}",
+                    deprecated_var.name, deprecated_var.replacement
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    })
+}
+
+fn metrics_port_requires_metrics_feature(args: &Args, _env: &Environment) -> Option<String> {
+    (cfg!(not(feature = "metrics")) && args.metrics_port.is_some()).then(|| {
+        "--metrics-port needs the `metrics` feature, which this build doesn't include. Rebuild with --features metrics or drop --metrics-port".to_string()
+    })
+}
+
+fn health_port_requires_health_feature(args: &Args, _env: &Environment) -> Option<String> {
+    (cfg!(not(feature = "health")) && args.health_port.is_some()).then(|| {
+        "--health-port needs the `health` feature, which this build doesn't include. Rebuild with --features health or drop --health-port".to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use crate::{env::DeprecatedEnvVar, network::Network, utils::url::EndpointUrl};
+
+    use super::*;
+
+    fn args(flags: &[&str]) -> Args {
+        Args::try_parse_from(std::iter::once("blobscan-indexer").chain(flags.iter().copied()))
+            .unwrap()
+    }
+
+    fn env() -> Environment {
+        Environment {
+            network_name: Network::Devnet,
+            blobscan_api_endpoint: EndpointUrl::parse("http://localhost:3001").unwrap(),
+            beacon_node_endpoint: EndpointUrl::parse("http://localhost:3500").unwrap(),
+            execution_node_endpoint: EndpointUrl::parse("http://localhost:8545").unwrap(),
+            consensus_only: false,
+            beacon_http_version: Default::default(),
+            blobscan_http_version: Default::default(),
+            secret_key: "secret".to_string(),
+            dencun_fork_slot: None,
+            beacon_trace_bodies: false,
+            blobscan_trace_bodies: false,
+            trace_bodies_max_bytes: 4096,
+            sentry_dsn: None,
+            deprecated_vars: vec![],
+        }
+    }
+
+    fn consensus_only_env() -> Environment {
+        Environment {
+            consensus_only: true,
+            ..env()
+        }
+    }
+
+    /// Asserts `rule` flags the first set of flags and accepts the second one
+    fn assert_rule(rule: Rule, env: &Environment, violating: &[&str], valid: &[&str]) {
+        assert!(rule(&args(violating), env).is_some(), "{violating:?}");
+        assert_eq!(rule(&args(valid), env), None, "{valid:?}");
+    }
+
+    #[test]
+    fn test_valid_config_passes() {
+        assert!(validate_config(&args(&[]), &env()).is_ok());
+        assert!(validate_config(&args(&["--from-slot", "10", "--to-slot", "20"]), &env()).is_ok());
+    }
+
+    #[test]
+    fn test_every_violation_is_reported() {
+        let error = validate_config(
+            &args(&[
+                "--once",
+                "--to-slot",
+                "10",
+                "--follow-distance",
+                "2",
+                "--finalized-only",
+            ]),
+            &env(),
+        )
+        .unwrap_err()
+        .to_string();
+
+        assert!(error.starts_with("Incompatible configuration:\n"));
+        assert!(error.contains("  - --once catches up"));
+        assert!(error.contains("  - --follow-distance and --finalized-only"));
+    }
+
+    #[test]
+    fn test_reindexing_requires_execution_node() {
+        let flags = ["--reindex-fields", "block", "--to-slot", "10"];
+
+        assert!(reindexing_requires_execution_node(&args(&flags), &consensus_only_env()).is_some());
+        assert_eq!(
+            reindexing_requires_execution_node(&args(&flags), &env()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_reindexing_requires_bounded_run() {
+        assert_rule(
+            reindexing_requires_bounded_run,
+            &env(),
+            &["--reindex-fields", "block,transactions"],
+            &["--reindex-fields", "block", "--to-slot", "10"],
+        );
+        assert_eq!(
+            reindexing_requires_bounded_run(
+                &args(&[
+                    "--reindex-fields",
+                    "block",
+                    "backfill",
+                    "--from",
+                    "1",
+                    "--to",
+                    "2"
+                ]),
+                &env()
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parent_verification_requires_execution_node() {
+        assert_rule(
+            parent_verification_requires_execution_node,
+            &consensus_only_env(),
+            &["--verify-parent-timestamp"],
+            &["--verify-parent-timestamp", "false"],
+        );
+        assert!(parent_verification_requires_execution_node(
+            &args(&["--verify-parent-number"]),
+            &consensus_only_env()
+        )
+        .is_some());
+        assert_eq!(
+            parent_verification_requires_execution_node(&args(&["--verify-parent-number"]), &env()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_empty_blocks_are_never_indexed_in_blobs_only_mode() {
+        assert_rule(
+            empty_blocks_are_never_indexed_in_blobs_only_mode,
+            &env(),
+            &["--index-empty-blocks", "--index-mode", "blobs-only"],
+            &["--index-empty-blocks", "--index-mode", "all"],
+        );
+    }
+
+    #[test]
+    fn test_empty_block_heartbeat_requires_skipped_empty_blocks() {
+        assert_rule(
+            empty_block_heartbeat_requires_skipped_empty_blocks,
+            &env(),
+            &["--empty-block-heartbeat-secs", "60", "--index-empty-blocks"],
+            &["--empty-block-heartbeat-secs", "60"],
+        );
+        assert!(empty_block_heartbeat_requires_skipped_empty_blocks(
+            &args(&[
+                "--empty-block-heartbeat-secs",
+                "60",
+                "--index-mode",
+                "blocks-and-blobs"
+            ]),
+            &env()
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn test_sampling_requires_historical_sync() {
+        assert_rule(
+            sampling_requires_historical_sync,
+            &env(),
+            &["--sample-rate", "4", "--disable-sync-historical"],
+            &["--sample-rate", "4"],
+        );
+    }
+
+    #[test]
+    fn test_sample_rate_below_slots_per_epoch() {
+        assert_rule(
+            sample_rate_below_slots_per_epoch,
+            &env(),
+            &["--sample-rate", "32", "--slots-per-epoch", "32"],
+            &["--sample-rate", "31", "--slots-per-epoch", "32"],
+        );
+        assert_eq!(
+            sample_rate_below_slots_per_epoch(&args(&["--sample-rate", "64"]), &env()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_options_require_checkpoint_saving() {
+        assert_rule(
+            checkpoint_options_require_checkpoint_saving,
+            &env(),
+            &["--slots-per-save", "10", "--disable-sync-checkpoint-save"],
+            &["--disable-sync-checkpoint-save"],
+        );
+        assert!(checkpoint_options_require_checkpoint_saving(
+            &args(&[
+                "--checkpoint-interval-secs",
+                "5",
+                "--disable-sync-checkpoint-save"
+            ]),
+            &env()
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn test_follow_distance_conflicts_with_finalized_only() {
+        assert_rule(
+            follow_distance_conflicts_with_finalized_only,
+            &env(),
+            &["--follow-distance", "2", "--finalized-only"],
+            &["--follow-distance", "0", "--finalized-only"],
+        );
+    }
+
+    #[test]
+    fn test_to_slot_not_below_from_slot() {
+        assert_rule(
+            to_slot_not_below_from_slot,
+            &env(),
+            &["--from-slot", "10", "--to-slot", "9"],
+            &["--from-slot", "10", "--to-slot", "10"],
+        );
+        assert!(to_slot_not_below_from_slot(
+            &args(&["backfill", "--from", "10", "--to", "9"]),
+            &env()
+        )
+        .is_some());
+        // Named blocks are only resolved at startup
+        assert_eq!(
+            to_slot_not_below_from_slot(&args(&["--from-slot", "10", "--to-slot", "head"]), &env()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_backfill_range_replaces_slot_flags() {
+        assert_rule(
+            backfill_range_replaces_slot_flags,
+            &env(),
+            &["--from-slot", "1", "backfill", "--from", "1", "--to", "2"],
+            &["backfill", "--from", "1", "--to", "2"],
+        );
+        assert_eq!(
+            backfill_range_replaces_slot_flags(&args(&["--from-slot", "1"]), &env()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_once_requires_unbounded_run() {
+        assert_rule(
+            once_requires_unbounded_run,
+            &env(),
+            &["--once", "--to-slot", "10"],
+            &["--once", "--from-slot", "10"],
+        );
+    }
+
+    #[test]
+    fn test_deprecated_env_vars_fail_when_disallowed() {
+        let deprecated_env = Environment {
+            deprecated_vars: vec![DeprecatedEnvVar {
+                name: "BEACON_NODE_ENDPOINT",
+                replacement: "BEACON_API_URL",
+            }],
+            ..env()
+        };
+
+        assert_rule(
+            deprecated_env_vars_fail_when_disallowed,
+            &deprecated_env,
+            &["--fail-on-deprecated"],
+            &[],
+        );
+        assert!(deprecated_env_vars_fail_when_disallowed(
+            &args(&["--fail-on-deprecated"]),
+            &deprecated_env
+        )
+        .is_some_and(|violation| violation.contains("BEACON_NODE_ENDPOINT to BEACON_API_URL")));
+        assert_eq!(
+            deprecated_env_vars_fail_when_disallowed(&args(&["--fail-on-deprecated"]), &env()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_ports_require_their_feature() {
+        let metrics_args = args(&["--metrics-port", "9090"]);
+        let health_args = args(&["--health-port", "9091"]);
+
+        assert_eq!(
+            metrics_port_requires_metrics_feature(&metrics_args, &env()).is_some(),
+            cfg!(not(feature = "metrics"))
+        );
+        assert_eq!(
+            health_port_requires_health_feature(&health_args, &env()).is_some(),
+            cfg!(not(feature = "health"))
+        );
+        assert_eq!(
+            metrics_port_requires_metrics_feature(&args(&[]), &env()),
+            None
+        );
+        assert_eq!(
+            health_port_requires_health_feature(&args(&[]), &env()),
+            None
+        );
+    }
+
+    fn sync_state(
+        last_lower_synced_slot: Option<u32>,
+        last_upper_synced_slot: Option<u32>,
+    ) -> BlockchainSyncState {
+        BlockchainSyncState {
+            last_finalized_block: None,
+            last_lower_synced_slot,
+            last_upper_synced_slot,
+        }
+    }
+
+    #[test]
+    fn test_bounded_run_below_the_last_upper_synced_slot_is_rejected() {
+        let sync_state = sync_state(Some(10), Some(1000));
+
+        for to_slot in [500, 1000] {
+            let violation = bounded_run_resumes_below_to_slot(
+                None,
+                Some(&BlockId::Slot(to_slot)),
+                Some(&sync_state),
+            );
+
+            assert!(violation.is_some_and(|violation| violation.contains("walk backwards")));
+        }
+    }
+
+    #[test]
+    fn test_bounded_run_above_the_last_upper_synced_slot_is_accepted() {
+        assert_eq!(
+            bounded_run_resumes_below_to_slot(
+                None,
+                Some(&BlockId::Slot(1001)),
+                Some(&sync_state(Some(10), Some(1000))),
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_last_lower_synced_slot_is_used_without_an_upper_one() {
+        let sync_state = sync_state(Some(1000), None);
+
+        assert!(bounded_run_resumes_below_to_slot(
+            None,
+            Some(&BlockId::Slot(900)),
+            Some(&sync_state)
+        )
+        .is_some());
+        assert_eq!(
+            bounded_run_resumes_below_to_slot(None, Some(&BlockId::Slot(1001)), Some(&sync_state)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_runs_the_sync_state_does_not_bound_are_accepted() {
+        let synced_state = sync_state(Some(10), Some(1000));
+
+        // An explicit start slot, an unbounded run, a named end block and no sync state
+        assert_eq!(
+            bounded_run_resumes_below_to_slot(
+                Some(&BlockId::Slot(100)),
+                Some(&BlockId::Slot(500)),
+                Some(&synced_state),
+            ),
+            None
+        );
+        assert_eq!(
+            bounded_run_resumes_below_to_slot(None, None, Some(&synced_state)),
+            None
+        );
+        assert_eq!(
+            bounded_run_resumes_below_to_slot(None, Some(&BlockId::Finalized), Some(&synced_state)),
+            None
+        );
+        assert_eq!(
+            bounded_run_resumes_below_to_slot(None, Some(&BlockId::Slot(500)), None),
+            None
+        );
+        assert_eq!(
+            bounded_run_resumes_below_to_slot(
+                None,
+                Some(&BlockId::Slot(500)),
+                Some(&sync_state(None, None)),
+            ),
+            None
+        );
+    }
+}
@@ -1,66 +1,193 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::BTreeMap,
+    ops::Deref,
+    sync::{Arc, OnceLock, RwLock, RwLockReadGuard},
+    time::Duration,
+};
 
 use anyhow::Result as AnyhowResult;
 use backoff::ExponentialBackoffBuilder;
 use ethers::prelude::*;
 
 use crate::{
-    clients::beacon::{BeaconClient, Config as BeaconClientConfig},
+    clients::beacon::{columns::ColumnLayout, BeaconClient, Config as BeaconClientConfig},
     clients::blobscan::{BlobscanClient, Config as BlobscanClientConfig},
+    clients::common::{build_http_client, HttpVersion},
+    duties_cache::ProposerDutiesCache,
     env::Environment,
+    health::HealthState,
+    indexing_stats::IndexingStats,
+    metrics::Metrics,
+    skip_list::SkipList,
+    utils::{
+        body_trace::BodyTrace,
+        clock::{Clock, SystemClock},
+        url::EndpointUrl,
+        web3::CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION,
+    },
 };
 
-#[derive(Debug, Clone)]
+/// Chain parameters resolved once at startup, from the beacon spec or from CLI overrides when
+/// the spec endpoint is unavailable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainSpec {
+    pub slots_per_epoch: u32,
+    pub seconds_per_slot: u32,
+    /// Unknown when the spec couldn't be fetched
+    pub genesis_time: Option<u64>,
+    /// Column layouts keyed by activation epoch. Empty when the spec doesn't describe any
+    pub column_layouts: BTreeMap<u64, ColumnLayout>,
+    /// Blob base fee update fractions keyed by activation epoch. Empty when the spec couldn't be
+    /// fetched
+    pub blob_base_fee_update_fractions: BTreeMap<u64, u64>,
+}
+
+impl ChainSpec {
+    pub fn epoch_of(&self, slot: u32) -> u64 {
+        (slot / self.slots_per_epoch) as u64
+    }
+
+    /// Layout active at the slot's epoch, unknown when the spec doesn't describe one
+    pub fn column_layout_at(&self, slot: u32) -> Option<ColumnLayout> {
+        self.column_layouts
+            .range(..=self.epoch_of(slot))
+            .next_back()
+            .map(|(_, layout)| *layout)
+    }
+
+    /// Fraction in force at the slot's epoch. Falls back to Cancun's when the spec doesn't
+    /// describe one
+    pub fn blob_base_fee_update_fraction_at(&self, slot: u32) -> u64 {
+        self.blob_base_fee_update_fractions
+            .range(..=self.epoch_of(slot))
+            .next_back()
+            .map_or(CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION, |(_, fraction)| {
+                *fraction
+            })
+    }
+}
+
+// Enough epochs for every worker of a synchronizer to keep the one it's processing
+const PROPOSER_DUTIES_CACHE_CAPACITY: usize = 16;
+
+#[derive(Debug)]
 struct ContextRef {
     pub beacon_client: BeaconClient,
     pub blobscan_client: BlobscanClient,
-    pub provider: Provider<Http>,
+    pub provider: Option<Provider<Http>>,
+    pub clock: Arc<dyn Clock>,
+    pub indexing_stats: IndexingStats,
+    pub metrics: Metrics,
+    pub health: HealthState,
+    pub skip_list: RwLock<SkipList>,
+    pub chain_spec: OnceLock<ChainSpec>,
 }
 
 pub struct Config {
-    pub blobscan_api_endpoint: String,
-    pub beacon_node_url: String,
-    pub execution_node_endpoint: String,
+    pub blobscan_api_endpoint: EndpointUrl,
+    pub beacon_node_url: EndpointUrl,
+    pub execution_node_endpoint: Option<EndpointUrl>,
     pub secret_key: String,
+    pub disable_retries: bool,
+    pub beacon_http_version: HttpVersion,
+    pub blobscan_http_version: HttpVersion,
+    pub beacon_body_trace: BodyTrace,
+    pub blobscan_body_trace: BodyTrace,
 }
 
+/// Shared handle to the clients. Cloning is cheap and every clone points to the same clients,
+/// so HTTP connection pools and JWT state are shared by all synchronizer tasks.
 #[derive(Debug, Clone)]
 pub struct Context {
     inner: Arc<ContextRef>,
 }
 
+/// A task's view of the [`Context`]. It derefs to the shared context, so the clients, stats and
+/// metrics stay shared, but it owns the scratch caches its task fills, so realtime syncing and
+/// backfills don't evict each other's entries. Clones of a handle share its caches, which is how
+/// the batches of one synchronizer share them.
+///
+/// Only proposer duties are cached. Apart from the parent lookups of the opt-in parent checks,
+/// each block is fetched once, by the prefetcher or by its slot, so a block cache would never be
+/// hit. Indexing stats are totals reported for the whole run, so they're recorded straight into
+/// the shared [`IndexingStats`] rather than through a per-task recorder.
+#[derive(Debug, Clone)]
+pub struct ContextHandle {
+    context: Context,
+    proposer_duties_cache: Arc<ProposerDutiesCache>,
+}
+
+// Context and its handles are moved into spawned tasks, so they must stay Send + Sync
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<Context>();
+    assert_send_sync::<ContextHandle>();
+};
+
 impl Context {
     pub fn try_new(config: Config) -> AnyhowResult<Self> {
+        Self::try_with_clock(config, Arc::new(SystemClock))
+    }
+
+    /// Builds the context with another time source, e.g. a mock clock in tests
+    pub fn try_with_clock(config: Config, clock: Arc<dyn Clock>) -> AnyhowResult<Self> {
         let Config {
             blobscan_api_endpoint,
             beacon_node_url,
             execution_node_endpoint,
             secret_key,
+            disable_retries,
+            beacon_http_version,
+            blobscan_http_version,
+            beacon_body_trace,
+            blobscan_body_trace,
         } = config;
-        let exp_backoff = Some(ExponentialBackoffBuilder::default().build());
+        let exp_backoff = if disable_retries {
+            None
+        } else {
+            Some(ExponentialBackoffBuilder::default().build())
+        };
 
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(3000))
-            .build()?;
+        let provider = execution_node_endpoint
+            .map(|endpoint| Provider::<Http>::try_from(endpoint.as_str()))
+            .transpose()?;
+
+        let timeout = Duration::from_secs(3000);
+        let beacon_http_client = build_http_client(beacon_http_version, timeout)?;
+        let blobscan_http_client = build_http_client(blobscan_http_version, timeout)?;
+        let metrics = Metrics::default();
 
         Ok(Self {
             inner: Arc::new(ContextRef {
                 blobscan_client: BlobscanClient::try_with_client(
-                    client.clone(),
+                    blobscan_http_client,
                     BlobscanClientConfig {
                         base_url: blobscan_api_endpoint,
                         secret_key,
                         exp_backoff: exp_backoff.clone(),
+                        clock: clock.clone(),
+                        body_trace: blobscan_body_trace,
+                        latency: metrics.blobscan_latency(),
                     },
                 )?,
                 beacon_client: BeaconClient::try_with_client(
-                    client,
+                    beacon_http_client,
                     BeaconClientConfig {
                         base_url: beacon_node_url,
                         exp_backoff,
+                        clock: clock.clone(),
+                        body_trace: beacon_body_trace,
+                        fork_mismatches: metrics.beacon_fork_mismatches(),
                     },
                 )?,
-                provider: Provider::<Http>::try_from(execution_node_endpoint)?,
+                provider,
+                clock,
+                indexing_stats: IndexingStats::default(),
+                metrics,
+                health: HealthState::default(),
+                skip_list: RwLock::new(SkipList::default()),
+                chain_spec: OnceLock::new(),
             }),
         })
     }
@@ -73,8 +200,85 @@ impl Context {
         &self.inner.blobscan_client
     }
 
-    pub fn provider(&self) -> &Provider<Http> {
-        &self.inner.provider
+    pub fn clock(&self) -> &dyn Clock {
+        self.inner.clock.as_ref()
+    }
+
+    pub fn indexing_stats(&self) -> &IndexingStats {
+        &self.inner.indexing_stats
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.inner.metrics
+    }
+
+    pub fn health(&self) -> &HealthState {
+        &self.inner.health
+    }
+
+    pub fn skip_list(&self) -> RwLockReadGuard<'_, SkipList> {
+        self.inner
+            .skip_list
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Replaces the skip list, taking effect from the next processed slot
+    pub fn set_skip_list(&self, skip_list: SkipList) {
+        *self
+            .inner
+            .skip_list
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = skip_list;
+    }
+
+    /// # Panics
+    ///
+    /// If called before the indexer resolved the chain spec at startup
+    pub fn chain_spec(&self) -> &ChainSpec {
+        self.inner
+            .chain_spec
+            .get()
+            .expect("chain spec is resolved before syncing starts")
+    }
+
+    /// Returns `None` until the chain spec is resolved
+    pub fn try_chain_spec(&self) -> Option<&ChainSpec> {
+        self.inner.chain_spec.get()
+    }
+
+    /// Sets the chain spec. Only the first call has an effect
+    pub fn set_chain_spec(&self, chain_spec: ChainSpec) {
+        let _ = self.inner.chain_spec.set(chain_spec);
+    }
+
+    /// Returns `None` when running in consensus-only mode
+    pub fn provider(&self) -> Option<&Provider<Http>> {
+        self.inner.provider.as_ref()
+    }
+
+    /// Creates a handle with its own empty scratch caches
+    pub fn handle(&self) -> ContextHandle {
+        ContextHandle {
+            context: self.clone(),
+            proposer_duties_cache: Arc::new(ProposerDutiesCache::new(
+                PROPOSER_DUTIES_CACHE_CAPACITY,
+            )),
+        }
+    }
+}
+
+impl ContextHandle {
+    pub fn proposer_duties_cache(&self) -> &ProposerDutiesCache {
+        self.proposer_duties_cache.as_ref()
+    }
+}
+
+impl Deref for ContextHandle {
+    type Target = Context;
+
+    fn deref(&self) -> &Context {
+        &self.context
     }
 }
 
@@ -83,8 +287,161 @@ impl From<&Environment> for Config {
         Self {
             blobscan_api_endpoint: env.blobscan_api_endpoint.clone(),
             beacon_node_url: env.beacon_node_endpoint.clone(),
-            execution_node_endpoint: env.execution_node_endpoint.clone(),
+            execution_node_endpoint: env.execution_node().cloned(),
             secret_key: env.secret_key.clone(),
+            disable_retries: false,
+            beacon_http_version: env.beacon_http_version,
+            blobscan_http_version: env.blobscan_http_version,
+            beacon_body_trace: BodyTrace {
+                enabled: env.beacon_trace_bodies,
+                max_bytes: env.trace_bodies_max_bytes,
+                omitted_fields: &["blob", "segments"],
+            },
+            blobscan_body_trace: BodyTrace {
+                enabled: env.blobscan_trace_bodies,
+                max_bytes: env.trace_bodies_max_bytes,
+                omitted_fields: &["data"],
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{ChainSpec, Config, Context, ContextHandle};
+    use crate::{
+        clients::test_server::{self, Reply},
+        utils::{
+            url::EndpointUrl,
+            web3::{CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION, PRAGUE_BLOB_BASE_FEE_UPDATE_FRACTION},
+        },
+    };
+
+    fn chain_spec(blob_base_fee_update_fractions: BTreeMap<u64, u64>) -> ChainSpec {
+        ChainSpec {
+            slots_per_epoch: 32,
+            seconds_per_slot: 12,
+            genesis_time: None,
+            column_layouts: BTreeMap::new(),
+            blob_base_fee_update_fractions,
         }
     }
+
+    #[test]
+    fn epoch_of_uses_the_chain_slots_per_epoch() {
+        let mut chain_spec = chain_spec(BTreeMap::new());
+
+        assert_eq!(chain_spec.epoch_of(0), 0);
+        assert_eq!(chain_spec.epoch_of(31), 0);
+        assert_eq!(chain_spec.epoch_of(32), 1);
+        assert_eq!(chain_spec.epoch_of(u32::MAX), (u32::MAX / 32) as u64);
+
+        chain_spec.slots_per_epoch = 6;
+
+        assert_eq!(chain_spec.epoch_of(5), 0);
+        assert_eq!(chain_spec.epoch_of(6), 1);
+        assert_eq!(chain_spec.epoch_of(320), 53);
+    }
+
+    #[test]
+    fn blob_base_fee_update_fraction_at_picks_the_active_fork() {
+        let chain_spec = chain_spec(BTreeMap::from([
+            (10, CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION),
+            (20, PRAGUE_BLOB_BASE_FEE_UPDATE_FRACTION),
+        ]));
+
+        assert_eq!(
+            chain_spec.blob_base_fee_update_fraction_at(20 * 32 - 1),
+            CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION
+        );
+        assert_eq!(
+            chain_spec.blob_base_fee_update_fraction_at(20 * 32),
+            PRAGUE_BLOB_BASE_FEE_UPDATE_FRACTION
+        );
+    }
+
+    #[test]
+    fn blob_base_fee_update_fraction_at_falls_back_to_cancun() {
+        assert_eq!(
+            chain_spec(BTreeMap::new()).blob_base_fee_update_fraction_at(1000),
+            CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION
+        );
+    }
+
+    fn context(url: EndpointUrl) -> Context {
+        Context::try_new(Config {
+            blobscan_api_endpoint: url.clone(),
+            beacon_node_url: url,
+            execution_node_endpoint: None,
+            secret_key: "test-secret".to_string(),
+            disable_retries: true,
+            beacon_http_version: Default::default(),
+            blobscan_http_version: Default::default(),
+            beacon_body_trace: Default::default(),
+            blobscan_body_trace: Default::default(),
+        })
+        .unwrap()
+    }
+
+    /// Looks up the epoch's duties through the handle's cache, fetching them from its beacon
+    /// client on a miss
+    async fn lookup_duties(handle: &ContextHandle, epoch: u64) {
+        handle
+            .proposer_duties_cache()
+            .get_or_try_fetch(epoch, || handle.beacon_client().get_proposer_duties(epoch))
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn handles_share_the_clients_but_not_the_scratch_caches() {
+        let duties = r#"{"data":[{"pubkey":"0xaa","validator_index":"7","slot":"160"}]}"#;
+        let (url, server) = test_server::serve(vec![
+            Reply::Respond("200 OK", String::new()),
+            Reply::Respond("200 OK", String::new()),
+            Reply::Respond("200 OK", duties.to_string()),
+            Reply::Respond("200 OK", duties.to_string()),
+        ])
+        .await;
+        let context = context(url);
+        let handle = context.handle();
+        let other_handle = context.handle();
+
+        // Every request of the blobscan client is timed, so both handles' requests being timed
+        // together shows they went through the same client
+        handle.blobscan_client().update_slot(1).await.unwrap();
+        other_handle.blobscan_client().update_slot(2).await.unwrap();
+
+        assert_eq!(context.metrics().blobscan_latency().total.count(), 2);
+
+        handle.indexing_stats().record_block(2, 3, None);
+
+        assert_eq!(other_handle.indexing_stats().snapshot().blocks, 1);
+
+        lookup_duties(&handle, 5).await;
+        lookup_duties(&handle, 5).await;
+        lookup_duties(&handle.clone(), 5).await;
+        lookup_duties(&other_handle, 5).await;
+
+        let paths = server
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|request| request.path)
+            .collect::<Vec<_>>();
+
+        // Only the first lookup of each handle reaches the beacon node
+        assert_eq!(
+            paths,
+            [
+                "/indexer/slot",
+                "/indexer/slot",
+                "/eth/v1/validator/duties/proposer/5",
+                "/eth/v1/validator/duties/proposer/5",
+            ]
+        );
+    }
 }
@@ -0,0 +1,218 @@
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::OnceCell;
+
+use crate::clients::beacon::types::ProposerDuty;
+
+type DutiesCell = Arc<OnceCell<Arc<Vec<ProposerDuty>>>>;
+
+enum FetchMiss<E> {
+    Empty,
+    Failed(E),
+}
+
+/// Cached epochs along with the lookup that last used each of them
+#[derive(Debug, Default)]
+struct Entries {
+    cells: BTreeMap<u64, (DutiesCell, u64)>,
+    lookups: u64,
+}
+
+/// Proposer duties keyed by epoch, shared by the `SlotsProcessor`s of a synchronizer through
+/// its `ContextHandle`.
+///
+/// Concurrent lookups of the same uncached epoch wait on a single upstream fetch. Failed or
+/// empty fetches aren't cached, so the next lookup retries them. When full, the least recently
+/// used epoch is evicted, as a synchronizer's batches move through the epochs in one direction.
+#[derive(Debug)]
+pub struct ProposerDutiesCache {
+    entries: Mutex<Entries>,
+    capacity: usize,
+}
+
+impl ProposerDutiesCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(Entries::default()),
+            capacity,
+        }
+    }
+
+    pub async fn get_or_try_fetch<F, Fut, E>(
+        &self,
+        epoch: u64,
+        fetch: F,
+    ) -> Result<Option<Arc<Vec<ProposerDuty>>>, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Option<Vec<ProposerDuty>>, E>>,
+    {
+        let cell = self.get_cell(epoch);
+
+        let result = cell
+            .get_or_try_init(|| async {
+                match fetch().await {
+                    Ok(Some(duties)) => Ok(Arc::new(duties)),
+                    Ok(None) => Err(FetchMiss::Empty),
+                    Err(error) => Err(FetchMiss::Failed(error)),
+                }
+            })
+            .await;
+
+        match result {
+            Ok(duties) => Ok(Some(duties.clone())),
+            Err(FetchMiss::Empty) => Ok(None),
+            Err(FetchMiss::Failed(error)) => Err(error),
+        }
+    }
+
+    /// Drops the cached duties of a single epoch
+    pub fn invalidate(&self, epoch: u64) {
+        self.lock().cells.remove(&epoch);
+    }
+
+    /// Drops the cached duties of the given epoch and every later one
+    pub fn invalidate_from(&self, epoch: u64) {
+        self.lock().cells.split_off(&epoch);
+    }
+
+    fn get_cell(&self, epoch: u64) -> DutiesCell {
+        let mut entries = self.lock();
+
+        entries.lookups += 1;
+
+        let lookup = entries.lookups;
+
+        if let Some((cell, last_used)) = entries.cells.get_mut(&epoch) {
+            *last_used = lookup;
+
+            return cell.clone();
+        }
+
+        while entries.cells.len() >= self.capacity.max(1) {
+            let least_recently_used = entries
+                .cells
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(epoch, _)| *epoch);
+
+            match least_recently_used {
+                Some(epoch) => entries.cells.remove(&epoch),
+                None => break,
+            };
+        }
+
+        let cell = DutiesCell::default();
+
+        entries.cells.insert(epoch, (cell.clone(), lookup));
+
+        cell
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        sync::atomic::{AtomicU32, Ordering},
+    };
+
+    use super::*;
+
+    async fn lookup(cache: &ProposerDutiesCache, epoch: u64, fetches: &AtomicU32) {
+        cache
+            .get_or_try_fetch(epoch, || async move {
+                fetches.fetch_add(1, Ordering::Relaxed);
+
+                Ok::<_, Infallible>(Some(vec![]))
+            })
+            .await
+            .unwrap();
+    }
+
+    fn count(fetches: &AtomicU32) -> u32 {
+        fetches.load(Ordering::Relaxed)
+    }
+
+    #[tokio::test]
+    async fn keeps_the_epochs_a_reverse_backfill_uses_next_to_realtime_ones() {
+        let cache = ProposerDutiesCache::new(2);
+        let fetches = AtomicU32::new(0);
+
+        // A backfill worker stays on epoch 10 while realtime syncing reaches new epochs
+        lookup(&cache, 10, &fetches).await;
+        lookup(&cache, 500, &fetches).await;
+        lookup(&cache, 10, &fetches).await;
+        lookup(&cache, 501, &fetches).await;
+        lookup(&cache, 10, &fetches).await;
+
+        assert_eq!(count(&fetches), 3);
+    }
+
+    #[tokio::test]
+    async fn evicts_the_least_recently_used_epoch() {
+        let cache = ProposerDutiesCache::new(2);
+        let fetches = AtomicU32::new(0);
+
+        lookup(&cache, 1, &fetches).await;
+        lookup(&cache, 2, &fetches).await;
+        lookup(&cache, 1, &fetches).await;
+        lookup(&cache, 3, &fetches).await;
+
+        assert_eq!(count(&fetches), 3);
+
+        // Epoch 2 was evicted, epoch 1 wasn't
+        lookup(&cache, 1, &fetches).await;
+        assert_eq!(count(&fetches), 3);
+        lookup(&cache, 2, &fetches).await;
+        assert_eq!(count(&fetches), 4);
+    }
+
+    #[tokio::test]
+    async fn doesnt_cache_empty_fetches() {
+        let cache = ProposerDutiesCache::new(2);
+        let fetches = &AtomicU32::new(0);
+
+        for _ in 0..2 {
+            let duties = cache
+                .get_or_try_fetch(7, || async move {
+                    fetches.fetch_add(1, Ordering::Relaxed);
+
+                    Ok::<_, Infallible>(None)
+                })
+                .await
+                .unwrap();
+
+            assert!(duties.is_none());
+        }
+
+        assert_eq!(count(fetches), 2);
+    }
+
+    #[tokio::test]
+    async fn invalidates_later_epochs() {
+        let cache = ProposerDutiesCache::new(4);
+        let fetches = AtomicU32::new(0);
+
+        lookup(&cache, 1, &fetches).await;
+        lookup(&cache, 2, &fetches).await;
+        lookup(&cache, 3, &fetches).await;
+
+        cache.invalidate_from(2);
+
+        lookup(&cache, 1, &fetches).await;
+        lookup(&cache, 3, &fetches).await;
+
+        assert_eq!(count(&fetches), 4);
+    }
+}
@@ -1,56 +1,421 @@
-use envy::Error::MissingValue;
+use std::collections::HashMap;
+
+use envy::Error::{Custom, MissingValue};
 use serde::Deserialize;
 
-use crate::network::Network;
+use crate::{clients::common::HttpVersion, network::Network, utils::url::EndpointUrl};
 
 #[derive(Deserialize, Debug)]
 pub struct Environment {
     #[serde(default = "default_network")]
     pub network_name: Network,
-    #[serde(default = "default_blobscan_api_endpoint")]
-    pub blobscan_api_endpoint: String,
-    #[serde(default = "default_beacon_node_endpoint")]
-    pub beacon_node_endpoint: String,
-    #[serde(default = "default_execution_node_endpoint")]
-    pub execution_node_endpoint: String,
+    #[serde(rename = "blobscan_api_url", default = "default_blobscan_api_endpoint")]
+    pub blobscan_api_endpoint: EndpointUrl,
+    #[serde(rename = "beacon_api_url", default = "default_beacon_node_endpoint")]
+    pub beacon_node_endpoint: EndpointUrl,
+    #[serde(
+        rename = "execution_node_url",
+        default = "default_execution_node_endpoint"
+    )]
+    pub execution_node_endpoint: EndpointUrl,
+    /// Runs without an execution node, indexing only what the beacon node provides
+    #[serde(default)]
+    pub consensus_only: bool,
+    #[serde(default)]
+    pub beacon_http_version: HttpVersion,
+    #[serde(default)]
+    pub blobscan_http_version: HttpVersion,
     pub secret_key: String,
     pub dencun_fork_slot: Option<u32>,
+    #[serde(default)]
+    pub beacon_trace_bodies: bool,
+    #[serde(default)]
+    pub blobscan_trace_bodies: bool,
+    #[serde(default = "default_trace_bodies_max_bytes")]
+    pub trace_bodies_max_bytes: usize,
     pub sentry_dsn: Option<String>,
+    /// Deprecated names the settings were read from
+    #[serde(skip)]
+    pub deprecated_vars: Vec<DeprecatedEnvVar>,
+}
+
+/// A setting read from its deprecated name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeprecatedEnvVar {
+    pub name: &'static str,
+    pub replacement: &'static str,
 }
 
+/// Renamed settings as (deprecated name, replacement). The deprecated names keep working until
+/// they're dropped from this table.
+pub const DEPRECATED_ENV_VARS: &[(&str, &str)] = &[
+    ("BLOBSCAN_API_ENDPOINT", "BLOBSCAN_API_URL"),
+    ("BEACON_NODE_ENDPOINT", "BEACON_API_URL"),
+    ("EXECUTION_NODE_ENDPOINT", "EXECUTION_NODE_URL"),
+];
+
+/// Environment-only settings as (name, default, description), listed in the config template
+pub const ENV_SETTINGS: &[(&str, &str, &str)] = &[
+    (
+        "SECRET_KEY",
+        "",
+        "Secret used to sign blobscan API tokens (required)",
+    ),
+    (
+        "NETWORK_NAME",
+        "devnet",
+        "Network preset: mainnet, goerli, sepolia, holesky, devnet, gnosis or chiado",
+    ),
+    (
+        "BLOBSCAN_API_URL",
+        "http://localhost:3001",
+        "Blobscan API endpoint",
+    ),
+    (
+        "BEACON_API_URL",
+        "http://localhost:3500",
+        "Beacon node endpoint",
+    ),
+    (
+        "EXECUTION_NODE_URL",
+        "http://localhost:8545",
+        "Execution node endpoint",
+    ),
+    (
+        "CONSENSUS_ONLY",
+        "false",
+        "Run without an execution node: blocks come from the beacon execution payload, transactions aren't indexed and blobs aren't linked to them",
+    ),
+    (
+        "BEACON_HTTP_VERSION",
+        "auto",
+        "HTTP protocol used for the beacon node: auto, http1 or http2",
+    ),
+    (
+        "BLOBSCAN_HTTP_VERSION",
+        "auto",
+        "HTTP protocol used for blobscan: auto, http1 or http2",
+    ),
+    (
+        "DENCUN_FORK_SLOT",
+        "",
+        "Overrides the network preset's Dencun fork slot",
+    ),
+    (
+        "BEACON_TRACE_BODIES",
+        "false",
+        "Log beacon node response bodies at trace level, with blob data omitted",
+    ),
+    (
+        "BLOBSCAN_TRACE_BODIES",
+        "false",
+        "Log blobscan request and response bodies at trace level, with blob data omitted and secrets redacted",
+    ),
+    (
+        "TRACE_BODIES_MAX_BYTES",
+        "4096",
+        "Maximum bytes of each traced body",
+    ),
+    ("SENTRY_DSN", "", "Sentry DSN to report errors to"),
+];
+
 fn default_network() -> Network {
     Network::Devnet
 }
 
-fn default_blobscan_api_endpoint() -> String {
-    "http://localhost:3001".to_string()
+fn default_trace_bodies_max_bytes() -> usize {
+    4096
 }
 
-fn default_beacon_node_endpoint() -> String {
-    "http://localhost:3500".to_string()
+fn default_blobscan_api_endpoint() -> EndpointUrl {
+    EndpointUrl::parse("http://localhost:3001").unwrap()
 }
 
-fn default_execution_node_endpoint() -> String {
-    "http://localhost:8545".to_string()
+fn default_beacon_node_endpoint() -> EndpointUrl {
+    EndpointUrl::parse("http://localhost:3500").unwrap()
+}
+
+fn default_execution_node_endpoint() -> EndpointUrl {
+    EndpointUrl::parse("http://localhost:8545").unwrap()
+}
+
+/// Finds the settings given under a deprecated name, failing when both names are set to different
+/// values. A replacement set to the same value still counts the deprecated name as used.
+fn resolve_deprecated_vars(
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<(DeprecatedEnvVar, String)>, envy::Error> {
+    let mut resolved = vec![];
+
+    for &(name, replacement) in DEPRECATED_ENV_VARS {
+        let Some(value) = lookup(name) else {
+            continue;
+        };
+
+        match lookup(replacement) {
+            Some(replacement_value) if replacement_value != value => {
+                return Err(Custom(format!(
+                    "{name} and {replacement} are set to different values. {name} is deprecated, keep only {replacement}"
+                )))
+            }
+            _ => resolved.push((DeprecatedEnvVar { name, replacement }, value)),
+        }
+    }
+
+    Ok(resolved)
 }
 
 impl Environment {
     pub fn from_env() -> Result<Self, envy::Error> {
-        match envy::from_env::<Environment>() {
-            Ok(config) => {
-                if config.beacon_node_endpoint.is_empty() {
-                    return Err(MissingValue("BEACON_NODE_ENDPOINT"));
-                } else if config.blobscan_api_endpoint.is_empty() {
-                    return Err(MissingValue("BLOBSCAN_API_ENDPOINT"));
-                } else if config.execution_node_endpoint.is_empty() {
-                    return Err(MissingValue("EXECUTION_NODE_ENDPOINT"));
-                } else if config.secret_key.is_empty() {
+        Self::from_vars(std::env::vars())
+    }
+
+    /// Reads the settings from the given variables, which may use deprecated names
+    fn from_vars(vars: impl IntoIterator<Item = (String, String)>) -> Result<Self, envy::Error> {
+        let mut vars = vars.into_iter().collect::<HashMap<_, _>>();
+        let deprecated_vars = resolve_deprecated_vars(|name| vars.get(name).cloned())?;
+
+        // The replacements are what gets deserialized, so deprecated values are copied over
+        for (deprecated_var, value) in &deprecated_vars {
+            vars.insert(deprecated_var.replacement.to_string(), value.clone());
+        }
+
+        match envy::from_iter::<_, Environment>(vars) {
+            Ok(mut config) => {
+                if config.secret_key.is_empty() {
                     return Err(MissingValue("SECRET_KEY"));
                 }
 
+                config.deprecated_vars = deprecated_vars
+                    .into_iter()
+                    .map(|(deprecated_var, _)| deprecated_var)
+                    .collect();
+
                 Ok(config)
             }
             Err(err) => Err(err),
         }
     }
+
+    /// The execution node to index from, `None` in consensus-only mode
+    pub fn execution_node(&self) -> Option<&EndpointUrl> {
+        (!self.consensus_only).then_some(&self.execution_node_endpoint)
+    }
+
+    /// The deprecated name `replacement` was read from, if any
+    pub fn deprecated_source(&self, replacement: &str) -> Option<&'static str> {
+        self.deprecated_vars
+            .iter()
+            .find(|deprecated_var| deprecated_var.replacement == replacement)
+            .map(|deprecated_var| deprecated_var.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use envy::Error::{Custom, MissingValue};
+    use serde::{
+        de::{self, value::Error as ValueError, Visitor},
+        Deserialize, Deserializer,
+    };
+
+    use super::{DeprecatedEnvVar, Environment, DEPRECATED_ENV_VARS, ENV_SETTINGS};
+    use crate::{args::Args, utils::url::EndpointUrl};
+
+    /// Captures the field names a struct's `Deserialize` impl reads, renames applied and skipped
+    /// fields left out
+    struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> Deserializer<'de> for FieldNames<'_> {
+        type Error = ValueError;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("only structs have field names"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+
+            Err(de::Error::custom("field names captured"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+            ignored_any
+        }
+    }
+
+    /// Variables `Environment` reads, from its `Deserialize` impl so they can't drift from it
+    fn environment_var_names() -> Vec<String> {
+        let mut fields: &'static [&'static str] = &[];
+        let _ = Environment::deserialize(FieldNames(&mut fields));
+
+        fields.iter().map(|field| field.to_uppercase()).collect()
+    }
+
+    fn from_vars(vars: &[(&str, &str)]) -> Result<Environment, envy::Error> {
+        Environment::from_vars(
+            vars.iter()
+                .map(|(name, value)| (name.to_string(), value.to_string())),
+        )
+    }
+
+    fn environment(vars: &[(&str, &str)]) -> Environment {
+        from_vars(vars).unwrap()
+    }
+
+    fn url(url: &str) -> EndpointUrl {
+        EndpointUrl::parse(url).unwrap()
+    }
+
+    #[test]
+    fn every_environment_variable_is_listed_as_a_setting() {
+        let mut var_names = environment_var_names();
+        let mut setting_names = ENV_SETTINGS
+            .iter()
+            .map(|(name, _, _)| name.to_string())
+            .collect::<Vec<_>>();
+
+        var_names.sort();
+        setting_names.sort();
+
+        assert!(var_names.contains(&"SECRET_KEY".to_string()));
+        assert_eq!(setting_names, var_names);
+    }
+
+    #[test]
+    fn every_environment_variable_appears_in_the_template() {
+        let template = Args::config_template();
+
+        for name in environment_var_names() {
+            assert!(template.contains(&format!("\n#{name}=")), "{name}");
+        }
+    }
+
+    #[test]
+    fn documented_defaults_are_the_actual_defaults() {
+        let defaults = format!("{:?}", environment(&[("SECRET_KEY", "secret")]));
+
+        for (name, default, _) in ENV_SETTINGS {
+            if default.is_empty() {
+                continue;
+            }
+
+            let env = environment(&[("SECRET_KEY", "secret"), (*name, *default)]);
+
+            assert_eq!(format!("{env:?}"), defaults, "{name}={default}");
+        }
+    }
+
+    #[test]
+    fn deprecated_names_are_replaced_by_settings_in_the_template() {
+        let setting_names = ENV_SETTINGS
+            .iter()
+            .map(|(name, _, _)| *name)
+            .collect::<Vec<_>>();
+
+        for (name, replacement) in DEPRECATED_ENV_VARS {
+            assert!(setting_names.contains(replacement), "{replacement}");
+            assert!(!setting_names.contains(name), "{name}");
+            assert!(
+                !DEPRECATED_ENV_VARS
+                    .iter()
+                    .any(|(_, other_replacement)| other_replacement == name),
+                "{name} is both deprecated and a replacement"
+            );
+        }
+    }
+
+    #[test]
+    fn deprecated_names_still_set_their_replacement() {
+        let env = environment(&[
+            ("SECRET_KEY", "secret"),
+            ("BEACON_NODE_ENDPOINT", "http://beacon:5052"),
+        ]);
+
+        assert_eq!(env.beacon_node_endpoint, url("http://beacon:5052"));
+        assert_eq!(
+            env.deprecated_vars,
+            vec![DeprecatedEnvVar {
+                name: "BEACON_NODE_ENDPOINT",
+                replacement: "BEACON_API_URL",
+            }]
+        );
+        assert_eq!(
+            env.deprecated_source("BEACON_API_URL"),
+            Some("BEACON_NODE_ENDPOINT")
+        );
+        assert_eq!(env.deprecated_source("BLOBSCAN_API_URL"), None);
+    }
+
+    #[test]
+    fn replacements_are_used_as_is() {
+        let env = environment(&[
+            ("SECRET_KEY", "secret"),
+            ("BEACON_API_URL", "http://beacon:5052"),
+        ]);
+
+        assert_eq!(env.beacon_node_endpoint, url("http://beacon:5052"));
+        assert!(env.deprecated_vars.is_empty());
+    }
+
+    #[test]
+    fn both_names_with_the_same_value_count_as_deprecated() {
+        let env = environment(&[
+            ("SECRET_KEY", "secret"),
+            ("EXECUTION_NODE_ENDPOINT", "http://el:8545"),
+            ("EXECUTION_NODE_URL", "http://el:8545"),
+        ]);
+
+        assert_eq!(env.execution_node_endpoint, url("http://el:8545"));
+        assert_eq!(
+            env.deprecated_source("EXECUTION_NODE_URL"),
+            Some("EXECUTION_NODE_ENDPOINT")
+        );
+    }
+
+    #[test]
+    fn both_names_with_different_values_fail() {
+        let result = from_vars(&[
+            ("SECRET_KEY", "secret"),
+            ("BLOBSCAN_API_ENDPOINT", "http://old:3001"),
+            ("BLOBSCAN_API_URL", "http://new:3001"),
+        ]);
+
+        assert!(
+            matches!(result, Err(Custom(message)) if message.contains("BLOBSCAN_API_ENDPOINT"))
+        );
+    }
+
+    #[test]
+    fn secret_key_is_required() {
+        assert!(matches!(from_vars(&[]), Err(MissingValue(_))));
+        assert!(matches!(
+            from_vars(&[("SECRET_KEY", "")]),
+            Err(MissingValue("SECRET_KEY"))
+        ));
+    }
+
+    #[test]
+    fn execution_node_defaults_to_localhost() {
+        let env = environment(&[("SECRET_KEY", "secret")]);
+
+        assert_eq!(env.execution_node(), Some(&url("http://localhost:8545")));
+    }
+
+    #[test]
+    fn consensus_only_must_be_opted_into() {
+        let env = environment(&[
+            ("SECRET_KEY", "secret"),
+            ("EXECUTION_NODE_URL", "http://el:8545"),
+            ("CONSENSUS_ONLY", "true"),
+        ]);
+
+        assert!(env.execution_node().is_none());
+    }
 }
@@ -0,0 +1,203 @@
+use std::{
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(feature = "health")]
+use serde::Serialize;
+
+#[cfg(feature = "health")]
+use crate::{indexer::types::RealtimeTarget, metrics::Metrics};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Dependency {
+    Beacon,
+    Execution,
+    Blobscan,
+}
+
+/// Limits past which `/healthz` reports the indexer as unhealthy
+#[cfg(feature = "health")]
+#[derive(Debug, Clone, Copy)]
+pub struct HealthThresholds {
+    /// Slots allowed behind the realtime target, on top of the distance it keeps from the head
+    pub max_lag: u32,
+    pub max_head_failures: u32,
+    pub realtime_target: RealtimeTarget,
+}
+
+/// Liveness signals updated from the syncing tasks. Only atomics are touched, so recording
+/// never contends with the slots being processed.
+#[derive(Debug, Default)]
+pub struct HealthState {
+    /// Unix timestamps in seconds, 0 until the first successful call
+    last_beacon_success: AtomicU64,
+    last_execution_success: AtomicU64,
+    last_blobscan_success: AtomicU64,
+    consecutive_head_failures: AtomicU32,
+}
+
+#[cfg(feature = "health")]
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub last_indexed_slot: Option<u64>,
+    pub last_processed_slot: Option<u64>,
+    pub beacon_head_slot: Option<u64>,
+    pub lag_slots: Option<u64>,
+    pub last_beacon_success_at: Option<u64>,
+    pub last_execution_success_at: Option<u64>,
+    pub last_blobscan_success_at: Option<u64>,
+    pub consecutive_head_failures: u32,
+    pub problems: Vec<String>,
+}
+
+impl HealthState {
+    pub fn record_success(&self, dependency: Dependency, at: SystemTime) {
+        let timestamp = at
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let last_success = match dependency {
+            Dependency::Beacon => &self.last_beacon_success,
+            Dependency::Execution => &self.last_execution_success,
+            Dependency::Blobscan => &self.last_blobscan_success,
+        };
+
+        last_success.store(timestamp, Ordering::Relaxed);
+    }
+
+    /// Tracks the head fetches failing in a row. A successful fetch resets the streak
+    pub fn record_head_fetch(&self, succeeded: bool) {
+        if succeeded {
+            self.consecutive_head_failures.store(0, Ordering::Relaxed);
+        } else {
+            self.consecutive_head_failures
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Lag is measured from the last processed slot, skipped ones included, so stretches of
+    /// slots with nothing to index don't read as falling behind. `slots_per_epoch` is unknown
+    /// until the chain spec is resolved.
+    #[cfg(feature = "health")]
+    pub fn report(
+        &self,
+        metrics: &Metrics,
+        thresholds: HealthThresholds,
+        slots_per_epoch: Option<u32>,
+    ) -> HealthReport {
+        let last_indexed_slot = non_zero(metrics.last_indexed_slot());
+        let last_processed_slot = non_zero(metrics.last_processed_slot());
+        let beacon_head_slot = non_zero(metrics.beacon_head_slot());
+        let lag_slots = last_processed_slot
+            .zip(beacon_head_slot)
+            .map(|(processed, head)| head.saturating_sub(processed));
+        let consecutive_head_failures = self.consecutive_head_failures.load(Ordering::Relaxed);
+        let mut problems = vec![];
+        let max_lag = thresholds.max_lag as u64
+            + slots_per_epoch.map_or(0, |slots_per_epoch| {
+                thresholds.realtime_target.head_distance(slots_per_epoch) as u64
+            });
+
+        if let Some(lag) = lag_slots.filter(|lag| *lag > max_lag) {
+            problems.push(format!(
+                "lagging {lag} slots behind the head (max {max_lag})"
+            ));
+        }
+
+        if consecutive_head_failures >= thresholds.max_head_failures {
+            problems.push(format!(
+                "last {consecutive_head_failures} head fetches failed"
+            ));
+        }
+
+        HealthReport {
+            healthy: problems.is_empty(),
+            last_indexed_slot,
+            last_processed_slot,
+            beacon_head_slot,
+            lag_slots,
+            last_beacon_success_at: non_zero(self.last_beacon_success.load(Ordering::Relaxed)),
+            last_execution_success_at: non_zero(
+                self.last_execution_success.load(Ordering::Relaxed),
+            ),
+            last_blobscan_success_at: non_zero(self.last_blobscan_success.load(Ordering::Relaxed)),
+            consecutive_head_failures,
+            problems,
+        }
+    }
+}
+
+#[cfg(feature = "health")]
+fn non_zero(value: u64) -> Option<u64> {
+    (value != 0).then_some(value)
+}
+
+#[cfg(all(test, feature = "health"))]
+mod tests {
+    use super::*;
+
+    fn thresholds(realtime_target: RealtimeTarget) -> HealthThresholds {
+        HealthThresholds {
+            max_lag: 64,
+            max_head_failures: 3,
+            realtime_target,
+        }
+    }
+
+    #[test]
+    fn measures_lag_from_the_last_processed_slot() {
+        let metrics = Metrics::default();
+
+        metrics.set_beacon_head_slot(1_000);
+        // Blobless slots were skipped past the last indexed one
+        metrics.record_indexed_slot(800);
+        metrics.record_processed_slot(990);
+
+        let report =
+            HealthState::default().report(&metrics, thresholds(RealtimeTarget::Head), Some(32));
+
+        assert!(report.healthy);
+        assert_eq!(report.lag_slots, Some(10));
+    }
+
+    #[test]
+    fn allows_the_finalized_target_to_trail_the_head() {
+        let metrics = Metrics::default();
+
+        metrics.set_beacon_head_slot(1_000);
+        metrics.record_processed_slot(1_000 - 96);
+
+        let finalized = HealthState::default().report(
+            &metrics,
+            thresholds(RealtimeTarget::Finalized),
+            Some(32),
+        );
+        let head =
+            HealthState::default().report(&metrics, thresholds(RealtimeTarget::Head), Some(32));
+
+        assert!(finalized.healthy);
+        assert!(!head.healthy);
+    }
+
+    #[test]
+    fn reports_consecutive_head_failures() {
+        let health = HealthState::default();
+
+        for _ in 0..3 {
+            health.record_head_fetch(false);
+        }
+
+        let report = health.report(&Metrics::default(), thresholds(RealtimeTarget::Head), None);
+
+        assert!(!report.healthy);
+
+        health.record_head_fetch(true);
+
+        assert!(
+            health
+                .report(&Metrics::default(), thresholds(RealtimeTarget::Head), None)
+                .healthy
+        );
+    }
+}
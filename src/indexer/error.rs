@@ -1,8 +1,11 @@
 use tokio::sync::mpsc::error::SendError;
 
-use crate::{clients::common::ClientError, synchronizer::error::SynchronizerError};
+use crate::{
+    clients::{beacon::types::BlockId, common::ClientError},
+    synchronizer::error::SynchronizerError,
+};
 
-use super::types::IndexerTaskMessage;
+use super::types::{IndexerTaskMessage, RealtimeTarget};
 
 #[derive(Debug, thiserror::Error)]
 pub enum IndexerError {
@@ -14,6 +17,23 @@ pub enum IndexerError {
     BlobscanSyncStateRetrievalError(#[source] ClientError),
     #[error("sync task message send failure")]
     SyncingTaskMessageSendFailure(#[from] SendError<IndexerTaskMessage>),
+    #[error("failed to fetch the beacon spec and no --slots-per-epoch and --seconds-per-slot overrides are set")]
+    ChainSpecUnavailable,
+    #[cfg_attr(not(any(feature = "metrics", feature = "health")), allow(dead_code))]
+    #[error("failed to start the HTTP server on port {0}")]
+    HttpServerFailure(u16, #[source] anyhow::Error),
+    #[error("failed to resolve start block {0} to a slot")]
+    StartBlockResolutionFailure(BlockId, #[source] anyhow::Error),
+    #[error("failed to resolve the slot of the realtime target ({0})")]
+    RealtimeTargetResolutionFailure(RealtimeTarget, #[source] anyhow::Error),
+    #[error("incompatible configuration: {0}")]
+    IncompatibleSyncState(String),
+    #[error("start slot {start_slot} is ahead of the realtime target ({target}) at slot {target_slot}. Lower --from-slot or wait for the target to reach it")]
+    StartAheadOfRealtimeTarget {
+        start_slot: u32,
+        target: RealtimeTarget,
+        target_slot: u32,
+    },
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -22,6 +42,10 @@ pub enum SyncingTaskError {
     HistoricalSyncingTaskError(#[from] HistoricalSyncingError),
     #[error("an error occurred while syncing realtime data")]
     RealtimeSyncingTaskError(#[from] RealtimeSyncingError),
+    #[error("an error occurred while catching up to the realtime target")]
+    CatchUpSyncingTaskError(#[from] CatchUpSyncingError),
+    #[error("the {task} syncing task panicked: {message}")]
+    TaskPanicked { task: &'static str, message: String },
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -30,6 +54,14 @@ pub enum HistoricalSyncingError {
     SynchronizerError(#[from] SynchronizerError),
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum CatchUpSyncingError {
+    #[error("failed to resolve the realtime target slot")]
+    TargetResolutionFailure(#[source] anyhow::Error),
+    #[error(transparent)]
+    SynchronizerError(#[from] SynchronizerError),
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum RealtimeSyncingError {
     #[error("an error ocurred while receiving beacon events")]
@@ -62,6 +94,8 @@ pub enum FinalizedBlockEventHandlingError {
     Other(#[from] anyhow::Error),
     #[error("failed to update blobscan's last finalized block")]
     BlobscanSyncStateUpdateError(#[source] ClientError),
+    #[error(transparent)]
+    SynchronizerError(#[from] SynchronizerError),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -78,6 +112,8 @@ pub enum ChainReorgedEventHandlingError {
 pub enum HeadBlockEventHandlingError {
     #[error(transparent)]
     EventDeserializationFailure(#[from] serde_json::Error),
+    #[error("failed to poll the head block")]
+    HeadRetrievalError(#[source] ClientError),
     #[error(transparent)]
     SynchronizerError(#[from] SynchronizerError),
 }
@@ -0,0 +1,79 @@
+use std::collections::VecDeque;
+
+use ethers::types::H256;
+
+/// Ids of the most recently handled reorgs, so events redelivered after a reconnect are
+/// recognized. Only the latest ids are kept, as redeliveries follow the original event closely
+#[derive(Debug)]
+pub struct HandledReorgs {
+    ids: VecDeque<H256>,
+    capacity: usize,
+}
+
+impl HandledReorgs {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            ids: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn contains(&self, reorg_id: &H256) -> bool {
+        self.ids.contains(reorg_id)
+    }
+
+    /// Records a handled reorg, forgetting the oldest one when full
+    pub fn insert(&mut self, reorg_id: H256) {
+        if self.capacity == 0 || self.contains(&reorg_id) {
+            return;
+        }
+
+        if self.ids.len() == self.capacity {
+            self.ids.pop_front();
+        }
+
+        self.ids.push_back(reorg_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::H256;
+
+    use super::HandledReorgs;
+
+    #[test]
+    fn test_remembers_handled_reorgs() {
+        let mut handled_reorgs = HandledReorgs::new(2);
+
+        handled_reorgs.insert(H256::repeat_byte(1));
+
+        assert!(handled_reorgs.contains(&H256::repeat_byte(1)));
+        assert!(!handled_reorgs.contains(&H256::repeat_byte(2)));
+    }
+
+    #[test]
+    fn test_forgets_the_oldest_reorg_when_full() {
+        let mut handled_reorgs = HandledReorgs::new(2);
+
+        for byte in 1..=3 {
+            handled_reorgs.insert(H256::repeat_byte(byte));
+        }
+
+        assert!(!handled_reorgs.contains(&H256::repeat_byte(1)));
+        assert!(handled_reorgs.contains(&H256::repeat_byte(2)));
+        assert!(handled_reorgs.contains(&H256::repeat_byte(3)));
+    }
+
+    #[test]
+    fn test_reinserting_doesnt_evict() {
+        let mut handled_reorgs = HandledReorgs::new(2);
+
+        handled_reorgs.insert(H256::repeat_byte(1));
+        handled_reorgs.insert(H256::repeat_byte(2));
+        handled_reorgs.insert(H256::repeat_byte(2));
+
+        assert!(handled_reorgs.contains(&H256::repeat_byte(1)));
+        assert!(handled_reorgs.contains(&H256::repeat_byte(2)));
+    }
+}
@@ -0,0 +1,109 @@
+use ethers::types::H256;
+
+use crate::{
+    clients::beacon::types::BlockId,
+    synchronizer::{error::SynchronizerError, Synchronizer},
+};
+
+use super::types::RealtimeTarget;
+
+/// Realtime sync progress, shared by head events, head polling and finalized checkpoints.
+pub struct HeadSync {
+    target: RealtimeTarget,
+    /// Where the first head sync starts from. Later syncs start at the new head
+    start_block_id: Option<BlockId>,
+    /// Next slot to sync when following a lagging target
+    next_lagging_slot: Option<u32>,
+    last_head_block: Option<H256>,
+}
+
+impl HeadSync {
+    pub fn new(target: RealtimeTarget, start_block_id: BlockId) -> Self {
+        let next_lagging_slot = match start_block_id {
+            BlockId::Slot(slot) => Some(slot),
+            _ => None,
+        };
+
+        Self {
+            target,
+            start_block_id: Some(start_block_id),
+            next_lagging_slot,
+            last_head_block: None,
+        }
+    }
+
+    /// Syncs up to a new head, or to the slot the follow distance puts behind it. A head that was
+    /// already synced, like an event redelivered after reconnecting or an unchanged polled head,
+    /// is skipped
+    pub async fn on_head(
+        &mut self,
+        synchronizer: &Synchronizer,
+        slot: u32,
+        block_root: H256,
+    ) -> Result<(), SynchronizerError> {
+        if self.last_head_block == Some(block_root) {
+            return Ok(());
+        }
+
+        match self.target {
+            RealtimeTarget::Head => {
+                let head_block_id = BlockId::Slot(slot);
+                let initial_block_id = self
+                    .start_block_id
+                    .take()
+                    .unwrap_or_else(|| head_block_id.clone());
+
+                synchronizer.run(&initial_block_id, &head_block_id).await?;
+            }
+            RealtimeTarget::FollowDistance(distance) => {
+                if let Some(target_slot) = slot.checked_sub(distance) {
+                    self.sync_up_to_lagging_target(synchronizer, target_slot)
+                        .await?;
+                }
+            }
+            // Synced when the finalized checkpoint moves
+            RealtimeTarget::Finalized => {}
+        }
+
+        self.last_head_block = Some(block_root);
+
+        Ok(())
+    }
+
+    /// The checkpoint advances a whole epoch at a time, so every slot it moved past gets synced
+    /// at once
+    pub async fn on_finalized(
+        &mut self,
+        synchronizer: &Synchronizer,
+        finalized_slot: u32,
+    ) -> Result<(), SynchronizerError> {
+        if self.target != RealtimeTarget::Finalized {
+            return Ok(());
+        }
+
+        self.sync_up_to_lagging_target(synchronizer, finalized_slot)
+            .await
+    }
+
+    /// Syncs the slots a lagging target moved past since the last sync. Without a known next
+    /// slot, syncing starts at the target itself
+    async fn sync_up_to_lagging_target(
+        &mut self,
+        synchronizer: &Synchronizer,
+        target_slot: u32,
+    ) -> Result<(), SynchronizerError> {
+        let initial_slot = self.next_lagging_slot.unwrap_or(target_slot);
+
+        if initial_slot > target_slot {
+            return Ok(());
+        }
+
+        synchronizer
+            .run(&BlockId::Slot(initial_slot), &BlockId::Slot(target_slot))
+            .await?;
+
+        self.next_lagging_slot = Some(target_slot + 1);
+
+        Ok(())
+    }
+}
@@ -1,46 +1,105 @@
-use std::thread;
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::{anyhow, Context as AnyhowContext};
 
-use futures::StreamExt;
+use ethers::providers::Middleware;
+use futures::{Stream, StreamExt};
 use reqwest_eventsource::Event;
 use tokio::{sync::mpsc, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn, Instrument};
 
 use crate::{
     args::Args,
     clients::{
-        beacon::types::{
-            BlockId, ChainReorgEventData, FinalizedCheckpointEventData, HeadEventData, Topic,
+        beacon::{
+            fork::ForkSchedule,
+            types::{
+                BlockId, ChainReorgEventData, FinalizedCheckpointEventData, HeadEventData, Topic,
+            },
         },
-        blobscan::types::BlockchainSyncState,
+        blobscan::types::{BlockchainSyncState, ChainConfig, FailedSlotsChunk},
     },
-    context::{Config as ContextConfig, Context},
+    config_validation::bounded_run_resumes_below_to_slot,
+    context::{ChainSpec, Config as ContextConfig, Context, ContextHandle},
     env::Environment,
+    health::Dependency,
     indexer::error::{
         ChainReorgedEventHandlingError, FinalizedBlockEventHandlingError,
         HeadBlockEventHandlingError, HistoricalSyncingError,
     },
-    synchronizer::{CheckpointType, Synchronizer, SynchronizerBuilder},
-    utils::web3::get_full_hash,
+    local_checkpoint::LocalCheckpointFile,
+    profile::ProfileSettings,
+    skip_list::SkipList,
+    slots_processor::{
+        heartbeat::EmptyBlockHeartbeat, Config as SlotsProcessorConfig, IndexMode, SlotTiming,
+    },
+    synchronizer::{error::SynchronizerError, CheckpointType, Synchronizer, SynchronizerBuilder},
+    utils::{redaction::redact_error, web3::get_full_hash},
 };
 
+#[cfg(feature = "health")]
+use crate::health::HealthThresholds;
+#[cfg(any(feature = "metrics", feature = "health"))]
+use crate::server::{self, Endpoints};
+
 use self::{
-    error::{IndexerError, RealtimeSyncingError},
-    types::{IndexerResult, IndexerTaskMessage},
+    error::{CatchUpSyncingError, IndexerError, RealtimeSyncingError, SyncingTaskError},
+    handled_reorgs::HandledReorgs,
+    head_sync::HeadSync,
+    shutdown::spawn_shutdown_handler,
+    stall::{detect_stall, StallKind, StallTracker},
+    types::{IndexerResult, IndexerTaskMessage, RealtimeTarget},
 };
 
 pub mod error;
+pub mod handled_reorgs;
+pub mod head_sync;
+pub mod shutdown;
+pub mod stall;
 pub mod types;
 
+/// Fork that introduced blobs, as named in the beacon spec's `<FORK>_FORK_EPOCH` keys
+const BLOB_FORK_NAME: &str = "deneb";
+/// Handled reorgs remembered to skip redelivered events
+const HANDLED_REORGS_CAPACITY: usize = 64;
+
 pub struct Indexer {
     context: Context,
     dencun_fork_slot: u32,
     disable_sync_historical: bool,
+    disable_chain_config_push: bool,
 
     checkpoint_slots: Option<u32>,
+    checkpoint_interval: Option<Duration>,
     disabled_checkpoint: Option<CheckpointType>,
-    num_threads: u32,
+    concurrency: u32,
+    slots_processor_config: SlotsProcessorConfig,
+    /// Only given to the realtime synchronizer
+    empty_block_heartbeat: Option<Arc<EmptyBlockHeartbeat>>,
+    sample_rate: Option<u32>,
+    stall_slots: u32,
+    poll_fallback_interval: Duration,
+    shutdown_grace_period: Duration,
+    realtime_target: RealtimeTarget,
+    once: bool,
+    skip_list_path: Option<PathBuf>,
+    local_checkpoint: Option<Arc<LocalCheckpointFile>>,
+    #[cfg(feature = "metrics")]
+    metrics_port: Option<u16>,
+    #[cfg(feature = "health")]
+    health_port: Option<u16>,
+    #[cfg(feature = "health")]
+    health_thresholds: HealthThresholds,
+    slots_per_epoch: Option<u32>,
+    seconds_per_slot: Option<u32>,
+    cancellation_token: CancellationToken,
 }
 
 impl Indexer {
@@ -48,7 +107,7 @@ impl Indexer {
         let context = match Context::try_new(ContextConfig::from(env)) {
             Ok(c) => c,
             Err(error) => {
-                error!(?error, "Failed to create context");
+                error!(error = %redact_error(error.as_ref()), "Failed to create context");
 
                 return Err(IndexerError::CreationFailure(anyhow!(
                     "Failed to create context: {:?}",
@@ -57,36 +116,103 @@ impl Indexer {
             }
         };
 
+        if let Some(skip_list_path) = &args.skip_list {
+            let skip_list =
+                SkipList::load(skip_list_path).map_err(IndexerError::CreationFailure)?;
+
+            info!(
+                entries = skip_list.entries_count(),
+                "Skip list loaded from {}",
+                skip_list_path.display()
+            );
+
+            context.set_skip_list(skip_list);
+        }
+
+        if context.provider().is_none() {
+            warn!("CONSENSUS_ONLY is set. Running without an execution node: blocks are built from the beacon execution payload, transactions aren't indexed and blobs aren't linked to their transactions");
+        }
+
         let checkpoint_slots = args.slots_per_save;
+        let checkpoint_interval = args.checkpoint_interval_secs.map(Duration::from_secs);
         let disabled_checkpoint = if args.disable_sync_checkpoint_save {
             Some(CheckpointType::Disabled)
         } else {
             None
         };
-        let num_threads = match args.num_threads {
-            Some(num_threads) => num_threads,
-            None => thread::available_parallelism()
-                .map_err(|err| {
-                    IndexerError::CreationFailure(anyhow!(
-                        "Failed to get number of available threads: {:?}",
-                        err
-                    ))
-                })?
-                .get() as u32,
-        };
-        let disable_sync_historical = args.disable_sync_historical;
+        if args.num_threads.is_some() {
+            warn!("--num-threads is deprecated. Use --concurrency instead");
+        }
 
+        let concurrency = args.concurrency();
+        let disable_sync_historical = args.disable_sync_historical;
+        let disable_chain_config_push = args.disable_chain_config_push;
+        let profile_settings = ProfileSettings::resolve(args);
         let dencun_fork_slot = env
             .dencun_fork_slot
             .unwrap_or(env.network_name.dencun_fork_slot());
+        let slots_processor_config = SlotsProcessorConfig {
+            reindex_fields: args.reindex_fields.clone(),
+            empty_response_policy: profile_settings.empty_response_policy,
+            index_mode: args.index_mode,
+            index_empty_blocks: args.index_empty_blocks,
+            empty_block_heartbeat: None,
+            continue_on_error: args.continue_on_error,
+            max_failure_rate: args.max_failure_rate,
+            sample_rate: None,
+            record_skipped_slots: args.record_skipped_slots,
+            verify_parent_timestamp: profile_settings.verify_parent_timestamp,
+            verify_parent_number: profile_settings.verify_parent_number,
+            // Blobs-only indexing has no blob transactions to link the blobs to either way
+            skip_blocks_mismatch: profile_settings.skip_blocks_mismatch
+                || args.index_mode == IndexMode::BlobsOnly,
+            slot_timing: None,
+            latency_freshness_window: match args.latency_freshness_window {
+                0 => None,
+                secs => Some(Duration::from_secs(secs)),
+            },
+            max_reorg_depth: args.max_reorg_depth,
+            prefetch_slots: args.prefetch_slots,
+            blob_fork_slot: Some(dencun_fork_slot),
+        };
 
         Ok(Self {
             context,
             dencun_fork_slot,
             disable_sync_historical,
+            disable_chain_config_push,
             checkpoint_slots,
+            checkpoint_interval,
             disabled_checkpoint,
-            num_threads,
+            concurrency,
+            slots_processor_config,
+            empty_block_heartbeat: args
+                .empty_block_heartbeat_secs
+                .map(|secs| Arc::new(EmptyBlockHeartbeat::new(Duration::from_secs(secs)))),
+            sample_rate: args.sample_rate,
+            stall_slots: args.stall_slots,
+            poll_fallback_interval: Duration::from_secs(args.poll_fallback_interval),
+            shutdown_grace_period: Duration::from_secs(args.shutdown_grace_period),
+            realtime_target: RealtimeTarget::from_args(args),
+            once: args.once,
+            skip_list_path: args.skip_list.clone(),
+            local_checkpoint: args
+                .checkpoint_file
+                .clone()
+                .map(|path| Arc::new(LocalCheckpointFile::open(path))),
+            #[cfg(feature = "metrics")]
+            metrics_port: args.metrics_port,
+            #[cfg(feature = "health")]
+            health_port: args.health_port,
+            #[cfg(feature = "health")]
+            health_thresholds: HealthThresholds {
+                max_lag: args.health_max_lag,
+                max_head_failures: args.health_max_head_failures,
+                realtime_target: RealtimeTarget::from_args(args),
+            },
+            slots_per_epoch: args.slots_per_epoch,
+            seconds_per_slot: args.seconds_per_slot,
+            cancellation_token: CancellationToken::new(),
         })
     }
 
@@ -95,26 +221,145 @@ impl Indexer {
         start_block_id: Option<BlockId>,
         end_block_id: Option<BlockId>,
     ) -> IndexerResult<()> {
-        let sync_state = match self.context.blobscan_client().get_sync_state().await {
-            Ok(state) => state,
+        let started_at = Instant::now();
+
+        spawn_shutdown_handler(self.cancellation_token.clone(), self.shutdown_grace_period);
+
+        #[cfg(unix)]
+        if let Some(skip_list_path) = self.skip_list_path.clone() {
+            self._reload_skip_list_on_sighup(skip_list_path);
+        }
+
+        #[cfg(any(feature = "metrics", feature = "health"))]
+        self._serve_endpoints().await?;
+
+        let chain_config = match self._fetch_chain_config().await {
+            Ok(chain_config) => Some(chain_config),
             Err(error) => {
-                error!(?error, "Failed to fetch blobscan's sync state");
+                warn!(
+                    error = %redact_error(error.as_ref()),
+                    "Failed to fetch chain config. Skipping chain config push and indexing latency reporting"
+                );
 
-                return Err(IndexerError::BlobscanSyncStateRetrievalError(error));
+                None
             }
         };
+        let chain_spec = match (&chain_config, self.slots_per_epoch, self.seconds_per_slot) {
+            (Some(chain_config), _, _) => ChainSpec {
+                slots_per_epoch: chain_config.slots_per_epoch,
+                seconds_per_slot: chain_config.seconds_per_slot,
+                genesis_time: Some(chain_config.genesis_time),
+                column_layouts: chain_config.column_layouts.clone(),
+                blob_base_fee_update_fractions: chain_config.blob_base_fee_update_fractions.clone(),
+            },
+            (None, Some(slots_per_epoch), Some(seconds_per_slot)) => ChainSpec {
+                slots_per_epoch,
+                seconds_per_slot,
+                genesis_time: None,
+                column_layouts: BTreeMap::new(),
+                blob_base_fee_update_fractions: BTreeMap::new(),
+            },
+            _ => return Err(IndexerError::ChainSpecUnavailable),
+        };
+
+        info!(
+            slots_per_epoch = chain_spec.slots_per_epoch,
+            seconds_per_slot = chain_spec.seconds_per_slot,
+            from_beacon_spec = chain_config.is_some(),
+            "Chain spec resolved"
+        );
+
+        self.context.set_chain_spec(chain_spec);
+
+        if let Some(chain_config) = chain_config {
+            self.context
+                .beacon_client()
+                .set_fork_schedule(ForkSchedule::new(
+                    chain_config.slots_per_epoch,
+                    chain_config.fork_epochs.clone(),
+                ));
+
+            self.slots_processor_config.slot_timing = Some(SlotTiming {
+                genesis_time: chain_config.genesis_time,
+                seconds_per_slot: chain_config.seconds_per_slot,
+            });
+
+            // The spec knows the chain's actual fork epoch, which beats the network default
+            if let Some(blob_fork_slot) = blob_fork_slot(&chain_config) {
+                self.slots_processor_config.blob_fork_slot = Some(blob_fork_slot);
+            }
+
+            if !self.disable_chain_config_push {
+                self._push_chain_config(chain_config).await;
+            }
+        }
+
+        let sync_state = self._resolve_sync_state().await?;
+
+        if let Some(violation) = bounded_run_resumes_below_to_slot(
+            start_block_id.as_ref(),
+            end_block_id.as_ref(),
+            sync_state.as_ref(),
+        ) {
+            return Err(IndexerError::IncompatibleSyncState(violation));
+        }
+
+        // Named block ids are pinned to a slot once, so both syncing tasks start from the same one
+        let start_block_id = match start_block_id {
+            Some(
+                block_id @ (BlockId::Head
+                | BlockId::Finalized
+                | BlockId::Justified
+                | BlockId::Genesis),
+            ) => {
+                let slot = resolve_block_slot(&self.context, &block_id)
+                    .await
+                    .map_err(|error| {
+                        IndexerError::StartBlockResolutionFailure(block_id.clone(), error)
+                    })?;
+
+                info!(start_block_id = %block_id, slot, "Start block resolved");
+
+                Some(BlockId::Slot(slot))
+            }
+            start_block_id => start_block_id,
+        };
+
+        // Lagging targets also bound where syncing starts, so no slot past them gets indexed
+        let realtime_target_slot = self._resolve_realtime_target_slot().await?;
+        let latest_block_id = match realtime_target_slot {
+            Some(slot) => BlockId::Slot(slot),
+            None => BlockId::Head,
+        };
+
+        if let (Some(BlockId::Slot(start_slot)), Some(target_slot), None) =
+            (&start_block_id, realtime_target_slot, &end_block_id)
+        {
+            if *start_slot > target_slot {
+                return Err(IndexerError::StartAheadOfRealtimeTarget {
+                    start_slot: *start_slot,
+                    target: self.realtime_target,
+                    target_slot,
+                });
+            }
+        }
 
         let current_lower_block_id = match start_block_id.clone() {
+            // Sync ranges include both ends, so when the realtime task also starts at the given
+            // slot the historical one starts right below it
+            Some(BlockId::Slot(slot)) if end_block_id.is_none() => {
+                BlockId::Slot(slot.saturating_sub(1))
+            }
             Some(block_id) => block_id,
             None => match &sync_state {
                 Some(state) => match state.last_lower_synced_slot {
                     Some(slot) => BlockId::Slot(if slot > 0 { slot - 1 } else { 0 }),
                     None => match state.last_upper_synced_slot {
                         Some(slot) => BlockId::Slot(slot - 1),
-                        None => BlockId::Head,
+                        None => latest_block_id.clone(),
                     },
                 },
-                None => BlockId::Head,
+                None => latest_block_id.clone(),
             },
         };
         let current_upper_block_id = match start_block_id {
@@ -127,7 +372,7 @@ impl Indexer {
                         None => BlockId::Slot(1),
                     },
                 },
-                None => BlockId::Head,
+                None => latest_block_id,
             },
         };
 
@@ -137,24 +382,64 @@ impl Indexer {
             "Starting indexer…",
         );
 
+        // Bounded runs only cover their own range
+        if end_block_id.is_none() {
+            self._retry_failed_slots_chunks().await;
+        }
+
         let (tx, mut rx) = mpsc::channel(32);
         let tx1 = tx.clone();
         let mut total_tasks = 0;
+        let is_bounded = end_block_id.is_some();
+
+        match end_block_id {
+            // A bounded run syncs its range from the start slot, or from the last slot blobscan
+            // knows about, and exits. It leaves blobscan's sync checkpoints alone, so disjoint
+            // ranges can be backfilled by several indexers at once
+            Some(end_block_id) => {
+                info!(
+                    "Bounded run: blobscan's sync checkpoints won't be updated, so later runs resume from where they were"
+                );
+
+                let handle = self._start_historical_syncing_task(
+                    tx.clone(),
+                    CheckpointType::Disabled,
+                    current_upper_block_id,
+                    end_block_id,
+                );
+
+                watch_task("backfill", tx, handle);
+                total_tasks += 1;
+            }
+            // Runs like the realtime task's first sync, without following the chain afterwards.
+            // Upper checkpoints are saved, so the next run resumes where this one stopped
+            None if self.once => {
+                let handle = self._start_catch_up_task(tx.clone(), current_upper_block_id);
 
-        if end_block_id.is_none() {
-            self._start_realtime_syncing_task(tx, current_upper_block_id);
-            total_tasks += 1;
-        }
+                watch_task("catch-up", tx, handle);
+                total_tasks += 1;
+            }
+            None => {
+                let handle = self._start_realtime_syncing_task(tx.clone(), current_upper_block_id);
+
+                watch_task("realtime", tx, handle);
+                total_tasks += 1;
 
-        let default_end_block = BlockId::Slot(self.dencun_fork_slot);
-        let end_block_id = end_block_id.unwrap_or(default_end_block);
-        let historical_sync_completed =
-            matches!(current_lower_block_id, BlockId::Slot(slot) if slot < self.dencun_fork_slot);
+                let end_block_id = BlockId::Slot(self.dencun_fork_slot);
+                let historical_sync_completed = matches!(current_lower_block_id, BlockId::Slot(slot) if slot < self.dencun_fork_slot);
 
-        if !self.disable_sync_historical && !historical_sync_completed {
-            self._start_historical_syncing_task(tx1, current_lower_block_id, end_block_id);
+                if !self.disable_sync_historical && !historical_sync_completed {
+                    let handle = self._start_historical_syncing_task(
+                        tx1.clone(),
+                        CheckpointType::Lower,
+                        current_lower_block_id,
+                        end_block_id,
+                    );
 
-            total_tasks += 1;
+                    watch_task("historical", tx1, handle);
+                    total_tasks += 1;
+                }
+            }
         }
 
         let mut completed_tasks = 0;
@@ -165,11 +450,34 @@ impl Indexer {
                     completed_tasks += 1;
 
                     if completed_tasks == total_tasks {
+                        if is_bounded {
+                            let stats = self.context.indexing_stats().snapshot();
+
+                            info!(
+                                blocks = stats.blocks,
+                                transactions = stats.transactions,
+                                blobs = stats.blobs,
+                                blob_fees_burned = %stats.blob_fees_burned,
+                                skipped_slots = ?self.context.metrics().skipped_slots(),
+                                elapsed = ?started_at.elapsed(),
+                                "Slot range synced"
+                            );
+                        }
+
                         return Ok(());
                     }
                 }
                 IndexerTaskMessage::Error(error) => {
-                    error!(?error, "An error occurred while running a syncing task");
+                    if self.cancellation_token.is_cancelled() {
+                        info!("Indexer stopped");
+
+                        return Ok(());
+                    }
+
+                    error!(error = %redact_error(&error), "An error occurred while running a syncing task");
+
+                    // Lets the remaining tasks stop at their next slot instead of mid-submission
+                    self.cancellation_token.cancel();
 
                     return Err(error.into());
                 }
@@ -182,10 +490,11 @@ impl Indexer {
     fn _start_historical_syncing_task(
         &self,
         tx: mpsc::Sender<IndexerTaskMessage>,
+        checkpoint_type: CheckpointType,
         start_block_id: BlockId,
         end_block_id: BlockId,
     ) -> JoinHandle<IndexerResult<()>> {
-        let mut synchronizer = self._create_synchronizer(CheckpointType::Lower);
+        let synchronizer = self._create_synchronizer(checkpoint_type);
 
         tokio::spawn(async move {
             let historical_syc_thread_span = tracing::info_span!("sync:historical");
@@ -215,13 +524,85 @@ impl Indexer {
         })
     }
 
-    fn _start_realtime_syncing_task(
+    fn _start_catch_up_task(
         &self,
         tx: mpsc::Sender<IndexerTaskMessage>,
         start_block_id: BlockId,
     ) -> JoinHandle<IndexerResult<()>> {
         let task_context = self.context.clone();
-        let mut synchronizer = self._create_synchronizer(CheckpointType::Upper);
+        let realtime_target = self.realtime_target;
+        let synchronizer = self._create_synchronizer(CheckpointType::Upper);
+
+        tokio::spawn(async move {
+            let catch_up_span = tracing::info_span!("sync:catch-up");
+
+            let result: Result<(), CatchUpSyncingError> = async {
+                let target_slot = resolve_target_slot(&task_context, realtime_target)
+                    .await
+                    .map_err(CatchUpSyncingError::TargetResolutionFailure)?;
+
+                if matches!(start_block_id, BlockId::Slot(slot) if slot > target_slot) {
+                    info!(target_slot, "Already caught up");
+
+                    return Ok(());
+                }
+
+                synchronizer
+                    .run(&start_block_id, &BlockId::Slot(target_slot))
+                    .await?;
+
+                // The target usually moves while syncing. A single extra pass closes that gap,
+                // anything newer is left to the next run
+                let latest_target_slot = resolve_target_slot(&task_context, realtime_target)
+                    .await
+                    .map_err(CatchUpSyncingError::TargetResolutionFailure)?;
+
+                if latest_target_slot > target_slot {
+                    synchronizer
+                        .run(
+                            &BlockId::Slot(target_slot + 1),
+                            &BlockId::Slot(latest_target_slot),
+                        )
+                        .await?;
+                }
+
+                info!(
+                    target_slot = latest_target_slot.max(target_slot),
+                    skipped_slots = ?task_context.metrics().skipped_slots(),
+                    "Caught up"
+                );
+
+                Ok(())
+            }
+            .instrument(catch_up_span)
+            .await;
+
+            match result {
+                Ok(()) => tx.send(IndexerTaskMessage::Done).await?,
+                Err(error) => tx.send(IndexerTaskMessage::Error(error.into())).await?,
+            }
+
+            Ok(())
+        })
+    }
+
+    fn _start_realtime_syncing_task(
+        &self,
+        tx: mpsc::Sender<IndexerTaskMessage>,
+        start_block_id: BlockId,
+    ) -> JoinHandle<IndexerResult<()>> {
+        // The synchronizer shares the task's handle, so reorgs invalidate the duties it cached
+        let task_context = self.context.handle();
+        let cancellation_token = self.cancellation_token.clone();
+        let realtime_target = self.realtime_target;
+        let poll_fallback_interval = self.poll_fallback_interval;
+        let synchronizer = self._create_realtime_synchronizer(task_context.clone());
+        let stall_timeout = match self.stall_slots {
+            0 => None,
+            stall_slots => Some(Duration::from_secs(
+                stall_slots as u64 * self.context.chain_spec().seconds_per_slot as u64,
+            )),
+        };
 
         tokio::spawn(async move {
             let realtime_sync_task_span = tracing::info_span!("sync:realtime");
@@ -237,7 +618,10 @@ impl Indexer {
                 let mut event_source = task_context
                     .beacon_client()
                     .subscribe_to_events(&topics).map_err(RealtimeSyncingError::BeaconEventsSubscriptionError)?;
-                let mut is_initial_sync_to_head = true;
+                let mut head_sync = HeadSync::new(realtime_target, start_block_id);
+                let mut is_polling_head = false;
+                let mut stall_tracker = StallTracker::default();
+                let mut handled_reorg_ids = HandledReorgs::new(HANDLED_REORGS_CAPACITY);
                 let events = topics
                 .iter()
                 .map(|topic| topic.into())
@@ -246,10 +630,79 @@ impl Indexer {
 
                 info!("Subscribed to beacon events: {events}");
 
-                while let Some(event) = event_source.next().await {
+                loop {
+                    let timeout = if is_polling_head {
+                        Some(poll_fallback_interval)
+                    } else {
+                        stall_timeout
+                    };
+                    let event = match next_event(&mut event_source, timeout, &cancellation_token).await {
+                        EventWait::Event(event) => {
+                            stall_tracker.reset();
+
+                            event
+                        }
+                        EventWait::Cancelled => break,
+                        EventWait::TimedOut if is_polling_head => {
+                            let head_poll_span = tracing::info_span!("head_poll");
+
+                            let result: Result<(), HeadBlockEventHandlingError> = async {
+                                let head_block_header = beacon_client.get_block_header(&BlockId::Head).await;
+
+                                task_context.health().record_head_fetch(head_block_header.is_ok());
+
+                                if let Some(head_block_header) = head_block_header.map_err(HeadBlockEventHandlingError::HeadRetrievalError)? {
+                                    task_context.health().record_success(Dependency::Beacon, task_context.clock().now());
+                                    task_context.metrics().set_beacon_head_slot(head_block_header.header.message.slot);
+                                    head_sync.on_head(&synchronizer, head_block_header.header.message.slot, head_block_header.root).await?;
+                                }
+
+                                Ok(())
+                            }.instrument(head_poll_span).await;
+
+                            if let Err(error) = result {
+                                return Err(RealtimeSyncingError::BeaconEventProcessingError(error.into()));
+                            }
+
+                            continue;
+                        }
+                        EventWait::TimedOut => {
+                            let stall_timeout = timeout.unwrap_or_default();
+                            let stall_kind = tokio::select! {
+                                biased;
+                                _ = cancellation_token.cancelled() => break,
+                                stall_kind = detect_stall(&task_context, stall_timeout) => stall_kind,
+                            };
+
+                            // Later timeouts of the same stall only repeat at debug level
+                            if !stall_tracker.observe(stall_kind) {
+                                debug!(stalled_for = ?stall_timeout, ?stall_kind, "Still no beacon events received");
+
+                                continue;
+                            }
+
+                            match stall_kind {
+                                StallKind::NodeStalled => warn!(stalled_for = ?stall_timeout, "No beacon events received while the execution layer keeps advancing. The beacon node looks stalled"),
+                                StallKind::ChainStalled => error!(stalled_for = ?stall_timeout, "No beacon events received and the execution layer stopped advancing. The chain looks stalled"),
+                                StallKind::Unknown => warn!(stalled_for = ?stall_timeout, "No beacon events received. Couldn't tell whether the node or the chain is stalled"),
+                            }
+
+                            continue;
+                        }
+                    };
+                    let Some(event) = event else {
+                        break;
+                    };
+
                     match event {
                         Ok(Event::Open) => {
-                            debug!("Subscription connection opened")
+                            debug!("Subscription connection opened");
+
+                            if is_polling_head {
+                                is_polling_head = false;
+
+                                info!("Beacon event stream reconnected. Stopped polling the head");
+                            }
                         }
                         Ok(Event::Message(event)) => {
                             let event_name = event.event.as_str();
@@ -264,6 +717,14 @@ impl Indexer {
                                         let slot = reorg_block_data.slot;
                                         let old_head_block = reorg_block_data.old_head_block;
                                         let target_depth = reorg_block_data.depth;
+                                        let reorg_id = reorg_block_data.reorg_id();
+
+                                        // Event streams may redeliver an event after reconnecting
+                                        if handled_reorg_ids.contains(&reorg_id) {
+                                            debug!(event=event_name, slot=slot, ?reorg_id, "Skipping already handled reorg");
+
+                                            return Ok(());
+                                        }
 
                                         let mut current_reorged_block = old_head_block;
                                         let mut reorged_slots: Vec<u32> = vec![];
@@ -281,7 +742,19 @@ impl Indexer {
                                             current_reorged_block = reorged_block_head.header.message.parent_root;
                                         }
 
-                                        let total_updated_slots = blobscan_client.handle_reorged_slots(&reorged_slots).await.map_err(|err| ChainReorgedEventHandlingError::ReorgedHandlingFailure(target_depth, get_full_hash(&old_head_block), err))?;
+                                        let total_updated_slots = blobscan_client.handle_reorged_slots(reorg_id, &reorged_slots).await.map_err(|err| ChainReorgedEventHandlingError::ReorgedHandlingFailure(target_depth, get_full_hash(&old_head_block), err))?;
+
+                                        // Duties are fixed at the epoch start, so only a reorg reaching into an earlier epoch can change them
+                                        if let Some(oldest_reorged_slot) = reorged_slots.iter().min() {
+                                            let chain_spec = task_context.chain_spec();
+                                            let oldest_reorged_epoch = chain_spec.epoch_of(*oldest_reorged_slot);
+
+                                            if oldest_reorged_epoch < chain_spec.epoch_of(slot) {
+                                                task_context.proposer_duties_cache().invalidate_from(oldest_reorged_epoch);
+                                            }
+                                        }
+
+                                        handled_reorg_ids.insert(reorg_id);
 
                                         info!(event=event_name, slot=slot, "Reorganization of depth {target_depth} detected. Found the following reorged slots: {:#?}. Total slots marked as reorged: {total_updated_slots}", reorged_slots);
 
@@ -310,17 +783,10 @@ impl Indexer {
                                         let head_block_data =
                                         serde_json::from_str::<HeadEventData>(&event.data)?;
 
-
-                                    let head_block_id = &BlockId::Slot(head_block_data.slot);
-                                    let initial_block_id = if is_initial_sync_to_head {
-                                        is_initial_sync_to_head = false;
-
-                                        &start_block_id
-                                    } else {
-                                        head_block_id
-                                    };
-
-                                    synchronizer.run(initial_block_id, &BlockId::Slot(head_block_data.slot + 1)).await?;
+                                    task_context.health().record_head_fetch(true);
+                                    task_context.health().record_success(Dependency::Beacon, task_context.clock().now());
+                                    task_context.metrics().set_beacon_head_slot(head_block_data.slot);
+                                    head_sync.on_head(&synchronizer, head_block_data.slot, head_block_data.block).await?;
 
                                     Ok(())
                                     }.instrument(head_span).await;
@@ -332,23 +798,27 @@ impl Indexer {
                                 "finalized_checkpoint" => {
                                     let finalized_checkpoint_span = tracing::info_span!("finalized_checkpoint");
 
-                                    let result: Result<(), FinalizedBlockEventHandlingError> = async move {
+                                    let result: Result<(), FinalizedBlockEventHandlingError> = async {
                                         let finalized_checkpoint_data =
                                             serde_json::from_str::<FinalizedCheckpointEventData>(
                                                 &event.data,
                                             )?;
                                         let block_hash = finalized_checkpoint_data.block;
-                                        let last_finalized_block_number = beacon_client
+                                        let finalized_block = beacon_client
                                             .get_block(&BlockId::Hash(block_hash))
                                             .await.map_err(|err| FinalizedBlockEventHandlingError::BlockRetrievalError(get_full_hash(&block_hash), err))?
                                             .with_context(|| {
                                                 anyhow!("Finalized block not found")
-                                            })?
+                                            })?;
+                                        let finalized_slot = finalized_block.message.slot;
+                                        let last_finalized_block_number = finalized_block
                                             .message.body.execution_payload
                                             .with_context(|| {
                                                 anyhow!("Finalized block has no execution payload")
                                             })?.block_number;
 
+                                        head_sync.on_finalized(&synchronizer, finalized_slot).await?;
+
                                         blobscan_client
                                             .update_sync_state(BlockchainSyncState {
                                                 last_lower_synced_slot: None,
@@ -374,10 +844,18 @@ impl Indexer {
                                 },
                             }
                         },
+                        // The event source reconnects on its own with backoff. Meanwhile the head is
+                        // polled so indexing doesn't stop, which also covers nodes without SSE support
                         Err(error) => {
-                            event_source.close();
+                            task_context.health().record_head_fetch(false);
 
-                            return Err(error.into());
+                            if is_polling_head {
+                                debug!(error = %redact_error(&error), "Beacon event stream still unavailable");
+                            } else {
+                                is_polling_head = true;
+
+                                warn!(error = %redact_error(&error), "Beacon event stream failed. Polling the head every {poll_fallback_interval:?} until it reconnects…");
+                            }
                         }
                     }
                 }
@@ -397,19 +875,614 @@ impl Indexer {
         })
     }
 
+    /// Re-reads the skip list on SIGHUP. An invalid file keeps the current list
+    #[cfg(unix)]
+    fn _reload_skip_list_on_sighup(&self, skip_list_path: PathBuf) {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let context = self.context.clone();
+
+        tokio::spawn(async move {
+            let mut hangups = match signal(SignalKind::hangup()) {
+                Ok(hangups) => hangups,
+                Err(error) => {
+                    warn!(
+                        error = %redact_error(&error),
+                        "Failed to listen for SIGHUP. The skip list won't be reloaded"
+                    );
+
+                    return;
+                }
+            };
+
+            while hangups.recv().await.is_some() {
+                match SkipList::load(&skip_list_path) {
+                    Ok(skip_list) => {
+                        info!(entries = skip_list.entries_count(), "Skip list reloaded");
+
+                        context.set_skip_list(skip_list);
+                    }
+                    Err(error) => warn!(
+                        error = %redact_error(error.as_ref()),
+                        "Failed to reload the skip list. Keeping the current one"
+                    ),
+                }
+            }
+        });
+    }
+
+    /// Starts the HTTP servers of the enabled endpoints, one per port
+    #[cfg(any(feature = "metrics", feature = "health"))]
+    async fn _serve_endpoints(&self) -> IndexerResult<()> {
+        let mut servers: BTreeMap<u16, Endpoints> = BTreeMap::new();
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics_port) = self.metrics_port {
+            servers.entry(metrics_port).or_default().metrics = true;
+        }
+
+        #[cfg(feature = "health")]
+        if let Some(health_port) = self.health_port {
+            servers.entry(health_port).or_default().health = Some(self.health_thresholds);
+        }
+
+        for (port, endpoints) in servers {
+            server::serve(self.context.clone(), port, endpoints)
+                .await
+                .map_err(|error| IndexerError::HttpServerFailure(port, error))?;
+        }
+
+        Ok(())
+    }
+
+    /// Blobscan's sync state, combined with the local checkpoint file when there is one. Each
+    /// synced slot is taken from whichever source went further
+    async fn _resolve_sync_state(&self) -> IndexerResult<Option<BlockchainSyncState>> {
+        let api_sync_state = self.context.blobscan_client().get_sync_state().await;
+        let local_checkpoint = self
+            .local_checkpoint
+            .as_ref()
+            .map(|local_checkpoint| local_checkpoint.checkpoint())
+            .filter(|checkpoint| !checkpoint.is_empty());
+
+        let Some(local_checkpoint) = local_checkpoint else {
+            return api_sync_state.map_err(|error| {
+                error!(error = %redact_error(&error), "Failed to fetch blobscan's sync state");
+
+                IndexerError::BlobscanSyncStateRetrievalError(error)
+            });
+        };
+
+        let api_sync_state = match api_sync_state {
+            Ok(Some(state)) => state,
+            Ok(None) => {
+                info!("Blobscan has no sync state. Resuming from the local checkpoint");
+
+                return Ok(Some(local_checkpoint.into()));
+            }
+            Err(error) => {
+                warn!(
+                    error = %redact_error(&error),
+                    "Failed to fetch blobscan's sync state. Resuming from the local checkpoint"
+                );
+
+                return Ok(Some(local_checkpoint.into()));
+            }
+        };
+
+        // Upper syncs move forward and lower ones backwards, so further means higher and lower
+        // respectively
+        let last_upper_synced_slot = match (
+            api_sync_state.last_upper_synced_slot,
+            local_checkpoint.last_upper_synced_slot,
+        ) {
+            (Some(api_slot), Some(local_slot)) if local_slot > api_slot => {
+                info!(
+                    api_slot,
+                    local_slot, "Resuming upper sync from the local checkpoint"
+                );
+
+                Some(local_slot)
+            }
+            (None, Some(local_slot)) => {
+                info!(local_slot, "Resuming upper sync from the local checkpoint");
+
+                Some(local_slot)
+            }
+            (api_slot, _) => api_slot,
+        };
+        let last_lower_synced_slot = match (
+            api_sync_state.last_lower_synced_slot,
+            local_checkpoint.last_lower_synced_slot,
+        ) {
+            (Some(api_slot), Some(local_slot)) if local_slot < api_slot => {
+                info!(
+                    api_slot,
+                    local_slot, "Resuming lower sync from the local checkpoint"
+                );
+
+                Some(local_slot)
+            }
+            (None, Some(local_slot)) => {
+                info!(local_slot, "Resuming lower sync from the local checkpoint");
+
+                Some(local_slot)
+            }
+            (api_slot, _) => api_slot,
+        };
+
+        Ok(Some(BlockchainSyncState {
+            last_upper_synced_slot,
+            last_lower_synced_slot,
+            ..api_sync_state
+        }))
+    }
+
+    async fn _resolve_realtime_target_slot(&self) -> IndexerResult<Option<u32>> {
+        if self.realtime_target == RealtimeTarget::Head {
+            return Ok(None);
+        }
+
+        let slot = resolve_target_slot(&self.context, self.realtime_target)
+            .await
+            .map_err(|error| {
+                IndexerError::RealtimeTargetResolutionFailure(self.realtime_target, error)
+            })?;
+
+        Ok(Some(slot))
+    }
+
+    async fn _push_chain_config(&self, chain_config: ChainConfig) {
+        match self
+            .context
+            .blobscan_client()
+            .update_chain_config(chain_config)
+            .await
+        {
+            Ok(_) => info!("Chain config pushed to blobscan"),
+            Err(error) => {
+                warn!(error = %redact_error(&error), "Failed to push chain config to blobscan")
+            }
+        }
+    }
+
+    async fn _fetch_chain_config(&self) -> Result<ChainConfig, anyhow::Error> {
+        let beacon_client = self.context.beacon_client();
+
+        let spec = beacon_client
+            .get_spec()
+            .await?
+            .with_context(|| "Beacon spec not found")?;
+        let genesis = beacon_client
+            .get_genesis()
+            .await?
+            .with_context(|| "Beacon genesis not found")?;
+        let chain_id = match self.context.provider() {
+            Some(provider) => provider.get_chainid().await?.as_u64(),
+            None => spec
+                .deposit_chain_id
+                .with_context(|| "Beacon spec has no deposit chain id")?,
+        };
+
+        Ok(ChainConfig {
+            chain_id,
+            genesis_time: genesis.genesis_time,
+            seconds_per_slot: spec.seconds_per_slot,
+            slots_per_epoch: spec.slots_per_epoch,
+            max_blobs_per_block: spec.max_blobs_per_block,
+            fork_epochs: spec.fork_epochs,
+            column_layouts: spec.column_layouts,
+            blob_base_fee_update_fractions: spec.blob_base_fee_update_fractions,
+        })
+    }
+
     fn _create_synchronizer(&self, checkpoint_type: CheckpointType) -> Synchronizer {
+        self._synchronizer_builder(checkpoint_type)
+            .build(self.context.handle())
+    }
+
+    /// Backfills and catch-up runs process slots behind the head, so only the realtime
+    /// synchronizer sends empty block heartbeats, keeping blobscan's freshness from moving back
+    fn _create_realtime_synchronizer(&self, context: ContextHandle) -> Synchronizer {
+        let mut synchronizer_builder = self._synchronizer_builder(CheckpointType::Upper);
+        let mut slots_processor_config = self.slots_processor_config.clone();
+
+        slots_processor_config.empty_block_heartbeat = self.empty_block_heartbeat.clone();
+
+        synchronizer_builder
+            .with_slots_processor_config(slots_processor_config)
+            .build(context)
+    }
+
+    fn _synchronizer_builder(&self, checkpoint_type: CheckpointType) -> SynchronizerBuilder {
         let mut synchronizer_builder = SynchronizerBuilder::new();
 
         if let Some(checkpoint_slots) = self.checkpoint_slots {
             synchronizer_builder.with_slots_checkpoint(checkpoint_slots);
         }
 
+        if let Some(checkpoint_interval) = self.checkpoint_interval {
+            synchronizer_builder.with_checkpoint_interval(checkpoint_interval);
+        }
+
+        // Sampling only applies to historical backfill
+        let is_historical_sync = checkpoint_type == CheckpointType::Lower;
         let checkpoint_type = self.disabled_checkpoint.unwrap_or(checkpoint_type);
 
         synchronizer_builder.with_checkpoint_type(checkpoint_type);
 
-        synchronizer_builder.with_num_threads(self.num_threads);
+        synchronizer_builder.with_concurrency(self.concurrency);
+
+        synchronizer_builder.with_cancellation_token(self.cancellation_token.child_token());
+
+        let mut slots_processor_config = self.slots_processor_config.clone();
+
+        if is_historical_sync {
+            slots_processor_config.sample_rate = self.sample_rate;
+        }
+
+        synchronizer_builder.with_slots_processor_config(slots_processor_config);
+
+        if let Some(local_checkpoint) = &self.local_checkpoint {
+            synchronizer_builder.with_local_checkpoint(local_checkpoint.clone());
+        }
+
+        synchronizer_builder
+    }
+
+    /// Reprocesses the chunks recorded as failed by previous runs, removing each one from
+    /// blobscan once it syncs. Chunks that fail again are kept for the next start
+    async fn _retry_failed_slots_chunks(&self) {
+        let blobscan_client = self.context.blobscan_client();
+        let failed_chunks = match blobscan_client.get_failed_slots_chunks().await {
+            Ok(failed_chunks) => failed_chunks.unwrap_or_default(),
+            Err(error) => {
+                warn!(
+                    error = %redact_error(&error),
+                    "Failed to fetch failed slots chunks. Skipping their retry"
+                );
+
+                return;
+            }
+        };
+
+        if failed_chunks.is_empty() {
+            return;
+        }
+
+        info!("Retrying {} failed slots chunks…", failed_chunks.len());
+
+        // Retries leave the sync checkpoints alone and don't record their own failures, as the
+        // chunk is still stored
+        let synchronizer = self
+            ._synchronizer_builder(CheckpointType::Disabled)
+            .with_failed_chunks_recording(false)
+            .build(self.context.handle());
+
+        let synced_chunk_ids = retry_failed_chunks(
+            failed_chunks,
+            &self.cancellation_token,
+            |initial_slot, final_slot| {
+                let synchronizer = &synchronizer;
+
+                async move {
+                    synchronizer
+                        .run(&BlockId::Slot(initial_slot), &BlockId::Slot(final_slot))
+                        .await
+                }
+            },
+        )
+        .await;
+
+        if synced_chunk_ids.is_empty() {
+            return;
+        }
+
+        if let Err(error) = blobscan_client
+            .remove_failed_slots_chunks(synced_chunk_ids)
+            .await
+        {
+            warn!(error = %redact_error(&error), "Failed slots chunks synced but couldn't be removed");
+        }
+    }
+}
+
+/// Outcome of waiting for the next beacon event
+#[derive(Debug)]
+enum EventWait<T> {
+    /// `None` once the stream ended
+    Event(Option<T>),
+    TimedOut,
+    Cancelled,
+}
+
+/// Waits for the next event of `event_source`, for at most `timeout` when set, giving up as soon
+/// as the indexer is cancelled
+async fn next_event<S>(
+    event_source: &mut S,
+    timeout: Option<Duration>,
+    cancellation_token: &CancellationToken,
+) -> EventWait<S::Item>
+where
+    S: Stream + Unpin,
+{
+    let wait = async {
+        match timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, event_source.next()).await {
+                Ok(event) => EventWait::Event(event),
+                Err(_) => EventWait::TimedOut,
+            },
+            None => EventWait::Event(event_source.next().await),
+        }
+    };
+
+    tokio::select! {
+        biased;
+        _ = cancellation_token.cancelled() => EventWait::Cancelled,
+        outcome = wait => outcome,
+    }
+}
+
+/// Syncs every failed chunk with `sync_chunk`, returning the ids of the ones that synced. Chunks
+/// that fail again are kept.
+async fn retry_failed_chunks<F, Fut>(
+    failed_chunks: Vec<FailedSlotsChunk>,
+    cancellation_token: &CancellationToken,
+    mut sync_chunk: F,
+) -> Vec<u32>
+where
+    F: FnMut(u32, u32) -> Fut,
+    Fut: Future<Output = Result<(), SynchronizerError>>,
+{
+    let mut synced_chunk_ids = vec![];
+
+    for failed_chunk in failed_chunks {
+        if cancellation_token.is_cancelled() {
+            break;
+        }
+
+        let initial_slot = failed_chunk.initial_slot;
+        let final_slot = failed_chunk.final_slot;
+
+        if let Err(error) = sync_chunk(initial_slot, final_slot).await {
+            warn!(
+                error = %redact_error(&error),
+                initial_slot, final_slot, "Failed slots chunk failed again. Keeping it"
+            );
+
+            continue;
+        }
+
+        info!(initial_slot, final_slot, "Failed slots chunk synced");
+
+        if let Some(chunk_id) = failed_chunk.id {
+            synced_chunk_ids.push(chunk_id);
+        }
+    }
+
+    synced_chunk_ids
+}
+
+async fn resolve_block_slot(context: &Context, block_id: &BlockId) -> Result<u32, anyhow::Error> {
+    let block_header = context
+        .beacon_client()
+        .get_block_header(block_id)
+        .await?
+        .with_context(|| format!("Block {block_id} not found"))?;
+
+    Ok(block_header.header.message.slot)
+}
+
+async fn resolve_target_slot(
+    context: &Context,
+    target: RealtimeTarget,
+) -> Result<u32, anyhow::Error> {
+    let (block_id, distance) = target.anchor();
+    let slot = resolve_block_slot(context, &block_id).await?;
+
+    Ok(slot.saturating_sub(distance))
+}
+
+/// First slot of the blob fork according to the chain's spec. Fork epochs too far in the future
+/// for a slot number, like the `u64::MAX` placeholder of unscheduled forks, saturate
+fn blob_fork_slot(chain_config: &ChainConfig) -> Option<u32> {
+    chain_config
+        .fork_epochs
+        .get(BLOB_FORK_NAME)
+        .map(|blob_fork_epoch| {
+            blob_fork_epoch
+                .saturating_mul(chain_config.slots_per_epoch as u64)
+                .min(u32::MAX as u64) as u32
+        })
+}
+
+/// Reports a syncing task that panicked, which would otherwise leave the indexer waiting on a
+/// task that silently died
+fn watch_task(
+    name: &'static str,
+    tx: mpsc::Sender<IndexerTaskMessage>,
+    handle: JoinHandle<IndexerResult<()>>,
+) {
+    tokio::spawn(async move {
+        let Err(error) = handle.await else {
+            return;
+        };
+
+        if !error.is_panic() {
+            return;
+        }
+
+        let panic = error.into_panic();
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic payload".to_string());
+
+        let _ = tx
+            .send(IndexerTaskMessage::Error(SyncingTaskError::TaskPanicked {
+                task: name,
+                message,
+            }))
+            .await;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use super::*;
+
+    fn chain_config(fork_epochs: &[(&str, u64)]) -> ChainConfig {
+        ChainConfig {
+            chain_id: 1,
+            genesis_time: 1606824023,
+            seconds_per_slot: 12,
+            slots_per_epoch: 32,
+            max_blobs_per_block: None,
+            fork_epochs: fork_epochs
+                .iter()
+                .map(|(fork_name, epoch)| (fork_name.to_string(), *epoch))
+                .collect(),
+            column_layouts: BTreeMap::new(),
+            blob_base_fee_update_fractions: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn blob_fork_slot_is_the_first_slot_of_the_deneb_epoch() {
+        assert_eq!(
+            blob_fork_slot(&chain_config(&[("deneb", 269568), ("electra", 364032)])),
+            Some(8626176)
+        );
+        assert_eq!(blob_fork_slot(&chain_config(&[("deneb", 0)])), Some(0));
+    }
+
+    #[test]
+    fn blob_fork_slot_is_unknown_without_a_deneb_epoch() {
+        assert_eq!(blob_fork_slot(&chain_config(&[("capella", 194048)])), None);
+    }
+
+    #[test]
+    fn unscheduled_blob_fork_saturates() {
+        assert_eq!(
+            blob_fork_slot(&chain_config(&[("deneb", u64::MAX)])),
+            Some(u32::MAX)
+        );
+    }
+
+    fn failed_chunk(id: u32, initial_slot: u32, final_slot: u32) -> FailedSlotsChunk {
+        FailedSlotsChunk {
+            id: Some(id),
+            initial_slot,
+            final_slot,
+            failed_slot: Some(initial_slot),
+            error: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_chunks_that_fail_permanently_and_removes_the_ones_that_sync_on_retry() {
+        let cancellation_token = CancellationToken::new();
+        let mut attempts = HashMap::<u32, u32>::new();
+        let mut sync_chunk = |initial_slot: u32, _final_slot: u32| {
+            let attempt = attempts.entry(initial_slot).or_default();
+
+            *attempt += 1;
+
+            // The chunk starting at 100 always fails, the one at 200 only on its first attempt
+            let result = match (initial_slot, *attempt) {
+                (100, _) | (200, 1) => Err(SynchronizerError::Other(anyhow!("slot failed"))),
+                _ => Ok(()),
+            };
+
+            async move { result }
+        };
+
+        let first_run = retry_failed_chunks(
+            vec![failed_chunk(1, 100, 149), failed_chunk(2, 200, 249)],
+            &cancellation_token,
+            &mut sync_chunk,
+        )
+        .await;
+        let second_run = retry_failed_chunks(
+            vec![failed_chunk(1, 100, 149), failed_chunk(2, 200, 249)],
+            &cancellation_token,
+            &mut sync_chunk,
+        )
+        .await;
+
+        assert!(first_run.is_empty());
+        assert_eq!(second_run, vec![2]);
+    }
+
+    #[tokio::test]
+    async fn stops_retrying_once_cancelled() {
+        let cancellation_token = CancellationToken::new();
+
+        cancellation_token.cancel();
+
+        let synced_chunk_ids = retry_failed_chunks(
+            vec![failed_chunk(1, 100, 149)],
+            &cancellation_token,
+            |_, _| async { Ok::<(), SynchronizerError>(()) },
+        )
+        .await;
+
+        assert!(synced_chunk_ids.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn next_event_stops_waiting_once_cancelled() {
+        let cancellation_token = CancellationToken::new();
+        let mut event_source = futures::stream::pending::<u32>();
+        let canceller = cancellation_token.clone();
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            canceller.cancel();
+        });
+
+        for timeout in [None, Some(Duration::from_secs(3600))] {
+            let wait = next_event(&mut event_source, timeout, &cancellation_token);
+
+            assert!(matches!(wait.await, EventWait::Cancelled));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn next_event_times_out_without_events() {
+        let mut event_source = futures::stream::pending::<u32>();
+
+        assert!(matches!(
+            next_event(
+                &mut event_source,
+                Some(Duration::from_millis(10)),
+                &CancellationToken::new()
+            )
+            .await,
+            EventWait::TimedOut
+        ));
+    }
 
-        synchronizer_builder.build(self.context.clone())
+    #[tokio::test]
+    async fn next_event_returns_events_and_the_stream_end() {
+        let mut event_source = futures::stream::iter([7u32]);
+        let cancellation_token = CancellationToken::new();
+
+        assert!(matches!(
+            next_event(&mut event_source, None, &cancellation_token).await,
+            EventWait::Event(Some(7))
+        ));
+        assert!(matches!(
+            next_event(
+                &mut event_source,
+                Some(Duration::from_secs(1)),
+                &cancellation_token
+            )
+            .await,
+            EventWait::Event(None)
+        ));
     }
 }
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// Cancels the token on the first SIGINT or SIGTERM so in-flight slots can finish and the
+/// checkpoint gets flushed. The process is terminated when a second signal arrives or when the
+/// grace period elapses first.
+pub fn spawn_shutdown_handler(cancellation_token: CancellationToken, grace_period: Duration) {
+    tokio::spawn(async move {
+        wait_for_shutdown_signal().await;
+
+        info!(
+            ?grace_period,
+            "Shutdown signal received. Stopping after in-flight slots complete. Send it again to force exit…"
+        );
+
+        cancellation_token.cancel();
+
+        tokio::select! {
+            _ = wait_for_shutdown_signal() => warn!("Second shutdown signal received. Exiting immediately"),
+            _ = tokio::time::sleep(grace_period) => warn!("Shutdown grace period elapsed. Exiting immediately"),
+        }
+
+        std::process::exit(1);
+    });
+}
+
+async fn wait_for_shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {},
+                    _ = terminate.recv() => {},
+                }
+
+                return;
+            }
+            Err(error) => warn!(
+                ?error,
+                "Failed to listen for SIGTERM. Only SIGINT stops the indexer gracefully"
+            ),
+        }
+    }
+
+    let _ = tokio::signal::ctrl_c().await;
+}
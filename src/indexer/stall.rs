@@ -0,0 +1,125 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use ethers::{providers::Middleware, types::BlockNumber};
+
+use crate::context::Context;
+
+/// Why the beacon head stopped advancing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StallKind {
+    /// The execution layer keeps producing blocks, so our beacon node is stuck
+    NodeStalled,
+    /// The execution layer stopped too, so the whole chain halted
+    ChainStalled,
+    /// No secondary source was available to tell them apart
+    Unknown,
+}
+
+/// Classifies a head stall given the age of the latest execution block, if known. A latest
+/// block younger than the stall duration means the chain kept moving without our node.
+pub fn classify_stall(
+    stalled_for: Duration,
+    latest_execution_block_age: Option<Duration>,
+) -> StallKind {
+    match latest_execution_block_age {
+        Some(age) if age < stalled_for => StallKind::NodeStalled,
+        Some(_) => StallKind::ChainStalled,
+        None => StallKind::Unknown,
+    }
+}
+
+/// Remembers how the current stall was classified, so a stall lasting several timeouts is only
+/// reported at full level when it starts or its classification changes
+#[derive(Debug, Default)]
+pub struct StallTracker {
+    current: Option<StallKind>,
+}
+
+impl StallTracker {
+    /// Records a timeout's classification, returning whether it should be reported
+    pub fn observe(&mut self, kind: StallKind) -> bool {
+        self.current.replace(kind) != Some(kind)
+    }
+
+    /// Ends the current stall once beacon events flow again
+    pub fn reset(&mut self) {
+        self.current = None;
+    }
+}
+
+/// Gathers the secondary observations and classifies the stall.
+pub async fn detect_stall(context: &Context, stalled_for: Duration) -> StallKind {
+    let latest_execution_block_age = match context.provider() {
+        Some(provider) => match provider.get_block(BlockNumber::Latest).await {
+            Ok(Some(block)) => (UNIX_EPOCH + Duration::from_secs(block.timestamp.as_u64()))
+                .elapsed()
+                .ok()
+                .or(Some(Duration::ZERO)),
+            _ => None,
+        },
+        None => None,
+    };
+
+    classify_stall(stalled_for, latest_execution_block_age)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{classify_stall, StallKind, StallTracker};
+
+    const STALLED_FOR: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn test_recent_execution_block_means_the_node_stalled() {
+        assert_eq!(
+            classify_stall(STALLED_FOR, Some(Duration::from_secs(12))),
+            StallKind::NodeStalled
+        );
+        assert_eq!(
+            classify_stall(STALLED_FOR, Some(Duration::ZERO)),
+            StallKind::NodeStalled
+        );
+    }
+
+    #[test]
+    fn test_old_execution_block_means_the_chain_stalled() {
+        assert_eq!(
+            classify_stall(STALLED_FOR, Some(Duration::from_secs(600))),
+            StallKind::ChainStalled
+        );
+        // A block exactly as old as the stall was produced when the head stopped
+        assert_eq!(
+            classify_stall(STALLED_FOR, Some(STALLED_FOR)),
+            StallKind::ChainStalled
+        );
+    }
+
+    #[test]
+    fn test_without_an_execution_block_the_stall_is_unknown() {
+        assert_eq!(classify_stall(STALLED_FOR, None), StallKind::Unknown);
+    }
+
+    #[test]
+    fn test_a_stall_is_reported_once_per_classification() {
+        let mut tracker = StallTracker::default();
+
+        assert!(tracker.observe(StallKind::ChainStalled));
+        assert!(!tracker.observe(StallKind::ChainStalled));
+        assert!(!tracker.observe(StallKind::ChainStalled));
+        assert!(tracker.observe(StallKind::NodeStalled));
+        assert!(tracker.observe(StallKind::ChainStalled));
+    }
+
+    #[test]
+    fn test_a_new_stall_is_reported_after_events_resume() {
+        let mut tracker = StallTracker::default();
+
+        assert!(tracker.observe(StallKind::ChainStalled));
+
+        tracker.reset();
+
+        assert!(tracker.observe(StallKind::ChainStalled));
+    }
+}
@@ -1,3 +1,7 @@
+use std::fmt;
+
+use crate::{args::Args, clients::beacon::types::BlockId};
+
 use super::error::{IndexerError, SyncingTaskError};
 
 pub type IndexerResult<T> = Result<T, IndexerError>;
@@ -6,3 +10,55 @@ pub enum IndexerTaskMessage {
     Done,
     Error(SyncingTaskError),
 }
+
+/// Block the realtime task keeps the index synced up to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RealtimeTarget {
+    Head,
+    /// Stays the given number of slots behind the head
+    FollowDistance(u32),
+    /// Syncs up to the latest finalized checkpoint
+    Finalized,
+}
+
+impl RealtimeTarget {
+    pub fn from_args(args: &Args) -> Self {
+        if args.finalized_only {
+            return RealtimeTarget::Finalized;
+        }
+
+        match args.follow_distance {
+            0 => RealtimeTarget::Head,
+            distance => RealtimeTarget::FollowDistance(distance),
+        }
+    }
+
+    /// Block the target follows and how many slots it stays behind it
+    pub fn anchor(&self) -> (BlockId, u32) {
+        match self {
+            RealtimeTarget::Head => (BlockId::Head, 0),
+            RealtimeTarget::FollowDistance(distance) => (BlockId::Head, *distance),
+            RealtimeTarget::Finalized => (BlockId::Finalized, 0),
+        }
+    }
+
+    /// Slots the target usually stays behind the head. The finalized checkpoint trails it by
+    /// two epochs plus the progress through the current one.
+    pub fn head_distance(&self, slots_per_epoch: u32) -> u32 {
+        match self {
+            RealtimeTarget::Head => 0,
+            RealtimeTarget::FollowDistance(distance) => *distance,
+            RealtimeTarget::Finalized => 3 * slots_per_epoch,
+        }
+    }
+}
+
+impl fmt::Display for RealtimeTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RealtimeTarget::Head => write!(f, "head"),
+            RealtimeTarget::FollowDistance(distance) => write!(f, "{distance} slots behind head"),
+            RealtimeTarget::Finalized => write!(f, "finalized checkpoint"),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+
+use ethers::types::U256;
+
+/// Running totals of what was submitted to blobscan, shared by every `SlotsProcessor`.
+#[derive(Debug, Default)]
+pub struct IndexingStats {
+    blocks: AtomicU64,
+    transactions: AtomicU64,
+    blobs: AtomicU64,
+    /// In wei. Doesn't fit an atomic, as a few ether already overflow a `u64`
+    blob_fees_burned: Mutex<U256>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IndexingStatsSnapshot {
+    pub blocks: u64,
+    pub transactions: u64,
+    pub blobs: u64,
+    pub blob_fees_burned: U256,
+}
+
+impl IndexingStats {
+    pub fn record_block(&self, transactions: usize, blobs: usize, blob_fee_burned: Option<U256>) {
+        self.blocks.fetch_add(1, Ordering::Relaxed);
+        self.transactions
+            .fetch_add(transactions as u64, Ordering::Relaxed);
+        self.blobs.fetch_add(blobs as u64, Ordering::Relaxed);
+
+        if let Some(blob_fee_burned) = blob_fee_burned {
+            let mut blob_fees_burned = self
+                .blob_fees_burned
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+            *blob_fees_burned = blob_fees_burned.saturating_add(blob_fee_burned);
+        }
+    }
+
+    pub fn snapshot(&self) -> IndexingStatsSnapshot {
+        IndexingStatsSnapshot {
+            blocks: self.blocks.load(Ordering::Relaxed),
+            transactions: self.transactions.load(Ordering::Relaxed),
+            blobs: self.blobs.load(Ordering::Relaxed),
+            blob_fees_burned: *self
+                .blob_fees_burned
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::U256;
+
+    use super::IndexingStats;
+
+    #[test]
+    fn record_block_accumulates_blob_fees_of_post_fork_blocks() {
+        let stats = IndexingStats::default();
+
+        stats.record_block(2, 3, Some(U256::from(393216 * 9)));
+        stats.record_block(0, 0, None);
+        stats.record_block(1, 1, Some(U256::from(u64::MAX)));
+
+        let snapshot = stats.snapshot();
+
+        assert_eq!(snapshot.blocks, 3);
+        assert_eq!(snapshot.transactions, 3);
+        assert_eq!(snapshot.blobs, 4);
+        assert_eq!(
+            snapshot.blob_fees_burned,
+            U256::from(u64::MAX) + U256::from(393216 * 9)
+        );
+    }
+}
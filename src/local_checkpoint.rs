@@ -0,0 +1,246 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use ethers::types::H256;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    clients::blobscan::types::BlockchainSyncState, synchronizer::CheckpointType,
+    utils::redaction::redact_error,
+};
+
+/// Sync progress mirrored to a local file, so restarts don't depend on blobscan being reachable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LocalCheckpoint {
+    pub last_lower_synced_slot: Option<u32>,
+    pub last_upper_synced_slot: Option<u32>,
+    /// Beacon block root of the last upper synced slot. `None` when that slot was missed
+    pub last_upper_block_root: Option<H256>,
+}
+
+impl LocalCheckpoint {
+    pub fn is_empty(&self) -> bool {
+        self.last_lower_synced_slot.is_none() && self.last_upper_synced_slot.is_none()
+    }
+}
+
+impl From<LocalCheckpoint> for BlockchainSyncState {
+    fn from(checkpoint: LocalCheckpoint) -> Self {
+        Self {
+            last_finalized_block: None,
+            last_lower_synced_slot: checkpoint.last_lower_synced_slot,
+            last_upper_synced_slot: checkpoint.last_upper_synced_slot,
+        }
+    }
+}
+
+/// The checkpoint file shared by every synchronizer. Each write replaces the whole file through a
+/// rename, so a crash mid-write leaves the previous checkpoint in place.
+#[derive(Debug)]
+pub struct LocalCheckpointFile {
+    path: PathBuf,
+    checkpoint: Mutex<LocalCheckpoint>,
+}
+
+impl LocalCheckpointFile {
+    /// Loads the file. A missing file starts empty, and so does an unreadable one, with a warning
+    pub fn open(path: PathBuf) -> Self {
+        let checkpoint = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|error| {
+                warn!(
+                    error = %redact_error(&error),
+                    "Ignoring unparsable checkpoint file {}",
+                    path.display()
+                );
+
+                LocalCheckpoint::default()
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => LocalCheckpoint::default(),
+            Err(error) => {
+                warn!(
+                    error = %redact_error(&error),
+                    "Ignoring unreadable checkpoint file {}",
+                    path.display()
+                );
+
+                LocalCheckpoint::default()
+            }
+        };
+
+        Self {
+            path,
+            checkpoint: Mutex::new(checkpoint),
+        }
+    }
+
+    pub fn checkpoint(&self) -> LocalCheckpoint {
+        *self
+            .checkpoint
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Saves a completed chunk's last slot. Failures are logged, as the file is only a fallback
+    pub fn record(&self, checkpoint_type: CheckpointType, slot: u32, block_root: Option<H256>) {
+        let mut checkpoint = self
+            .checkpoint
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        match checkpoint_type {
+            CheckpointType::Disabled => return,
+            CheckpointType::Lower => checkpoint.last_lower_synced_slot = Some(slot),
+            CheckpointType::Upper => {
+                checkpoint.last_upper_synced_slot = Some(slot);
+                checkpoint.last_upper_block_root = block_root;
+            }
+        }
+
+        if let Err(error) = self.write(&checkpoint) {
+            warn!(
+                error = %redact_error(&error),
+                "Failed to write checkpoint file {}",
+                self.path.display()
+            );
+        }
+    }
+
+    /// Writes a temp file and renames it over the checkpoint. The directory is synced too, as
+    /// the rename itself isn't durable until it is
+    fn write(&self, checkpoint: &LocalCheckpoint) -> io::Result<()> {
+        let mut temp_path = self.path.clone().into_os_string();
+
+        temp_path.push(".tmp");
+
+        let mut temp_file = fs::File::create(&temp_path)?;
+
+        temp_file.write_all(&serde_json::to_vec(checkpoint)?)?;
+        temp_file.sync_all()?;
+
+        fs::rename(&temp_path, &self.path)?;
+
+        let directory = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        fs::File::open(directory)?.sync_all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::PathBuf,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use ethers::types::H256;
+
+    use super::{LocalCheckpoint, LocalCheckpointFile};
+    use crate::synchronizer::CheckpointType;
+
+    fn temp_checkpoint_path(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let directory = std::env::temp_dir().join(format!(
+            "local-checkpoint-{name}-{}-{nanos}",
+            std::process::id()
+        ));
+
+        fs::create_dir_all(&directory).unwrap();
+
+        directory.join("checkpoint.json")
+    }
+
+    #[test]
+    fn test_missing_file_starts_empty() {
+        let path = temp_checkpoint_path("missing");
+
+        assert!(LocalCheckpointFile::open(path).checkpoint().is_empty());
+    }
+
+    #[test]
+    fn test_records_survive_reopening() {
+        let path = temp_checkpoint_path("reopen");
+        let block_root = H256::repeat_byte(0xab);
+        let file = LocalCheckpointFile::open(path.clone());
+
+        file.record(CheckpointType::Lower, 100, None);
+        file.record(CheckpointType::Upper, 200, Some(block_root));
+        file.record(CheckpointType::Disabled, 300, None);
+
+        assert_eq!(
+            LocalCheckpointFile::open(path).checkpoint(),
+            LocalCheckpoint {
+                last_lower_synced_slot: Some(100),
+                last_upper_synced_slot: Some(200),
+                last_upper_block_root: Some(block_root),
+            }
+        );
+    }
+
+    #[test]
+    fn test_crash_mid_write_keeps_the_previous_checkpoint() {
+        let path = temp_checkpoint_path("crash");
+        let file = LocalCheckpointFile::open(path.clone());
+
+        file.record(CheckpointType::Upper, 200, Some(H256::repeat_byte(1)));
+
+        // A crash before the rename leaves a truncated temp file next to the checkpoint
+        let mut temp_path = path.clone().into_os_string();
+
+        temp_path.push(".tmp");
+        fs::write(&temp_path, br#"{"last_lower_synced_slot":nu"#).unwrap();
+
+        let recovered = LocalCheckpointFile::open(path.clone());
+
+        assert_eq!(recovered.checkpoint().last_upper_synced_slot, Some(200));
+
+        // The next write replaces the leftover temp file
+        recovered.record(CheckpointType::Upper, 300, None);
+
+        assert_eq!(
+            LocalCheckpointFile::open(path)
+                .checkpoint()
+                .last_upper_synced_slot,
+            Some(300)
+        );
+    }
+
+    #[test]
+    fn test_corrupt_file_is_ignored() {
+        let path = temp_checkpoint_path("corrupt");
+
+        fs::write(&path, "not json").unwrap();
+
+        assert!(LocalCheckpointFile::open(path).checkpoint().is_empty());
+    }
+
+    #[test]
+    fn test_legacy_execution_hash_is_not_read_as_a_root() {
+        let path = temp_checkpoint_path("legacy");
+
+        fs::write(
+            &path,
+            format!(
+                r#"{{"last_lower_synced_slot":null,"last_upper_synced_slot":7,"last_upper_block_hash":"{:?}"}}"#,
+                H256::repeat_byte(2)
+            ),
+        )
+        .unwrap();
+
+        let checkpoint = LocalCheckpointFile::open(path).checkpoint();
+
+        assert_eq!(checkpoint.last_upper_synced_slot, Some(7));
+        assert_eq!(checkpoint.last_upper_block_root, None);
+    }
+}
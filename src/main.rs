@@ -1,51 +1,117 @@
+#[cfg(feature = "sentry")]
+use std::sync::Arc;
+
 use anyhow::{anyhow, Result as AnyhowResult};
-use args::Args;
+use args::{Args, Command};
 use clap::Parser;
+use config_validation::validate_config;
 use env::Environment;
 use indexer::Indexer;
+use tracing::warn;
 use utils::{
     banner::print_banner,
+    redaction::redact_error,
     telemetry::{get_subscriber, init_subscriber},
 };
 
+#[cfg(feature = "sentry")]
+use utils::redaction::redact_sentry_event;
+
 mod args;
+mod check;
 mod clients;
+mod config_validation;
 mod context;
+mod duties_cache;
 mod env;
+mod health;
 mod indexer;
+mod indexing_stats;
+mod local_checkpoint;
+mod metrics;
 mod network;
+mod profile;
+mod reupload;
+#[cfg(any(feature = "metrics", feature = "health"))]
+mod server;
+mod skip_list;
 mod slots_processor;
+mod status;
 mod synchronizer;
 mod utils;
+mod verify;
 
 async fn run() -> AnyhowResult<()> {
     dotenv::dotenv().ok();
+
+    let args = Args::parse();
+
+    if args.print_config_template {
+        print!("{}", Args::config_template());
+
+        return Ok(());
+    }
+
     let env = match Environment::from_env() {
         Ok(env) => env,
         Err(err) => return Err(anyhow!(format!("Failed to load env variables: {}", err))),
     };
 
+    #[cfg(feature = "sentry")]
     let mut _guard;
 
+    #[cfg(feature = "sentry")]
     if let Some(sentry_dsn) = env.sentry_dsn.clone() {
         _guard = sentry::init((
             sentry_dsn,
             sentry::ClientOptions {
                 release: sentry::release_name!(),
+                before_send: Some(Arc::new(redact_sentry_event)),
                 ..Default::default()
             },
         ));
     }
 
+    #[cfg(not(feature = "sentry"))]
+    if env.sentry_dsn.is_some() {
+        return Err(anyhow!(
+            "SENTRY_DSN is set but the indexer was built without the `sentry` feature"
+        ));
+    }
+
     let subscriber = get_subscriber("info".into(), std::io::stdout);
     init_subscriber(subscriber);
 
-    let args = Args::parse();
+    if !args.fail_on_deprecated {
+        for deprecated_var in &env.deprecated_vars {
+            warn!(
+                "{} is deprecated. Use {} instead",
+                deprecated_var.name, deprecated_var.replacement
+            );
+        }
+    }
 
     print_banner(&args, &env);
 
+    validate_config(&args, &env)?;
+
+    if args.check {
+        return check::run_checks(&env).await;
+    }
+
+    match args.command {
+        Some(Command::Status) => return status::print_status(&env).await,
+        Some(Command::Verify { from, to }) => return verify::verify_slots(&env, from, to).await,
+        Some(Command::ReuploadBlob { versioned_hash }) => {
+            return reupload::reupload_blob(&env, versioned_hash).await
+        }
+        _ => {}
+    }
+
+    let (from_slot, to_slot) = args.slot_bounds();
+
     Indexer::try_new(&env, &args)?
-        .run(args.from_slot, args.to_slot)
+        .run(from_slot, to_slot)
         .await
         .map_err(|err| anyhow!(err))
 }
@@ -53,7 +119,7 @@ async fn run() -> AnyhowResult<()> {
 #[tokio::main]
 async fn main() {
     if let Err(err) = run().await {
-        eprintln!("Error: {err:?}");
+        eprintln!("Error: {}", redact_error(err.as_ref()));
         std::process::exit(1);
     }
 }
@@ -0,0 +1,565 @@
+// Recording is always compiled in, as the rest of the indexer reports through it. Only the
+// Prometheus rendering needs the `metrics` feature, which leaves some readers unused without it
+#![cfg_attr(not(feature = "metrics"), allow(dead_code))]
+
+#[cfg(feature = "metrics")]
+use std::fmt::Write;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+#[cfg(feature = "metrics")]
+use ethers::types::U256;
+
+use crate::clients::{
+    beacon::fork::ForkMismatches,
+    blobscan::{server_timing::BlobscanLatency, types::SlotSkipReason},
+};
+#[cfg(feature = "metrics")]
+use crate::indexing_stats::IndexingStatsSnapshot;
+
+/// Upper bounds, in seconds, of the latency histogram buckets
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Progress gauges and error counters exported in the Prometheus text format. Block,
+/// transaction and blob counters come from `IndexingStats` so they're only counted once.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    last_indexed_slot: AtomicU64,
+    /// Highest slot handled, whether it was indexed, skipped or failed and moved past
+    last_processed_slot: AtomicU64,
+    beacon_head_slot: AtomicU64,
+    /// `f64` bits of the rate measured over the last synced chunk
+    slots_per_second: AtomicU64,
+    slot_errors: Mutex<BTreeMap<&'static str, u64>>,
+    /// Beacon responses that came back empty for a resource that can't be, by resource
+    empty_response_anomalies: Mutex<BTreeMap<&'static str, u64>>,
+    /// Beacon blocks carrying commitments for an execution block without blob transactions, by
+    /// whether the slot failed or was skipped
+    blocks_mismatches: Mutex<BTreeMap<&'static str, u64>>,
+    skipped_slots: Mutex<BTreeMap<SlotSkipReason, u64>>,
+    blobscan_latency: Arc<BlobscanLatency>,
+    beacon_fork_mismatches: Arc<ForkMismatches>,
+}
+
+/// Prometheus histogram of durations over `LATENCY_BUCKETS`
+#[derive(Debug, Default)]
+pub struct LatencyHistogram {
+    /// Samples per bucket, not cumulative. The last one holds samples above every bound
+    buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    sum_micros: AtomicU64,
+    count: AtomicU64,
+}
+
+impl LatencyHistogram {
+    pub fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| secs <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of durations observed
+    #[cfg(test)]
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    #[cfg(feature = "metrics")]
+    fn render(&self, output: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+
+        for (bucket, bound) in LATENCY_BUCKETS
+            .iter()
+            .map(|bound| bound.to_string())
+            .chain(std::iter::once("+Inf".to_string()))
+            .enumerate()
+        {
+            cumulative += self.buckets[bucket].load(Ordering::Relaxed);
+
+            let _ = writeln!(
+                output,
+                "{name}_bucket{{{labels},le=\"{bound}\"}} {cumulative}"
+            );
+        }
+
+        let _ = writeln!(
+            output,
+            "{name}_sum{{{labels}}} {}",
+            self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0
+        );
+        let _ = writeln!(
+            output,
+            "{name}_count{{{labels}}} {}",
+            self.count.load(Ordering::Relaxed)
+        );
+    }
+}
+
+/// Closest `f64` to the value. Integers above 2^53 lose their lowest digits
+#[cfg(feature = "metrics")]
+fn u256_to_f64(value: U256) -> f64 {
+    value.0.iter().rev().fold(0.0, |total, limb| {
+        total * 18_446_744_073_709_551_616.0 + *limb as f64
+    })
+}
+
+impl Metrics {
+    /// Slots may be indexed out of order by parallel or backwards syncs, so the highest one is
+    /// kept
+    pub fn record_indexed_slot(&self, slot: u32) {
+        self.last_indexed_slot
+            .fetch_max(slot as u64, Ordering::Relaxed);
+    }
+
+    pub fn last_indexed_slot(&self) -> u64 {
+        self.last_indexed_slot.load(Ordering::Relaxed)
+    }
+
+    pub fn record_processed_slot(&self, slot: u32) {
+        self.last_processed_slot
+            .fetch_max(slot as u64, Ordering::Relaxed);
+    }
+
+    pub fn last_processed_slot(&self) -> u64 {
+        self.last_processed_slot.load(Ordering::Relaxed)
+    }
+
+    pub fn beacon_head_slot(&self) -> u64 {
+        self.beacon_head_slot.load(Ordering::Relaxed)
+    }
+
+    pub fn set_beacon_head_slot(&self, slot: u32) {
+        self.beacon_head_slot.store(slot as u64, Ordering::Relaxed);
+    }
+
+    pub fn set_slots_per_second(&self, slots_per_second: f64) {
+        self.slots_per_second
+            .store(slots_per_second.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn blobscan_latency(&self) -> Arc<BlobscanLatency> {
+        self.blobscan_latency.clone()
+    }
+
+    pub fn beacon_fork_mismatches(&self) -> Arc<ForkMismatches> {
+        self.beacon_fork_mismatches.clone()
+    }
+
+    pub fn record_slot_error(&self, kind: &'static str) {
+        let mut slot_errors = self
+            .slot_errors
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        *slot_errors.entry(kind).or_default() += 1;
+    }
+
+    pub fn record_empty_response_anomaly(&self, resource: &'static str) {
+        let mut empty_response_anomalies = self
+            .empty_response_anomalies
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        *empty_response_anomalies.entry(resource).or_default() += 1;
+    }
+
+    pub fn record_blocks_mismatch(&self, handling: &'static str) {
+        let mut blocks_mismatches = self
+            .blocks_mismatches
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        *blocks_mismatches.entry(handling).or_default() += 1;
+    }
+
+    pub fn record_skipped_slot(&self, reason: SlotSkipReason) {
+        let mut skipped_slots = self
+            .skipped_slots
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        *skipped_slots.entry(reason).or_default() += 1;
+    }
+
+    /// Slots intentionally skipped since startup, by reason
+    pub fn skipped_slots(&self) -> BTreeMap<&'static str, u64> {
+        self.skipped_slots
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .map(|(reason, count)| (reason.as_str(), *count))
+            .collect()
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn render(&self, stats: IndexingStatsSnapshot) -> String {
+        let last_indexed_slot = self.last_indexed_slot();
+        let last_processed_slot = self.last_processed_slot();
+        let beacon_head_slot = self.beacon_head_slot();
+        let slots_per_second = f64::from_bits(self.slots_per_second.load(Ordering::Relaxed));
+        let mut output = String::new();
+
+        let mut write_metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = writeln!(output, "# HELP {name} {help}");
+            let _ = writeln!(output, "# TYPE {name} {kind}");
+            let _ = writeln!(output, "{name} {value}");
+        };
+
+        write_metric(
+            "indexer_last_indexed_slot",
+            "gauge",
+            "Highest slot indexed so far",
+            last_indexed_slot.to_string(),
+        );
+        write_metric(
+            "indexer_last_processed_slot",
+            "gauge",
+            "Highest slot processed so far, including skipped slots",
+            last_processed_slot.to_string(),
+        );
+        write_metric(
+            "indexer_beacon_head_slot",
+            "gauge",
+            "Slot of the latest head seen on the beacon node",
+            beacon_head_slot.to_string(),
+        );
+        write_metric(
+            "indexer_lag_slots",
+            "gauge",
+            "Slots between the beacon head and the last processed slot",
+            beacon_head_slot
+                .saturating_sub(last_processed_slot)
+                .to_string(),
+        );
+        write_metric(
+            "indexer_slots_per_second",
+            "gauge",
+            "Slots processed per second over the last synced chunk",
+            slots_per_second.to_string(),
+        );
+        write_metric(
+            "indexer_blocks_indexed_total",
+            "counter",
+            "Blocks submitted to blobscan",
+            stats.blocks.to_string(),
+        );
+        write_metric(
+            "indexer_transactions_indexed_total",
+            "counter",
+            "Blob transactions submitted to blobscan",
+            stats.transactions.to_string(),
+        );
+        write_metric(
+            "indexer_blobs_indexed_total",
+            "counter",
+            "Blobs submitted to blobscan",
+            stats.blobs.to_string(),
+        );
+        write_metric(
+            "indexer_blob_fees_burned_total",
+            "counter",
+            "Blob fees burned by the indexed blocks, in wei. A float, so only exact up to 2^53 wei",
+            u256_to_f64(stats.blob_fees_burned).to_string(),
+        );
+
+        let _ = writeln!(
+            output,
+            "# HELP indexer_slot_errors_total Slot processing errors by kind"
+        );
+        let _ = writeln!(output, "# TYPE indexer_slot_errors_total counter");
+
+        let slot_errors = self
+            .slot_errors
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        for (kind, count) in slot_errors.iter() {
+            let _ = writeln!(
+                output,
+                "indexer_slot_errors_total{{kind=\"{kind}\"}} {count}"
+            );
+        }
+
+        let _ = writeln!(
+            output,
+            "# HELP indexer_empty_response_anomalies_total Empty beacon responses for resources that can't be empty, by resource"
+        );
+        let _ = writeln!(
+            output,
+            "# TYPE indexer_empty_response_anomalies_total counter"
+        );
+
+        let empty_response_anomalies = self
+            .empty_response_anomalies
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        for (resource, count) in empty_response_anomalies.iter() {
+            let _ = writeln!(
+                output,
+                "indexer_empty_response_anomalies_total{{resource=\"{resource}\"}} {count}"
+            );
+        }
+
+        let _ = writeln!(
+            output,
+            "# HELP indexer_blocks_mismatches_total Beacon blocks carrying blob commitments whose execution block has no blob transactions, by whether the slot failed or was skipped"
+        );
+        let _ = writeln!(output, "# TYPE indexer_blocks_mismatches_total counter");
+
+        let blocks_mismatches = self
+            .blocks_mismatches
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        for (handling, count) in blocks_mismatches.iter() {
+            let _ = writeln!(
+                output,
+                "indexer_blocks_mismatches_total{{handling=\"{handling}\"}} {count}"
+            );
+        }
+
+        let _ = writeln!(
+            output,
+            "# HELP indexer_skipped_slots_total Slots intentionally not indexed, by reason"
+        );
+        let _ = writeln!(output, "# TYPE indexer_skipped_slots_total counter");
+
+        for (reason, count) in self.skipped_slots() {
+            let _ = writeln!(
+                output,
+                "indexer_skipped_slots_total{{reason=\"{reason}\"}} {count}"
+            );
+        }
+
+        let _ = writeln!(
+            output,
+            "# HELP indexer_beacon_fork_mismatches_total Beacon blocks whose declared consensus version isn't the fork of their slot, by endpoint"
+        );
+        let _ = writeln!(
+            output,
+            "# TYPE indexer_beacon_fork_mismatches_total counter"
+        );
+
+        for (endpoint, count) in self.beacon_fork_mismatches.counts() {
+            let _ = writeln!(
+                output,
+                "indexer_beacon_fork_mismatches_total{{endpoint=\"{endpoint}\"}} {count}"
+            );
+        }
+
+        let _ = writeln!(
+            output,
+            "# HELP indexer_blobscan_request_seconds Time until blobscan responded, in total and split into server and network time when blobscan reports it"
+        );
+        let _ = writeln!(output, "# TYPE indexer_blobscan_request_seconds histogram");
+
+        for (component, histogram) in [
+            ("total", &self.blobscan_latency.total),
+            ("server", &self.blobscan_latency.server),
+            ("network", &self.blobscan_latency.network),
+        ] {
+            histogram.render(
+                &mut output,
+                "indexer_blobscan_request_seconds",
+                &format!("component=\"{component}\""),
+            );
+        }
+
+        output
+    }
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use ethers::types::U256;
+
+    use super::{u256_to_f64, Metrics};
+    use crate::{clients::blobscan::types::SlotSkipReason, indexing_stats::IndexingStats};
+
+    #[test]
+    fn u256_to_f64_approximates_values_beyond_u64() {
+        assert_eq!(u256_to_f64(U256::zero()), 0.0);
+        assert_eq!(u256_to_f64(U256::from(1_000_000_000u64)), 1e9);
+        assert_eq!(
+            u256_to_f64(U256::from(u64::MAX) + 1),
+            18_446_744_073_709_551_616.0
+        );
+        assert_eq!(u256_to_f64(U256::from(3) << 100), 3.0 * 2f64.powi(100));
+    }
+
+    #[test]
+    fn every_series_is_documented() {
+        let output = Metrics::default().render(IndexingStats::default().snapshot());
+        let series = [
+            ("indexer_last_indexed_slot", "gauge"),
+            ("indexer_last_processed_slot", "gauge"),
+            ("indexer_beacon_head_slot", "gauge"),
+            ("indexer_lag_slots", "gauge"),
+            ("indexer_slots_per_second", "gauge"),
+            ("indexer_blocks_indexed_total", "counter"),
+            ("indexer_transactions_indexed_total", "counter"),
+            ("indexer_blobs_indexed_total", "counter"),
+            ("indexer_blob_fees_burned_total", "counter"),
+            ("indexer_slot_errors_total", "counter"),
+            ("indexer_empty_response_anomalies_total", "counter"),
+            ("indexer_blocks_mismatches_total", "counter"),
+            ("indexer_skipped_slots_total", "counter"),
+            ("indexer_beacon_fork_mismatches_total", "counter"),
+            ("indexer_blobscan_request_seconds", "histogram"),
+        ];
+
+        for (name, kind) in series {
+            assert!(output.contains(&format!("# HELP {name} ")), "{name}");
+            assert!(
+                output.contains(&format!("# TYPE {name} {kind}\n")),
+                "{name}"
+            );
+        }
+
+        assert_eq!(output.matches("# TYPE ").count(), series.len());
+    }
+
+    #[test]
+    fn progress_gauges_keep_the_highest_slots() {
+        let metrics = Metrics::default();
+
+        metrics.record_indexed_slot(120);
+        metrics.record_indexed_slot(100);
+        metrics.record_processed_slot(125);
+        metrics.set_beacon_head_slot(130);
+        metrics.set_slots_per_second(2.5);
+
+        let output = metrics.render(IndexingStats::default().snapshot());
+
+        assert!(output.contains("indexer_last_indexed_slot 120\n"));
+        assert!(output.contains("indexer_last_processed_slot 125\n"));
+        assert!(output.contains("indexer_lag_slots 5\n"));
+        assert!(output.contains("indexer_slots_per_second 2.5\n"));
+    }
+
+    #[test]
+    fn lag_doesnt_go_negative() {
+        let metrics = Metrics::default();
+
+        metrics.record_processed_slot(140);
+        metrics.set_beacon_head_slot(130);
+
+        let output = metrics.render(IndexingStats::default().snapshot());
+
+        assert!(output.contains("indexer_lag_slots 0\n"));
+    }
+
+    #[test]
+    fn indexed_entities_come_from_the_indexing_stats() {
+        let stats = IndexingStats::default();
+
+        stats.record_block(2, 3, Some(U256::from(1_000)));
+        stats.record_block(0, 0, None);
+
+        let output = Metrics::default().render(stats.snapshot());
+
+        assert!(output.contains("indexer_blocks_indexed_total 2\n"));
+        assert!(output.contains("indexer_transactions_indexed_total 2\n"));
+        assert!(output.contains("indexer_blobs_indexed_total 3\n"));
+        assert!(output.contains("indexer_blob_fees_burned_total 1000\n"));
+    }
+
+    #[test]
+    fn slot_errors_are_counted_by_kind() {
+        let metrics = Metrics::default();
+
+        metrics.record_slot_error("blocks_mismatch");
+        metrics.record_slot_error("blocks_mismatch");
+        metrics.record_slot_error("other");
+
+        let output = metrics.render(IndexingStats::default().snapshot());
+
+        assert!(output.contains("indexer_slot_errors_total{kind=\"blocks_mismatch\"} 2\n"));
+        assert!(output.contains("indexer_slot_errors_total{kind=\"other\"} 1\n"));
+    }
+
+    #[test]
+    fn empty_response_anomalies_are_counted_by_resource() {
+        let metrics = Metrics::default();
+
+        metrics.record_empty_response_anomaly("columns_sidecar");
+        metrics.record_empty_response_anomaly("columns_sidecar");
+        metrics.record_empty_response_anomaly("proposer_duties");
+
+        let output = metrics.render(IndexingStats::default().snapshot());
+
+        assert!(output
+            .contains("indexer_empty_response_anomalies_total{resource=\"columns_sidecar\"} 2\n"));
+        assert!(output
+            .contains("indexer_empty_response_anomalies_total{resource=\"proposer_duties\"} 1\n"));
+    }
+
+    #[test]
+    fn blocks_mismatches_are_counted_by_handling() {
+        let metrics = Metrics::default();
+
+        metrics.record_blocks_mismatch("skipped");
+        metrics.record_blocks_mismatch("skipped");
+        metrics.record_blocks_mismatch("failed");
+
+        let output = metrics.render(IndexingStats::default().snapshot());
+
+        assert!(output.contains("indexer_blocks_mismatches_total{handling=\"skipped\"} 2\n"));
+        assert!(output.contains("indexer_blocks_mismatches_total{handling=\"failed\"} 1\n"));
+    }
+
+    #[test]
+    fn skipped_slots_are_counted_by_reason() {
+        let metrics = Metrics::default();
+
+        metrics.record_skipped_slot(SlotSkipReason::NotSampled);
+        metrics.record_skipped_slot(SlotSkipReason::NotSampled);
+        metrics.record_skipped_slot(SlotSkipReason::MissedSlot);
+
+        assert_eq!(
+            metrics.skipped_slots(),
+            BTreeMap::from([("missed_slot", 1), ("not_sampled", 2)])
+        );
+
+        let output = metrics.render(IndexingStats::default().snapshot());
+
+        assert!(output.contains("indexer_skipped_slots_total{reason=\"not_sampled\"} 2\n"));
+        assert!(output.contains("indexer_skipped_slots_total{reason=\"missed_slot\"} 1\n"));
+    }
+
+    #[test]
+    fn beacon_fork_mismatches_are_counted_by_endpoint() {
+        let metrics = Metrics::default();
+        let fork_mismatches = metrics.beacon_fork_mismatches();
+
+        fork_mismatches.record("http://beacon-a:5052/");
+        fork_mismatches.record("http://beacon-a:5052/");
+        fork_mismatches.record("http://beacon-b:5052/");
+
+        let output = metrics.render(IndexingStats::default().snapshot());
+
+        assert!(output.contains(
+            "indexer_beacon_fork_mismatches_total{endpoint=\"http://beacon-a:5052/\"} 2\n"
+        ));
+        assert!(output.contains(
+            "indexer_beacon_fork_mismatches_total{endpoint=\"http://beacon-b:5052/\"} 1\n"
+        ));
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{args::Args, slots_processor::EmptyResponsePolicy};
+
+/// Operating profile bundling the validation toggles into coherent defaults.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum Profile {
+    /// Fail slots on any inconsistency and run every verification
+    Strict,
+    /// Fail slots on inconsistent beacon data but skip checks requiring extra requests
+    Balanced,
+    /// Index whatever parses and only warn
+    #[default]
+    Lenient,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProfileSettings {
+    pub empty_response_policy: EmptyResponsePolicy,
+    pub verify_parent_timestamp: bool,
+    pub verify_parent_number: bool,
+    /// Skip blocks whose commitments have no blob transaction to belong to instead of failing them
+    pub skip_blocks_mismatch: bool,
+}
+
+impl Profile {
+    pub fn settings(&self) -> ProfileSettings {
+        // (empty response policy, verify parent timestamp, verify parent number, skip blocks mismatch)
+        let (
+            empty_response_policy,
+            verify_parent_timestamp,
+            verify_parent_number,
+            skip_blocks_mismatch,
+        ) = match self {
+            Profile::Strict => (EmptyResponsePolicy::Strict, true, true, false),
+            Profile::Balanced => (EmptyResponsePolicy::Strict, false, false, false),
+            Profile::Lenient => (EmptyResponsePolicy::Lenient, false, false, true),
+        };
+
+        ProfileSettings {
+            empty_response_policy,
+            verify_parent_timestamp,
+            verify_parent_number,
+            skip_blocks_mismatch,
+        }
+    }
+}
+
+impl ProfileSettings {
+    /// Resolves the profile defaults, letting individually set flags override them.
+    pub fn resolve(args: &Args) -> Self {
+        let defaults = args.profile.settings();
+
+        Self {
+            empty_response_policy: args
+                .empty_response_policy
+                .unwrap_or(defaults.empty_response_policy),
+            verify_parent_timestamp: args
+                .verify_parent_timestamp
+                .unwrap_or(defaults.verify_parent_timestamp),
+            verify_parent_number: args
+                .verify_parent_number
+                .unwrap_or(defaults.verify_parent_number),
+            skip_blocks_mismatch: defaults.skip_blocks_mismatch,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    fn args(flags: &[&str]) -> Args {
+        Args::try_parse_from(std::iter::once("blobscan-indexer").chain(flags.iter().copied()))
+            .unwrap()
+    }
+
+    #[test]
+    fn profiles_bundle_their_validation_toggles() {
+        assert_eq!(
+            Profile::Strict.settings(),
+            ProfileSettings {
+                empty_response_policy: EmptyResponsePolicy::Strict,
+                verify_parent_timestamp: true,
+                verify_parent_number: true,
+                skip_blocks_mismatch: false,
+            }
+        );
+        assert_eq!(
+            Profile::Balanced.settings(),
+            ProfileSettings {
+                empty_response_policy: EmptyResponsePolicy::Strict,
+                verify_parent_timestamp: false,
+                verify_parent_number: false,
+                skip_blocks_mismatch: false,
+            }
+        );
+        assert_eq!(
+            Profile::Lenient.settings(),
+            ProfileSettings {
+                empty_response_policy: EmptyResponsePolicy::Lenient,
+                verify_parent_timestamp: false,
+                verify_parent_number: false,
+                skip_blocks_mismatch: true,
+            }
+        );
+    }
+
+    #[test]
+    fn lenient_is_the_default_profile() {
+        assert_eq!(args(&[]).profile, Profile::Lenient);
+        assert_eq!(
+            ProfileSettings::resolve(&args(&[])),
+            Profile::Lenient.settings()
+        );
+    }
+
+    #[test]
+    fn unset_flags_take_the_profile_defaults() {
+        for (name, profile) in [
+            ("strict", Profile::Strict),
+            ("balanced", Profile::Balanced),
+            ("lenient", Profile::Lenient),
+        ] {
+            assert_eq!(
+                ProfileSettings::resolve(&args(&["--profile", name])),
+                profile.settings(),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn set_flags_override_the_profile() {
+        let settings = ProfileSettings::resolve(&args(&[
+            "--profile",
+            "strict",
+            "--empty-response-policy",
+            "lenient",
+            "--verify-parent-timestamp=false",
+        ]));
+
+        assert_eq!(settings.empty_response_policy, EmptyResponsePolicy::Lenient);
+        assert!(!settings.verify_parent_timestamp);
+        assert!(settings.verify_parent_number);
+        assert!(!settings.skip_blocks_mismatch);
+    }
+
+    #[test]
+    fn valueless_verification_flags_enable_the_check() {
+        let settings =
+            ProfileSettings::resolve(&args(&["--profile", "lenient", "--verify-parent-number"]));
+
+        assert!(settings.verify_parent_number);
+        assert!(!settings.verify_parent_timestamp);
+        assert!(settings.skip_blocks_mismatch);
+    }
+}
@@ -0,0 +1,454 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result as AnyhowResult;
+use ethers::types::H256;
+
+use crate::{
+    clients::{
+        beacon::{
+            columns::{reconstruct_blobs, ColumnReconstructionError},
+            types::{Blob as BeaconBlob, BlockId},
+        },
+        blobscan::types::{
+            Blob, BlobBlockIndex, BlobTxIndex, IndexedBlobDetails, IndexedBlobTransaction,
+        },
+        common::ClientError,
+    },
+    context::{ChainSpec, Config as ContextConfig, Context},
+    env::Environment,
+    utils::{kzg::blob_to_kzg_commitment, web3::calculate_versioned_hash},
+};
+
+#[derive(Debug, thiserror::Error)]
+enum ReuploadError {
+    #[error("failed to look up blob {0:?} in blobscan")]
+    BlobLookupFailure(H256, #[source] ClientError),
+    #[error("blob {0:?} isn't indexed by blobscan")]
+    UnknownBlob(H256),
+    #[error("blobscan doesn't link blob {0:?} to any transaction")]
+    NoTransaction(H256),
+    #[error("failed to look up block {0} in blobscan")]
+    BlockLookupFailure(u32, #[source] ClientError),
+    #[error("block {0} carrying the blob isn't indexed by blobscan")]
+    UnknownBlock(u32),
+    #[error("blobscan doesn't report the slot of block {0}")]
+    MissingSlot(u32),
+    #[error("failed to fetch the beacon spec")]
+    SpecUnavailable(#[source] anyhow::Error),
+    #[error("failed to fetch the columns of slot {0}")]
+    ColumnsFetchFailure(u32, #[source] ClientError),
+    #[error("the beacon node has no columns for slot {0}")]
+    MissingColumns(u32),
+    #[error("the beacon spec doesn't describe the column layout of slot {0}")]
+    UnknownColumnLayout(u32),
+    #[error("failed to rebuild the blobs of slot {0}")]
+    Reconstruction(u32, #[source] ColumnReconstructionError),
+    #[error("slot {slot} has no blob with versioned hash {versioned_hash:?}")]
+    NotInSlot { slot: u32, versioned_hash: H256 },
+    #[error("blobscan stores commitment {stored} but the chain holds {onchain}")]
+    CommitmentMismatch { stored: String, onchain: String },
+    #[error("reconstructed blob {0:?} is invalid")]
+    InvalidBlob(H256, #[source] anyhow::Error),
+    #[error("failed to compute the KZG commitment of blob {0:?}: {1}")]
+    KzgFailure(H256, String),
+    #[error(
+        "blob {versioned_hash:?} data commits to {computed}, not to its commitment {expected}"
+    )]
+    KzgCommitmentMismatch {
+        versioned_hash: H256,
+        expected: String,
+        computed: String,
+    },
+    #[error("failed to upload blob {0:?}")]
+    UploadFailure(H256, #[source] ClientError),
+}
+
+/// Rebuilds a single blob from the beacon node and uploads it again, replacing whatever blobscan
+/// stores for it. The blob's slot and transaction are taken from blobscan's read API.
+pub async fn reupload_blob(env: &Environment, versioned_hash: H256) -> AnyhowResult<()> {
+    let context = Context::try_new(ContextConfig::from(env))?;
+    let blobscan_client = context.blobscan_client();
+    let beacon_client = context.beacon_client();
+
+    let indexed_blob = blobscan_client
+        .get_blob(versioned_hash)
+        .await
+        .map_err(|error| ReuploadError::BlobLookupFailure(versioned_hash, error))?;
+    let (indexed_blob, transaction) = indexed_transaction(versioned_hash, indexed_blob.as_ref())?;
+    let block_number = transaction.block_number;
+    let slot = blobscan_client
+        .get_block(block_number)
+        .await
+        .map_err(|error| ReuploadError::BlockLookupFailure(block_number, error))?
+        .ok_or(ReuploadError::UnknownBlock(block_number))?
+        .slot
+        .ok_or(ReuploadError::MissingSlot(block_number))?;
+
+    let spec = beacon_client
+        .get_spec()
+        .await
+        .map_err(|error| ReuploadError::SpecUnavailable(error.into()))?
+        .ok_or_else(|| ReuploadError::SpecUnavailable(anyhow::anyhow!("Beacon spec not found")))?;
+    let chain_spec = ChainSpec {
+        slots_per_epoch: spec.slots_per_epoch,
+        seconds_per_slot: spec.seconds_per_slot,
+        genesis_time: None,
+        column_layouts: spec.column_layouts,
+        blob_base_fee_update_fractions: spec.blob_base_fee_update_fractions,
+    };
+    let columns = beacon_client
+        .get_columns(&BlockId::Slot(slot))
+        .await
+        .map_err(|error| ReuploadError::ColumnsFetchFailure(slot, error))?
+        .ok_or(ReuploadError::MissingColumns(slot))?;
+    let layout = chain_spec
+        .column_layout_at(slot)
+        .ok_or(ReuploadError::UnknownColumnLayout(slot))?;
+    let blobs = reconstruct_blobs(columns, &layout)
+        .map_err(|error| ReuploadError::Reconstruction(slot, error))?;
+
+    let blob = build_reupload_blob(
+        versioned_hash,
+        slot,
+        indexed_blob,
+        transaction,
+        &blobs,
+        |blob| blob_to_kzg_commitment(blob).map_err(|error| format!("{error:?}")),
+    )?;
+
+    blobscan_client
+        .put_blob(blob)
+        .await
+        .map_err(|error| ReuploadError::UploadFailure(versioned_hash, error))?;
+
+    println!(
+        "Blob {versioned_hash:?} of transaction {:?} (slot {slot}) uploaded",
+        transaction.tx_hash
+    );
+
+    Ok(())
+}
+
+/// The blob as blobscan stores it, along with the first transaction carrying it
+fn indexed_transaction(
+    versioned_hash: H256,
+    indexed_blob: Option<&IndexedBlobDetails>,
+) -> Result<(&IndexedBlobDetails, &IndexedBlobTransaction), ReuploadError> {
+    let indexed_blob = indexed_blob.ok_or(ReuploadError::UnknownBlob(versioned_hash))?;
+    let transaction = indexed_blob
+        .transactions
+        .first()
+        .ok_or(ReuploadError::NoTransaction(versioned_hash))?;
+
+    Ok((indexed_blob, transaction))
+}
+
+/// Picks the blob with `versioned_hash` among the slot's reconstructed blobs, which follow the
+/// order of the block's commitments. Its commitment must match the one blobscan stores, and its
+/// data must commit to it, so a faulty reconstruction is never uploaded
+fn build_reupload_blob(
+    versioned_hash: H256,
+    slot: u32,
+    indexed_blob: &IndexedBlobDetails,
+    transaction: &IndexedBlobTransaction,
+    blobs: &[BeaconBlob],
+    kzg_commitment: impl Fn(&[u8]) -> Result<String, String>,
+) -> Result<Blob, ReuploadError> {
+    let mut blobs_by_versioned_hash = BTreeMap::new();
+
+    for (block_index, blob) in blobs.iter().enumerate() {
+        let blob_versioned_hash = calculate_versioned_hash(&blob.kzg_commitment)
+            .map_err(|error| ReuploadError::InvalidBlob(versioned_hash, error))?;
+
+        blobs_by_versioned_hash.insert(
+            blob_versioned_hash,
+            (BlobBlockIndex(block_index as u32), blob),
+        );
+    }
+
+    let (block_index, beacon_blob) =
+        blobs_by_versioned_hash
+            .get(&versioned_hash)
+            .ok_or(ReuploadError::NotInSlot {
+                slot,
+                versioned_hash,
+            })?;
+
+    if beacon_blob.kzg_commitment != indexed_blob.commitment {
+        return Err(ReuploadError::CommitmentMismatch {
+            stored: indexed_blob.commitment.clone(),
+            onchain: beacon_blob.kzg_commitment.clone(),
+        });
+    }
+
+    let computed = kzg_commitment(beacon_blob.blob.as_ref())
+        .map_err(|error| ReuploadError::KzgFailure(versioned_hash, error))?;
+
+    if !computed.eq_ignore_ascii_case(&beacon_blob.kzg_commitment) {
+        return Err(ReuploadError::KzgCommitmentMismatch {
+            versioned_hash,
+            expected: beacon_blob.kzg_commitment.clone(),
+            computed,
+        });
+    }
+
+    Blob::new(
+        beacon_blob,
+        versioned_hash,
+        Some(transaction.tx_hash),
+        BlobTxIndex(transaction.index),
+        *block_index,
+    )
+    .map_err(|error| ReuploadError::InvalidBlob(versioned_hash, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Bytes, H256};
+    use serde_json::{json, Value};
+
+    use super::{build_reupload_blob, indexed_transaction, reupload_blob, ReuploadError};
+    use crate::{
+        clients::{
+            beacon::types::Blob as BeaconBlob,
+            blobscan::types::{IndexedBlobDetails, IndexedBlobTransaction},
+            test_server::{self, Reply},
+        },
+        env::Environment,
+        network::Network,
+        utils::{url::EndpointUrl, web3::calculate_versioned_hash},
+    };
+
+    const BYTES_PER_BLOB: usize = 131072;
+
+    fn env(blobscan_api_endpoint: EndpointUrl, beacon_node_endpoint: EndpointUrl) -> Environment {
+        Environment {
+            network_name: Network::Devnet,
+            blobscan_api_endpoint,
+            beacon_node_endpoint,
+            execution_node_endpoint: EndpointUrl::parse("http://localhost:8545").unwrap(),
+            consensus_only: true,
+            beacon_http_version: Default::default(),
+            blobscan_http_version: Default::default(),
+            secret_key: "secret".to_string(),
+            dencun_fork_slot: None,
+            beacon_trace_bodies: false,
+            blobscan_trace_bodies: false,
+            trace_bodies_max_bytes: 4096,
+            sentry_dsn: None,
+            deprecated_vars: vec![],
+        }
+    }
+
+    fn commitment(byte: u8) -> String {
+        format!("0x{}", hex::encode([byte; 48]))
+    }
+
+    fn beacon_blob(index: u32, byte: u8) -> BeaconBlob {
+        BeaconBlob {
+            index: index.to_string(),
+            kzg_commitment: commitment(byte),
+            kzg_proof: commitment(0),
+            blob: Bytes::from(vec![byte; 8]),
+        }
+    }
+
+    fn indexed_blob(commitment: String) -> IndexedBlobDetails {
+        IndexedBlobDetails {
+            commitment,
+            transactions: vec![IndexedBlobTransaction {
+                tx_hash: H256::repeat_byte(9),
+                index: 0,
+                block_number: 100,
+            }],
+        }
+    }
+
+    // Stands in for KZG: the fake blobs repeat their commitment's byte
+    fn fake_kzg_commitment(blob: &[u8]) -> Result<String, String> {
+        Ok(commitment(blob[0]))
+    }
+
+    #[tokio::test]
+    async fn reuploads_the_blob_rebuilt_from_the_columns() {
+        // The zero blob commits to the point at infinity
+        let commitment = format!("0xc0{}", "00".repeat(47));
+        let versioned_hash = calculate_versioned_hash(&commitment).unwrap();
+        let blob_data = format!("0x{}", "00".repeat(BYTES_PER_BLOB));
+        let tx_hash = H256::repeat_byte(9);
+        let (blobscan_url, blobscan_server) = test_server::serve(vec![
+            Reply::Respond(
+                "200 OK",
+                json!({
+                    "commitment": commitment,
+                    "transactions": [{ "txHash": tx_hash, "index": 1, "blockNumber": 100 }],
+                })
+                .to_string(),
+            ),
+            Reply::Respond(
+                "200 OK",
+                json!({ "hash": H256::repeat_byte(1), "slot": 10 }).to_string(),
+            ),
+            Reply::Respond("200 OK", String::new()),
+        ])
+        .await;
+        // A single data column carrying the whole blob in its only cell
+        let (beacon_url, beacon_server) = test_server::serve(vec![
+            Reply::Respond(
+                "200 OK",
+                json!({
+                    "data": {
+                        "SECONDS_PER_SLOT": "12",
+                        "SLOTS_PER_EPOCH": "32",
+                        "NUMBER_OF_COLUMNS": "2",
+                    }
+                })
+                .to_string(),
+            ),
+            Reply::Respond(
+                "200 OK",
+                json!({
+                    "data": [{
+                        "index": "0",
+                        "blob_kzg_commitments": [commitment],
+                        "segment_kzg_proofs": [],
+                        "segments": [blob_data],
+                    }]
+                })
+                .to_string(),
+            ),
+        ])
+        .await;
+
+        reupload_blob(&env(blobscan_url, beacon_url), versioned_hash)
+            .await
+            .unwrap();
+
+        let beacon_requests = beacon_server.await.unwrap();
+        let blobscan_requests = blobscan_server.await.unwrap();
+        let upload = &blobscan_requests[2];
+        let body: Value = serde_json::from_str(&upload.body).unwrap();
+
+        assert_eq!(beacon_requests[1].path, "/eth/v1/beacon/column_sidecars/10");
+        assert_eq!(
+            blobscan_requests[0].path,
+            format!("/blobs/{versioned_hash:?}")
+        );
+        assert_eq!(blobscan_requests[1].path, "/blocks/100");
+        assert_eq!(upload.method, "PUT");
+        assert_eq!(upload.path, "/indexer/blobs");
+        assert_eq!(
+            body,
+            json!({
+                "blob": {
+                    "versionedHash": versioned_hash,
+                    "commitment": commitment,
+                    "proof": "",
+                    "data": blob_data,
+                    "txHash": tx_hash,
+                    "index": 1,
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn fails_when_blobscan_doesnt_know_the_blob() {
+        let versioned_hash = H256::repeat_byte(1);
+        let (blobscan_url, blobscan_server) =
+            test_server::serve(vec![Reply::Respond("404 Not Found", String::new())]).await;
+        let (beacon_url, beacon_server) = test_server::serve(vec![]).await;
+
+        let error = reupload_blob(&env(blobscan_url, beacon_url), versioned_hash)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<ReuploadError>(),
+            Some(ReuploadError::UnknownBlob(hash)) if *hash == versioned_hash
+        ));
+        assert_eq!(
+            blobscan_server.await.unwrap()[0].path,
+            format!("/blobs/{versioned_hash:?}")
+        );
+        assert!(beacon_server.await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn rebuilds_the_blob_with_its_block_position() {
+        let blobs = vec![beacon_blob(0, 1), beacon_blob(1, 2)];
+        let versioned_hash = calculate_versioned_hash(&commitment(2)).unwrap();
+        let indexed_blob = indexed_blob(commitment(2));
+        let (indexed_blob, transaction) =
+            indexed_transaction(versioned_hash, Some(&indexed_blob)).unwrap();
+
+        let blob = build_reupload_blob(
+            versioned_hash,
+            10,
+            indexed_blob,
+            transaction,
+            &blobs,
+            fake_kzg_commitment,
+        )
+        .unwrap();
+
+        assert_eq!(blob.versioned_hash, versioned_hash);
+        assert_eq!(blob.commitment, commitment(2));
+        assert_eq!(blob.tx_hash, Some(H256::repeat_byte(9)));
+        assert_eq!(blob.index, 0);
+    }
+
+    #[test]
+    fn fails_for_a_blob_unknown_to_blobscan() {
+        let versioned_hash = H256::repeat_byte(1);
+
+        assert!(matches!(
+            indexed_transaction(versioned_hash, None),
+            Err(ReuploadError::UnknownBlob(hash)) if hash == versioned_hash
+        ));
+    }
+
+    #[test]
+    fn refuses_data_that_doesnt_commit_to_the_commitment() {
+        let blobs = vec![beacon_blob(0, 1)];
+        let versioned_hash = calculate_versioned_hash(&commitment(1)).unwrap();
+        let indexed_blob = indexed_blob(commitment(1));
+        let (indexed_blob, transaction) =
+            indexed_transaction(versioned_hash, Some(&indexed_blob)).unwrap();
+
+        let result = build_reupload_blob(
+            versioned_hash,
+            10,
+            indexed_blob,
+            transaction,
+            &blobs,
+            |_| Ok(commitment(7)),
+        );
+
+        assert!(matches!(
+            result,
+            Err(ReuploadError::KzgCommitmentMismatch { computed, .. }) if computed == commitment(7)
+        ));
+    }
+
+    #[test]
+    fn refuses_a_commitment_blobscan_doesnt_store() {
+        let blobs = vec![beacon_blob(0, 1)];
+        let versioned_hash = calculate_versioned_hash(&commitment(1)).unwrap();
+        let indexed_blob = indexed_blob(commitment(3));
+        let (indexed_blob, transaction) =
+            indexed_transaction(versioned_hash, Some(&indexed_blob)).unwrap();
+
+        assert!(matches!(
+            build_reupload_blob(
+                versioned_hash,
+                10,
+                indexed_blob,
+                transaction,
+                &blobs,
+                fake_kzg_commitment
+            ),
+            Err(ReuploadError::CommitmentMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,239 @@
+use std::net::SocketAddr;
+
+use anyhow::Result as AnyhowResult;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{debug, info};
+
+use crate::context::Context;
+#[cfg(feature = "health")]
+use crate::health::HealthThresholds;
+
+/// Endpoints served on a port. Metrics and health share a listener when given the same port
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Endpoints {
+    #[cfg(feature = "metrics")]
+    pub metrics: bool,
+    #[cfg(feature = "health")]
+    pub health: Option<HealthThresholds>,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+/// Binds the port and serves the endpoints in the background
+pub async fn serve(context: Context, port: u16, endpoints: Endpoints) -> AnyhowResult<()> {
+    let listener = TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port))).await?;
+
+    info!(?endpoints, "Serving HTTP endpoints on port {port}");
+
+    spawn_listener(listener, context, endpoints);
+
+    Ok(())
+}
+
+fn spawn_listener(listener: TcpListener, context: Context, endpoints: Endpoints) {
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let context = context.clone();
+
+                    tokio::spawn(async move {
+                        if let Err(error) = respond(stream, &context, endpoints).await {
+                            debug!(?error, "Failed to serve HTTP request");
+                        }
+                    });
+                }
+                Err(error) => debug!(?error, "Failed to accept HTTP connection"),
+            }
+        }
+    });
+}
+
+async fn respond(
+    mut stream: TcpStream,
+    context: &Context,
+    endpoints: Endpoints,
+) -> std::io::Result<()> {
+    let mut buffer = [0; 1024];
+    let read = stream.read(&mut buffer).await?;
+    let request = String::from_utf8_lossy(&buffer[..read]);
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+
+    let response = match (path, endpoints) {
+        #[cfg(feature = "metrics")]
+        ("/metrics", Endpoints { metrics: true, .. }) => Response {
+            status: "200 OK",
+            content_type: "text/plain; version=0.0.4",
+            body: context
+                .metrics()
+                .render(context.indexing_stats().snapshot()),
+        },
+        #[cfg(feature = "health")]
+        (
+            "/healthz",
+            Endpoints {
+                health: Some(thresholds),
+                ..
+            },
+        ) => {
+            let slots_per_epoch = context
+                .try_chain_spec()
+                .map(|chain_spec| chain_spec.slots_per_epoch);
+            let report = context
+                .health()
+                .report(context.metrics(), thresholds, slots_per_epoch);
+
+            Response {
+                status: if report.healthy {
+                    "200 OK"
+                } else {
+                    "503 Service Unavailable"
+                },
+                content_type: "application/json",
+                body: serde_json::to_string(&report).unwrap_or_default(),
+            }
+        }
+        _ => Response {
+            status: "404 Not Found",
+            content_type: "text/plain",
+            body: String::new(),
+        },
+    };
+
+    let http_response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    );
+
+    stream.write_all(http_response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use std::net::SocketAddr;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::{spawn_listener, Endpoints};
+    use crate::{
+        clients::common::HttpVersion,
+        context::{Config, Context},
+        utils::url::EndpointUrl,
+    };
+
+    fn context() -> Context {
+        Context::try_new(Config {
+            blobscan_api_endpoint: EndpointUrl::parse("http://localhost:3001").unwrap(),
+            beacon_node_url: EndpointUrl::parse("http://localhost:3500").unwrap(),
+            execution_node_endpoint: None,
+            secret_key: "test-secret".to_string(),
+            disable_retries: true,
+            beacon_http_version: HttpVersion::Auto,
+            blobscan_http_version: HttpVersion::Auto,
+            beacon_body_trace: Default::default(),
+            blobscan_body_trace: Default::default(),
+        })
+        .unwrap()
+    }
+
+    async fn get(context: Context, endpoints: Endpoints, path: &str) -> String {
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .unwrap();
+        let address = listener.local_addr().unwrap();
+
+        spawn_listener(listener, context, endpoints);
+
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        let mut response = String::new();
+
+        stream
+            .write_all(format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_bytes())
+            .await
+            .unwrap();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        response
+    }
+
+    #[tokio::test]
+    async fn metrics_endpoint_serves_every_series() {
+        let context = context();
+
+        context.metrics().record_indexed_slot(120);
+        context.metrics().record_processed_slot(125);
+        context.metrics().set_beacon_head_slot(130);
+        context.metrics().record_slot_error("blocks_mismatch");
+        context.indexing_stats().record_block(2, 3, None);
+
+        let response = get(
+            context,
+            Endpoints {
+                metrics: true,
+                ..Default::default()
+            },
+            "/metrics",
+        )
+        .await;
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.contains("Content-Type: text/plain; version=0.0.4\r\n"));
+
+        for series in [
+            "indexer_last_indexed_slot 120\n",
+            "indexer_last_processed_slot 125\n",
+            "indexer_beacon_head_slot 130\n",
+            "indexer_lag_slots 5\n",
+            "indexer_slots_per_second 0\n",
+            "indexer_blocks_indexed_total 1\n",
+            "indexer_transactions_indexed_total 2\n",
+            "indexer_blobs_indexed_total 3\n",
+            "indexer_blob_fees_burned_total 0\n",
+            "indexer_slot_errors_total{kind=\"blocks_mismatch\"} 1\n",
+        ] {
+            assert!(
+                response.contains(series),
+                "missing {series:?} in {response}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn disabled_or_unknown_endpoints_are_not_found() {
+        let response = get(context(), Endpoints::default(), "/metrics").await;
+
+        assert!(
+            response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+            "{response}"
+        );
+
+        let response = get(
+            context(),
+            Endpoints {
+                metrics: true,
+                ..Default::default()
+            },
+            "/other",
+        )
+        .await;
+
+        assert!(
+            response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+            "{response}"
+        );
+    }
+}
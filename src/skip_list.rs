@@ -0,0 +1,181 @@
+use std::{fs, ops::RangeInclusive, path::Path};
+
+use anyhow::{anyhow, Context as AnyhowContext, Result as AnyhowResult};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkipListEntry {
+    pub slots: RangeInclusive<u32>,
+    pub reason: String,
+}
+
+/// Slots an operator excluded from indexing for good, e.g. slots whose data no node can serve
+/// anymore.
+///
+/// Each non-empty line holds a slot or an inclusive `start-end` range followed by the reason.
+/// Lines starting with `#` are comments:
+///
+/// ```text
+/// # Corrupted node data
+/// 1234 sidecars lost on every node
+/// 2000-2010 pruned before they were indexed
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SkipList {
+    entries: Vec<SkipListEntry>,
+}
+
+impl SkipList {
+    pub fn load(path: &Path) -> AnyhowResult<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read skip list {}", path.display()))?;
+
+        Self::parse(&content).with_context(|| format!("Invalid skip list {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> AnyhowResult<Self> {
+        let mut entries = vec![];
+
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let entry = parse_entry(line).with_context(|| format!("line {}", index + 1))?;
+
+            entries.push(entry);
+        }
+
+        Ok(Self { entries })
+    }
+
+    pub fn entries_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Reason the slot was excluded for, if it is
+    pub fn reason_for(&self, slot: u32) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| entry.slots.contains(&slot))
+            .map(|entry| entry.reason.as_str())
+    }
+}
+
+fn parse_entry(line: &str) -> AnyhowResult<SkipListEntry> {
+    let (slots, reason) = line
+        .split_once(char::is_whitespace)
+        .map(|(slots, reason)| (slots, reason.trim()))
+        .unwrap_or((line, ""));
+
+    if reason.is_empty() {
+        return Err(anyhow!("missing reason for {slots}"));
+    }
+
+    let parse_slot = |slot: &str| {
+        slot.parse::<u32>()
+            .with_context(|| format!("invalid slot {slot}"))
+    };
+
+    let slots = match slots.split_once('-') {
+        Some((start, end)) => {
+            let (start, end) = (parse_slot(start)?, parse_slot(end)?);
+
+            if end < start {
+                return Err(anyhow!("range {start}-{end} ends before it starts"));
+            }
+
+            start..=end
+        }
+        None => {
+            let slot = parse_slot(slots)?;
+
+            slot..=slot
+        }
+    };
+
+    Ok(SkipListEntry {
+        slots,
+        reason: reason.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SkipList, SkipListEntry};
+
+    #[test]
+    fn parses_slots_ranges_and_reasons() {
+        let skip_list = SkipList::parse(
+            "# Corrupted node data\n\
+             1234 sidecars lost on every node\n\
+             \n\
+             2000-2010   pruned before they were indexed  \n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            skip_list.entries,
+            vec![
+                SkipListEntry {
+                    slots: 1234..=1234,
+                    reason: "sidecars lost on every node".to_string(),
+                },
+                SkipListEntry {
+                    slots: 2000..=2010,
+                    reason: "pruned before they were indexed".to_string(),
+                },
+            ]
+        );
+        assert_eq!(skip_list.entries_count(), 2);
+    }
+
+    #[test]
+    fn comments_and_blank_lines_make_an_empty_list() {
+        let skip_list = SkipList::parse("\n  # nothing to skip\n\n").unwrap();
+
+        assert_eq!(skip_list, SkipList::default());
+    }
+
+    #[test]
+    fn finds_the_reason_of_skipped_slots() {
+        let skip_list = SkipList::parse("5 lost\n10-12 pruned").unwrap();
+
+        assert_eq!(skip_list.reason_for(5), Some("lost"));
+        assert_eq!(skip_list.reason_for(10), Some("pruned"));
+        assert_eq!(skip_list.reason_for(12), Some("pruned"));
+        assert_eq!(skip_list.reason_for(4), None);
+        assert_eq!(skip_list.reason_for(13), None);
+    }
+
+    #[test]
+    fn single_slot_ranges_are_allowed() {
+        let skip_list = SkipList::parse("7-7 lost").unwrap();
+
+        assert_eq!(skip_list.reason_for(7), Some("lost"));
+        assert_eq!(skip_list.reason_for(8), None);
+    }
+
+    #[test]
+    fn rejects_invalid_lines_with_their_line_number() {
+        let cases = [
+            ("1234", "line 1: missing reason for 1234"),
+            ("# header\n1234   ", "line 2: missing reason for 1234"),
+            ("abc lost", "line 1: invalid slot abc"),
+            ("10-x lost", "line 1: invalid slot x"),
+            ("-5 lost", "line 1: invalid slot "),
+            ("12-10 lost", "line 1: range 12-10 ends before it starts"),
+            ("4294967296 lost", "line 1: invalid slot 4294967296"),
+        ];
+
+        for (content, expected) in cases {
+            let error = SkipList::parse(content).unwrap_err();
+
+            assert!(
+                format!("{error:#}").starts_with(expected),
+                "{content:?}: {error:#}"
+            );
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+
+use ethers::types::H256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackedBlock {
+    pub slot: u32,
+    pub execution_block_hash: H256,
+    /// Whether the slot was indexed, rather than skipped after being checked
+    pub indexed: bool,
+}
+
+/// The most recently processed blocks of a forward sync, oldest first. Used to check that every
+/// new block builds on the previous one and to find the fork point when it doesn't.
+#[derive(Debug)]
+pub struct ChainTracker {
+    blocks: VecDeque<TrackedBlock>,
+    capacity: usize,
+}
+
+impl ChainTracker {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            blocks: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub fn tip(&self) -> Option<&TrackedBlock> {
+        self.blocks.back()
+    }
+
+    pub fn oldest(&self) -> Option<&TrackedBlock> {
+        self.blocks.front()
+    }
+
+    pub fn contains(&self, slot: u32, execution_block_hash: H256) -> bool {
+        self.blocks
+            .iter()
+            .any(|block| block.slot == slot && block.execution_block_hash == execution_block_hash)
+    }
+
+    pub fn push(&mut self, slot: u32, execution_block_hash: H256) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        if self.blocks.len() == self.capacity {
+            self.blocks.pop_front();
+        }
+
+        self.blocks.push_back(TrackedBlock {
+            slot,
+            execution_block_hash,
+            indexed: false,
+        });
+    }
+
+    pub fn mark_indexed(&mut self, slot: u32) {
+        if let Some(block) = self
+            .blocks
+            .iter_mut()
+            .rev()
+            .find(|block| block.slot == slot)
+        {
+            block.indexed = true;
+        }
+    }
+
+    /// Indexed slots at the given slot and above
+    pub fn indexed_slots_from(&self, slot: u32) -> Vec<u32> {
+        self.blocks
+            .iter()
+            .filter(|block| block.slot >= slot && block.indexed)
+            .map(|block| block.slot)
+            .collect()
+    }
+
+    /// Forgets the blocks at the given slot and above
+    pub fn truncate_from(&mut self, slot: u32) {
+        while self.tip().is_some_and(|block| block.slot >= slot) {
+            self.blocks.pop_back();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(byte: u8) -> H256 {
+        H256::repeat_byte(byte)
+    }
+
+    #[test]
+    fn keeps_the_most_recent_blocks() {
+        let mut chain_tracker = ChainTracker::new(2);
+
+        chain_tracker.push(1, hash(1));
+        chain_tracker.push(2, hash(2));
+        chain_tracker.push(3, hash(3));
+
+        assert_eq!(chain_tracker.oldest().map(|block| block.slot), Some(2));
+        assert_eq!(chain_tracker.tip().map(|block| block.slot), Some(3));
+        assert!(!chain_tracker.contains(1, hash(1)));
+        assert!(chain_tracker.contains(3, hash(3)));
+        assert!(!chain_tracker.contains(3, hash(4)));
+    }
+
+    #[test]
+    fn only_reports_indexed_slots() {
+        let mut chain_tracker = ChainTracker::new(8);
+
+        for slot in 1..=4 {
+            chain_tracker.push(slot, hash(slot as u8));
+        }
+
+        chain_tracker.mark_indexed(2);
+        chain_tracker.mark_indexed(4);
+
+        assert_eq!(chain_tracker.indexed_slots_from(2), vec![2, 4]);
+        assert_eq!(chain_tracker.indexed_slots_from(3), vec![4]);
+    }
+
+    #[test]
+    fn truncates_from_a_slot() {
+        let mut chain_tracker = ChainTracker::new(8);
+
+        for slot in 1..=4 {
+            chain_tracker.push(slot, hash(slot as u8));
+        }
+
+        chain_tracker.truncate_from(3);
+
+        assert_eq!(chain_tracker.tip().map(|block| block.slot), Some(2));
+    }
+
+    #[test]
+    fn disabled_tracker_ignores_blocks() {
+        let mut chain_tracker = ChainTracker::new(0);
+
+        chain_tracker.push(1, hash(1));
+
+        assert!(!chain_tracker.is_enabled());
+        assert!(chain_tracker.tip().is_none());
+    }
+}
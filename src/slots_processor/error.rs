@@ -1,24 +1,245 @@
+use std::collections::BTreeMap;
+
+use ethers::providers::{HttpClientError, ProviderError};
+
+use crate::clients::{blobscan::types::SlotSkipReason, common::ClientError};
+
 #[derive(Debug, thiserror::Error)]
 pub enum SlotProcessingError {
     #[error(transparent)]
     ClientError(#[from] crate::clients::common::ClientError),
     #[error(transparent)]
     Provider(#[from] ethers::providers::ProviderError),
+    #[error("beacon node returned an empty {resource} for slot {slot} after {attempts} attempts")]
+    EmptyResponseAnomaly {
+        resource: &'static str,
+        slot: u32,
+        attempts: u32,
+    },
+    #[error("execution block {number} timestamp {timestamp} doesn't advance past parent block timestamp {parent_timestamp}")]
+    TimestampRegression {
+        number: u64,
+        timestamp: u64,
+        parent_timestamp: u64,
+    },
+    #[error("execution block number {number} doesn't follow parent block number {parent_number}")]
+    NonSequentialBlockNumber { number: u64, parent_number: u64 },
+    #[error("slot {slot} has {commitments} blob commitments but {blobs} blobs were reconstructed. Versioned hashes without a blob: {missing_versioned_hashes:?}")]
+    BlobCountMismatch {
+        slot: u32,
+        commitments: usize,
+        blobs: usize,
+        missing_versioned_hashes: Vec<ethers::types::H256>,
+    },
+    #[error("blob {index} of slot {slot} has versioned hash {found:?} but the block's commitment at that position has {expected:?}. {mismatches} blobs don't match their commitment")]
+    BlobCommitmentMismatch {
+        slot: u32,
+        index: usize,
+        expected: ethers::types::H256,
+        found: ethers::types::H256,
+        mismatches: usize,
+    },
+    #[error("beacon block of slot {slot} carries {commitments} blob commitments but its execution block has no blob transactions")]
+    BlocksMismatch { slot: u32, commitments: usize },
+    #[error("failed to submit the slot to blobscan")]
+    SubmissionFailure(#[source] crate::clients::common::ClientError),
+    #[error("failed to rebuild the blobs of slot {slot} from its columns")]
+    BlobReconstruction {
+        slot: u32,
+        #[source]
+        source: crate::clients::beacon::columns::ColumnReconstructionError,
+    },
+    #[error("proposer duties don't include slot {slot}")]
+    MissingProposer { slot: u32 },
+    #[error("couldn't find the fork point of slot {slot} within {max_reorg_depth} slots")]
+    ReorgTooDeep { slot: u32, max_reorg_depth: u32 },
+    #[error("slot {slot} still doesn't build on the processed chain after {rollbacks} rollbacks")]
+    UnstableChain { slot: u32, rollbacks: u32 },
+    #[error("slot processing was cancelled")]
+    Cancelled,
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
+impl SlotProcessingError {
+    /// Stable label identifying the variant, used to break down errors in metrics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ClientError(_) => "client_error",
+            Self::Provider(_) => "provider",
+            Self::EmptyResponseAnomaly { .. } => "empty_response_anomaly",
+            Self::TimestampRegression { .. } => "timestamp_regression",
+            Self::NonSequentialBlockNumber { .. } => "non_sequential_block_number",
+            Self::BlobCountMismatch { .. } => "blob_count_mismatch",
+            Self::BlobCommitmentMismatch { .. } => "blob_commitment_mismatch",
+            Self::BlocksMismatch { .. } => "blocks_mismatch",
+            Self::SubmissionFailure(_) => "submission_failure",
+            Self::BlobReconstruction { .. } => "blob_reconstruction",
+            Self::MissingProposer { .. } => "missing_proposer",
+            Self::ReorgTooDeep { .. } => "reorg_too_deep",
+            Self::UnstableChain { .. } => "unstable_chain",
+            Self::Cancelled => "cancelled",
+            Self::Other(_) => "other",
+        }
+    }
+
+    /// Errors that stop a sync even when failed slots are skipped: cancellation, blobscan
+    /// rejecting what was submitted and unreachable nodes, which would fail every following slot
+    /// too
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            Self::Cancelled | Self::SubmissionFailure(ClientError::ApiError(_)) => true,
+            Self::ClientError(error) | Self::SubmissionFailure(error) => {
+                error.is_connectivity_error()
+            }
+            Self::Provider(error) => is_provider_connectivity_error(error),
+            _ => false,
+        }
+    }
+}
+
+fn is_provider_connectivity_error(error: &ProviderError) -> bool {
+    let reqwest_error = match error {
+        ProviderError::HTTPError(error) => Some(error),
+        ProviderError::JsonRpcClientError(error) => match error.downcast_ref::<HttpClientError>() {
+            Some(HttpClientError::ReqwestError(error)) => Some(error),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    reqwest_error.is_some_and(|error| error.is_connect() || error.is_timeout())
+}
+
+/// Slots of a range handled before it stopped. Slots are visited in order, so every slot before
+/// the failed one was either indexed, skipped or, when failures are tolerated, failed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SlotsProgress {
+    pub indexed_slots: u32,
+    /// Skipped slots counted by reason. Counts rather than slots, as a sampled range skips most
+    /// of its slots and the progress ends up in logs
+    pub skipped_slots: BTreeMap<SlotSkipReason, u32>,
+    /// Slots that failed and were moved past, only filled when failures are tolerated
+    pub failed_slots: Vec<u32>,
+}
+
+impl SlotsProgress {
+    pub fn record_indexed(&mut self) {
+        self.indexed_slots += 1;
+    }
+
+    pub fn record_skipped(&mut self, reason: SlotSkipReason) {
+        *self.skipped_slots.entry(reason).or_default() += 1;
+    }
+
+    pub fn record_failed(&mut self, slot: u32) {
+        self.failed_slots.push(slot);
+    }
+
+    pub fn skipped_count(&self) -> u32 {
+        self.skipped_slots.values().sum()
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum SlotsProcessorError {
     #[error(
-        "Error processing slots range {initial_slot}-{final_slot}. Slot {failed_slot} failed: {error}"
+        "Error processing slots range {initial_slot}-{final_slot}. Slot {failed_slot} failed after {} indexed and {} skipped slots: {error}",
+        .progress.indexed_slots,
+        .progress.skipped_count()
     )]
     FailedSlotsProcessing {
         initial_slot: u32,
         final_slot: u32,
         failed_slot: u32,
+        progress: SlotsProgress,
         error: SlotProcessingError,
     },
+    #[error("{} of the {processed_slots} slots of range {initial_slot}-{final_slot} failed, over the {max_failure_rate} failure rate. Failed slots: {failed_slots:?}", .failed_slots.len())]
+    FailureRateExceeded {
+        initial_slot: u32,
+        final_slot: u32,
+        processed_slots: u32,
+        failed_slots: Vec<u32>,
+        max_failure_rate: f64,
+    },
+    #[error("Error rolling back slots range {initial_slot}-{final_slot}: {error}")]
+    FailedSlotsRollback {
+        initial_slot: u32,
+        final_slot: u32,
+        error: crate::clients::common::ClientError,
+    },
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
+
+impl SlotsProcessorError {
+    /// Whether the error would fail any other range too, rather than being specific to the
+    /// slot that failed
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            Self::FailedSlotsProcessing { error, .. } => error.is_fatal(),
+            Self::FailureRateExceeded { .. }
+            | Self::FailedSlotsRollback { .. }
+            | Self::Other(_) => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{SlotProcessingError, SlotsProcessorError, SlotsProgress};
+    use crate::clients::blobscan::types::SlotSkipReason;
+
+    #[test]
+    fn test_progress_counts_a_mixed_range() {
+        let mut progress = SlotsProgress::default();
+
+        progress.record_indexed();
+        progress.record_skipped(SlotSkipReason::MissedSlot);
+        progress.record_skipped(SlotSkipReason::EmptyBlock);
+        progress.record_indexed();
+        progress.record_failed(104);
+        progress.record_skipped(SlotSkipReason::MissedSlot);
+
+        assert_eq!(
+            progress,
+            SlotsProgress {
+                indexed_slots: 2,
+                skipped_slots: BTreeMap::from([
+                    (SlotSkipReason::MissedSlot, 2),
+                    (SlotSkipReason::EmptyBlock, 1),
+                ]),
+                failed_slots: vec![104],
+            }
+        );
+        assert_eq!(progress.skipped_count(), 3);
+    }
+
+    #[test]
+    fn test_failed_range_reports_the_progress_counts() {
+        let mut progress = SlotsProgress::default();
+
+        progress.record_indexed();
+
+        for _ in 0..1000 {
+            progress.record_skipped(SlotSkipReason::NotSampled);
+        }
+
+        let error = SlotsProcessorError::FailedSlotsProcessing {
+            initial_slot: 100,
+            final_slot: 2000,
+            failed_slot: 1102,
+            progress,
+            error: SlotProcessingError::Cancelled,
+        };
+
+        assert!(error
+            .to_string()
+            .contains("Slot 1102 failed after 1 indexed and 1000 skipped slots"));
+        // The skipped slots are logged as counts, not one entry per slot
+        assert!(format!("{error:?}").contains("skipped_slots: {NotSampled: 1000}"));
+    }
+}
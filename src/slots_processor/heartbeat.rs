@@ -0,0 +1,94 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+/// Rate limits the slot heartbeats sent for skipped empty blocks. Shared by every batch, so a
+/// run of empty blocks sends at most one heartbeat per interval
+#[derive(Debug)]
+pub struct EmptyBlockHeartbeat {
+    interval: Duration,
+    /// Slot of the last heartbeat and when it was sent
+    last_sent: Mutex<Option<(u32, SystemTime)>>,
+}
+
+impl EmptyBlockHeartbeat {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_sent: Mutex::new(None),
+        }
+    }
+
+    /// Claims the heartbeat for `slot` when none was sent in the last interval. Slots at or below
+    /// the last one sent are never claimed, so blobscan's freshness only moves forward. A claimed
+    /// heartbeat counts as sent even if the request then fails, so failures aren't retried on
+    /// every empty block. A clock that jumped back counts as no time elapsed
+    pub fn try_claim(&self, slot: u32, now: SystemTime) -> bool {
+        let mut last_sent = self
+            .last_sent
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if last_sent.is_some_and(|(last_slot, last_sent_at)| {
+            slot <= last_slot
+                || now.duration_since(last_sent_at).unwrap_or_default() < self.interval
+        }) {
+            return false;
+        }
+
+        *last_sent = Some((slot, now));
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use super::EmptyBlockHeartbeat;
+
+    fn start() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    }
+
+    #[test]
+    fn test_first_heartbeat_is_sent() {
+        let heartbeat = EmptyBlockHeartbeat::new(Duration::from_secs(60));
+
+        assert!(heartbeat.try_claim(10, start()));
+    }
+
+    #[test]
+    fn test_heartbeats_within_the_interval_are_dropped() {
+        let heartbeat = EmptyBlockHeartbeat::new(Duration::from_secs(60));
+        let start = start();
+
+        assert!(heartbeat.try_claim(10, start));
+        assert!(!heartbeat.try_claim(11, start + Duration::from_secs(1)));
+        assert!(!heartbeat.try_claim(12, start + Duration::from_secs(59)));
+        assert!(heartbeat.try_claim(13, start + Duration::from_secs(60)));
+        assert!(!heartbeat.try_claim(14, start + Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn test_heartbeats_after_the_clock_jumped_back_are_dropped() {
+        let heartbeat = EmptyBlockHeartbeat::new(Duration::from_secs(60));
+        let start = start();
+
+        assert!(heartbeat.try_claim(10, start));
+        assert!(!heartbeat.try_claim(11, start - Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_heartbeats_never_go_back_to_earlier_slots() {
+        let heartbeat = EmptyBlockHeartbeat::new(Duration::from_secs(60));
+        let start = start();
+
+        assert!(heartbeat.try_claim(100, start));
+        assert!(!heartbeat.try_claim(100, start + Duration::from_secs(60)));
+        assert!(!heartbeat.try_claim(50, start + Duration::from_secs(120)));
+        assert!(heartbeat.try_claim(101, start + Duration::from_secs(120)));
+    }
+}
@@ -3,10 +3,12 @@ use std::collections::HashMap;
 use ethers::types::{Block as EthersBlock, Transaction as EthersTransaction, H256};
 
 use crate::{
-    clients::beacon::types::Blob as BeaconBlob,
-    utils::web3::{calculate_versioned_hash, get_tx_versioned_hashes},
+    clients::{beacon::types::Blob as BeaconBlob, blobscan::types::BlobBlockIndex},
+    utils::web3::{calculate_versioned_hash, get_tx_versioned_hashes, is_blob_tx},
 };
 
+use super::error::SlotProcessingError;
+
 pub fn create_tx_hash_versioned_hashes_mapping(
     block: &EthersBlock<EthersTransaction>,
 ) -> Result<HashMap<H256, Vec<H256>>, anyhow::Error> {
@@ -36,3 +38,516 @@ pub fn create_versioned_hash_blob_mapping(
 
     Ok(version_hash_to_blob)
 }
+
+pub fn create_versioned_hash_commitment_mapping(
+    commitments: &[String],
+) -> Result<HashMap<H256, &String>, anyhow::Error> {
+    let mut version_hash_to_commitment = HashMap::new();
+
+    for commitment in commitments {
+        let versioned_hash = calculate_versioned_hash(commitment)?;
+
+        version_hash_to_commitment
+            .entry(versioned_hash)
+            .or_insert(commitment);
+    }
+
+    Ok(version_hash_to_commitment)
+}
+
+/// Position of every commitment of the block, keyed by its versioned hash
+pub fn create_versioned_hash_block_index_mapping(
+    commitments: &[String],
+) -> Result<HashMap<H256, BlobBlockIndex>, anyhow::Error> {
+    let mut versioned_hash_to_block_index = HashMap::new();
+
+    for (index, commitment) in commitments.iter().enumerate() {
+        let versioned_hash = calculate_versioned_hash(commitment)?;
+
+        versioned_hash_to_block_index
+            .entry(versioned_hash)
+            .or_insert(BlobBlockIndex(index as u32));
+    }
+
+    Ok(versioned_hash_to_block_index)
+}
+
+/// Checks that a blob was reconstructed for every commitment of the block, in the block's order.
+/// Blobs and commitments are compared by versioned hash, so a blob carrying another commitment
+/// than the one at its position fails even when the counts match
+pub fn ensure_blobs_match_commitments(
+    slot: u32,
+    blobs: &[BeaconBlob],
+    commitments: &[String],
+) -> Result<(), SlotProcessingError> {
+    if blobs.len() == commitments.len() {
+        let mut mismatches = vec![];
+
+        for (index, (blob, commitment)) in blobs.iter().zip(commitments).enumerate() {
+            let expected = calculate_versioned_hash(commitment)?;
+            let found = calculate_versioned_hash(&blob.kzg_commitment)?;
+
+            if expected != found {
+                mismatches.push((index, expected, found));
+            }
+        }
+
+        return match mismatches.first() {
+            Some(&(index, expected, found)) => Err(SlotProcessingError::BlobCommitmentMismatch {
+                slot,
+                index,
+                expected,
+                found,
+                mismatches: mismatches.len(),
+            }),
+            None => Ok(()),
+        };
+    }
+
+    let missing_versioned_hashes = commitments
+        .iter()
+        .filter(|commitment| !blobs.iter().any(|blob| blob.kzg_commitment == **commitment))
+        .map(|commitment| calculate_versioned_hash(commitment))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Err(SlotProcessingError::BlobCountMismatch {
+        slot,
+        commitments: commitments.len(),
+        blobs: blobs.len(),
+        missing_versioned_hashes,
+    })
+}
+
+/// Hashes of the execution block's blob transactions. A beacon block carrying commitments while
+/// its execution block has no blob transaction, as a builder bug once produced on a devnet, is a
+/// blocks mismatch
+pub fn blob_tx_hashes_for_commitments(
+    slot: u32,
+    block: &EthersBlock<EthersTransaction>,
+    blob_kzg_commitments: &[String],
+) -> Result<Vec<H256>, SlotProcessingError> {
+    let blob_tx_hashes = block
+        .transactions
+        .iter()
+        .filter(|tx| is_blob_tx(tx))
+        .map(|tx| tx.hash)
+        .collect::<Vec<_>>();
+
+    if blob_tx_hashes.is_empty() && !blob_kzg_commitments.is_empty() {
+        return Err(SlotProcessingError::BlocksMismatch {
+            slot,
+            commitments: blob_kzg_commitments.len(),
+        });
+    }
+
+    Ok(blob_tx_hashes)
+}
+
+/// Derives the versioned hashes of the block's blob transaction from the beacon block
+/// commitments. Only possible when there is a single blob transaction, as nothing tells which
+/// commitments belong to which transaction otherwise
+pub fn derive_tx_hash_versioned_hashes_mapping(
+    slot: u32,
+    blob_tx_hashes: &[H256],
+    blob_kzg_commitments: &[String],
+) -> Result<HashMap<H256, Vec<H256>>, SlotProcessingError> {
+    match blob_tx_hashes {
+        [tx_hash] => {
+            let versioned_hashes = blob_kzg_commitments
+                .iter()
+                .map(|commitment| calculate_versioned_hash(commitment))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(HashMap::from([(*tx_hash, versioned_hashes)]))
+        }
+        _ => Err(anyhow::anyhow!(
+            "Couldn't resolve blob versioned hashes for slot {slot}: {} commitments across {} blob transactions",
+            blob_kzg_commitments.len(),
+            blob_tx_hashes.len()
+        )
+        .into()),
+    }
+}
+
+/// Whether `slot` comes after the blob fork. When the fork slot is unknown, every slot is treated
+/// as post-fork
+pub fn is_blob_fork_active(slot: u32, blob_fork_slot: Option<u32>) -> bool {
+    blob_fork_slot.map_or(true, |blob_fork_slot| slot >= blob_fork_slot)
+}
+
+/// Whether `slot` is one of the `sample_rate` slots sampled from its epoch. The sampled slots are
+/// evenly spaced over the epoch, starting at an offset derived from the epoch number, so they
+/// don't always land on the same positions. The selection is deterministic
+pub fn is_sampled(slot: u32, slots_per_epoch: u32, sample_rate: u32) -> bool {
+    let slots_per_epoch = slots_per_epoch as u64;
+    let epoch = slot as u64 / slots_per_epoch;
+    let offset = mix(epoch) % slots_per_epoch;
+    let position = (slot as u64 % slots_per_epoch + slots_per_epoch - offset) % slots_per_epoch;
+
+    // Exactly `sample_rate` positions pass, one every `slots_per_epoch / sample_rate` on average
+    (position * sample_rate as u64) % slots_per_epoch < sample_rate as u64
+}
+
+/// SplitMix64 finalizer, which spreads consecutive epochs over unrelated offsets
+fn mix(value: u64) -> u64 {
+    let mut value = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+    value ^ (value >> 31)
+}
+
+/// A beacon response for a resource that is never empty when it exists
+#[derive(Debug, PartialEq)]
+pub enum NonEmptyResponse<T> {
+    Found(T),
+    /// The node answered 404, a definitive absence
+    NotFound,
+    /// The node answered with empty data, as some proxies do while the node restarts
+    Empty,
+}
+
+pub fn classify_non_empty<T>(
+    response: Option<T>,
+    is_empty: impl FnOnce(&T) -> bool,
+) -> NonEmptyResponse<T> {
+    match response {
+        Some(data) if is_empty(&data) => NonEmptyResponse::Empty,
+        Some(data) => NonEmptyResponse::Found(data),
+        None => NonEmptyResponse::NotFound,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Block as EthersBlock, Bytes, Transaction as EthersTransaction, H256, U64};
+
+    use crate::{
+        clients::beacon::types::Blob as BeaconBlob,
+        slots_processor::error::SlotProcessingError,
+        utils::web3::{calculate_versioned_hash, BLOB_TX_TYPE},
+    };
+
+    use super::{
+        blob_tx_hashes_for_commitments, classify_non_empty,
+        create_tx_hash_versioned_hashes_mapping, derive_tx_hash_versioned_hashes_mapping,
+        ensure_blobs_match_commitments, is_blob_fork_active, is_sampled, NonEmptyResponse,
+    };
+
+    const COMMITMENT: &str = "0xc0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0";
+
+    /// The devnet block's execution payload, which had no transactions while its beacon block
+    /// carried commitments
+    fn zero_tx_block() -> EthersBlock<EthersTransaction> {
+        EthersBlock {
+            hash: Some(H256::repeat_byte(0xbb)),
+            number: Some(U64::from(1_234)),
+            transactions: vec![],
+            ..Default::default()
+        }
+    }
+
+    fn tx(hash: u8, transaction_type: u64) -> EthersTransaction {
+        EthersTransaction {
+            hash: H256::repeat_byte(hash),
+            transaction_type: Some(U64::from(transaction_type)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_zero_tx_block_with_commitments_is_a_blocks_mismatch() {
+        let block = zero_tx_block();
+        let commitments = vec![COMMITMENT.to_string(), COMMITMENT.to_string()];
+
+        // The versioned hashes mapping alone can't tell this block from one without blobs
+        assert!(create_tx_hash_versioned_hashes_mapping(&block)
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            blob_tx_hashes_for_commitments(7, &block, &commitments),
+            Err(SlotProcessingError::BlocksMismatch {
+                slot: 7,
+                commitments: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn test_block_without_blob_txs_but_with_commitments_is_a_blocks_mismatch() {
+        let mut block = zero_tx_block();
+
+        block.transactions = vec![tx(1, 0), tx(2, 2)];
+
+        assert!(matches!(
+            blob_tx_hashes_for_commitments(7, &block, &[COMMITMENT.to_string()]),
+            Err(SlotProcessingError::BlocksMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_blob_tx_hashes_are_returned() {
+        let mut block = zero_tx_block();
+
+        block.transactions = vec![tx(1, 2), tx(2, BLOB_TX_TYPE), tx(3, BLOB_TX_TYPE)];
+
+        assert_eq!(
+            blob_tx_hashes_for_commitments(7, &block, &[COMMITMENT.to_string()]).unwrap(),
+            vec![H256::repeat_byte(2), H256::repeat_byte(3)]
+        );
+    }
+
+    #[test]
+    fn test_zero_tx_block_without_commitments_is_fine() {
+        assert!(blob_tx_hashes_for_commitments(7, &zero_tx_block(), &[])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_stripped_versioned_hashes_leave_the_mapping_empty() {
+        let versioned_hash = calculate_versioned_hash(COMMITMENT).unwrap();
+        let mut block = zero_tx_block();
+        let mut tx_with_hashes = tx(2, BLOB_TX_TYPE);
+
+        tx_with_hashes.other =
+            serde_json::from_value(serde_json::json!({ "blobVersionedHashes": [versioned_hash] }))
+                .unwrap();
+        block.transactions = vec![tx(1, BLOB_TX_TYPE)];
+
+        assert!(create_tx_hash_versioned_hashes_mapping(&block)
+            .unwrap()
+            .is_empty());
+
+        block.transactions.push(tx_with_hashes);
+
+        let mapping = create_tx_hash_versioned_hashes_mapping(&block).unwrap();
+
+        assert_eq!(mapping.len(), 1);
+        assert_eq!(mapping[&H256::repeat_byte(2)], vec![versioned_hash]);
+    }
+
+    #[test]
+    fn test_single_blob_tx_takes_every_commitment() {
+        let commitments = vec![COMMITMENT.to_string(), commitment(0xc1)];
+        let mapping =
+            derive_tx_hash_versioned_hashes_mapping(7, &[H256::repeat_byte(1)], &commitments)
+                .unwrap();
+
+        assert_eq!(mapping.len(), 1);
+        assert_eq!(
+            mapping[&H256::repeat_byte(1)],
+            vec![
+                calculate_versioned_hash(COMMITMENT).unwrap(),
+                calculate_versioned_hash(&commitment(0xc1)).unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_commitments_across_several_blob_txs_cant_be_derived() {
+        let tx_hashes = [H256::repeat_byte(1), H256::repeat_byte(2)];
+        let error =
+            derive_tx_hash_versioned_hashes_mapping(7, &tx_hashes, &[COMMITMENT.to_string()])
+                .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Couldn't resolve blob versioned hashes for slot 7: 1 commitments across 2 blob transactions"
+        );
+    }
+
+    #[test]
+    fn test_invalid_commitment_cant_be_derived() {
+        assert!(derive_tx_hash_versioned_hashes_mapping(
+            7,
+            &[H256::repeat_byte(1)],
+            &["0xnothex".to_string()]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_blob_fork_starts_at_its_first_slot() {
+        assert!(!is_blob_fork_active(99, Some(100)));
+        assert!(is_blob_fork_active(100, Some(100)));
+        assert!(is_blob_fork_active(101, Some(100)));
+        assert!(is_blob_fork_active(0, Some(0)));
+    }
+
+    #[test]
+    fn test_unknown_blob_fork_treats_every_slot_as_post_fork() {
+        assert!(is_blob_fork_active(0, None));
+        assert!(is_blob_fork_active(u32::MAX, None));
+    }
+
+    #[test]
+    fn test_classify_non_empty() {
+        assert_eq!(
+            classify_non_empty(Some(vec![1]), Vec::is_empty),
+            NonEmptyResponse::Found(vec![1])
+        );
+        assert_eq!(
+            classify_non_empty(Some(Vec::<u32>::new()), Vec::is_empty),
+            NonEmptyResponse::Empty
+        );
+        assert_eq!(
+            classify_non_empty(None::<Vec<u32>>, Vec::is_empty),
+            NonEmptyResponse::NotFound
+        );
+    }
+
+    fn sampled_positions(epoch: u32, slots_per_epoch: u32, sample_rate: u32) -> Vec<u32> {
+        (epoch * slots_per_epoch..(epoch + 1) * slots_per_epoch)
+            .filter(|slot| is_sampled(*slot, slots_per_epoch, sample_rate))
+            .map(|slot| slot % slots_per_epoch)
+            .collect()
+    }
+
+    #[test]
+    fn test_every_epoch_samples_exactly_sample_rate_slots() {
+        for (slots_per_epoch, sample_rate) in [(32, 1), (32, 3), (32, 8), (32, 31), (6, 4), (7, 3)]
+        {
+            for epoch in 0..200 {
+                assert_eq!(
+                    sampled_positions(epoch, slots_per_epoch, sample_rate).len(),
+                    sample_rate as usize,
+                    "epoch {epoch}, {sample_rate} of {slots_per_epoch}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_sampled_slots_are_spread_over_the_epoch() {
+        for epoch in 0..200 {
+            let positions = sampled_positions(epoch, 32, 4);
+            let gaps = positions
+                .iter()
+                .zip(positions.iter().cycle().skip(1))
+                .map(|(position, next)| (next + 32 - position) % 32)
+                .collect::<Vec<_>>();
+
+            assert_eq!(gaps, vec![8; 4], "epoch {epoch}: {positions:?}");
+        }
+    }
+
+    #[test]
+    fn test_sampled_positions_vary_across_epochs() {
+        let first_positions = (0..64)
+            .map(|epoch| sampled_positions(epoch, 32, 1)[0])
+            .collect::<std::collections::BTreeSet<_>>();
+
+        assert!(first_positions.len() > 16, "{first_positions:?}");
+    }
+
+    #[test]
+    fn test_sampling_is_deterministic() {
+        let sampled = |slot| is_sampled(slot, 32, 3);
+        let first_run = (0..10_000)
+            .filter(|slot| sampled(*slot))
+            .collect::<Vec<_>>();
+        let second_run = (0..10_000)
+            .filter(|slot| sampled(*slot))
+            .collect::<Vec<_>>();
+
+        assert_eq!(first_run, second_run);
+        assert!(is_sampled(9_000_123, 32, 32));
+    }
+
+    fn commitment(byte: u8) -> String {
+        format!("0x{}", hex::encode([byte; 48]))
+    }
+
+    fn blob(index: usize, kzg_commitment: String) -> BeaconBlob {
+        BeaconBlob {
+            index: index.to_string(),
+            kzg_commitment,
+            kzg_proof: format!("0x{}", hex::encode([0; 48])),
+            blob: Bytes::default(),
+        }
+    }
+
+    #[test]
+    fn test_blobs_matching_their_commitments_pass() {
+        let commitments = vec![commitment(1), commitment(2)];
+        let blobs = vec![blob(0, commitment(1)), blob(1, commitment(2))];
+
+        assert!(ensure_blobs_match_commitments(7, &blobs, &commitments).is_ok());
+    }
+
+    #[test]
+    fn test_commitments_are_compared_by_versioned_hash() {
+        let commitments = vec![commitment(0xab)];
+        let blobs = vec![blob(0, commitment(0xab).to_uppercase().replace("0X", "0x"))];
+
+        assert!(ensure_blobs_match_commitments(7, &blobs, &commitments).is_ok());
+    }
+
+    #[test]
+    fn test_blob_with_another_commitment_fails_despite_matching_counts() {
+        let commitments = vec![commitment(1), commitment(2), commitment(3)];
+        let blobs = vec![
+            blob(0, commitment(1)),
+            blob(1, commitment(9)),
+            blob(2, commitment(8)),
+        ];
+
+        let error = ensure_blobs_match_commitments(7, &blobs, &commitments).unwrap_err();
+
+        match error {
+            SlotProcessingError::BlobCommitmentMismatch {
+                slot,
+                index,
+                expected,
+                found,
+                mismatches,
+            } => {
+                assert_eq!(slot, 7);
+                assert_eq!(index, 1);
+                assert_eq!(expected, calculate_versioned_hash(&commitment(2)).unwrap());
+                assert_eq!(found, calculate_versioned_hash(&commitment(9)).unwrap());
+                assert_eq!(mismatches, 2);
+            }
+            error => panic!("unexpected error: {error}"),
+        }
+    }
+
+    #[test]
+    fn test_blobs_out_of_block_order_fail() {
+        let commitments = vec![commitment(1), commitment(2)];
+        let blobs = vec![blob(0, commitment(2)), blob(1, commitment(1))];
+
+        assert!(matches!(
+            ensure_blobs_match_commitments(7, &blobs, &commitments),
+            Err(SlotProcessingError::BlobCommitmentMismatch {
+                index: 0,
+                mismatches: 2,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_missing_blobs_are_reported_by_versioned_hash() {
+        let commitments = vec![commitment(1), commitment(2)];
+        let blobs = vec![blob(0, commitment(1))];
+
+        match ensure_blobs_match_commitments(7, &blobs, &commitments).unwrap_err() {
+            SlotProcessingError::BlobCountMismatch {
+                commitments,
+                blobs,
+                missing_versioned_hashes,
+                ..
+            } => {
+                assert_eq!((commitments, blobs), (2, 1));
+                assert_eq!(
+                    missing_versioned_hashes,
+                    vec![calculate_versioned_hash(&commitment(2)).unwrap()]
+                );
+            }
+            error => panic!("unexpected error: {error}"),
+        }
+    }
+}
@@ -1,24 +1,199 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
 use anyhow::{Context as AnyhowContext, Result};
 
 use ethers::prelude::*;
-use tracing::{debug, info};
+use ethers::types::{Block as EthersBlock, Transaction as EthersTransaction};
+use futures::StreamExt;
+use tokio::sync::{Mutex, Semaphore};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
 
 use crate::{
     clients::{
-        beacon::types::{BlobsResponse, BlockHeader, BlockId},
-        blobscan::types::{Blob, Block, Transaction},
+        beacon::{
+            columns::blobs_from_columns,
+            types::{
+                Blob as BeaconBlob, BlockHeader, BlockId, ColumnsResponse, ExecutionPayload,
+                ProposerDuty,
+            },
+        },
+        blobscan::types::{
+            Blob, BlobBlockIndex, BlobMetadata, BlobTxIndex, Block, FailedSlotsChunk,
+            SkippedSlotRange, SlotSkipReason, Transaction,
+        },
+        common::ClientError,
+    },
+    context::ContextHandle,
+    health::Dependency,
+    utils::{
+        redaction::redact_error,
+        web3::{
+            calculate_versioned_hash, get_tx_versioned_hashes, CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION,
+        },
     },
-    context::Context,
 };
 
-use self::error::{SlotProcessingError, SlotsProcessorError};
-use self::helpers::{create_tx_hash_versioned_hashes_mapping, create_versioned_hash_blob_mapping};
+use self::chain_tracker::ChainTracker;
+use self::error::{SlotProcessingError, SlotsProcessorError, SlotsProgress};
+use self::heartbeat::EmptyBlockHeartbeat;
+use self::helpers::{
+    blob_tx_hashes_for_commitments, classify_non_empty, create_tx_hash_versioned_hashes_mapping,
+    create_versioned_hash_blob_mapping, create_versioned_hash_block_index_mapping,
+    create_versioned_hash_commitment_mapping, derive_tx_hash_versioned_hashes_mapping,
+    ensure_blobs_match_commitments, is_blob_fork_active, is_sampled, NonEmptyResponse,
+};
+use self::prefetch::{prefetch_slot, PrefetchedSlot, Prefetcher};
+use self::slot_range::SlotRange;
 
+pub mod chain_tracker;
 pub mod error;
+pub mod heartbeat;
 mod helpers;
-const SLOT_PER_EPOCH: u32 = 6;
+mod prefetch;
+pub mod slot_range;
+const MAX_EMPTY_RESPONSE_RETRIES: u32 = 3;
+const EMPTY_RESPONSE_RETRY_DELAY: Duration = Duration::from_secs(2);
+const MAX_CONCURRENT_TX_FETCHES: usize = 8;
+const MAX_PROPOSER_LOOKUP_ATTEMPTS: u32 = 2;
+const MAX_ROLLBACKS_PER_SLOT: u32 = 3;
+
+/// Entity kinds that can be rebuilt and patched when re-indexing already indexed slots.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+#[value(rename_all = "snake_case")]
+pub enum ReindexField {
+    Block,
+    Transactions,
+    BlobMetadata,
+}
+
+/// How to treat beacon responses that are valid but unexpectedly empty once retries are exhausted.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum EmptyResponsePolicy {
+    /// Fail the slot
+    Strict,
+    /// Skip the slot with a warning
+    #[default]
+    Lenient,
+}
+
+/// Which blocks get indexed and with which entities.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum IndexMode {
+    /// Index every non-empty block with all its transactions and blobs
+    #[default]
+    All,
+    /// Only index blocks carrying blobs
+    BlobsOnly,
+    /// Index every block, but only include transactions and blobs for blocks carrying blobs
+    BlocksAndBlobs,
+}
+
+impl IndexMode {
+    /// Whether blocks without blobs are indexed at all
+    pub fn indexes_blobless_blocks(self) -> bool {
+        self != Self::BlobsOnly
+    }
+
+    /// Whether blocks without transactions are indexed rather than skipped
+    pub fn indexes_empty_blocks(self, index_empty_blocks: bool) -> bool {
+        index_empty_blocks || self == Self::BlocksAndBlobs
+    }
+
+    /// Whether the block is submitted alone, without its transactions and blobs
+    pub fn indexes_bare_block(self, has_blobs: bool) -> bool {
+        self == Self::BlocksAndBlobs && !has_blobs
+    }
+
+    /// Whether the block carries its transactions and blobs counts
+    pub fn counts_block_entities(self) -> bool {
+        self == Self::BlocksAndBlobs
+    }
+}
+
+/// Chain timing parameters used to compute the expected time of a slot.
+#[derive(Debug, Clone, Copy)]
+pub struct SlotTiming {
+    pub genesis_time: u64,
+    pub seconds_per_slot: u32,
+}
+
+impl SlotTiming {
+    pub fn slot_timestamp(&self, slot: u32) -> u64 {
+        self.genesis_time + slot as u64 * self.seconds_per_slot as u64
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// When set, slots are re-indexed by patching only the given entity kinds
+    pub reindex_fields: Option<Vec<ReindexField>>,
+    pub empty_response_policy: EmptyResponsePolicy,
+    pub index_mode: IndexMode,
+    /// Submit blocks without transactions instead of skipping them
+    pub index_empty_blocks: bool,
+    /// Send a rate-limited slot heartbeat for skipped empty blocks, so blobscan's freshness
+    /// tracking keeps moving through them. Only meant for realtime syncing. Disabled when unset
+    pub empty_block_heartbeat: Option<Arc<EmptyBlockHeartbeat>>,
+    /// Skip failed slots instead of stopping at the first one, unless the error is fatal
+    pub continue_on_error: bool,
+    /// Share of a range's slots allowed to fail when `continue_on_error` is set
+    pub max_failure_rate: f64,
+    /// Only process `sample_rate` evenly spread slots of every epoch
+    pub sample_rate: Option<u32>,
+    /// Report intentionally skipped slots and their reason to blobscan
+    pub record_skipped_slots: bool,
+    /// Fetch the parent execution block and require a strictly greater timestamp
+    pub verify_parent_timestamp: bool,
+    /// Fetch the parent execution block and require the block number to follow it
+    pub verify_parent_number: bool,
+    /// Skip blocks whose commitments have no blob transaction to belong to instead of failing
+    /// them
+    pub skip_blocks_mismatch: bool,
+    pub slot_timing: Option<SlotTiming>,
+    /// Indexing latency is only reported for slots indexed within this window of their slot time
+    pub latency_freshness_window: Option<Duration>,
+    /// Deepest reorg followed back when a block doesn't build on the previous one. 0 disables the
+    /// parent continuity check
+    pub max_reorg_depth: u32,
+    /// Slots fetched ahead of the one being processed. 0 disables prefetching
+    pub prefetch_slots: u32,
+    /// First slot of the blob fork. Earlier slots skip every blob check and have their blob
+    /// fields nulled. When unknown, every slot is treated as post-fork
+    pub blob_fork_slot: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SlotOutcome {
+    Indexed,
+    Skipped(SlotSkipReason),
+    /// The block doesn't build on the last processed one. The slots after the fork point must be
+    /// rolled back and re-indexed before retrying the slot
+    Reorged(Rollback),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rollback {
+    pub slots_to_reindex: SlotRange,
+    /// Slots of the range that were indexed from the orphaned chain. The others were never
+    /// indexed, so there's nothing to remove for them
+    pub indexed_slots: Vec<u32>,
+}
+
 pub struct SlotsProcessor {
-    context: Context,
+    context: ContextHandle,
+    config: Config,
+    cancellation_token: CancellationToken,
+    chain_tracker: Option<Arc<Mutex<ChainTracker>>>,
+    /// Bounds the slots prefetched at once by every batch. Prefetching is disabled without it
+    prefetch_permits: Option<Arc<Semaphore>>,
+    /// Data prefetched for the slot about to be processed
+    prefetched: Option<PrefetchedSlot>,
 }
 
 #[derive(Debug, Clone)]
@@ -37,53 +212,480 @@ impl From<BlockHeader> for BlockData {
 }
 
 impl SlotsProcessor {
-    pub fn new(context: Context) -> SlotsProcessor {
-        Self { context }
+    pub fn new(
+        context: ContextHandle,
+        config: Config,
+        cancellation_token: CancellationToken,
+    ) -> SlotsProcessor {
+        Self {
+            context,
+            config,
+            cancellation_token,
+            chain_tracker: None,
+            prefetch_permits: None,
+            prefetched: None,
+        }
+    }
+
+    /// Checks that every processed block builds on the previous one, rolling back orphaned
+    /// slots when it doesn't. Only meaningful when slots are processed in order
+    pub fn with_chain_tracker(&mut self, chain_tracker: Arc<Mutex<ChainTracker>>) -> &mut Self {
+        self.chain_tracker = Some(chain_tracker);
+
+        self
+    }
+
+    /// Lets the processor prefetch upcoming slots, fetching only while holding one of `permits`
+    pub fn with_prefetch_permits(&mut self, permits: Arc<Semaphore>) -> &mut Self {
+        self.prefetch_permits = Some(permits);
+
+        self
+    }
+
+    /// Races a network call against cancellation so cancelled slots stop at the next await point
+    async fn cancellable<T>(
+        &self,
+        future: impl Future<Output = T>,
+    ) -> Result<T, SlotProcessingError> {
+        tokio::select! {
+            biased;
+            _ = self.cancellation_token.cancelled() => Err(SlotProcessingError::Cancelled),
+            output = future => Ok(output),
+        }
+    }
+
+    /// Submission to blobscan is the atomicity point of a slot: it's checked here right before
+    /// submitting and never raced, so once started it runs to completion and a cancelled slot
+    /// never submits partial data.
+    fn ensure_not_cancelled(&self) -> Result<(), SlotProcessingError> {
+        if self.cancellation_token.is_cancelled() {
+            return Err(SlotProcessingError::Cancelled);
+        }
+
+        Ok(())
     }
 
+    /// Processes every slot of the range in visiting order, stopping at the first failed slot.
+    /// With `continue_on_error`, failed slots are skipped instead, and the range only fails when
+    /// too many of them did
     pub async fn process_slots(
         &mut self,
-        initial_slot: u32,
-        final_slot: u32,
-    ) -> Result<(), SlotsProcessorError> {
-        let is_reverse = initial_slot > final_slot;
-        let slots = if is_reverse {
-            (final_slot..initial_slot).rev().collect::<Vec<_>>()
-        } else {
-            (initial_slot..final_slot).collect::<Vec<_>>()
-        };
+        slot_range: SlotRange,
+    ) -> Result<SlotsProgress, SlotsProcessorError> {
+        let mut progress = SlotsProgress::default();
+        // Only kept when the skipped slots are reported to blobscan
+        let mut skipped_slots = vec![];
+        let mut prefetcher = self.start_prefetcher(slot_range);
+
+        for current_slot in slot_range.slots() {
+            if let Some(reason) = self.context.skip_list().reason_for(current_slot) {
+                debug!(
+                    slot = current_slot,
+                    reason, "Skipping slot excluded by the skip list"
+                );
+
+                self.skip_slot(
+                    &mut progress,
+                    &mut skipped_slots,
+                    current_slot,
+                    SlotSkipReason::OperatorExclusion,
+                );
+
+                continue;
+            }
+
+            // Slots left out of the sample are accounted for as processed
+            if !self.is_slot_sampled(current_slot) {
+                self.skip_slot(
+                    &mut progress,
+                    &mut skipped_slots,
+                    current_slot,
+                    SlotSkipReason::NotSampled,
+                );
+
+                continue;
+            }
+
+            if self.cancellation_token.is_cancelled() {
+                self.record_skipped_slots(&progress, skipped_slots).await;
 
-        for current_slot in slots {
-            if let Err(error) = self.process_slot(current_slot).await {
                 return Err(SlotsProcessorError::FailedSlotsProcessing {
-                    initial_slot,
-                    final_slot,
+                    initial_slot: slot_range.start,
+                    final_slot: slot_range.end,
                     failed_slot: current_slot,
-                    error,
+                    progress,
+                    error: SlotProcessingError::Cancelled,
                 });
             }
+
+            if let Some(prefetcher) = prefetcher.as_mut() {
+                self.prefetched = prefetcher.take(current_slot).await;
+            }
+
+            let mut rollbacks = 0;
+
+            let outcome = loop {
+                match self.process_slot(current_slot).await {
+                    Ok(SlotOutcome::Reorged(rollback)) if rollbacks < MAX_ROLLBACKS_PER_SLOT => {
+                        rollbacks += 1;
+
+                        // Slots prefetched before the reorg may belong to the orphaned chain
+                        prefetcher = slot_range
+                            .split_at(slot_range.offset_of(current_slot) + 1)
+                            .1
+                            .and_then(|remaining_range| self.start_prefetcher(remaining_range));
+
+                        if let Err(error) = self.rollback_slots(rollback).await {
+                            self.record_skipped_slots(&progress, skipped_slots).await;
+
+                            return Err(error);
+                        }
+                    }
+                    Ok(SlotOutcome::Reorged(_)) => {
+                        break Err(SlotProcessingError::UnstableChain {
+                            slot: current_slot,
+                            rollbacks,
+                        })
+                    }
+                    outcome => break outcome,
+                }
+            };
+
+            match outcome {
+                Ok(SlotOutcome::Indexed) | Ok(SlotOutcome::Reorged(_)) => {
+                    progress.record_indexed();
+
+                    if let Some(chain_tracker) = &self.chain_tracker {
+                        chain_tracker.lock().await.mark_indexed(current_slot);
+                    }
+
+                    self.context.metrics().record_indexed_slot(current_slot);
+                    self.context.metrics().record_processed_slot(current_slot);
+                }
+                Ok(SlotOutcome::Skipped(reason)) => {
+                    self.skip_slot(&mut progress, &mut skipped_slots, current_slot, reason);
+                }
+                Err(error) => {
+                    self.context.metrics().record_slot_error(error.kind());
+
+                    if self.config.continue_on_error && !error.is_fatal() {
+                        self.skip_failed_slot(current_slot, &error).await;
+                        progress.record_failed(current_slot);
+                        self.context.metrics().record_processed_slot(current_slot);
+
+                        continue;
+                    }
+
+                    self.record_skipped_slots(&progress, skipped_slots).await;
+
+                    return Err(SlotsProcessorError::FailedSlotsProcessing {
+                        initial_slot: slot_range.start,
+                        final_slot: slot_range.end,
+                        failed_slot: current_slot,
+                        progress,
+                        error,
+                    });
+                }
+            }
+        }
+
+        self.record_skipped_slots(&progress, skipped_slots).await;
+
+        let processed_slots = slot_range.slots_count();
+
+        if progress.failed_slots.len() as f64
+            > processed_slots as f64 * self.config.max_failure_rate
+        {
+            return Err(SlotsProcessorError::FailureRateExceeded {
+                initial_slot: slot_range.start,
+                final_slot: slot_range.end,
+                processed_slots,
+                failed_slots: progress.failed_slots,
+                max_failure_rate: self.config.max_failure_rate,
+            });
+        }
+
+        Ok(progress)
+    }
+
+    /// Logs a failed slot and records it to blobscan for a later retry. The chain tracker is
+    /// reset, as the next block builds on the one that failed
+    async fn skip_failed_slot(&self, slot: u32, error: &SlotProcessingError) {
+        warn!(
+            slot,
+            error = %redact_error(error),
+            "Failed to process slot. Skipping it"
+        );
+
+        if let Some(chain_tracker) = &self.chain_tracker {
+            chain_tracker.lock().await.clear();
+        }
+
+        let failed_chunk = FailedSlotsChunk {
+            id: None,
+            initial_slot: slot,
+            final_slot: slot,
+            failed_slot: Some(slot),
+            error: Some(error.to_string()),
+        };
+
+        if let Err(error) = self
+            .context
+            .blobscan_client()
+            .add_failed_slots_chunk(failed_chunk)
+            .await
+        {
+            warn!(
+                slot,
+                error = %redact_error(&error),
+                "Failed to record failed slot"
+            );
+        }
+    }
+
+    /// Removes whatever was indexed for the range's slots and re-indexes them from the canonical
+    /// chain, lowest slot first. Kept apart from `process_slots` so walking a range backwards
+    /// never deletes data by accident.
+    ///
+    /// A reorg detected while re-indexing extends the rollback down to its own fork point rather
+    /// than starting a nested one, so the slots are re-indexed in a loop instead of recursively.
+    pub async fn rollback_slots(&mut self, rollback: Rollback) -> Result<(), SlotsProcessorError> {
+        let mut pending_rollback = Some(rollback);
+        let mut rollbacks = 0;
+
+        while let Some(Rollback {
+            slots_to_reindex: slot_range,
+            indexed_slots,
+        }) = pending_rollback.take()
+        {
+            let lowest_slot = std::cmp::min(slot_range.start, slot_range.end);
+            let highest_slot = std::cmp::max(slot_range.start, slot_range.end);
+            let failed_slots_processing =
+                |failed_slot, error| SlotsProcessorError::FailedSlotsProcessing {
+                    initial_slot: lowest_slot,
+                    final_slot: highest_slot,
+                    failed_slot,
+                    progress: SlotsProgress::default(),
+                    error,
+                };
+
+            self.ensure_not_cancelled()
+                .map_err(|error| failed_slots_processing(lowest_slot, error))?;
+
+            let total_removed_blocks = if indexed_slots.is_empty() {
+                0
+            } else {
+                self.context
+                    .blobscan_client()
+                    .remove_blocks(&indexed_slots)
+                    .await
+                    .map_err(|error| SlotsProcessorError::FailedSlotsRollback {
+                        initial_slot: slot_range.start,
+                        final_slot: slot_range.end,
+                        error,
+                    })?
+            };
+
+            info!(
+                initial_slot = slot_range.start,
+                final_slot = slot_range.end,
+                total_removed_blocks,
+                "Rolled back slots. Re-indexing them from the canonical chain…"
+            );
+
+            for slot in lowest_slot..=highest_slot {
+                if self.context.skip_list().reason_for(slot).is_some()
+                    || !self.is_slot_sampled(slot)
+                {
+                    continue;
+                }
+
+                self.ensure_not_cancelled()
+                    .map_err(|error| failed_slots_processing(slot, error))?;
+
+                match self.process_slot(slot).await {
+                    Ok(SlotOutcome::Indexed) => {
+                        if let Some(chain_tracker) = &self.chain_tracker {
+                            chain_tracker.lock().await.mark_indexed(slot);
+                        }
+
+                        self.context.metrics().record_indexed_slot(slot);
+                    }
+                    Ok(SlotOutcome::Skipped(_)) => {}
+                    Ok(SlotOutcome::Reorged(_)) if rollbacks >= MAX_ROLLBACKS_PER_SLOT => {
+                        return Err(failed_slots_processing(
+                            slot,
+                            SlotProcessingError::UnstableChain { slot, rollbacks },
+                        ));
+                    }
+                    Ok(SlotOutcome::Reorged(rollback)) => {
+                        rollbacks += 1;
+
+                        // The slots above the new fork point that are still to be re-indexed
+                        // were already removed
+                        pending_rollback = Some(Rollback {
+                            slots_to_reindex: SlotRange::new(
+                                rollback.slots_to_reindex.start,
+                                highest_slot,
+                            ),
+                            indexed_slots: rollback.indexed_slots,
+                        });
+
+                        break;
+                    }
+                    Err(error) => {
+                        self.context.metrics().record_slot_error(error.kind());
+
+                        if self.config.continue_on_error && !error.is_fatal() {
+                            self.skip_failed_slot(slot, &error).await;
+
+                            continue;
+                        }
+
+                        return Err(failed_slots_processing(slot, error));
+                    }
+                }
+            }
         }
 
         Ok(())
     }
 
-    pub async fn process_slot(&mut self, slot: u32) -> Result<(), SlotProcessingError> {
+    /// Prefetches the slots `process_slots` would fetch: skip-listed slots and the ones left out
+    /// of the sample aren't
+    fn start_prefetcher(&self, slot_range: SlotRange) -> Option<Prefetcher> {
+        if self.config.prefetch_slots == 0 {
+            return None;
+        }
+
+        let permits = self.prefetch_permits.clone()?;
+        let filter_context = self.context.clone();
+        let fetch_context = self.context.clone();
+        let sample_rate = self.config.sample_rate;
+        let skip_blobless_execution = !self.config.index_mode.indexes_blobless_blocks()
+            && self.config.reindex_fields.is_none();
+
+        Some(Prefetcher::start(
+            slot_range,
+            self.config.prefetch_slots,
+            permits,
+            &self.cancellation_token,
+            move |slot| {
+                filter_context.skip_list().reason_for(slot).is_none()
+                    && sample_rate.map_or(true, |sample_rate| {
+                        is_sampled(
+                            slot,
+                            filter_context.chain_spec().slots_per_epoch,
+                            sample_rate,
+                        )
+                    })
+            },
+            move |slot| {
+                let context = fetch_context.clone();
+
+                async move { prefetch_slot(&context, slot, skip_blobless_execution).await }
+            },
+        ))
+    }
+
+    fn skip_slot(
+        &self,
+        progress: &mut SlotsProgress,
+        skipped_slots: &mut Vec<SkippedSlotRange>,
+        slot: u32,
+        reason: SlotSkipReason,
+    ) {
+        progress.record_skipped(reason);
+
+        // Consecutive skips, such as the slots between two sampled ones, share a single record
+        if self.config.record_skipped_slots
+            && !skipped_slots
+                .last_mut()
+                .is_some_and(|range| range.try_extend(slot, reason))
+        {
+            skipped_slots.push(SkippedSlotRange::new(slot, reason));
+        }
+
+        self.context.metrics().record_skipped_slot(reason);
+        self.context.metrics().record_processed_slot(slot);
+    }
+
+    async fn record_skipped_slots(
+        &self,
+        progress: &SlotsProgress,
+        skipped_slots: Vec<SkippedSlotRange>,
+    ) {
+        if progress.skipped_slots.is_empty() {
+            return;
+        }
+
+        debug!(skipped_by_reason = ?progress.skipped_slots, "Skipped slots");
+
+        if skipped_slots.is_empty() {
+            return;
+        }
+
+        if let Err(error) = self
+            .context
+            .blobscan_client()
+            .record_skipped_slots(skipped_slots)
+            .await
+        {
+            warn!(
+                error = %redact_error(&error),
+                "Failed to record skipped slots"
+            );
+        }
+    }
+
+    fn record_success(&self, dependency: Dependency) {
+        self.context
+            .health()
+            .record_success(dependency, self.context.clock().now());
+    }
+
+    fn is_slot_sampled(&self, slot: u32) -> bool {
+        self.config.sample_rate.map_or(true, |sample_rate| {
+            is_sampled(slot, self.context.chain_spec().slots_per_epoch, sample_rate)
+        })
+    }
+
+    pub async fn process_slot(&mut self, slot: u32) -> Result<SlotOutcome, SlotProcessingError> {
         let beacon_client = self.context.beacon_client();
         let blobscan_client = self.context.blobscan_client();
-        let provider = self.context.provider();
         if slot == 0 {
             debug!(
                 target = "slots_processor",
                 slot, "Slot = 0! Skipping getting initial beacon block as it's empty."
             );
-            return Ok(());
+            return Ok(SlotOutcome::Skipped(SlotSkipReason::Genesis));
         }
-        let beacon_block = match beacon_client.get_block(&BlockId::Slot(slot)).await? {
+        let prefetched = self
+            .prefetched
+            .take()
+            .filter(|prefetched| prefetched.slot == slot);
+        let (beacon_block, prefetched_execution_block, prefetched_columns) = match prefetched {
+            Some(prefetched) => (
+                prefetched.beacon_block?,
+                prefetched.execution_block,
+                prefetched.columns,
+            ),
+            None => (
+                self.cancellable(beacon_client.get_block(&BlockId::Slot(slot)))
+                    .await??,
+                None,
+                None,
+            ),
+        };
+
+        self.record_success(Dependency::Beacon);
+
+        let beacon_block = match beacon_block {
             Some(block) => block,
             None => {
                 debug!(slot = slot, "Skipping as there is no beacon block");
 
-                return Ok(());
+                return Ok(SlotOutcome::Skipped(SlotSkipReason::MissedSlot));
             }
         };
 
@@ -95,14 +697,29 @@ impl SlotsProcessor {
                     "Skipping as beacon block doesn't contain execution payload"
                 );
 
-                return Ok(());
+                return Ok(SlotOutcome::Skipped(SlotSkipReason::NoExecutionPayload));
             }
         };
 
-        let has_kzg_blob_commitments = match beacon_block.message.body.blob_kzg_commitments {
-            Some(commitments) => !commitments.is_empty(),
-            None => false,
+        if let Some(rollback) = self
+            .check_chain_continuity(slot, beacon_block.message.parent_root, &execution_payload)
+            .await?
+        {
+            return Ok(SlotOutcome::Reorged(rollback));
+        }
+
+        let is_blob_fork_active = self.is_blob_fork_active(slot);
+        // Pre-fork blocks have no commitments field at all, so there is nothing to check
+        let blob_kzg_commitments = if is_blob_fork_active {
+            beacon_block
+                .message
+                .body
+                .blob_kzg_commitments
+                .unwrap_or_default()
+        } else {
+            vec![]
         };
+        let has_kzg_blob_commitments = !blob_kzg_commitments.is_empty();
 
         // if !has_kzg_blob_commitments {
         //     debug!(
@@ -113,21 +730,139 @@ impl SlotsProcessor {
         //     return Ok(());
         // }
 
+        // The beacon block already tells whether the slot carries blobs, so blobless slots are
+        // skipped before any execution node request
+        if !self.config.index_mode.indexes_blobless_blocks()
+            && !has_kzg_blob_commitments
+            && self.config.reindex_fields.is_none()
+        {
+            debug!(
+                target = "slots_processor",
+                slot, "Skipping as block doesn't contain blobs"
+            );
+
+            return Ok(SlotOutcome::Skipped(SlotSkipReason::NoBlobs));
+        }
+
+        let provider = match self.context.provider() {
+            Some(provider) => provider,
+            None => {
+                return self
+                    .process_consensus_only_slot(
+                        slot,
+                        &execution_payload,
+                        &blob_kzg_commitments,
+                        prefetched_columns,
+                    )
+                    .await
+            }
+        };
+
         let execution_block_hash = execution_payload.block_hash;
 
-        // Fetch execution block and perform some checks
+        // Only the beacon block is a true dependency of the remaining fetches, so the execution
+        // block, the proposer duties and the columns are requested concurrently. Reindexing needs
+        // neither the proposer nor the columns.
+        let is_reindexing = self.config.reindex_fields.is_some();
+        let fetch_started_at = Instant::now();
 
-        let execution_block = provider
-            .get_block_with_txs(execution_block_hash)
-            .await?
+        let (execution_block, validator_pubkey, columns) = tokio::join!(
+            async {
+                match prefetched_execution_block {
+                    Some(execution_block) => execution_block.map_err(SlotProcessingError::from),
+                    None => self
+                        .cancellable(provider.get_block_with_txs(execution_block_hash))
+                        .await
+                        .and_then(|execution_block| {
+                            execution_block.map_err(SlotProcessingError::from)
+                        }),
+                }
+            },
+            async {
+                if is_reindexing {
+                    None
+                } else {
+                    Some(self.get_validator_pubkey(slot).await)
+                }
+            },
+            async {
+                if is_reindexing || !has_kzg_blob_commitments {
+                    None
+                } else {
+                    Some(
+                        self.cancellable(
+                            self.get_columns_with_anomaly_retries(slot, prefetched_columns),
+                        )
+                        .await
+                        .and_then(|columns| columns),
+                    )
+                }
+            }
+        );
+
+        debug!(
+            target = "slots_processor",
+            slot,
+            elapsed = ?fetch_started_at.elapsed(),
+            "Fetched execution block, proposer and columns"
+        );
+
+        // Errors are surfaced in the order the fetches used to run sequentially
+        let execution_block = execution_block?
             .with_context(|| format!("Execution block {execution_block_hash} not found"))?;
+
+        self.record_success(Dependency::Execution);
+
+        self.verify_parent_invariants(provider, &execution_block)
+            .await?;
+
         //create versioned_hashes for blob transactions
-        let tx_hash_to_versioned_hashes =
-            create_tx_hash_versioned_hashes_mapping(&execution_block)?;
+        let tx_hash_to_versioned_hashes = if is_blob_fork_active {
+            match self
+                .get_tx_hash_versioned_hashes_mapping(
+                    provider,
+                    slot,
+                    &execution_block,
+                    &blob_kzg_commitments,
+                )
+                .await
+            {
+                Err(SlotProcessingError::BlocksMismatch { commitments, .. })
+                    if self.config.skip_blocks_mismatch =>
+                {
+                    self.context.metrics().record_blocks_mismatch("skipped");
 
-        // if tx_hash_to_versioned_hashes.is_empty() {
-        //     return Err(anyhow!("Blocks mismatch: Beacon block contains blob KZG commitments, but the corresponding execution block does not contain any blob transactions").into());
-        // }
+                    warn!(
+                        target = "slots_processor",
+                        slot,
+                        commitments,
+                        "Skipping as beacon block carries blob commitments but the execution block has no blob transactions"
+                    );
+
+                    return Ok(SlotOutcome::Skipped(SlotSkipReason::BlocksMismatch));
+                }
+                Err(error @ SlotProcessingError::BlocksMismatch { .. }) => {
+                    self.context.metrics().record_blocks_mismatch("failed");
+
+                    return Err(error);
+                }
+                result => result?,
+            }
+        } else {
+            HashMap::new()
+        };
+
+        if let Some(reindex_fields) = self.config.reindex_fields.clone() {
+            return self
+                .reindex_slot(
+                    slot,
+                    &reindex_fields,
+                    &execution_block,
+                    &tx_hash_to_versioned_hashes,
+                    &blob_kzg_commitments,
+                )
+                .await;
+        }
 
         // Fetch blobs and perform some checks
 
@@ -157,8 +892,8 @@ impl SlotsProcessor {
         //         return Ok(());
         //     }
         // };
-        
 
+        let index_mode = self.config.index_mode;
 
         // Create entities to be indexed
 
@@ -169,73 +904,120 @@ impl SlotsProcessor {
             .map(|tx| Transaction::try_from((tx, &execution_block)))
             .collect::<Result<Vec<Transaction>>>()?;
 
-        if transactions_entities.is_empty() {
+        if transactions_entities.is_empty()
+            && !index_mode.indexes_empty_blocks(self.config.index_empty_blocks)
+        {
             debug!(
                 target = "slots_processor",
                 slot, "Skipping as there are no transactions to index, it is a empty block!"
             );
 
-            return Ok(());
-        }
+            if let Some(heartbeat) = &self.config.empty_block_heartbeat {
+                if heartbeat.try_claim(slot, self.context.clock().now()) {
+                    if let Err(error) = blobscan_client.update_slot(slot).await {
+                        warn!(
+                            slot,
+                            error = %redact_error(&error),
+                            "Failed to send slot heartbeat for empty block"
+                        );
+                    }
+                }
+            }
 
-        let validators = match beacon_client.get_validators(&BlockId::Slot(slot/SLOT_PER_EPOCH)).await? {
-            Some(validators) => validators,
-            None => {
-                debug!(
-                    target = "slots_processor",
-                    slot, "Skipping as there are no validators"
-                );
+            return Ok(SlotOutcome::Skipped(SlotSkipReason::EmptyBlock));
+        }
 
-                return Ok(());
-            }
+        let validator_pubkey = match validator_pubkey {
+            Some(validator_pubkey) => validator_pubkey?,
+            None => self.get_validator_pubkey(slot).await?,
+        };
+        let validator_pubkey = match validator_pubkey {
+            Some(validator_pubkey) => validator_pubkey,
+            None => return Ok(SlotOutcome::Skipped(SlotSkipReason::MissingProposer)),
         };
-        //选出其中slot为当前slot的validator_pubkey
-        let validator_pubkey = validators.iter().find(|validator| validator.slot == slot).unwrap().pubkey.clone();
-        // println!("validator_pubkeys: {:?}", validator_pubkeys);
-        
-        let block_entity = Block::try_from((&execution_block, slot, validator_pubkey))?;
+
+        let mut block_entity = Block::try_from((&execution_block, slot, validator_pubkey))?;
+
+        if is_blob_fork_active {
+            block_entity.set_blob_fee_burned(self.blob_base_fee_update_fraction(slot));
+        } else {
+            block_entity.clear_blob_fields();
+        }
+
+        if self.config.sample_rate.is_some() {
+            block_entity.sampled = Some(true);
+        }
+
+        if index_mode.counts_block_entities() {
+            block_entity.transactions_count = Some(transactions_entities.len() as u32);
+        }
+
+        if index_mode.indexes_bare_block(has_kzg_blob_commitments) {
+            block_entity.blobs_count = Some(0);
+            block_entity.indexing_latency_ms = self.get_indexing_latency_ms(slot);
+
+            let block_number = block_entity.number.as_u32();
+            let blob_fee_burned = block_entity.blob_fee_burned;
+
+            self.ensure_not_cancelled()?;
+
+            blobscan_client
+                .index_block(block_entity)
+                .await
+                .map_err(SlotProcessingError::SubmissionFailure)?;
+
+            self.record_success(Dependency::Blobscan);
+            self.context
+                .indexing_stats()
+                .record_block(0, 0, blob_fee_burned);
+
+            info!(
+                slot,
+                block_number, "Block indexed without transactions and blobs"
+            );
+
+            return Ok(SlotOutcome::Indexed);
+        }
 
         let mut blob_entities: Vec<Blob> = vec![];
         //if there are blobs, create blob entities
         if has_kzg_blob_commitments {
-            let columns = match beacon_client
-                .get_columns(&BlockId::Slot(slot))
-                .await
-                .map_err(SlotProcessingError::ClientError)?
-            {
-                Some(columns) => {
-                    if columns.data.is_empty() {
-                        debug!(
-                            target = "slots_processor",
-                            slot, "Skipping as columns sidecar is empty"
-                        );
-
-                        return Ok(());
-                    } else {
-                        columns
-                    }
-                }
+            // The block carries commitments, so an empty or missing columns sidecar is an anomaly
+            // rather than a genuine absence of data
+            let columns = match columns {
+                Some(columns) => columns?,
                 None => {
-                    debug!(
-                        target = "slots_processor",
-                        slot, "Skipping as there is no columns sidecar"
-                    );
-
-                    return Ok(());
+                    self.cancellable(self.get_columns_with_anomaly_retries(slot, None))
+                        .await??
                 }
             };
+            let columns = match columns {
+                Some(columns) => columns,
+                None => return Ok(SlotOutcome::Skipped(SlotSkipReason::MissingBlobData)),
+            };
+
+            let blobs = self.reconstruct_blobs(slot, columns)?;
+
+            ensure_blobs_match_commitments(slot, &blobs, &blob_kzg_commitments)?;
 
-            let blobs = BlobsResponse::from(columns).data;
             let versioned_hash_to_blob = create_versioned_hash_blob_mapping(&blobs)?;
+            let versioned_hash_to_block_index =
+                create_versioned_hash_block_index_mapping(&blob_kzg_commitments)?;
             for (tx_hash, versioned_hashes) in tx_hash_to_versioned_hashes.iter() {
                 for (i, versioned_hash) in versioned_hashes.iter().enumerate() {
                     let blob = *versioned_hash_to_blob.get(versioned_hash).with_context(|| format!("Sidecar not found for blob {i} with versioned hash {versioned_hash} from tx {tx_hash}"))?;
-    
-                    blob_entities.push(Blob::from((blob, versioned_hash, i, tx_hash)));
+                    let block_index = *versioned_hash_to_block_index.get(versioned_hash).with_context(|| format!("Commitment not found for blob {i} with versioned hash {versioned_hash} from tx {tx_hash}"))?;
+
+                    blob_entities.push(Blob::new(
+                        blob,
+                        *versioned_hash,
+                        Some(*tx_hash),
+                        BlobTxIndex(i as u32),
+                        block_index,
+                    )?);
                 }
             }
         }
-        
 
         /*
         let tx_hashes = transactions_entities
@@ -248,15 +1030,1111 @@ impl SlotsProcessor {
             .collect::<Vec<String>>();
          */
 
+        if index_mode.counts_block_entities() {
+            block_entity.blobs_count = Some(blob_entities.len() as u32);
+        }
+
+        block_entity.indexing_latency_ms = self.get_indexing_latency_ms(slot);
+
         let block_number = block_entity.number.as_u32();
+        let transactions_count = transactions_entities.len();
+        let blobs_count = blob_entities.len();
+        let blob_fee_burned = block_entity.blob_fee_burned;
+
+        self.ensure_not_cancelled()?;
 
         blobscan_client
             .index(block_entity, transactions_entities, blob_entities)
             .await
-            .map_err(SlotProcessingError::ClientError)?;
+            .map_err(SlotProcessingError::SubmissionFailure)?;
+
+        self.record_success(Dependency::Blobscan);
+        self.context.indexing_stats().record_block(
+            transactions_count,
+            blobs_count,
+            blob_fee_burned,
+        );
 
         info!(slot, block_number, "Block indexed successfully");
 
-        Ok(())
+        Ok(SlotOutcome::Indexed)
+    }
+
+    /// Indexes a slot without an execution node. The block is built from the beacon block's
+    /// execution payload, transactions are skipped and blobs are indexed in sidecar order
+    /// without being linked to their transactions.
+    async fn process_consensus_only_slot(
+        &self,
+        slot: u32,
+        execution_payload: &ExecutionPayload,
+        blob_kzg_commitments: &[String],
+        prefetched_columns: Option<ColumnsResponse>,
+    ) -> Result<SlotOutcome, SlotProcessingError> {
+        let has_kzg_blob_commitments = !blob_kzg_commitments.is_empty();
+
+        if self.config.reindex_fields.is_some() {
+            return Err(anyhow::anyhow!("Re-indexing requires an execution node").into());
+        }
+
+        let index_mode = self.config.index_mode;
+
+        if !index_mode.indexes_blobless_blocks() && !has_kzg_blob_commitments {
+            debug!(
+                target = "slots_processor",
+                slot, "Skipping as block doesn't contain blobs"
+            );
+
+            return Ok(SlotOutcome::Skipped(SlotSkipReason::NoBlobs));
+        }
+
+        let validator_pubkey = match self.get_validator_pubkey(slot).await? {
+            Some(validator_pubkey) => validator_pubkey,
+            None => return Ok(SlotOutcome::Skipped(SlotSkipReason::MissingProposer)),
+        };
+
+        let mut block_entity = Block::try_from((execution_payload, slot, validator_pubkey))?;
+
+        if self.is_blob_fork_active(slot) {
+            block_entity.set_blob_fee_burned(self.blob_base_fee_update_fraction(slot));
+        } else {
+            block_entity.clear_blob_fields();
+        }
+
+        if self.config.sample_rate.is_some() {
+            block_entity.sampled = Some(true);
+        }
+
+        let blob_entities = if has_kzg_blob_commitments {
+            let columns = match self
+                .cancellable(self.get_columns_with_anomaly_retries(slot, prefetched_columns))
+                .await??
+            {
+                Some(columns) => columns,
+                None => return Ok(SlotOutcome::Skipped(SlotSkipReason::MissingBlobData)),
+            };
+
+            let blobs = self.reconstruct_blobs(slot, columns)?;
+
+            ensure_blobs_match_commitments(slot, &blobs, blob_kzg_commitments)?;
+
+            blobs
+                .iter()
+                .enumerate()
+                // Without transactions, blobs are indexed by their position in the block
+                .map(|(i, blob)| {
+                    Blob::new(
+                        blob,
+                        calculate_versioned_hash(&blob.kzg_commitment)?,
+                        None,
+                        BlobTxIndex(i as u32),
+                        BlobBlockIndex(i as u32),
+                    )
+                })
+                .collect::<Result<Vec<Blob>>>()?
+        } else {
+            vec![]
+        };
+
+        if index_mode.counts_block_entities() {
+            block_entity.blobs_count = Some(blob_entities.len() as u32);
+        }
+
+        block_entity.indexing_latency_ms = self.get_indexing_latency_ms(slot);
+
+        let block_number = block_entity.number.as_u32();
+        let blobs_count = blob_entities.len();
+        let blob_fee_burned = block_entity.blob_fee_burned;
+
+        self.ensure_not_cancelled()?;
+
+        self.context
+            .blobscan_client()
+            .index(block_entity, vec![], blob_entities)
+            .await
+            .map_err(SlotProcessingError::SubmissionFailure)?;
+
+        self.record_success(Dependency::Blobscan);
+        self.context
+            .indexing_stats()
+            .record_block(0, blobs_count, blob_fee_burned);
+
+        info!(
+            slot,
+            block_number, "Block indexed successfully in consensus-only mode"
+        );
+
+        Ok(SlotOutcome::Indexed)
+    }
+
+    async fn reindex_slot(
+        &self,
+        slot: u32,
+        reindex_fields: &[ReindexField],
+        execution_block: &EthersBlock<EthersTransaction>,
+        tx_hash_to_versioned_hashes: &HashMap<H256, Vec<H256>>,
+        blob_kzg_commitments: &[String],
+    ) -> Result<SlotOutcome, SlotProcessingError> {
+        let blobscan_client = self.context.blobscan_client();
+
+        let block_entity = if reindex_fields.contains(&ReindexField::Block) {
+            let validator_pubkey = match self.get_validator_pubkey(slot).await? {
+                Some(validator_pubkey) => validator_pubkey,
+                None => return Ok(SlotOutcome::Skipped(SlotSkipReason::MissingProposer)),
+            };
+
+            let mut block_entity = Block::try_from((execution_block, slot, validator_pubkey))?;
+
+            if self.is_blob_fork_active(slot) {
+                block_entity.set_blob_fee_burned(self.blob_base_fee_update_fraction(slot));
+            } else {
+                block_entity.clear_blob_fields();
+            }
+
+            Some(block_entity)
+        } else {
+            None
+        };
+
+        let transactions_entities = if reindex_fields.contains(&ReindexField::Transactions) {
+            Some(
+                execution_block
+                    .transactions
+                    .iter()
+                    .map(|tx| Transaction::try_from((tx, execution_block)))
+                    .collect::<Result<Vec<Transaction>>>()?,
+            )
+        } else {
+            None
+        };
+
+        // Blob metadata is derived from the beacon block commitments so no sidecar needs to be downloaded
+        let blob_metadata_entities = if reindex_fields.contains(&ReindexField::BlobMetadata) {
+            let versioned_hash_to_commitment =
+                create_versioned_hash_commitment_mapping(blob_kzg_commitments)?;
+            let mut blob_metadata_entities: Vec<BlobMetadata> = vec![];
+
+            for (tx_hash, versioned_hashes) in tx_hash_to_versioned_hashes.iter() {
+                for (i, versioned_hash) in versioned_hashes.iter().enumerate() {
+                    let commitment = *versioned_hash_to_commitment
+                        .get(versioned_hash)
+                        .with_context(|| {
+                            format!("Commitment not found for blob {i} with versioned hash {versioned_hash} from tx {tx_hash}")
+                        })?;
+
+                    blob_metadata_entities.push(BlobMetadata::new(
+                        commitment,
+                        *versioned_hash,
+                        *tx_hash,
+                        BlobTxIndex(i as u32),
+                    ));
+                }
+            }
+
+            Some(blob_metadata_entities)
+        } else {
+            None
+        };
+
+        self.ensure_not_cancelled()?;
+
+        blobscan_client
+            .patch(block_entity, transactions_entities, blob_metadata_entities)
+            .await
+            .map_err(SlotProcessingError::SubmissionFailure)?;
+
+        info!(slot, fields = ?reindex_fields, "Block re-indexed successfully");
+
+        Ok(SlotOutcome::Indexed)
+    }
+
+    async fn get_validator_pubkey(&self, slot: u32) -> Result<Option<String>, SlotProcessingError> {
+        let epoch = self.context.chain_spec().epoch_of(slot);
+        let duties_cache = self.context.proposer_duties_cache();
+
+        // A freshly restarted node may serve duties that don't cover every slot of the epoch yet,
+        // so a missing proposer drops the cached duties and gets one refetch before failing the slot
+        for attempt in 1..=MAX_PROPOSER_LOOKUP_ATTEMPTS {
+            // Raced as a whole, so a cancelled slot doesn't wait out the empty response retries
+            let duties = match self
+                .cancellable(
+                    duties_cache.get_or_try_fetch(epoch, || self.get_proposer_duties(slot, epoch)),
+                )
+                .await??
+            {
+                Some(duties) => duties,
+                None => return Ok(None),
+            };
+
+            if let Some(duty) = duties.iter().find(|duty| duty.slot == slot) {
+                return Ok(Some(duty.pubkey.clone()));
+            }
+
+            warn!(
+                target = "slots_processor",
+                slot, epoch, attempt, "Proposer duties don't include the slot"
+            );
+
+            duties_cache.invalidate(epoch);
+        }
+
+        Err(SlotProcessingError::MissingProposer { slot })
+    }
+
+    async fn get_proposer_duties(
+        &self,
+        slot: u32,
+        epoch: u64,
+    ) -> Result<Option<Vec<ProposerDuty>>, SlotProcessingError> {
+        let beacon_client = self.context.beacon_client();
+
+        let mut attempts = 0;
+
+        // A live epoch always has proposer duties, so an empty response is retried as an anomaly
+        loop {
+            attempts += 1;
+
+            match beacon_client
+                .get_proposer_duties(epoch)
+                .await
+                .map(|duties| classify_non_empty(duties, Vec::is_empty))
+            {
+                Ok(NonEmptyResponse::Found(duties)) => return Ok(Some(duties)),
+                Ok(NonEmptyResponse::NotFound) => return Ok(None),
+                // Nodes reject epochs past their head's next epoch, which happens while the node
+                // is still catching up to the block that got us here
+                Err(ClientError::ApiError(error)) => {
+                    if attempts > MAX_EMPTY_RESPONSE_RETRIES {
+                        return Err(ClientError::ApiError(error).into());
+                    }
+
+                    warn!(
+                        target = "slots_processor",
+                        slot,
+                        epoch,
+                        attempts,
+                        ?error,
+                        "Beacon node rejected the proposer duties epoch. Retrying…"
+                    );
+
+                    self.context.clock().sleep(EMPTY_RESPONSE_RETRY_DELAY).await;
+                }
+                Err(error) => return Err(error.into()),
+                Ok(NonEmptyResponse::Empty) => {
+                    self.context
+                        .metrics()
+                        .record_empty_response_anomaly("proposer_duties");
+
+                    if attempts > MAX_EMPTY_RESPONSE_RETRIES {
+                        return self.handle_empty_response_anomaly(
+                            slot,
+                            "proposer duties",
+                            attempts,
+                        );
+                    }
+
+                    warn!(
+                        target = "slots_processor",
+                        slot, epoch, attempts, "Beacon node returned no proposer duties. Retrying…"
+                    );
+
+                    self.context.clock().sleep(EMPTY_RESPONSE_RETRY_DELAY).await;
+                }
+            }
+        }
+    }
+
+    /// Checks that the slot's block builds on the last tracked block. When it doesn't, walks the
+    /// beacon chain back to the fork point and returns the slots between the fork point and this
+    /// one, which hold orphaned blocks or canonical blocks that were never processed.
+    async fn check_chain_continuity(
+        &self,
+        slot: u32,
+        parent_root: H256,
+        execution_payload: &ExecutionPayload,
+    ) -> Result<Option<Rollback>, SlotProcessingError> {
+        let Some(chain_tracker) = &self.chain_tracker else {
+            return Ok(None);
+        };
+        let mut chain_tracker = chain_tracker.lock().await;
+
+        if !chain_tracker.is_enabled() {
+            return Ok(None);
+        }
+
+        // A re-processed slot replaces whatever was tracked for it and the slots after it
+        chain_tracker.truncate_from(slot);
+
+        let tip = match chain_tracker.tip() {
+            Some(tip) if execution_payload.parent_hash != tip.execution_block_hash => *tip,
+            _ => {
+                chain_tracker.push(slot, execution_payload.block_hash);
+
+                return Ok(None);
+            }
+        };
+
+        let fork_slot = self
+            .find_fork_slot(slot, &chain_tracker, parent_root)
+            .await?;
+
+        // Reaching the tip means the block builds on it through slots that were never tracked,
+        // such as skip-listed, non-sampled or failed ones, so there's no reorg
+        if fork_slot == tip.slot || fork_slot + 1 >= slot {
+            chain_tracker.push(slot, execution_payload.block_hash);
+
+            return Ok(None);
+        }
+
+        let indexed_slots = chain_tracker.indexed_slots_from(fork_slot + 1);
+
+        chain_tracker.truncate_from(fork_slot + 1);
+
+        warn!(
+            target = "slots_processor",
+            slot,
+            fork_slot,
+            last_processed_slot = tip.slot,
+            "Block doesn't build on the last processed block. Rolling back to the fork point…"
+        );
+
+        Ok(Some(Rollback {
+            slots_to_reindex: SlotRange::new(fork_slot + 1, slot - 1),
+            indexed_slots,
+        }))
+    }
+
+    async fn find_fork_slot(
+        &self,
+        slot: u32,
+        chain_tracker: &ChainTracker,
+        parent_root: H256,
+    ) -> Result<u32, SlotProcessingError> {
+        let beacon_client = self.context.beacon_client();
+        let max_reorg_depth = self.config.max_reorg_depth;
+        let mut block_root = parent_root;
+
+        for _ in 0..max_reorg_depth {
+            let block = self
+                .cancellable(beacon_client.get_block(&BlockId::Hash(block_root)))
+                .await??
+                .with_context(|| {
+                    format!("Beacon block {block_root} not found while looking for the fork point of slot {slot}")
+                })?;
+            let block_slot = block.message.slot;
+
+            if let Some(execution_payload) = &block.message.body.execution_payload {
+                if chain_tracker.contains(block_slot, execution_payload.block_hash) {
+                    return Ok(block_slot);
+                }
+            }
+
+            if chain_tracker
+                .oldest()
+                .map_or(true, |oldest| block_slot <= oldest.slot)
+            {
+                break;
+            }
+
+            block_root = block.message.parent_root;
+        }
+
+        Err(SlotProcessingError::ReorgTooDeep {
+            slot,
+            max_reorg_depth,
+        })
+    }
+
+    /// Prefetched columns are used as is, as only non-empty ones are kept
+    async fn get_columns_with_anomaly_retries(
+        &self,
+        slot: u32,
+        prefetched_columns: Option<ColumnsResponse>,
+    ) -> Result<Option<ColumnsResponse>, SlotProcessingError> {
+        if let Some(columns) = prefetched_columns {
+            return Ok(Some(columns));
+        }
+
+        let beacon_client = self.context.beacon_client();
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+
+            let columns = beacon_client
+                .get_columns(&BlockId::Slot(slot))
+                .await
+                .map_err(SlotProcessingError::ClientError)?;
+
+            match classify_non_empty(columns, |columns| columns.data.is_empty()) {
+                NonEmptyResponse::Found(columns) => return Ok(Some(columns)),
+                // Sidecars past the node's retention window are genuinely gone
+                NonEmptyResponse::NotFound => return Ok(None),
+                NonEmptyResponse::Empty => {
+                    self.context
+                        .metrics()
+                        .record_empty_response_anomaly("columns_sidecar");
+
+                    if attempts > MAX_EMPTY_RESPONSE_RETRIES {
+                        return self.handle_empty_response_anomaly(
+                            slot,
+                            "columns sidecar",
+                            attempts,
+                        );
+                    }
+
+                    warn!(
+                        target = "slots_processor",
+                        slot, attempts, "Beacon node returned no columns sidecar for a block with blob commitments. Retrying…"
+                    );
+
+                    self.context.clock().sleep(EMPTY_RESPONSE_RETRY_DELAY).await;
+                }
+            }
+        }
+    }
+
+    fn reconstruct_blobs(
+        &self,
+        slot: u32,
+        columns: ColumnsResponse,
+    ) -> Result<Vec<BeaconBlob>, SlotProcessingError> {
+        let layout = self.context.chain_spec().column_layout_at(slot);
+
+        blobs_from_columns(columns, layout.as_ref())
+            .map_err(|source| SlotProcessingError::BlobReconstruction { slot, source })
+    }
+
+    fn is_blob_fork_active(&self, slot: u32) -> bool {
+        is_blob_fork_active(slot, self.config.blob_fork_slot)
+    }
+
+    fn blob_base_fee_update_fraction(&self, slot: u32) -> u64 {
+        self.context
+            .try_chain_spec()
+            .map_or(CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION, |chain_spec| {
+                chain_spec.blob_base_fee_update_fraction_at(slot)
+            })
+    }
+
+    fn get_indexing_latency_ms(&self, slot: u32) -> Option<u64> {
+        let slot_timing = self.config.slot_timing?;
+        let freshness_window = self.config.latency_freshness_window?;
+        let now_ms = self
+            .context
+            .clock()
+            .now()
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_millis() as i128;
+        let slot_time_ms = slot_timing.slot_timestamp(slot) as i128 * 1000;
+        let latency_ms = now_ms - slot_time_ms;
+
+        if latency_ms < 0 {
+            // Tolerate clock skew between the node and the indexer
+            debug!(
+                slot,
+                latency_ms, "Slot time is ahead of wall clock. Clamping indexing latency to 0"
+            );
+
+            return Some(0);
+        }
+
+        if latency_ms > freshness_window.as_millis() as i128 {
+            return None;
+        }
+
+        Some(latency_ms as u64)
+    }
+
+    async fn verify_parent_invariants(
+        &self,
+        provider: &Provider<Http>,
+        execution_block: &EthersBlock<EthersTransaction>,
+    ) -> Result<(), SlotProcessingError> {
+        if !self.config.verify_parent_timestamp && !self.config.verify_parent_number {
+            return Ok(());
+        }
+
+        let number = execution_block
+            .number
+            .context("Missing block number field in execution block")?
+            .as_u64();
+
+        if number == 0 {
+            return Ok(());
+        }
+
+        let parent_hash = execution_block.parent_hash;
+        let parent_block = provider
+            .get_block(parent_hash)
+            .await?
+            .with_context(|| format!("Parent execution block {parent_hash} not found"))?;
+
+        if self.config.verify_parent_number {
+            let parent_number = parent_block
+                .number
+                .context("Missing block number field in parent execution block")?
+                .as_u64();
+
+            if number != parent_number + 1 {
+                return Err(SlotProcessingError::NonSequentialBlockNumber {
+                    number,
+                    parent_number,
+                });
+            }
+        }
+
+        if self.config.verify_parent_timestamp
+            && execution_block.timestamp <= parent_block.timestamp
+        {
+            return Err(SlotProcessingError::TimestampRegression {
+                number,
+                timestamp: execution_block.timestamp.as_u64(),
+                parent_timestamp: parent_block.timestamp.as_u64(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Builds the blob transaction to versioned hashes mapping. Some RPC providers strip
+    /// `blobVersionedHashes` from the transactions returned with the block, so when the beacon
+    /// block carries commitments but the mapping comes back empty, the blob transactions are
+    /// re-fetched individually. If that still yields nothing and there is a single blob
+    /// transaction, the versioned hashes are derived from the beacon block commitments.
+    async fn get_tx_hash_versioned_hashes_mapping(
+        &self,
+        provider: &Provider<Http>,
+        slot: u32,
+        execution_block: &EthersBlock<EthersTransaction>,
+        blob_kzg_commitments: &[String],
+    ) -> Result<HashMap<H256, Vec<H256>>, SlotProcessingError> {
+        let tx_hash_to_versioned_hashes = create_tx_hash_versioned_hashes_mapping(execution_block)?;
+
+        if !tx_hash_to_versioned_hashes.is_empty() || blob_kzg_commitments.is_empty() {
+            return Ok(tx_hash_to_versioned_hashes);
+        }
+
+        let blob_tx_hashes =
+            blob_tx_hashes_for_commitments(slot, execution_block, blob_kzg_commitments)?;
+
+        warn!(
+            slot,
+            blob_txs = blob_tx_hashes.len(),
+            "Execution block transactions are missing blob versioned hashes. Re-fetching blob transactions individually"
+        );
+
+        // Mapping owned hashes rather than references keeps the closure free of a higher-ranked
+        // lifetime, which would stop the `process_slots` future from being provably `Send`
+        let fetched_txs = futures::stream::iter(blob_tx_hashes.iter().copied())
+            .map(|tx_hash| provider.get_transaction(tx_hash))
+            .buffered(MAX_CONCURRENT_TX_FETCHES)
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut tx_hash_to_versioned_hashes = HashMap::new();
+
+        for tx in fetched_txs {
+            if let Some(tx) = tx? {
+                if let Some(versioned_hashes) = get_tx_versioned_hashes(&tx)? {
+                    tx_hash_to_versioned_hashes.insert(tx.hash, versioned_hashes);
+                }
+            }
+        }
+
+        if !tx_hash_to_versioned_hashes.is_empty() {
+            info!(
+                slot,
+                "Resolved blob versioned hashes from individually fetched transactions"
+            );
+
+            return Ok(tx_hash_to_versioned_hashes);
+        }
+
+        let tx_hash_to_versioned_hashes =
+            derive_tx_hash_versioned_hashes_mapping(slot, &blob_tx_hashes, blob_kzg_commitments)?;
+
+        info!(
+            slot,
+            "Derived blob versioned hashes from beacon block commitments"
+        );
+
+        Ok(tx_hash_to_versioned_hashes)
+    }
+
+    fn handle_empty_response_anomaly<T>(
+        &self,
+        slot: u32,
+        resource: &'static str,
+        attempts: u32,
+    ) -> Result<Option<T>, SlotProcessingError> {
+        match self.config.empty_response_policy {
+            EmptyResponsePolicy::Strict => Err(SlotProcessingError::EmptyResponseAnomaly {
+                resource,
+                slot,
+                attempts,
+            }),
+            EmptyResponsePolicy::Lenient => {
+                warn!(
+                    target = "slots_processor",
+                    slot, attempts, "Skipping as beacon node kept returning an empty {resource}"
+                );
+
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        sync::Arc,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    use futures::future::BoxFuture;
+    use tokio_util::sync::CancellationToken;
+
+    use super::{
+        error::SlotProcessingError, heartbeat::EmptyBlockHeartbeat, Config, IndexMode, SlotOutcome,
+        SlotSkipReason, SlotsProcessor,
+    };
+    use crate::{
+        clients::test_server::{self, ReceivedRequest, Reply},
+        context::{ChainSpec, Config as ContextConfig, Context},
+        utils::{
+            clock::{Clock, MockClock},
+            url::EndpointUrl,
+        },
+    };
+
+    fn context_config(
+        beacon_url: EndpointUrl,
+        execution_url: Option<EndpointUrl>,
+        blobscan_url: EndpointUrl,
+    ) -> ContextConfig {
+        ContextConfig {
+            blobscan_api_endpoint: blobscan_url,
+            beacon_node_url: beacon_url,
+            execution_node_endpoint: execution_url,
+            secret_key: "test-secret".to_string(),
+            disable_retries: true,
+            beacon_http_version: Default::default(),
+            blobscan_http_version: Default::default(),
+            beacon_body_trace: Default::default(),
+            blobscan_body_trace: Default::default(),
+        }
+    }
+
+    fn empty_beacon_block(slot: u32) -> String {
+        serde_json::json!({
+            "data": {
+                "message": {
+                    "slot": slot.to_string(),
+                    "parent_root": format!("0x{}", "11".repeat(32)),
+                    "body": {
+                        "execution_payload": {
+                            "block_hash": format!("0x{}", "22".repeat(32)),
+                            "parent_hash": format!("0x{}", "33".repeat(32)),
+                            "block_number": "100",
+                            "timestamp": "1700000000"
+                        },
+                        "blob_kzg_commitments": []
+                    }
+                }
+            }
+        })
+        .to_string()
+    }
+
+    fn execution_block(transactions: Vec<serde_json::Value>) -> String {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "result": {
+                "hash": format!("0x{}", "22".repeat(32)),
+                "parentHash": format!("0x{}", "33".repeat(32)),
+                "number": "0x64",
+                "timestamp": "0x6553f100",
+                "transactions": transactions
+            }
+        })
+        .to_string()
+    }
+
+    /// A legacy transfer, so carrying no blobs
+    fn transaction() -> serde_json::Value {
+        serde_json::json!({
+            "hash": format!("0x{}", "44".repeat(32)),
+            "nonce": "0x0",
+            "blockHash": format!("0x{}", "22".repeat(32)),
+            "blockNumber": "0x64",
+            "transactionIndex": "0x0",
+            "from": format!("0x{}", "55".repeat(20)),
+            "to": format!("0x{}", "66".repeat(20)),
+            "value": "0x1",
+            "gasPrice": "0x3b9aca00",
+            "gas": "0x5208",
+            "input": "0x",
+            "v": "0x1b",
+            "r": "0x1",
+            "s": "0x1"
+        })
+    }
+
+    fn proposer_duties(slot: u32) -> String {
+        serde_json::json!({
+            "data": [{ "pubkey": "0xa0", "validator_index": "7", "slot": slot.to_string() }]
+        })
+        .to_string()
+    }
+
+    /// Processes the blobless block at `slot` against mocked nodes and returns its outcome along
+    /// with the requests blobscan received
+    async fn process_mocked_slot(
+        slot: u32,
+        transactions: Vec<serde_json::Value>,
+        config: Config,
+    ) -> (SlotOutcome, Vec<ReceivedRequest>) {
+        // Whichever requests the slot needs, the servers are stopped once it's processed
+        let stop = CancellationToken::new();
+        let (beacon_url, beacon_server) = test_server::serve_until(
+            vec![
+                Reply::Respond("200 OK", empty_beacon_block(slot)),
+                // Proposer duties, fetched along with the execution block
+                Reply::Respond("200 OK", proposer_duties(slot)),
+            ],
+            stop.clone(),
+        )
+        .await;
+        let (execution_url, execution_server) = test_server::serve_until(
+            vec![Reply::Respond("200 OK", execution_block(transactions))],
+            stop.clone(),
+        )
+        .await;
+        let (blobscan_url, blobscan_server) =
+            test_server::serve_until(vec![Reply::Respond("200 OK", String::new())], stop.clone())
+                .await;
+        let context = Context::try_new(context_config(
+            beacon_url,
+            Some(execution_url),
+            blobscan_url,
+        ))
+        .unwrap();
+
+        context.set_chain_spec(ChainSpec {
+            slots_per_epoch: 32,
+            seconds_per_slot: 12,
+            genesis_time: None,
+            column_layouts: BTreeMap::new(),
+            blob_base_fee_update_fractions: BTreeMap::new(),
+        });
+
+        let mut slots_processor =
+            SlotsProcessor::new(context.handle(), config, CancellationToken::new());
+
+        let outcome = slots_processor.process_slot(slot).await.unwrap();
+
+        stop.cancel();
+        beacon_server.await.unwrap();
+        execution_server.await.unwrap();
+
+        (outcome, blobscan_server.await.unwrap())
+    }
+
+    /// Processes an empty block at `slot` and returns the requests blobscan received, which can
+    /// only be a heartbeat
+    async fn process_empty_block(
+        slot: u32,
+        empty_block_heartbeat: Option<Arc<EmptyBlockHeartbeat>>,
+    ) -> Vec<ReceivedRequest> {
+        let (outcome, requests) = process_mocked_slot(
+            slot,
+            vec![],
+            Config {
+                empty_block_heartbeat,
+                ..Default::default()
+            },
+        )
+        .await;
+
+        assert_eq!(outcome, SlotOutcome::Skipped(SlotSkipReason::EmptyBlock));
+
+        requests
+    }
+
+    #[tokio::test]
+    async fn test_empty_blocks_send_a_slot_heartbeat() {
+        let heartbeat = Arc::new(EmptyBlockHeartbeat::new(Duration::from_secs(60)));
+        let requests = process_empty_block(40, Some(heartbeat)).await;
+
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].path, "/indexer/slot");
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&requests[0].body).unwrap(),
+            serde_json::json!({ "slot": 40 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_empty_block_heartbeats_are_rate_limited() {
+        let heartbeat = Arc::new(EmptyBlockHeartbeat::new(Duration::from_secs(60)));
+
+        assert_eq!(
+            process_empty_block(40, Some(heartbeat.clone())).await.len(),
+            1
+        );
+        assert!(process_empty_block(41, Some(heartbeat)).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_empty_block_heartbeats_never_go_back_to_earlier_slots() {
+        let heartbeat = Arc::new(EmptyBlockHeartbeat::new(Duration::ZERO));
+
+        assert_eq!(
+            process_empty_block(40, Some(heartbeat.clone())).await.len(),
+            1
+        );
+        assert!(process_empty_block(39, Some(heartbeat.clone()))
+            .await
+            .is_empty());
+        assert_eq!(process_empty_block(41, Some(heartbeat)).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_empty_blocks_send_no_heartbeat_when_disabled() {
+        assert!(process_empty_block(40, None).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_empty_blocks_are_submitted_when_enabled() {
+        let (outcome, requests) = process_mocked_slot(
+            40,
+            vec![],
+            Config {
+                index_empty_blocks: true,
+                ..Default::default()
+            },
+        )
+        .await;
+
+        assert_eq!(outcome, SlotOutcome::Indexed);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].path, "/indexer/block-txs-blobs");
+
+        let body = serde_json::from_str::<serde_json::Value>(&requests[0].body).unwrap();
+
+        assert_eq!(body["block"]["slot"], 40);
+        assert_eq!(body["block"]["validatorPubkey"], "0xa0");
+        assert_eq!(body["transactions"], serde_json::json!([]));
+        assert_eq!(body["blobs"], serde_json::json!([]));
+        assert!(body.get("partial").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_all_mode_submits_blobless_blocks_with_their_transactions() {
+        let (outcome, requests) =
+            process_mocked_slot(40, vec![transaction()], Config::default()).await;
+
+        assert_eq!(outcome, SlotOutcome::Indexed);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/indexer/block-txs-blobs");
+
+        let body = serde_json::from_str::<serde_json::Value>(&requests[0].body).unwrap();
+
+        assert_eq!(body["block"]["slot"], 40);
+        assert!(body["block"].get("transactionsCount").is_none());
+        assert_eq!(body["transactions"].as_array().unwrap().len(), 1);
+        assert_eq!(body["transactions"][0]["hash"], transaction()["hash"]);
+        assert_eq!(body["blobs"], serde_json::json!([]));
+        assert!(body.get("partial").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_blobs_only_mode_skips_blobless_blocks() {
+        let (outcome, requests) = process_mocked_slot(
+            40,
+            vec![transaction()],
+            Config {
+                index_mode: IndexMode::BlobsOnly,
+                ..Default::default()
+            },
+        )
+        .await;
+
+        assert_eq!(outcome, SlotOutcome::Skipped(SlotSkipReason::NoBlobs));
+        assert!(requests.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_blocks_and_blobs_mode_submits_blobless_blocks_alone() {
+        let (outcome, requests) = process_mocked_slot(
+            40,
+            vec![transaction()],
+            Config {
+                index_mode: IndexMode::BlocksAndBlobs,
+                ..Default::default()
+            },
+        )
+        .await;
+
+        assert_eq!(outcome, SlotOutcome::Indexed);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/indexer/block-txs-blobs");
+
+        let body = serde_json::from_str::<serde_json::Value>(&requests[0].body).unwrap();
+
+        assert_eq!(body["block"]["slot"], 40);
+        assert_eq!(body["block"]["transactionsCount"], 1);
+        assert_eq!(body["block"]["blobsCount"], 0);
+        assert_eq!(body["transactions"], serde_json::json!([]));
+        assert_eq!(body["blobs"], serde_json::json!([]));
+        assert_eq!(body["partial"], true);
+    }
+
+    #[test]
+    fn test_only_blobs_only_mode_skips_blobless_blocks() {
+        assert!(IndexMode::All.indexes_blobless_blocks());
+        assert!(!IndexMode::BlobsOnly.indexes_blobless_blocks());
+        assert!(IndexMode::BlocksAndBlobs.indexes_blobless_blocks());
+    }
+
+    #[test]
+    fn test_empty_blocks_are_indexed_when_enabled_or_in_blocks_and_blobs_mode() {
+        assert!(!IndexMode::All.indexes_empty_blocks(false));
+        assert!(IndexMode::All.indexes_empty_blocks(true));
+        assert!(!IndexMode::BlobsOnly.indexes_empty_blocks(false));
+        assert!(IndexMode::BlocksAndBlobs.indexes_empty_blocks(false));
+        assert!(IndexMode::BlocksAndBlobs.indexes_empty_blocks(true));
+    }
+
+    #[test]
+    fn test_blocks_and_blobs_mode_submits_blobless_blocks_bare() {
+        for index_mode in [IndexMode::All, IndexMode::BlobsOnly] {
+            assert!(!index_mode.indexes_bare_block(false), "{index_mode:?}");
+            assert!(!index_mode.indexes_bare_block(true), "{index_mode:?}");
+            assert!(!index_mode.counts_block_entities(), "{index_mode:?}");
+        }
+
+        assert!(IndexMode::BlocksAndBlobs.indexes_bare_block(false));
+        assert!(!IndexMode::BlocksAndBlobs.indexes_bare_block(true));
+        assert!(IndexMode::BlocksAndBlobs.counts_block_entities());
+    }
+
+    #[test]
+    fn test_index_mode_flag_values() {
+        let values = [
+            ("all", IndexMode::All),
+            ("blobs-only", IndexMode::BlobsOnly),
+            ("blocks-and-blobs", IndexMode::BlocksAndBlobs),
+        ];
+
+        for (value, index_mode) in values {
+            assert_eq!(
+                <IndexMode as clap::ValueEnum>::from_str(value, false).unwrap(),
+                index_mode
+            );
+        }
+
+        assert_eq!(IndexMode::default(), IndexMode::All);
+    }
+
+    /// Clock whose sleeps never end, so only cancellation can stop a retry wait
+    #[derive(Debug)]
+    struct StoppedClock;
+
+    impl Clock for StoppedClock {
+        fn now(&self) -> SystemTime {
+            UNIX_EPOCH
+        }
+
+        fn sleep(&self, _duration: Duration) -> BoxFuture<'static, ()> {
+            Box::pin(futures::future::pending())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_slots_stop_waiting_for_proposer_duty_retries() {
+        let (beacon_url, beacon_server) =
+            test_server::serve(vec![Reply::Respond("200 OK", r#"{"data":[]}"#.to_string())]).await;
+        let (blobscan_url, _) = test_server::serve(vec![]).await;
+        let context = Context::try_with_clock(
+            context_config(beacon_url, None, blobscan_url),
+            Arc::new(StoppedClock),
+        )
+        .unwrap();
+        let cancellation_token = CancellationToken::new();
+
+        context.set_chain_spec(ChainSpec {
+            slots_per_epoch: 32,
+            seconds_per_slot: 12,
+            genesis_time: None,
+            column_layouts: BTreeMap::new(),
+            blob_base_fee_update_fractions: BTreeMap::new(),
+        });
+
+        let slots_processor = SlotsProcessor::new(
+            context.handle(),
+            Config::default(),
+            cancellation_token.clone(),
+        );
+
+        // Cancelled once the empty duties were served, while the lookup waits to retry them
+        tokio::spawn(async move {
+            beacon_server.await.unwrap();
+            cancellation_token.cancel();
+        });
+
+        assert!(matches!(
+            slots_processor.get_validator_pubkey(64).await,
+            Err(SlotProcessingError::Cancelled)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_empty_proposer_duties_are_retried_on_the_context_clock() {
+        let (beacon_url, beacon_server) = test_server::serve(vec![
+            Reply::Respond("200 OK", r#"{"data":[]}"#.to_string()),
+            Reply::Respond(
+                "200 OK",
+                serde_json::json!({
+                    "data": [{ "pubkey": "0xaa", "validator_index": "7", "slot": "64" }]
+                })
+                .to_string(),
+            ),
+        ])
+        .await;
+        let (blobscan_url, _) = test_server::serve(vec![]).await;
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let clock = Arc::new(MockClock::new(start));
+        let context = Context::try_with_clock(
+            context_config(beacon_url, None, blobscan_url),
+            clock.clone(),
+        )
+        .unwrap();
+        let slots_processor = SlotsProcessor::new(
+            context.handle(),
+            Config::default(),
+            CancellationToken::new(),
+        );
+
+        let duties = slots_processor
+            .get_proposer_duties(64, 2)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(duties.len(), 1);
+        assert_eq!(duties[0].slot, 64);
+        assert_eq!(
+            clock.now(),
+            start + super::EMPTY_RESPONSE_RETRY_DELAY,
+            "the retry should wait on the context clock"
+        );
+        assert_eq!(beacon_server.await.unwrap().len(), 2);
     }
 }
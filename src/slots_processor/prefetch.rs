@@ -0,0 +1,262 @@
+use std::{cmp::Ordering, future::Future, sync::Arc};
+
+use ethers::{
+    providers::{Middleware, ProviderError},
+    types::{Block as EthersBlock, Transaction as EthersTransaction},
+};
+use tokio::sync::{mpsc, Semaphore};
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+use crate::{
+    clients::{
+        beacon::types::{Block as BeaconBlock, BlockId, ColumnsResponse},
+        common::ClientResult,
+    },
+    context::Context,
+};
+
+use super::slot_range::SlotRange;
+
+/// Data fetched for a slot ahead of its processing. Fetch errors are kept so they surface when
+/// the slot is processed, as if the slot had fetched them itself
+#[derive(Debug)]
+pub struct PrefetchedSlot {
+    pub slot: u32,
+    pub beacon_block: ClientResult<Option<BeaconBlock>>,
+    /// Not fetched for slots without execution payload, or blobless ones the slot would skip
+    pub execution_block: Option<Result<Option<EthersBlock<EthersTransaction>>, ProviderError>>,
+    /// Only kept when the beacon node returned some columns. Anything else is fetched again,
+    /// with the usual retries
+    pub columns: Option<ColumnsResponse>,
+}
+
+/// Fetches the slots of a range ahead of their processing, at most `depth` slots ahead. The
+/// fetching task stops when the prefetcher is dropped
+pub struct Prefetcher {
+    slot_range: SlotRange,
+    /// Prefetched slots in visiting order
+    receiver: mpsc::Receiver<PrefetchedSlot>,
+    /// Received ahead of the slot asked for, kept for a later `take`
+    pending: Option<PrefetchedSlot>,
+    _stop_guard: DropGuard,
+}
+
+impl Prefetcher {
+    /// Only fetches the slots `should_prefetch` accepts, so the ones the processor skips before
+    /// fetching anything aren't fetched either.
+    ///
+    /// Fetches only run with a permit of `permits`, which bounds how many slots the prefetchers
+    /// of every batch fetch at once. Batches don't hold these permits, so waiting for one can't
+    /// stall the batch the prefetcher works for
+    pub fn start<P, F, Fut>(
+        slot_range: SlotRange,
+        depth: u32,
+        permits: Arc<Semaphore>,
+        cancellation_token: &CancellationToken,
+        should_prefetch: P,
+        fetch: F,
+    ) -> Self
+    where
+        P: Fn(u32) -> bool + Send + 'static,
+        F: Fn(u32) -> Fut + Send + 'static,
+        Fut: Future<Output = PrefetchedSlot> + Send,
+    {
+        let (sender, receiver) = mpsc::channel(depth.max(1) as usize);
+        let stop_token = cancellation_token.child_token();
+        let task_stop_token = stop_token.clone();
+
+        tokio::spawn(async move {
+            for slot in slot_range.slots() {
+                if !should_prefetch(slot) {
+                    continue;
+                }
+
+                let _permit = tokio::select! {
+                    biased;
+                    _ = task_stop_token.cancelled() => return,
+                    permit = permits.acquire() => match permit {
+                        Ok(permit) => permit,
+                        Err(_) => return,
+                    },
+                };
+                let prefetched = tokio::select! {
+                    biased;
+                    _ = task_stop_token.cancelled() => return,
+                    prefetched = fetch(slot) => prefetched,
+                };
+
+                if sender.send(prefetched).await.is_err() {
+                    return;
+                }
+            }
+        });
+
+        Self {
+            slot_range,
+            receiver,
+            pending: None,
+            _stop_guard: stop_token.drop_guard(),
+        }
+    }
+
+    /// Prefetched data of `slot`, dropping that of earlier slots that were skipped without it.
+    /// Returns `None` without waiting when the prefetcher skipped the slot, e.g. because the
+    /// skip list changed since
+    pub async fn take(&mut self, slot: u32) -> Option<PrefetchedSlot> {
+        let offset = self.slot_range.offset_of(slot);
+
+        loop {
+            let prefetched = match self.pending.take() {
+                Some(pending) => pending,
+                None => self.receiver.recv().await?,
+            };
+
+            match self.slot_range.offset_of(prefetched.slot).cmp(&offset) {
+                Ordering::Less => continue,
+                Ordering::Equal => return Some(prefetched),
+                Ordering::Greater => {
+                    self.pending = Some(prefetched);
+
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Fetches the beacon block of a slot and, depending on it, its execution block and columns.
+/// `skip_blobless_execution` leaves out the execution block of slots without blob commitments,
+/// for modes that skip them before fetching it
+pub async fn prefetch_slot(
+    context: &Context,
+    slot: u32,
+    skip_blobless_execution: bool,
+) -> PrefetchedSlot {
+    let beacon_client = context.beacon_client();
+    let beacon_block = beacon_client.get_block(&BlockId::Slot(slot)).await;
+    let mut execution_block = None;
+    let mut columns = None;
+
+    if let Ok(Some(block)) = &beacon_block {
+        let has_blobs = block
+            .message
+            .body
+            .blob_kzg_commitments
+            .as_ref()
+            .is_some_and(|commitments| !commitments.is_empty());
+
+        if has_blobs {
+            columns = beacon_client
+                .get_columns(&BlockId::Slot(slot))
+                .await
+                .ok()
+                .flatten()
+                .filter(|columns| !columns.data.is_empty());
+        }
+
+        if let (Some(payload), Some(provider)) =
+            (&block.message.body.execution_payload, context.provider())
+        {
+            if has_blobs || !skip_blobless_execution {
+                execution_block = Some(provider.get_block_with_txs(payload.block_hash).await);
+            }
+        }
+    }
+
+    PrefetchedSlot {
+        slot,
+        beacon_block,
+        execution_block,
+        columns,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::sync::Semaphore;
+    use tokio_util::sync::CancellationToken;
+
+    use super::{PrefetchedSlot, Prefetcher};
+    use crate::slots_processor::slot_range::SlotRange;
+
+    fn start(
+        slot_range: SlotRange,
+        permits: usize,
+        skipped_slots: &'static [u32],
+    ) -> (Prefetcher, Arc<Mutex<Vec<u32>>>) {
+        start_with_permits(slot_range, Arc::new(Semaphore::new(permits)), skipped_slots)
+    }
+
+    fn start_with_permits(
+        slot_range: SlotRange,
+        permits: Arc<Semaphore>,
+        skipped_slots: &'static [u32],
+    ) -> (Prefetcher, Arc<Mutex<Vec<u32>>>) {
+        let fetched_slots = Arc::new(Mutex::new(vec![]));
+        let fetch_log = fetched_slots.clone();
+        let prefetcher = Prefetcher::start(
+            slot_range,
+            2,
+            permits,
+            &CancellationToken::new(),
+            move |slot| !skipped_slots.contains(&slot),
+            move |slot| {
+                fetch_log.lock().unwrap().push(slot);
+
+                async move {
+                    PrefetchedSlot {
+                        slot,
+                        beacon_block: Ok(None),
+                        execution_block: None,
+                        columns: None,
+                    }
+                }
+            },
+        );
+
+        (prefetcher, fetched_slots)
+    }
+
+    #[tokio::test]
+    async fn skips_the_slots_the_processor_skips() {
+        let (mut prefetcher, fetched_slots) = start(SlotRange::new(10, 15), 1, &[11, 13]);
+
+        for slot in [10, 12, 14, 15] {
+            assert_eq!(prefetcher.take(slot).await.map(|p| p.slot), Some(slot));
+        }
+
+        assert_eq!(*fetched_slots.lock().unwrap(), vec![10, 12, 14, 15]);
+    }
+
+    #[tokio::test]
+    async fn waits_for_a_permit_before_fetching() {
+        let permits = Arc::new(Semaphore::new(1));
+        let held_permit = permits.clone().acquire_owned().await.unwrap();
+        let (mut prefetcher, fetched_slots) =
+            start_with_permits(SlotRange::new(10, 12), permits, &[]);
+
+        tokio::task::yield_now().await;
+
+        assert!(fetched_slots.lock().unwrap().is_empty());
+
+        drop(held_permit);
+
+        for slot in [10, 11, 12] {
+            assert_eq!(prefetcher.take(slot).await.map(|p| p.slot), Some(slot));
+        }
+
+        assert_eq!(*fetched_slots.lock().unwrap(), vec![10, 11, 12]);
+    }
+
+    #[tokio::test]
+    async fn take_keeps_later_slots_when_asked_for_a_skipped_one() {
+        let (mut prefetcher, _) = start(SlotRange::new(20, 17), 1, &[19]);
+
+        assert_eq!(prefetcher.take(20).await.map(|p| p.slot), Some(20));
+        assert!(prefetcher.take(19).await.is_none());
+        assert_eq!(prefetcher.take(18).await.map(|p| p.slot), Some(18));
+        assert_eq!(prefetcher.take(17).await.map(|p| p.slot), Some(17));
+    }
+}
@@ -0,0 +1,158 @@
+use std::fmt;
+
+/// Slots from `start` to `end`, both inclusive. Slots are visited from `start` towards `end`,
+/// so the range runs backwards when `end` is lower than `start`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl SlotRange {
+    pub fn new(start: u32, end: u32) -> Self {
+        Self { start, end }
+    }
+
+    pub fn is_reverse(&self) -> bool {
+        self.end < self.start
+    }
+
+    /// Number of slots in the range. A range always holds at least one slot
+    pub fn slots_count(&self) -> u32 {
+        self.start.abs_diff(self.end) + 1
+    }
+
+    /// Position of `slot` in visiting order. The slot must be part of the range
+    pub fn offset_of(&self, slot: u32) -> u32 {
+        self.start.abs_diff(slot)
+    }
+
+    /// Slots in visiting order
+    pub fn slots(&self) -> impl Iterator<Item = u32> {
+        let range = *self;
+
+        (0..range.slots_count()).map(move |offset| range.slot_at(offset))
+    }
+
+    /// Splits the range into its first `count` slots (at least one) and the remaining ones,
+    /// if there are any
+    pub fn split_at(&self, count: u32) -> (SlotRange, Option<SlotRange>) {
+        let count = count.max(1);
+
+        if count >= self.slots_count() {
+            return (*self, None);
+        }
+
+        (
+            SlotRange::new(self.start, self.slot_at(count - 1)),
+            Some(SlotRange::new(self.slot_at(count), self.end)),
+        )
+    }
+
+    /// Slot visited after `offset` others
+    pub fn slot_at(&self, offset: u32) -> u32 {
+        if self.is_reverse() {
+            self.start - offset
+        } else {
+            self.start + offset
+        }
+    }
+}
+
+impl fmt::Display for SlotRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..={}", self.start, self.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SlotRange;
+
+    #[test]
+    fn test_ranges_include_both_ends() {
+        let range = SlotRange::new(10, 13);
+
+        assert!(!range.is_reverse());
+        assert_eq!(range.slots_count(), 4);
+        assert_eq!(range.slots().collect::<Vec<_>>(), vec![10, 11, 12, 13]);
+        assert_eq!(range.to_string(), "10..=13");
+    }
+
+    #[test]
+    fn test_reverse_ranges_are_visited_backwards() {
+        let range = SlotRange::new(13, 10);
+
+        assert!(range.is_reverse());
+        assert_eq!(range.slots_count(), 4);
+        assert_eq!(range.slots().collect::<Vec<_>>(), vec![13, 12, 11, 10]);
+        assert_eq!(range.offset_of(11), 2);
+        assert_eq!(range.slot_at(2), 11);
+    }
+
+    #[test]
+    fn test_single_slot_range() {
+        let range = SlotRange::new(7, 7);
+
+        assert!(!range.is_reverse());
+        assert_eq!(range.slots_count(), 1);
+        assert_eq!(range.slots().collect::<Vec<_>>(), vec![7]);
+        assert_eq!(range.split_at(1), (range, None));
+        assert_eq!(range.split_at(5), (range, None));
+    }
+
+    #[test]
+    fn test_split_at_takes_the_first_slots() {
+        assert_eq!(
+            SlotRange::new(10, 19).split_at(4),
+            (SlotRange::new(10, 13), Some(SlotRange::new(14, 19)))
+        );
+        assert_eq!(
+            SlotRange::new(10, 19).split_at(9),
+            (SlotRange::new(10, 18), Some(SlotRange::new(19, 19)))
+        );
+        assert_eq!(
+            SlotRange::new(10, 19).split_at(10),
+            (SlotRange::new(10, 19), None)
+        );
+    }
+
+    #[test]
+    fn test_split_at_follows_reverse_ranges() {
+        assert_eq!(
+            SlotRange::new(19, 10).split_at(4),
+            (SlotRange::new(19, 16), Some(SlotRange::new(15, 10)))
+        );
+        assert_eq!(
+            SlotRange::new(19, 10).split_at(20),
+            (SlotRange::new(19, 10), None)
+        );
+    }
+
+    #[test]
+    fn test_split_at_takes_at_least_one_slot() {
+        assert_eq!(
+            SlotRange::new(10, 19).split_at(0),
+            (SlotRange::new(10, 10), Some(SlotRange::new(11, 19)))
+        );
+    }
+
+    #[test]
+    fn test_splitting_covers_every_slot_once() {
+        for range in [SlotRange::new(3, 25), SlotRange::new(25, 3)] {
+            let mut slots = vec![];
+            let mut remaining = Some(range);
+
+            while let Some(rest) = remaining {
+                let (chunk, next) = rest.split_at(5);
+
+                assert!(chunk.slots_count() <= 5);
+
+                slots.extend(chunk.slots());
+                remaining = next;
+            }
+
+            assert_eq!(slots, range.slots().collect::<Vec<_>>());
+        }
+    }
+}
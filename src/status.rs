@@ -0,0 +1,74 @@
+use anyhow::{Context as AnyhowContext, Result as AnyhowResult};
+
+use crate::{
+    clients::beacon::types::BlockId,
+    context::{Config as ContextConfig, Context},
+    env::Environment,
+};
+
+/// Prints the beacon head, the last slot blobscan synced and the lag between them.
+pub async fn print_status(env: &Environment) -> AnyhowResult<()> {
+    let context = Context::try_new(ContextConfig::from(env))?;
+    let beacon_client = context.beacon_client();
+
+    let head = beacon_client
+        .get_block(&BlockId::Head)
+        .await?
+        .with_context(|| "Beacon head not found")?;
+    let head_slot = head.message.slot;
+    let head_block_number = head
+        .message
+        .body
+        .execution_payload
+        .map(|execution_payload| execution_payload.block_number);
+
+    println!(
+        "Beacon head: slot {head_slot}{}",
+        describe_block_number(head_block_number)
+    );
+
+    let last_synced_slot = context
+        .blobscan_client()
+        .get_sync_state()
+        .await?
+        .and_then(|sync_state| sync_state.last_upper_synced_slot);
+
+    let Some(last_synced_slot) = last_synced_slot else {
+        println!("Blobscan: no slot synced yet");
+
+        return Ok(());
+    };
+
+    // The synced slot may have been missed, in which case its block number stays unknown
+    let synced_block_number = beacon_client
+        .get_block(&BlockId::Slot(last_synced_slot))
+        .await?
+        .and_then(|block| block.message.body.execution_payload)
+        .map(|execution_payload| execution_payload.block_number);
+
+    println!(
+        "Blobscan: slot {last_synced_slot}{}",
+        describe_block_number(synced_block_number)
+    );
+
+    let blocks_lag = match (head_block_number, synced_block_number) {
+        (Some(head_block_number), Some(synced_block_number)) => head_block_number
+            .saturating_sub(synced_block_number)
+            .to_string(),
+        _ => "unknown".to_string(),
+    };
+
+    println!(
+        "Lag: {} slots, {blocks_lag} blocks",
+        head_slot.saturating_sub(last_synced_slot)
+    );
+
+    Ok(())
+}
+
+fn describe_block_number(block_number: Option<u32>) -> String {
+    match block_number {
+        Some(block_number) => format!(" (execution block {block_number})"),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+
+use crate::slots_processor::slot_range::SlotRange;
+
+/// Completed parts of a chunk whose batches run concurrently. Batches finish in any order, but
+/// only slots whose every predecessor in the chunk completed can be checkpointed, or a crash
+/// would leave the slots of a slower batch behind for good.
+#[derive(Debug)]
+pub struct CheckpointTracker {
+    chunk_range: SlotRange,
+    /// Completed parts not yet joined to the contiguous prefix, as first and last offset
+    pending: BTreeMap<u32, u32>,
+    /// Number of leading slots of the chunk that completed
+    contiguous_slots: u32,
+}
+
+impl CheckpointTracker {
+    pub fn new(chunk_range: SlotRange) -> Self {
+        Self {
+            chunk_range,
+            pending: BTreeMap::new(),
+            contiguous_slots: 0,
+        }
+    }
+
+    /// Marks every slot of `range`, a part of the chunk, as completed
+    pub fn complete(&mut self, range: SlotRange) {
+        self.pending.insert(
+            self.chunk_range.offset_of(range.start),
+            self.chunk_range.offset_of(range.end),
+        );
+
+        while let Some(last_offset) = self.pending.remove(&self.contiguous_slots) {
+            self.contiguous_slots = last_offset + 1;
+        }
+    }
+
+    /// Marks the slots of `range` visited before `failed_slot` as completed. A failed slot outside
+    /// the range, such as one re-indexed after a reorg, completes none of them
+    pub fn complete_until(&mut self, range: SlotRange, failed_slot: u32) {
+        let lowest_slot = std::cmp::min(range.start, range.end);
+        let highest_slot = std::cmp::max(range.start, range.end);
+
+        if !(lowest_slot..=highest_slot).contains(&failed_slot) {
+            return;
+        }
+
+        let completed_slots = range.offset_of(failed_slot);
+
+        if completed_slots > 0 {
+            self.complete(SlotRange::new(
+                range.start,
+                range.slot_at(completed_slots - 1),
+            ));
+        }
+    }
+
+    /// Last slot of the completed prefix, if any slot of it completed
+    pub fn last_contiguous_slot(&self) -> Option<u32> {
+        self.contiguous_slots
+            .checked_sub(1)
+            .map(|offset| self.chunk_range.slot_at(offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CheckpointTracker;
+    use crate::slots_processor::slot_range::SlotRange;
+
+    fn tracker() -> CheckpointTracker {
+        CheckpointTracker::new(SlotRange::new(100, 139))
+    }
+
+    #[test]
+    fn test_nothing_is_checkpointed_before_the_first_batch_completes() {
+        let mut tracker = tracker();
+
+        assert_eq!(tracker.last_contiguous_slot(), None);
+
+        tracker.complete(SlotRange::new(110, 119));
+        tracker.complete(SlotRange::new(130, 139));
+
+        assert_eq!(tracker.last_contiguous_slot(), None);
+    }
+
+    #[test]
+    fn test_shuffled_completions_only_advance_over_a_gap_free_prefix() {
+        let mut tracker = tracker();
+        let completions = [
+            (SlotRange::new(120, 129), None),
+            (SlotRange::new(100, 109), Some(109)),
+            (SlotRange::new(130, 139), Some(109)),
+            (SlotRange::new(110, 119), Some(139)),
+        ];
+
+        for (batch_range, last_contiguous_slot) in completions {
+            tracker.complete(batch_range);
+
+            assert_eq!(
+                tracker.last_contiguous_slot(),
+                last_contiguous_slot,
+                "after completing {batch_range}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_a_failed_batch_holds_back_the_later_batches() {
+        let mut tracker = tracker();
+
+        tracker.complete(SlotRange::new(110, 119));
+        tracker.complete_until(SlotRange::new(100, 109), 105);
+
+        assert_eq!(tracker.last_contiguous_slot(), Some(104));
+
+        // A crash now must resume from slot 105, whatever completed after it
+        tracker.complete(SlotRange::new(120, 129));
+        tracker.complete(SlotRange::new(130, 139));
+
+        assert_eq!(tracker.last_contiguous_slot(), Some(104));
+    }
+
+    #[test]
+    fn test_a_later_failed_batch_keeps_the_completed_prefix() {
+        let mut tracker = tracker();
+
+        tracker.complete_until(SlotRange::new(120, 129), 126);
+        tracker.complete(SlotRange::new(100, 109));
+
+        assert_eq!(tracker.last_contiguous_slot(), Some(109));
+
+        tracker.complete(SlotRange::new(110, 119));
+
+        assert_eq!(tracker.last_contiguous_slot(), Some(125));
+    }
+
+    #[test]
+    fn test_failing_at_the_first_slot_completes_nothing() {
+        let mut tracker = tracker();
+
+        tracker.complete_until(SlotRange::new(100, 109), 100);
+
+        assert_eq!(tracker.last_contiguous_slot(), None);
+    }
+
+    #[test]
+    fn test_failed_slots_outside_the_batch_complete_nothing() {
+        let mut tracker = tracker();
+
+        tracker.complete_until(SlotRange::new(110, 119), 95);
+        tracker.complete(SlotRange::new(100, 109));
+
+        assert_eq!(tracker.last_contiguous_slot(), Some(109));
+    }
+
+    #[test]
+    fn test_reverse_chunks_advance_downwards() {
+        let mut tracker = CheckpointTracker::new(SlotRange::new(139, 100));
+
+        tracker.complete(SlotRange::new(129, 120));
+        tracker.complete_until(SlotRange::new(139, 130), 133);
+
+        assert_eq!(tracker.last_contiguous_slot(), Some(134));
+
+        tracker.complete(SlotRange::new(119, 100));
+
+        assert_eq!(tracker.last_contiguous_slot(), Some(134));
+    }
+}
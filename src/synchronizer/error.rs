@@ -20,6 +20,8 @@ pub enum SynchronizerError {
         slot: u32,
         error: crate::clients::common::ClientError,
     },
+    #[error("syncing was cancelled")]
+    Cancelled,
     #[error(transparent)]
     FailedSlotsProcessing(#[from] SlotsProcessorError),
     #[error(transparent)]
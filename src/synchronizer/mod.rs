@@ -1,33 +1,72 @@
+use std::{sync::Arc, time::Duration};
+
 use anyhow::anyhow;
+use ethers::types::H256;
 use futures::future::join_all;
-use tokio::task::JoinHandle;
-use tracing::{debug, info, Instrument};
+use tokio::{
+    sync::{Mutex, Semaphore},
+    task::JoinHandle,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn, Instrument};
 
 use crate::{
-    clients::{beacon::types::BlockId, blobscan::types::BlockchainSyncState, common::ClientError},
-    context::Context,
-    slots_processor::{error::SlotsProcessorError, SlotsProcessor},
+    clients::{
+        beacon::types::BlockId,
+        blobscan::types::{BlockchainSyncState, FailedSlotsChunk},
+        common::ClientError,
+    },
+    context::ContextHandle,
+    local_checkpoint::LocalCheckpointFile,
+    slots_processor::{
+        chain_tracker::ChainTracker, error::SlotsProcessorError, slot_range::SlotRange,
+        Config as SlotsProcessorConfig, SlotsProcessor,
+    },
+    utils::redaction::redact_error,
 };
 
+use self::checkpoint_tracker::CheckpointTracker;
 use self::error::{SlotsChunksErrors, SynchronizerError};
 
+mod checkpoint_tracker;
 pub mod error;
 
 #[derive(Debug)]
 pub struct SynchronizerBuilder {
-    num_threads: u32,
-    min_slots_per_thread: u32,
+    concurrency: u32,
+    min_slots_per_batch: u32,
     slots_checkpoint: u32,
+    checkpoint_interval: Option<Duration>,
     checkpoint_type: CheckpointType,
+    slots_processor_config: SlotsProcessorConfig,
+    cancellation_token: CancellationToken,
+    local_checkpoint: Option<Arc<LocalCheckpointFile>>,
+    record_failed_chunks: bool,
 }
 
 #[derive(Debug)]
 pub struct Synchronizer {
-    context: Context,
-    num_threads: u32,
-    min_slots_per_thread: u32,
+    context: ContextHandle,
+    concurrency: u32,
+    min_slots_per_batch: u32,
+    /// Bounds how many slot batches are processed at once
+    permits: Arc<Semaphore>,
+    /// Bounds how many slots the batches' prefetchers fetch at once. Kept apart from `permits`
+    /// so prefetching doesn't depend on batches being idle
+    prefetch_permits: Arc<Semaphore>,
     slots_checkpoint: u32,
+    checkpoint_interval: Option<Duration>,
     checkpoint_type: CheckpointType,
+    slots_processor_config: SlotsProcessorConfig,
+    cancellation_token: CancellationToken,
+    local_checkpoint: Option<Arc<LocalCheckpointFile>>,
+    record_failed_chunks: bool,
+    /// Blocks processed by previous forward runs, so a new run can check its first block builds
+    /// on them
+    chain_tracker: Arc<Mutex<ChainTracker>>,
+    /// Held for a whole run. Tokio's mutex is fair, so concurrent runs queue in call order
+    /// instead of interleaving their slots
+    run_lock: Mutex<()>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -40,10 +79,15 @@ pub enum CheckpointType {
 impl Default for SynchronizerBuilder {
     fn default() -> Self {
         SynchronizerBuilder {
-            num_threads: 1,
-            min_slots_per_thread: 50,
+            concurrency: 1,
+            min_slots_per_batch: 50,
             slots_checkpoint: 1000,
+            checkpoint_interval: None,
             checkpoint_type: CheckpointType::Upper,
+            slots_processor_config: SlotsProcessorConfig::default(),
+            cancellation_token: CancellationToken::new(),
+            local_checkpoint: None,
+            record_failed_chunks: true,
         }
     }
 }
@@ -59,8 +103,9 @@ impl SynchronizerBuilder {
         self
     }
 
-    pub fn with_num_threads(&mut self, num_threads: u32) -> &mut Self {
-        self.num_threads = num_threads;
+    /// Maximum number of slot batches processed at once
+    pub fn with_concurrency(&mut self, concurrency: u32) -> &mut Self {
+        self.concurrency = concurrency.max(1);
 
         self
     }
@@ -70,32 +115,88 @@ impl SynchronizerBuilder {
         self
     }
 
-    pub fn build(&self, context: Context) -> Synchronizer {
+    /// Also save the checkpoint once this much time has elapsed since the last save, even if
+    /// fewer than `slots_checkpoint` slots have been processed.
+    pub fn with_checkpoint_interval(&mut self, checkpoint_interval: Duration) -> &mut Self {
+        self.checkpoint_interval = Some(checkpoint_interval);
+
+        self
+    }
+
+    pub fn with_slots_processor_config(
+        &mut self,
+        slots_processor_config: SlotsProcessorConfig,
+    ) -> &mut Self {
+        self.slots_processor_config = slots_processor_config;
+
+        self
+    }
+
+    pub fn with_cancellation_token(&mut self, cancellation_token: CancellationToken) -> &mut Self {
+        self.cancellation_token = cancellation_token;
+
+        self
+    }
+
+    /// Also mirrors every completed chunk to a local checkpoint file
+    pub fn with_local_checkpoint(
+        &mut self,
+        local_checkpoint: Arc<LocalCheckpointFile>,
+    ) -> &mut Self {
+        self.local_checkpoint = Some(local_checkpoint);
+
+        self
+    }
+
+    /// When enabled (the default), a chunk that fails is recorded to blobscan for a later retry
+    /// and syncing moves past it. Otherwise the failure stops the run
+    pub fn with_failed_chunks_recording(&mut self, record_failed_chunks: bool) -> &mut Self {
+        self.record_failed_chunks = record_failed_chunks;
+
+        self
+    }
+
+    pub fn build(&self, context: ContextHandle) -> Synchronizer {
         Synchronizer {
             context,
-            num_threads: self.num_threads,
-            min_slots_per_thread: self.min_slots_per_thread,
+            concurrency: self.concurrency,
+            min_slots_per_batch: self.min_slots_per_batch,
+            permits: Arc::new(Semaphore::new(self.concurrency as usize)),
+            prefetch_permits: Arc::new(Semaphore::new(
+                self.slots_processor_config.prefetch_slots as usize,
+            )),
             slots_checkpoint: self.slots_checkpoint,
+            checkpoint_interval: self.checkpoint_interval,
             checkpoint_type: self.checkpoint_type,
+            slots_processor_config: self.slots_processor_config.clone(),
+            cancellation_token: self.cancellation_token.clone(),
+            local_checkpoint: self.local_checkpoint.clone(),
+            record_failed_chunks: self.record_failed_chunks,
+            chain_tracker: Arc::new(Mutex::new(ChainTracker::new(
+                self.slots_processor_config.max_reorg_depth as usize,
+            ))),
+            run_lock: Mutex::new(()),
         }
     }
 }
 
 impl Synchronizer {
+    /// Syncs every slot between both block ids, inclusive of both ends. When the final block id
+    /// moves while syncing (e.g. `head`), the range is synced again up to its new slot.
+    ///
+    /// The synchronizer can be shared: a run started while another is in progress waits for it
+    /// to finish.
     pub async fn run(
-        &mut self,
+        &self,
         initial_block_id: &BlockId,
         final_block_id: &BlockId,
     ) -> Result<(), SynchronizerError> {
+        let _run_guard = self.run_lock.lock().await;
         let initial_slot = self._resolve_to_slot(initial_block_id).await?;
         let mut final_slot = self._resolve_to_slot(final_block_id).await?;
 
-        if initial_slot == final_slot {
-            return Ok(());
-        }
-
         loop {
-            self._sync_slots_by_checkpoints(initial_slot, final_slot)
+            self._sync_slots_by_checkpoints(SlotRange::new(initial_slot, final_slot))
                 .await?;
 
             let latest_final_slot = self._resolve_to_slot(final_block_id).await?;
@@ -108,57 +209,102 @@ impl Synchronizer {
         }
     }
 
-    async fn _sync_slots(&mut self, from_slot: u32, to_slot: u32) -> Result<(), SynchronizerError> {
-        let is_reverse_sync = to_slot < from_slot;
-        let unprocessed_slots = to_slot.abs_diff(from_slot);
-        let min_slots_per_thread = std::cmp::min(unprocessed_slots, self.min_slots_per_thread);
-        let slots_per_thread =
-            std::cmp::max(min_slots_per_thread, unprocessed_slots / self.num_threads);
-        let num_threads = std::cmp::max(1, unprocessed_slots / slots_per_thread);
-        let remaining_slots = unprocessed_slots % num_threads;
+    /// Syncs the chunk, recording every batch that completes to `checkpoint_tracker`, including
+    /// the part of a failed batch processed before its failure
+    async fn _sync_slots(
+        &self,
+        slot_range: SlotRange,
+        checkpoint_tracker: Arc<Mutex<CheckpointTracker>>,
+    ) -> Result<(), SynchronizerError> {
+        let unprocessed_slots = slot_range.slots_count();
+        // Without concurrency, the chunk is processed as a single batch
+        let slots_per_batch = if self.concurrency == 1 {
+            unprocessed_slots
+        } else {
+            std::cmp::min(unprocessed_slots, self.min_slots_per_batch)
+        };
+        // The chain can only be followed from one block to the next when a single batch
+        // processes the whole chunk in order, as realtime chunks are
+        let tracks_chain = unprocessed_slots <= slots_per_batch && !slot_range.is_reverse();
+
+        if !tracks_chain {
+            self.chain_tracker.lock().await.clear();
+        }
 
         let mut handles: Vec<JoinHandle<Result<(), SlotsProcessorError>>> = vec![];
+        let mut unassigned_range = Some(slot_range);
+        let mut batch = 0;
 
-        for i in 0..num_threads {
-            let mut slots_processor = SlotsProcessor::new(self.context.clone());
-            let thread_total_slots = slots_per_thread
-                + if i == num_threads - 1 {
-                    remaining_slots
-                } else {
-                    0
-                };
-            let thread_initial_slot = if is_reverse_sync {
-                from_slot - i * slots_per_thread
-            } else {
-                from_slot + i * slots_per_thread
-            };
-            let thread_final_slot = if is_reverse_sync {
-                thread_initial_slot - thread_total_slots
-            } else {
-                thread_initial_slot + thread_total_slots
-            };
-
-            let synchronizer_thread_span = tracing::debug_span!(
+        while let Some(remaining_range) = unassigned_range {
+            let (batch_range, rest) = remaining_range.split_at(slots_per_batch);
+
+            unassigned_range = rest;
+
+            let mut slots_processor = SlotsProcessor::new(
+                self.context.clone(),
+                self.slots_processor_config.clone(),
+                self.cancellation_token.child_token(),
+            );
+
+            if tracks_chain {
+                slots_processor.with_chain_tracker(self.chain_tracker.clone());
+            }
+
+            slots_processor.with_prefetch_permits(self.prefetch_permits.clone());
+
+            let batch_span = tracing::debug_span!(
                 parent:  &tracing::Span::current(),
-                "thread",
-                thread = i,
-                chunk_initial_slot = thread_initial_slot,
-                chunk_final_slot = thread_final_slot
+                "batch",
+                batch,
+                batch_initial_slot = batch_range.start,
+                batch_final_slot = batch_range.end
             );
+            let permits = self.permits.clone();
+            let checkpoint_tracker = checkpoint_tracker.clone();
 
+            // Batches waiting for a permit when syncing is cancelled stop at their first slot,
+            // so every batch still reports where it stopped
             let handle = tokio::spawn(
                 async move {
-                    slots_processor
-                        .process_slots(thread_initial_slot, thread_final_slot)
-                        .await?;
+                    let _permit = permits
+                        .acquire_owned()
+                        .await
+                        .map_err(|error| anyhow!("Failed to acquire a batch permit: {error}"))?;
+                    let progress = match slots_processor.process_slots(batch_range).await {
+                        Ok(progress) => progress,
+                        Err(error) => {
+                            if let SlotsProcessorError::FailedSlotsProcessing {
+                                failed_slot, ..
+                            } = &error
+                            {
+                                checkpoint_tracker
+                                    .lock()
+                                    .await
+                                    .complete_until(batch_range, *failed_slot);
+                            }
+
+                            return Err(error);
+                        }
+                    };
+
+                    checkpoint_tracker.lock().await.complete(batch_range);
+
+                    if !progress.failed_slots.is_empty() {
+                        warn!(
+                            failed_slots = ?progress.failed_slots,
+                            "Skipped {} failed slots",
+                            progress.failed_slots.len()
+                        );
+                    }
 
                     Ok(())
                 }
-                .instrument(synchronizer_thread_span)
+                .instrument(batch_span)
                 .in_current_span(),
             );
 
             handles.push(handle);
+            batch += 1;
         }
 
         let handle_outputs = join_all(handles).await;
@@ -167,12 +313,12 @@ impl Synchronizer {
 
         for handle in handle_outputs {
             match handle {
-                Ok(thread_result) => match thread_result {
+                Ok(batch_result) => match batch_result {
                     Ok(()) => {}
                     Err(error) => errors.push(error),
                 },
                 Err(error) => {
-                    let err = anyhow!("Synchronizer thread panicked: {:?}", error);
+                    let err = anyhow!("Synchronizer batch panicked: {:?}", error);
 
                     errors.push(err.into());
                 }
@@ -183,116 +329,239 @@ impl Synchronizer {
             Ok(())
         } else {
             Err(SynchronizerError::FailedParallelSlotsProcessing {
-                initial_slot: from_slot,
-                final_slot: to_slot,
+                initial_slot: slot_range.start,
+                final_slot: slot_range.end,
                 chunk_errors: SlotsChunksErrors(errors),
             })
         }
     }
 
     async fn _sync_slots_by_checkpoints(
-        &mut self,
-        initial_slot: u32,
-        final_slot: u32,
+        &self,
+        slot_range: SlotRange,
     ) -> Result<(), SynchronizerError> {
-        let is_reverse_sync = final_slot < initial_slot;
-        let mut current_slot = initial_slot;
-        let mut unprocessed_slots = final_slot.abs_diff(current_slot);
+        let max_slots_chunk = checkpoint_chunk_size(
+            self.slots_checkpoint,
+            self.checkpoint_interval,
+            self.concurrency * self.min_slots_per_batch,
+        );
+        let mut unprocessed_range = Some(slot_range);
+        let mut slots_since_checkpoint = 0;
+        let mut last_checkpoint_at = self.context.clock().now();
+        let mut last_synced_slot: Option<u32> = None;
 
         info!(
-            initial_slot,
-            final_slot,
-            reverse_sync = is_reverse_sync,
-            "Syncing {unprocessed_slots} slots…"
+            initial_slot = slot_range.start,
+            final_slot = slot_range.end,
+            reverse_sync = slot_range.is_reverse(),
+            "Syncing {} slots…",
+            slot_range.slots_count()
         );
 
-        while unprocessed_slots > 0 {
-            let slots_chunk = std::cmp::min(unprocessed_slots, self.slots_checkpoint);
-            let initial_chunk_slot = current_slot;
-            let final_chunk_slot = if is_reverse_sync {
-                current_slot - slots_chunk
-            } else {
-                current_slot + slots_chunk
-            };
+        while let Some(remaining_range) = unprocessed_range {
+            if self.cancellation_token.is_cancelled() {
+                if let Some(slot) = last_synced_slot.filter(|_| slots_since_checkpoint > 0) {
+                    self._flush_checkpoint(slot).await?;
+                }
+
+                return Err(SynchronizerError::Cancelled);
+            }
+
+            let (chunk_range, rest) = remaining_range.split_at(max_slots_chunk);
 
             let sync_slots_chunk_span = tracing::debug_span!(
                 parent: &tracing::Span::current(),
                 "checkpoint",
-                checkpoint_initial_slot = initial_chunk_slot,
-                checkpoint_final_slot = final_chunk_slot
+                checkpoint_initial_slot = chunk_range.start,
+                checkpoint_final_slot = chunk_range.end
             );
 
-            self._sync_slots(initial_chunk_slot, final_chunk_slot)
+            let chunk_started_at = self.context.clock().now();
+            let checkpoint_tracker = Arc::new(Mutex::new(CheckpointTracker::new(chunk_range)));
+
+            if let Err(error) = self
+                ._sync_slots(chunk_range, checkpoint_tracker.clone())
                 .instrument(sync_slots_chunk_span)
-                .await?;
+                .await
+            {
+                // A cancelled chunk still moves the checkpoint over the slots that completed
+                // along with all the ones before them
+                if self.cancellation_token.is_cancelled() {
+                    let last_contiguous_slot =
+                        checkpoint_tracker.lock().await.last_contiguous_slot();
+
+                    if let Some(slot) = last_contiguous_slot.or(last_synced_slot) {
+                        self._flush_checkpoint(slot).await?;
+                    }
+
+                    return Err(error);
+                }
 
-            let last_slot = Some(if is_reverse_sync {
-                final_chunk_slot
-            } else {
-                final_chunk_slot - 1
-            });
+                if !self.record_failed_chunks {
+                    return Err(error);
+                }
 
-            if self.checkpoint_type != CheckpointType::Disabled {
-                let last_lower_synced_slot = if self.checkpoint_type == CheckpointType::Lower {
-                    last_slot
-                } else {
-                    None
-                };
-                let last_upper_synced_slot = if self.checkpoint_type == CheckpointType::Upper {
-                    last_slot
+                self._record_failed_chunk(error, &chunk_range).await?;
+            }
+
+            let chunk_secs = self
+                .context
+                .clock()
+                .elapsed_since(chunk_started_at)
+                .as_secs_f64();
+
+            if chunk_secs > 0.0 {
+                self.context
+                    .metrics()
+                    .set_slots_per_second(chunk_range.slots_count() as f64 / chunk_secs);
+            }
+
+            last_synced_slot = Some(chunk_range.end);
+
+            if let Some(local_checkpoint) = &self.local_checkpoint {
+                let block_root = if self.checkpoint_type == CheckpointType::Upper {
+                    self._get_block_root(chunk_range.end).await
                 } else {
                     None
                 };
 
-                if let Err(error) = self
-                    .context
-                    .blobscan_client()
-                    .update_sync_state(BlockchainSyncState {
-                        last_finalized_block: None,
-                        last_lower_synced_slot,
-                        last_upper_synced_slot,
-                    })
-                    .await
-                {
-                    let new_synced_slot = match last_lower_synced_slot {
-                        Some(slot) => slot,
-                        None => match last_upper_synced_slot {
-                            Some(slot) => slot,
-                            None => {
-                                return Err(SynchronizerError::Other(anyhow!(
-                                    "Failed to get new last synced slot: last_lower_synced_slot and last_upper_synced_slot are both None"
-                                )))
-                            }
-                        }
-                    };
+                local_checkpoint.record(self.checkpoint_type, chunk_range.end, block_root);
+            }
 
-                    return Err(SynchronizerError::FailedSlotCheckpointSave {
-                        slot: new_synced_slot,
-                        error,
-                    });
-                }
+            slots_since_checkpoint += chunk_range.slots_count();
+
+            let is_checkpoint_due = is_checkpoint_due(
+                slots_since_checkpoint,
+                self.slots_checkpoint,
+                rest.is_none(),
+                self.context.clock().elapsed_since(last_checkpoint_at),
+                self.checkpoint_interval,
+            );
 
-                if unprocessed_slots >= self.slots_checkpoint {
+            if self.checkpoint_type != CheckpointType::Disabled && is_checkpoint_due {
+                self._save_checkpoint(chunk_range.end).await?;
+
+                if rest.is_some() {
                     debug!(
-                        new_last_lower_synced_slot = last_lower_synced_slot,
-                        new_last_upper_synced_slot = last_upper_synced_slot,
+                        checkpoint_type = ?self.checkpoint_type,
+                        new_last_synced_slot = chunk_range.end,
                         "Checkpoint reached. Last synced slot saved…"
                     );
                 }
+
+                slots_since_checkpoint = 0;
+                last_checkpoint_at = self.context.clock().now();
             }
 
-            current_slot = if is_reverse_sync {
-                current_slot - slots_chunk
-            } else {
-                current_slot + slots_chunk
-            };
+            unprocessed_range = rest;
+        }
+
+        Ok(())
+    }
+
+    /// Saves `last_slot` as the lower or upper synced slot, depending on the checkpoint type
+    async fn _save_checkpoint(&self, last_slot: u32) -> Result<(), SynchronizerError> {
+        let (last_lower_synced_slot, last_upper_synced_slot) = match self.checkpoint_type {
+            CheckpointType::Disabled => return Ok(()),
+            CheckpointType::Lower => (Some(last_slot), None),
+            CheckpointType::Upper => (None, Some(last_slot)),
+        };
+
+        self.context
+            .blobscan_client()
+            .update_sync_state(BlockchainSyncState {
+                last_finalized_block: None,
+                last_lower_synced_slot,
+                last_upper_synced_slot,
+            })
+            .await
+            .map_err(|error| SynchronizerError::FailedSlotCheckpointSave {
+                slot: last_slot,
+                error,
+            })
+    }
+
+    async fn _flush_checkpoint(&self, last_slot: u32) -> Result<(), SynchronizerError> {
+        if self.checkpoint_type == CheckpointType::Disabled {
+            return Ok(());
+        }
+
+        self._save_checkpoint(last_slot).await?;
+
+        info!(
+            checkpoint_type = ?self.checkpoint_type,
+            last_synced_slot = last_slot,
+            "Syncing cancelled. Checkpoint saved at the last contiguous synced slot"
+        );
+
+        Ok(())
+    }
+
+    /// Saves the unprocessed part of every failed batch to blobscan, so the chunk can be retried
+    /// on the next start while syncing moves on. If they can't be saved, or a batch failed for a
+    /// reason that isn't specific to its slots, the chunk's error is returned, as the slots would
+    /// otherwise be lost
+    async fn _record_failed_chunk(
+        &self,
+        error: SynchronizerError,
+        chunk_range: &SlotRange,
+    ) -> Result<(), SynchronizerError> {
+        let Some(failed_chunks) = failed_chunks_to_record(&error, chunk_range) else {
+            return Err(error);
+        };
+
+        for failed_chunk in failed_chunks {
+            let initial_slot = failed_chunk.initial_slot;
+            let final_slot = failed_chunk.final_slot;
+
+            if let Err(record_error) = self
+                .context
+                .blobscan_client()
+                .add_failed_slots_chunk(failed_chunk)
+                .await
+            {
+                warn!(
+                    error = %redact_error(&record_error),
+                    initial_slot, final_slot, "Failed to record failed slots chunk"
+                );
+
+                return Err(error);
+            }
 
-            unprocessed_slots -= slots_chunk;
+            warn!(
+                initial_slot,
+                final_slot, "Slots chunk failed. Recorded for a retry on the next start"
+            );
         }
 
+        // The skipped slots break the chain the tracker follows
+        self.chain_tracker.lock().await.clear();
+
         Ok(())
     }
 
+    /// Beacon block root of the given slot, or `None` when the slot was missed. Lookup failures
+    /// are logged and also give `None`, as the root is only informative
+    async fn _get_block_root(&self, slot: u32) -> Option<H256> {
+        match self
+            .context
+            .beacon_client()
+            .get_block_header(&BlockId::Slot(slot))
+            .await
+        {
+            Ok(block_header) => block_header.map(|block_header| block_header.root),
+            Err(error) => {
+                warn!(
+                    slot,
+                    error = %redact_error(&error),
+                    "Failed to fetch the block root for the checkpoint file"
+                );
+
+                None
+            }
+        }
+    }
+
     async fn _resolve_to_slot(&self, block_id: &BlockId) -> Result<u32, SynchronizerError> {
         let beacon_client = self.context.beacon_client();
 
@@ -318,3 +587,208 @@ impl Synchronizer {
         }
     }
 }
+
+/// Number of slots synced between checkpoint decisions. With a time-based checkpoint, slots are
+/// synced in chunks of one round of batches so the interval can be honored before
+/// `slots_checkpoint` slots have been processed
+fn checkpoint_chunk_size(
+    slots_checkpoint: u32,
+    checkpoint_interval: Option<Duration>,
+    slots_per_round: u32,
+) -> u32 {
+    match checkpoint_interval {
+        Some(_) => std::cmp::min(slots_checkpoint, slots_per_round),
+        None => slots_checkpoint,
+    }
+}
+
+/// Whether the checkpoint is saved after a chunk: once `slots_checkpoint` slots were synced since
+/// the last save, after the last chunk, or once the checkpoint interval has elapsed
+fn is_checkpoint_due(
+    slots_since_checkpoint: u32,
+    slots_checkpoint: u32,
+    is_last_chunk: bool,
+    since_last_checkpoint: Duration,
+    checkpoint_interval: Option<Duration>,
+) -> bool {
+    slots_since_checkpoint >= slots_checkpoint
+        || is_last_chunk
+        || checkpoint_interval.is_some_and(|interval| since_last_checkpoint >= interval)
+}
+
+/// The failed chunks to record for a chunk's error, or `None` when the chunk can't be skipped
+/// because one of its batches failed for a reason that would fail any other range too
+fn failed_chunks_to_record(
+    error: &SynchronizerError,
+    chunk_range: &SlotRange,
+) -> Option<Vec<FailedSlotsChunk>> {
+    let SynchronizerError::FailedParallelSlotsProcessing { chunk_errors, .. } = error else {
+        return None;
+    };
+
+    if chunk_errors.0.iter().any(SlotsProcessorError::is_fatal) {
+        return None;
+    }
+
+    let failed_chunks = chunk_errors
+        .0
+        .iter()
+        .map(|chunk_error| match chunk_error {
+            SlotsProcessorError::FailedSlotsProcessing {
+                final_slot,
+                failed_slot,
+                error,
+                ..
+            } => FailedSlotsChunk {
+                id: None,
+                initial_slot: *failed_slot,
+                final_slot: *final_slot,
+                failed_slot: Some(*failed_slot),
+                error: Some(error.to_string()),
+            },
+            // Where the batch stopped is unknown, so the whole chunk is retried
+            chunk_error => FailedSlotsChunk {
+                id: None,
+                initial_slot: chunk_range.start,
+                final_slot: chunk_range.end,
+                failed_slot: None,
+                error: Some(chunk_error.to_string()),
+            },
+        })
+        .collect();
+
+    Some(failed_chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        clients::common::{ClientError, ErrorResponse, NumericOrTextCode},
+        slots_processor::error::{SlotProcessingError, SlotsProgress},
+    };
+
+    use super::*;
+
+    fn failed_batch(
+        initial_slot: u32,
+        final_slot: u32,
+        failed_slot: u32,
+        error: SlotProcessingError,
+    ) -> SlotsProcessorError {
+        SlotsProcessorError::FailedSlotsProcessing {
+            initial_slot,
+            final_slot,
+            failed_slot,
+            progress: SlotsProgress::default(),
+            error,
+        }
+    }
+
+    fn chunk_error(chunk_errors: Vec<SlotsProcessorError>) -> SynchronizerError {
+        SynchronizerError::FailedParallelSlotsProcessing {
+            initial_slot: 0,
+            final_slot: 99,
+            chunk_errors: SlotsChunksErrors(chunk_errors),
+        }
+    }
+
+    #[test]
+    fn records_the_unprocessed_part_of_slot_specific_failures() {
+        let error = chunk_error(vec![failed_batch(
+            50,
+            99,
+            73,
+            SlotProcessingError::BlocksMismatch {
+                slot: 73,
+                commitments: 2,
+            },
+        )]);
+
+        let failed_chunks = failed_chunks_to_record(&error, &SlotRange::new(0, 99)).unwrap();
+
+        assert_eq!(failed_chunks.len(), 1);
+        assert_eq!(failed_chunks[0].initial_slot, 73);
+        assert_eq!(failed_chunks[0].final_slot, 99);
+        assert_eq!(failed_chunks[0].failed_slot, Some(73));
+    }
+
+    #[test]
+    fn doesnt_record_chunks_failed_by_systemic_errors() {
+        let rejected_submission =
+            SlotProcessingError::SubmissionFailure(ClientError::ApiError(ErrorResponse {
+                code: NumericOrTextCode::Number(500),
+                message: None,
+            }));
+        let error = chunk_error(vec![
+            failed_batch(
+                0,
+                49,
+                10,
+                SlotProcessingError::BlocksMismatch {
+                    slot: 10,
+                    commitments: 1,
+                },
+            ),
+            failed_batch(50, 99, 50, rejected_submission),
+        ]);
+
+        assert!(failed_chunks_to_record(&error, &SlotRange::new(0, 99)).is_none());
+        assert!(
+            failed_chunks_to_record(&SynchronizerError::Cancelled, &SlotRange::new(0, 99))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn checkpoint_chunks_shrink_only_with_a_checkpoint_interval() {
+        assert_eq!(checkpoint_chunk_size(1000, None, 200), 1000);
+        assert_eq!(
+            checkpoint_chunk_size(1000, Some(Duration::from_secs(60)), 200),
+            200
+        );
+        assert_eq!(
+            checkpoint_chunk_size(100, Some(Duration::from_secs(60)), 200),
+            100
+        );
+    }
+
+    #[test]
+    fn checkpoint_is_due_every_slots_checkpoint_slots() {
+        assert!(!is_checkpoint_due(999, 1000, false, Duration::ZERO, None));
+        assert!(is_checkpoint_due(1000, 1000, false, Duration::ZERO, None));
+        assert!(is_checkpoint_due(1200, 1000, false, Duration::ZERO, None));
+    }
+
+    #[test]
+    fn checkpoint_is_due_after_the_last_chunk() {
+        assert!(is_checkpoint_due(1, 1000, true, Duration::ZERO, None));
+    }
+
+    #[test]
+    fn checkpoint_is_due_once_the_interval_elapses() {
+        let interval = Some(Duration::from_secs(60));
+
+        assert!(!is_checkpoint_due(
+            200,
+            1000,
+            false,
+            Duration::from_secs(59),
+            interval
+        ));
+        assert!(is_checkpoint_due(
+            200,
+            1000,
+            false,
+            Duration::from_secs(60),
+            interval
+        ));
+        // Without an interval, elapsed time alone never makes a checkpoint due
+        assert!(!is_checkpoint_due(
+            200,
+            1000,
+            false,
+            Duration::from_secs(3600),
+            None
+        ));
+    }
+}
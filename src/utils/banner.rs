@@ -1,6 +1,9 @@
 use url::Url;
 
-use crate::{args::Args, env::Environment};
+use crate::{
+    args::Args, env::Environment, indexer::types::RealtimeTarget, profile::ProfileSettings,
+    slots_processor::IndexMode,
+};
 
 fn mask_quik_node_url(url_string: &str) -> Option<String> {
     match Url::parse(url_string) {
@@ -55,6 +58,12 @@ pub fn remove_credentials_from_url(url_string: &str) -> Option<String> {
     }
 }
 
+fn deprecated_source_note(env: &Environment, name: &str) -> String {
+    env.deprecated_source(name)
+        .map(|deprecated_name| format!(" (from deprecated {deprecated_name})"))
+        .unwrap_or_default()
+}
+
 pub fn print_banner(args: &Args, env: &Environment) {
     println!(" ____  _  _  _    ");
     println!("|  _ \\(_)| || |___  ___ __ _ _ __    ");
@@ -79,11 +88,7 @@ pub fn print_banner(args: &Args, env: &Environment) {
         println!("Custom end slot: {}", to_slot.to_detailed_string());
     }
 
-    if let Some(num_threads) = args.num_threads {
-        println!("Number of threads: {}", num_threads);
-    } else {
-        println!("Number of threads: auto");
-    }
+    println!("Concurrency: {}", args.concurrency());
 
     if let Some(slots_per_save) = args.slots_per_save {
         println!("Slots checkpoint size: {}", slots_per_save);
@@ -91,6 +96,26 @@ pub fn print_banner(args: &Args, env: &Environment) {
         println!("Slots checkpoint size: 200");
     }
 
+    println!(
+        "Stall detection: {}",
+        match args.stall_slots {
+            0 => "disabled".to_string(),
+            stall_slots => format!("after {stall_slots} slots without beacon events"),
+        }
+    );
+
+    if let Some(slots_per_epoch) = args.slots_per_epoch {
+        println!("Slots per epoch fallback: {slots_per_epoch}");
+    }
+
+    if let Some(seconds_per_slot) = args.seconds_per_slot {
+        println!("Seconds per slot fallback: {seconds_per_slot}s");
+    }
+
+    if let Some(checkpoint_interval_secs) = args.checkpoint_interval_secs {
+        println!("Slots checkpoint interval: {}s", checkpoint_interval_secs);
+    }
+
     println!(
         "Disable sync checkpoint saving: {}",
         if args.disable_sync_checkpoint_save {
@@ -109,15 +134,163 @@ pub fn print_banner(args: &Args, env: &Environment) {
         }
     );
 
-    println!("Blobscan API endpoint: {}", env.blobscan_api_endpoint);
     println!(
-        "CL endpoint: {:?}",
-        remove_credentials_from_url(env.beacon_node_endpoint.as_str())
+        "Disable chain config push: {}",
+        if args.disable_chain_config_push {
+            "yes"
+        } else {
+            "no"
+        }
+    );
+
+    let profile_settings = ProfileSettings::resolve(args);
+
+    println!("Profile: {:?}", args.profile);
+    println!(
+        "Empty response policy: {:?}",
+        profile_settings.empty_response_policy
+    );
+    println!("Index mode: {:?}", args.index_mode);
+    println!(
+        "Blocks mismatch: {}",
+        if profile_settings.skip_blocks_mismatch || args.index_mode == IndexMode::BlobsOnly {
+            "skip"
+        } else {
+            "fail"
+        }
+    );
+    if let Some(sample_rate) = args.sample_rate {
+        println!("Historical sample rate: {} slots per epoch", sample_rate);
+    }
+
+    println!(
+        "Record skipped slots: {}",
+        if args.record_skipped_slots {
+            "yes"
+        } else {
+            "no"
+        }
+    );
+    if args.continue_on_error {
+        println!(
+            "Continue on error: yes (max failure rate {})",
+            args.max_failure_rate
+        );
+    }
+    println!(
+        "Index empty blocks: {}",
+        if args.index_empty_blocks { "yes" } else { "no" }
+    );
+    println!(
+        "Empty block heartbeat: {}",
+        match args.empty_block_heartbeat_secs {
+            Some(secs) => format!("every {secs}s at most"),
+            None => "disabled".to_string(),
+        }
+    );
+    println!(
+        "Latency freshness window: {}",
+        match args.latency_freshness_window {
+            0 => "disabled".to_string(),
+            secs => format!("{secs}s"),
+        }
+    );
+    println!(
+        "Realtime target: {}{}",
+        RealtimeTarget::from_args(args),
+        if args.once { " (once)" } else { "" }
+    );
+    println!(
+        "Head poll fallback interval: {}s",
+        args.poll_fallback_interval
+    );
+    println!("Shutdown grace period: {}s", args.shutdown_grace_period);
+    if let Some(checkpoint_file) = &args.checkpoint_file {
+        println!("Checkpoint file: {}", checkpoint_file.display());
+    }
+    if let Some(metrics_port) = args.metrics_port {
+        println!("Metrics port: {metrics_port}");
+    }
+    if let Some(health_port) = args.health_port {
+        println!(
+            "Health port: {health_port} (max lag {} slots, max {} failed head fetches)",
+            args.health_max_lag, args.health_max_head_failures
+        );
+    }
+    if let Some(skip_list) = &args.skip_list {
+        println!("Skip list: {}", skip_list.display());
+    }
+    println!(
+        "Prefetched slots: {}",
+        match args.prefetch_slots {
+            0 => "disabled".to_string(),
+            slots => slots.to_string(),
+        }
+    );
+    println!(
+        "Max reorg depth: {}",
+        match args.max_reorg_depth {
+            0 => "disabled".to_string(),
+            depth => format!("{depth} slots"),
+        }
+    );
+    println!(
+        "Verify parent timestamp: {}",
+        if profile_settings.verify_parent_timestamp {
+            "yes"
+        } else {
+            "no"
+        }
+    );
+    println!(
+        "Verify parent number: {}",
+        if profile_settings.verify_parent_number {
+            "yes"
+        } else {
+            "no"
+        }
+    );
+
+    if let Some(reindex_fields) = args.reindex_fields.clone() {
+        println!("Re-index fields: {:?}", reindex_fields);
+    }
+
+    println!(
+        "Blobscan API endpoint: {}{}",
+        env.blobscan_api_endpoint,
+        deprecated_source_note(env, "BLOBSCAN_API_URL")
     );
     println!(
-        "EL endpoint: {:?}",
-        remove_credentials_from_url(env.execution_node_endpoint.as_str())
+        "CL endpoint: {:?}{}",
+        remove_credentials_from_url(env.beacon_node_endpoint.as_str()),
+        deprecated_source_note(env, "BEACON_API_URL")
     );
+    match env.execution_node() {
+        Some(execution_node_endpoint) => println!(
+            "EL endpoint: {:?}{}",
+            remove_credentials_from_url(execution_node_endpoint.as_str()),
+            deprecated_source_note(env, "EXECUTION_NODE_URL")
+        ),
+        None => println!("EL endpoint: none (consensus-only mode)"),
+    }
+    println!("CL HTTP version: {:?}", env.beacon_http_version);
+    println!("Blobscan HTTP version: {:?}", env.blobscan_http_version);
+    if env.beacon_trace_bodies || env.blobscan_trace_bodies {
+        println!(
+            "Traced bodies: {}{} (up to {} bytes)",
+            if env.beacon_trace_bodies {
+                "beacon "
+            } else {
+                ""
+            },
+            if env.blobscan_trace_bodies {
+                "blobscan"
+            } else {
+                ""
+            },
+            env.trace_bodies_max_bytes
+        );
+    }
 
     if let Some(sentry_dsn) = env.sentry_dsn.clone() {
         println!("Sentry DSN: {}", sentry_dsn);
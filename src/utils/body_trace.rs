@@ -0,0 +1,298 @@
+use std::io::Write;
+
+use super::redaction::redact;
+
+const REDACTED_VALUE: &[u8] = b"\"******\"";
+/// Cap of the response excerpts logged along with warnings
+const EXCERPT_MAX_BYTES: usize = 512;
+const SECRET_FIELDS: [&[u8]; 5] = [
+    b"secretKey",
+    b"secret_key",
+    b"token",
+    b"accessToken",
+    b"authorization",
+];
+
+/// Trace-level logging of a client's JSON request and response bodies. Blob data fields are
+/// replaced by their size, secret fields are masked and the output is capped to `max_bytes`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BodyTrace {
+    pub enabled: bool,
+    pub max_bytes: usize,
+    /// JSON keys whose values hold blob data
+    pub omitted_fields: &'static [&'static str],
+}
+
+impl BodyTrace {
+    /// Returns the body to log, or `None` when tracing is disabled for the client
+    pub fn render(&self, body: &[u8]) -> Option<String> {
+        if !self.enabled || !tracing::enabled!(tracing::Level::TRACE) {
+            return None;
+        }
+
+        Some(self.sanitize(body))
+    }
+
+    /// Returns a capped and sanitized excerpt of the body for warnings, which are logged
+    /// whether or not body tracing is enabled
+    pub fn excerpt(&self, body: &[u8]) -> String {
+        Self {
+            max_bytes: EXCERPT_MAX_BYTES,
+            ..*self
+        }
+        .sanitize(body)
+    }
+
+    /// Walks the serialized body once, stopping as soon as the cap is reached, so large payloads
+    /// are never copied in full
+    fn sanitize(&self, body: &[u8]) -> String {
+        let mut output = Vec::with_capacity(self.max_bytes.min(body.len()));
+        let mut position = 0;
+
+        while position < body.len() && output.len() < self.max_bytes {
+            if body[position] != b'"' {
+                output.push(body[position]);
+                position += 1;
+
+                continue;
+            }
+
+            let string_end = find_string_end(body, position);
+            let key = &body[position + 1..string_end.saturating_sub(1).max(position + 1)];
+
+            self.push_capped(&mut output, &body[position..string_end]);
+            position = string_end;
+
+            let colon = skip_whitespace(body, position);
+
+            if body.get(colon) != Some(&b':') {
+                continue;
+            }
+
+            let is_omitted = self
+                .omitted_fields
+                .iter()
+                .any(|field| field.as_bytes() == key);
+            let is_secret = SECRET_FIELDS.contains(&key);
+
+            if !is_omitted && !is_secret {
+                continue;
+            }
+
+            let value_start = skip_whitespace(body, colon + 1);
+            let value_end = find_value_end(body, value_start);
+
+            output.extend_from_slice(&body[position..value_start]);
+
+            if is_secret {
+                output.extend_from_slice(REDACTED_VALUE);
+            } else {
+                let _ = write!(output, "\"<{} bytes omitted>\"", value_end - value_start);
+            }
+
+            position = value_end;
+        }
+
+        let is_truncated = position < body.len() || output.len() > self.max_bytes;
+
+        output.truncate(self.max_bytes);
+
+        let mut rendered = String::from_utf8_lossy(&output).into_owned();
+
+        if is_truncated {
+            rendered.push_str(&format!("… ({} bytes total)", body.len()));
+        }
+
+        redact(&rendered)
+    }
+
+    fn push_capped(&self, output: &mut Vec<u8>, bytes: &[u8]) {
+        let remaining = self.max_bytes.saturating_sub(output.len()) + 1;
+
+        output.extend_from_slice(&bytes[..bytes.len().min(remaining)]);
+    }
+}
+
+fn skip_whitespace(body: &[u8], mut position: usize) -> usize {
+    while body.get(position).is_some_and(u8::is_ascii_whitespace) {
+        position += 1;
+    }
+
+    position
+}
+
+/// Position right after the closing quote of the string starting at `start`
+fn find_string_end(body: &[u8], start: usize) -> usize {
+    let mut position = start + 1;
+
+    while position < body.len() {
+        match body[position] {
+            b'\\' => position += 2,
+            b'"' => return position + 1,
+            _ => position += 1,
+        }
+    }
+
+    body.len()
+}
+
+/// Position right after the value starting at `start`, which may be a string, an array, an
+/// object or a scalar
+fn find_value_end(body: &[u8], start: usize) -> usize {
+    let mut depth = 0usize;
+    let mut position = start;
+
+    while position < body.len() {
+        match body[position] {
+            b'"' => {
+                position = find_string_end(body, position);
+
+                if depth == 0 {
+                    return position;
+                }
+
+                continue;
+            }
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' if depth == 0 => return position,
+            b']' | b'}' => {
+                depth -= 1;
+
+                if depth == 0 {
+                    return position + 1;
+                }
+            }
+            b',' if depth == 0 => return position,
+            _ => {}
+        }
+
+        position += 1;
+    }
+
+    body.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BodyTrace;
+
+    fn body_trace(max_bytes: usize, omitted_fields: &'static [&'static str]) -> BodyTrace {
+        BodyTrace {
+            enabled: true,
+            max_bytes,
+            omitted_fields,
+        }
+    }
+
+    #[test]
+    fn omitted_fields_are_replaced_by_their_size() {
+        let trace = body_trace(1000, &["blob"]);
+
+        assert_eq!(
+            trace.sanitize(br#"{"index":"1","blob":"0xabcdef","kzgProof":"0x01"}"#),
+            r#"{"index":"1","blob":"<10 bytes omitted>","kzgProof":"0x01"}"#
+        );
+    }
+
+    #[test]
+    fn omitted_arrays_and_objects_are_replaced_whole() {
+        assert_eq!(
+            body_trace(1000, &["segments"])
+                .sanitize(br#"{"segments":["0xaa","0xbb"],"index":"2"}"#),
+            r#"{"segments":"<15 bytes omitted>","index":"2"}"#
+        );
+        assert_eq!(
+            body_trace(1000, &["data"])
+                .sanitize(br#"{"data": "0x0102", "nested": {"data": {"a": [1, 2]}}}"#),
+            r#"{"data": "<8 bytes omitted>", "nested": {"data": "<13 bytes omitted>"}}"#
+        );
+    }
+
+    #[test]
+    fn field_names_inside_strings_are_left_alone() {
+        assert_eq!(
+            body_trace(1000, &["blob"]).sanitize(br#"{"note":"say \"blob\": here","blob":"0xff"}"#),
+            r#"{"note":"say \"blob\": here","blob":"<6 bytes omitted>"}"#
+        );
+    }
+
+    #[test]
+    fn secret_fields_are_masked() {
+        assert_eq!(
+            body_trace(1000, &[]).sanitize(br#"{"secretKey":"abc","token": "xyz","slot":1}"#),
+            r#"{"secretKey":"******","token": "******","slot":1}"#
+        );
+    }
+
+    #[test]
+    fn output_is_capped() {
+        let body = br#"{"slot":12345,"hash":"0xabcdef"}"#;
+
+        assert_eq!(
+            body_trace(10, &[]).sanitize(body),
+            r#"{"slot":12… (32 bytes total)"#
+        );
+        assert_eq!(
+            body_trace(15, &[]).sanitize(body),
+            r#"{"slot":12345,"… (32 bytes total)"#
+        );
+        assert_eq!(
+            body_trace(1000, &[]).sanitize(body),
+            r#"{"slot":12345,"hash":"0xabcdef"}"#
+        );
+    }
+
+    #[test]
+    fn omission_markers_are_capped_too() {
+        assert_eq!(
+            body_trace(20, &["blob"]).sanitize(br#"{"blob":"0x00112233445566778899"}"#),
+            r#"{"blob":"<24 bytes o… (33 bytes total)"#
+        );
+    }
+
+    #[test]
+    fn excerpts_are_sanitized_and_capped_even_when_disabled() {
+        let trace = BodyTrace {
+            enabled: false,
+            ..body_trace(0, &["blob"])
+        };
+        let body = format!(
+            r#"{{"token":"xyz","blob":"0xff","message":"{}"}}"#,
+            "a".repeat(1000)
+        );
+        let excerpt = trace.excerpt(body.as_bytes());
+
+        assert!(
+            excerpt.starts_with(r#"{"token":"******","blob":"<6 bytes omitted>","message":"aaa"#)
+        );
+        assert!(excerpt.ends_with(&format!("… ({} bytes total)", body.len())));
+        assert!(excerpt.len() < 600);
+    }
+
+    #[test]
+    fn disabled_trace_renders_nothing() {
+        let trace = BodyTrace {
+            enabled: false,
+            ..body_trace(1000, &[])
+        };
+
+        tracing::subscriber::with_default(tracing_subscriber::registry(), || {
+            assert_eq!(trace.render(br#"{"slot":1}"#), None);
+            assert_eq!(
+                body_trace(1000, &[]).render(br#"{"slot":1}"#),
+                Some(r#"{"slot":1}"#.to_string())
+            );
+        });
+    }
+
+    #[test]
+    fn trace_renders_nothing_below_trace_level() {
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            assert_eq!(body_trace(1000, &[]).render(br#"{"slot":1}"#), None);
+        });
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    fmt::Debug,
+    time::{Duration, SystemTime},
+};
+
+use futures::future::BoxFuture;
+
+/// Source of time for everything that waits on or measures it, so tests can swap it for a
+/// [`MockClock`] instead of sleeping for real.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> SystemTime;
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+
+    /// Time elapsed since `earlier`. A wall clock that jumped backwards counts as no time elapsed
+    /// rather than an error.
+    fn elapsed_since(&self, earlier: SystemTime) -> Duration {
+        self.now().duration_since(earlier).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// Clock that only moves when advanced. Sleeping advances it by the slept duration and returns
+/// right away.
+#[cfg(test)]
+#[derive(Debug)]
+pub struct MockClock {
+    now: std::sync::Mutex<SystemTime>,
+}
+
+#[cfg(test)]
+impl MockClock {
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            now: std::sync::Mutex::new(now),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+
+    pub fn rewind(&self, duration: Duration) {
+        *self.now.lock().unwrap() -= duration;
+    }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        self.advance(duration);
+
+        Box::pin(futures::future::ready(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{Clock, MockClock};
+
+    #[tokio::test]
+    async fn test_mock_clock_sleep_advances_time() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let clock = MockClock::new(start);
+
+        clock.sleep(Duration::from_secs(30)).await;
+        clock.advance(Duration::from_secs(5));
+
+        assert_eq!(clock.now(), start + Duration::from_secs(35));
+        assert_eq!(clock.elapsed_since(start), Duration::from_secs(35));
+    }
+
+    #[test]
+    fn test_elapsed_since_ignores_backward_jumps() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let clock = MockClock::new(start);
+
+        clock.rewind(Duration::from_secs(10));
+
+        assert_eq!(clock.elapsed_since(start), Duration::ZERO);
+    }
+}
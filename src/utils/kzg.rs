@@ -0,0 +1,32 @@
+use c_kzg::{ethereum_kzg_settings, Blob};
+
+/// Computes a blob's KZG commitment with Ethereum's trusted setup, hex encoded like the beacon API
+/// encodes commitments
+pub fn blob_to_kzg_commitment(blob: &[u8]) -> Result<String, c_kzg::Error> {
+    let blob = Blob::from_bytes(blob)?;
+    let commitment = ethereum_kzg_settings(0).blob_to_kzg_commitment(&blob)?;
+
+    Ok(format!(
+        "0x{}",
+        hex::encode(commitment.to_bytes().into_inner())
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::blob_to_kzg_commitment;
+
+    const BYTES_PER_BLOB: usize = 131072;
+
+    #[test]
+    fn zero_blob_commits_to_the_point_at_infinity() {
+        let commitment = blob_to_kzg_commitment(&[0u8; BYTES_PER_BLOB]).unwrap();
+
+        assert_eq!(commitment, format!("0xc0{}", "00".repeat(47)));
+    }
+
+    #[test]
+    fn rejects_blobs_of_the_wrong_size() {
+        assert!(blob_to_kzg_commitment(&[0u8; 32]).is_err());
+    }
+}
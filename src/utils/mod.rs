@@ -1,3 +1,8 @@
 pub mod banner;
+pub mod body_trace;
+pub mod clock;
+pub mod kzg;
+pub mod redaction;
 pub mod telemetry;
+pub mod url;
 pub mod web3;
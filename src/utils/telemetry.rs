@@ -17,10 +17,12 @@ where
         .compact() // Use the Pretty formatter.
         .with_writer(sink);
 
-    Registry::default()
-        .with(env_filter)
-        .with(formatting_layer)
-        .with(sentry_tracing::layer())
+    let subscriber = Registry::default().with(env_filter).with(formatting_layer);
+
+    #[cfg(feature = "sentry")]
+    let subscriber = subscriber.with(sentry_tracing::layer());
+
+    subscriber
 }
 
 /// Inits the logger with the given subscriber.
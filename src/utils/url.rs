@@ -0,0 +1,200 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer};
+use url::Url;
+
+const SUPPORTED_SCHEMES: [&str; 2] = ["http", "https"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum EndpointUrlError {
+    #[error("endpoint URL is empty")]
+    Empty,
+    #[error("endpoint URL \"{0}\" is missing a scheme (e.g. \"http://\")")]
+    MissingScheme(String),
+    #[error(
+        "endpoint URL \"{input}\" has unsupported scheme \"{scheme}\". Expected http or https"
+    )]
+    UnsupportedScheme { input: String, scheme: String },
+    #[error("endpoint URL \"{0}\" is missing a host")]
+    MissingHost(String),
+    #[error("invalid endpoint URL \"{0}\"")]
+    InvalidUrl(String, #[source] url::ParseError),
+}
+
+/// A validated http(s) endpoint URL with no trailing slash in its path.
+///
+/// Request URLs must be built through [`EndpointUrl::join_path`] so that any path component
+/// of the endpoint (e.g. a provider token) is preserved and no double slashes are produced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointUrl(Url);
+
+impl EndpointUrl {
+    pub fn parse(input: &str) -> Result<Self, EndpointUrlError> {
+        let input = input.trim();
+
+        if input.is_empty() {
+            return Err(EndpointUrlError::Empty);
+        }
+
+        if !input.contains("://") {
+            return Err(EndpointUrlError::MissingScheme(input.to_string()));
+        }
+
+        let mut url = Url::parse(input)
+            .map_err(|err| EndpointUrlError::InvalidUrl(input.to_string(), err))?;
+
+        if !SUPPORTED_SCHEMES.contains(&url.scheme()) {
+            return Err(EndpointUrlError::UnsupportedScheme {
+                input: input.to_string(),
+                scheme: url.scheme().to_string(),
+            });
+        }
+
+        if url.host().is_none() {
+            return Err(EndpointUrlError::MissingHost(input.to_string()));
+        }
+
+        let path = url.path().trim_end_matches('/').to_string();
+
+        url.set_path(&path);
+
+        Ok(Self(url))
+    }
+
+    /// Appends the given path segments to the endpoint path. Segments may contain slashes,
+    /// empty segments are skipped and the endpoint query string is kept.
+    pub fn join_path(&self, segments: &[&str]) -> Url {
+        let mut url = self.0.clone();
+
+        if let Ok(mut path_segments) = url.path_segments_mut() {
+            path_segments.pop_if_empty().extend(
+                segments
+                    .iter()
+                    .flat_map(|segment| segment.split('/'))
+                    .filter(|segment| !segment.is_empty()),
+            );
+        }
+
+        url
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl fmt::Display for EndpointUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for EndpointUrl {
+    type Err = EndpointUrlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for EndpointUrl {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+
+        Self::parse(&value).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EndpointUrl, EndpointUrlError};
+
+    fn url(input: &str) -> EndpointUrl {
+        EndpointUrl::parse(input).unwrap()
+    }
+
+    #[test]
+    fn test_trailing_slashes_are_trimmed() {
+        assert_eq!(url("http://localhost:3001/"), url("http://localhost:3001"));
+        assert_eq!(
+            url("https://rpc.example/token/"),
+            url("https://rpc.example/token")
+        );
+        assert_eq!(
+            url(" http://localhost:3001 ").as_str(),
+            "http://localhost:3001/"
+        );
+    }
+
+    #[test]
+    fn test_invalid_urls_are_rejected() {
+        assert!(matches!(
+            EndpointUrl::parse(""),
+            Err(EndpointUrlError::Empty)
+        ));
+        assert!(matches!(
+            EndpointUrl::parse("  "),
+            Err(EndpointUrlError::Empty)
+        ));
+        assert!(matches!(
+            EndpointUrl::parse("localhost:3001"),
+            Err(EndpointUrlError::MissingScheme(_))
+        ));
+        assert!(matches!(
+            EndpointUrl::parse("ftp://localhost"),
+            Err(EndpointUrlError::UnsupportedScheme { scheme, .. }) if scheme == "ftp"
+        ));
+        assert!(matches!(
+            EndpointUrl::parse("http://"),
+            Err(EndpointUrlError::InvalidUrl(..))
+        ));
+    }
+
+    #[test]
+    fn test_join_path_keeps_the_endpoint_path() {
+        assert_eq!(
+            url("http://localhost:3001/")
+                .join_path(&["indexer/skipped-slots"])
+                .as_str(),
+            "http://localhost:3001/indexer/skipped-slots"
+        );
+        assert_eq!(
+            url("https://rpc.example/token/")
+                .join_path(&["eth/v1/beacon", "headers"])
+                .as_str(),
+            "https://rpc.example/token/eth/v1/beacon/headers"
+        );
+    }
+
+    #[test]
+    fn test_join_path_skips_empty_segments() {
+        assert_eq!(
+            url("http://localhost:3500")
+                .join_path(&["/eth/", "", "//v1"])
+                .as_str(),
+            "http://localhost:3500/eth/v1"
+        );
+    }
+
+    #[test]
+    fn test_join_path_keeps_the_query_string() {
+        assert_eq!(
+            url("https://rpc.example/?key=abc")
+                .join_path(&["eth/v1/node/version"])
+                .as_str(),
+            "https://rpc.example/eth/v1/node/version?key=abc"
+        );
+    }
+
+    #[test]
+    fn test_deserializes_through_parse() {
+        assert_eq!(
+            serde_json::from_str::<EndpointUrl>("\"http://localhost:3001/\"").unwrap(),
+            url("http://localhost:3001")
+        );
+        assert!(serde_json::from_str::<EndpointUrl>("\"localhost:3001\"").is_err());
+    }
+}
@@ -5,6 +5,12 @@ use ethers::core::k256::sha2::{Digest, Sha256};
 use ethers::{prelude::*, types::H256};
 
 const BLOB_COMMITMENT_VERSION_KZG: u8 = 0x01;
+pub const BLOB_TX_TYPE: u64 = 3;
+const MIN_BLOB_BASE_FEE: u64 = 1;
+/// Blob base fee update fraction set by Cancun (EIP-4844)
+pub const CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION: u64 = 3338477;
+/// Blob base fee update fraction set by Prague (EIP-7691)
+pub const PRAGUE_BLOB_BASE_FEE_UPDATE_FRACTION: u64 = 5007716;
 
 pub fn sha256(value: &str) -> Result<H256> {
     let value_without_prefix = if let Some(value_without_prefix) = value.strip_prefix("0x") {
@@ -65,6 +71,194 @@ pub fn get_tx_versioned_hashes(tx: &Transaction) -> Result<Option<Vec<H256>>> {
     }
 }
 
+/// Approximates `factor * e ** (numerator / denominator)` as specified in EIP-4844
+fn fake_exponential(factor: U256, numerator: U256, denominator: U256) -> U256 {
+    let mut i = U256::one();
+    let mut output = U256::zero();
+    let mut numerator_accum = factor * denominator;
+
+    while !numerator_accum.is_zero() {
+        output = output.saturating_add(numerator_accum);
+        numerator_accum = numerator_accum.saturating_mul(numerator) / (denominator * i);
+        i += U256::one();
+    }
+
+    output / denominator
+}
+
+/// Blob gas price for a block's excess blob gas. The update fraction depends on the fork the
+/// block belongs to
+pub fn calculate_blob_gas_price(excess_blob_gas: U256, update_fraction: u64) -> U256 {
+    fake_exponential(
+        U256::from(MIN_BLOB_BASE_FEE),
+        excess_blob_gas,
+        U256::from(update_fraction),
+    )
+}
+
+pub fn calculate_blob_fee_burned(
+    blob_gas_used: U256,
+    excess_blob_gas: U256,
+    update_fraction: u64,
+) -> U256 {
+    blob_gas_used.saturating_mul(calculate_blob_gas_price(excess_blob_gas, update_fraction))
+}
+
+pub fn is_blob_tx(tx: &Transaction) -> bool {
+    tx.transaction_type == Some(U64::from(BLOB_TX_TYPE))
+}
+
 pub fn get_full_hash(hash: &H256) -> String {
     format!("0x{:x}", hash)
 }
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Transaction, H256, U256, U64};
+
+    use super::{
+        calculate_blob_fee_burned, calculate_blob_gas_price, get_tx_versioned_hashes, is_blob_tx,
+        BLOB_TX_TYPE, CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION, PRAGUE_BLOB_BASE_FEE_UPDATE_FRACTION,
+    };
+
+    // Blob gas used by a block carrying a single blob
+    const GAS_PER_BLOB: u64 = 131072;
+
+    #[test]
+    fn blob_gas_price_matches_the_eip_4844_reference() {
+        let cases: [(u64, u64); 6] = [
+            (0, 1),
+            (393216, 1),
+            (CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION, 2),
+            (7463386, 9),
+            (10 * CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION, 22026),
+            (100000000, 10203769476395),
+        ];
+
+        for (excess_blob_gas, expected_price) in cases {
+            assert_eq!(
+                calculate_blob_gas_price(
+                    U256::from(excess_blob_gas),
+                    CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION
+                ),
+                U256::from(expected_price),
+                "excess blob gas {excess_blob_gas}"
+            );
+        }
+    }
+
+    #[test]
+    fn blob_gas_price_uses_the_given_update_fraction() {
+        let cases = [
+            (0, 1),
+            (PRAGUE_BLOB_BASE_FEE_UPDATE_FRACTION, 2),
+            (7463386, 4),
+            (100000000, 470442149),
+        ];
+
+        for (excess_blob_gas, expected_price) in cases {
+            assert_eq!(
+                calculate_blob_gas_price(
+                    U256::from(excess_blob_gas),
+                    PRAGUE_BLOB_BASE_FEE_UPDATE_FRACTION
+                ),
+                U256::from(expected_price),
+                "excess blob gas {excess_blob_gas}"
+            );
+        }
+    }
+
+    #[test]
+    fn blob_fee_burned_is_blob_gas_used_times_the_blob_gas_price() {
+        let blob_gas_used = U256::from(3 * GAS_PER_BLOB);
+
+        assert_eq!(
+            calculate_blob_fee_burned(
+                blob_gas_used,
+                U256::from(7463386),
+                CANCUN_BLOB_BASE_FEE_UPDATE_FRACTION
+            ),
+            U256::from(3 * GAS_PER_BLOB * 9)
+        );
+        assert_eq!(
+            calculate_blob_fee_burned(
+                U256::zero(),
+                U256::from(100000000),
+                PRAGUE_BLOB_BASE_FEE_UPDATE_FRACTION
+            ),
+            U256::zero()
+        );
+    }
+
+    fn tx_with_versioned_hashes(versioned_hashes: Option<serde_json::Value>) -> Transaction {
+        let mut tx = Transaction {
+            transaction_type: Some(U64::from(BLOB_TX_TYPE)),
+            ..Default::default()
+        };
+
+        if let Some(versioned_hashes) = versioned_hashes {
+            tx.other = serde_json::from_value(
+                serde_json::json!({ "blobVersionedHashes": versioned_hashes }),
+            )
+            .unwrap();
+        }
+
+        tx
+    }
+
+    #[test]
+    fn blob_txs_are_told_by_their_type() {
+        let mut tx = tx_with_versioned_hashes(None);
+
+        assert!(is_blob_tx(&tx));
+
+        tx.transaction_type = Some(U64::from(2));
+
+        assert!(!is_blob_tx(&tx));
+
+        tx.transaction_type = None;
+
+        assert!(!is_blob_tx(&tx));
+    }
+
+    #[test]
+    fn tx_versioned_hashes_are_read_from_the_tx() {
+        let versioned_hash = H256::repeat_byte(1);
+        let tx = tx_with_versioned_hashes(Some(serde_json::json!([versioned_hash])));
+
+        assert_eq!(
+            get_tx_versioned_hashes(&tx).unwrap(),
+            Some(vec![versioned_hash])
+        );
+    }
+
+    #[test]
+    fn stripped_or_empty_tx_versioned_hashes_are_none() {
+        assert_eq!(
+            get_tx_versioned_hashes(&tx_with_versioned_hashes(None)).unwrap(),
+            None
+        );
+        assert_eq!(
+            get_tx_versioned_hashes(&tx_with_versioned_hashes(Some(serde_json::json!([]))))
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn malformed_tx_versioned_hashes_are_rejected() {
+        let malformed = [
+            serde_json::json!("0x01"),
+            serde_json::json!([1]),
+            serde_json::json!(["0x01"]),
+        ];
+
+        for versioned_hashes in malformed {
+            assert!(
+                get_tx_versioned_hashes(&tx_with_versioned_hashes(Some(versioned_hashes.clone())))
+                    .is_err(),
+                "{versioned_hashes}"
+            );
+        }
+    }
+}
@@ -0,0 +1,508 @@
+use std::{collections::HashSet, io::Write};
+
+use anyhow::{anyhow, Context as AnyhowContext, Result as AnyhowResult};
+use ethers::{providers::Middleware, types::H256};
+use serde::Serialize;
+
+use crate::{
+    clients::beacon::types::BlockId,
+    context::{Config as ContextConfig, Context},
+    env::Environment,
+    utils::{
+        redaction::redact_error,
+        web3::{calculate_versioned_hash, get_tx_versioned_hashes, is_blob_tx},
+    },
+};
+
+/// A difference between what blobscan stores for a slot and what the chain holds.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Discrepancy {
+    MissingBlock {
+        slot: u32,
+        block_number: u32,
+    },
+    BlockHashMismatch {
+        slot: u32,
+        expected: H256,
+        found: H256,
+    },
+    MissingTransaction {
+        slot: u32,
+        tx_hash: H256,
+    },
+    ExtraTransaction {
+        slot: u32,
+        tx_hash: H256,
+    },
+    MissingBlob {
+        slot: u32,
+        versioned_hash: H256,
+    },
+    ExtraBlob {
+        slot: u32,
+        versioned_hash: H256,
+    },
+}
+
+/// A slot that couldn't be compared, e.g. because a node didn't answer. Printed along with the
+/// discrepancies, under its own kind
+#[derive(Serialize, Debug)]
+#[serde(tag = "kind", rename = "verification_failure")]
+struct VerificationFailure {
+    slot: u32,
+    error: String,
+}
+
+/// What a range verification found
+#[derive(Debug, Default, PartialEq)]
+struct VerificationSummary {
+    discrepancies: usize,
+    failed_slots: usize,
+}
+
+/// Compares every slot of the range against blobscan, printing each discrepancy as a JSON line.
+/// A slot that can't be compared is printed as a failure line and doesn't stop the run. Fails
+/// when any discrepancy or failure was found, so cron jobs can alert on the exit code.
+pub async fn verify_slots(env: &Environment, from_slot: u32, to_slot: u32) -> AnyhowResult<()> {
+    if to_slot < from_slot {
+        return Err(anyhow!(
+            "--to {to_slot} is below --from {from_slot}. Swap them"
+        ));
+    }
+
+    let context = Context::try_new(ContextConfig::from(env))?;
+    let summary = verify_range(&context, from_slot, to_slot, &mut std::io::stdout()).await?;
+
+    if summary != VerificationSummary::default() {
+        return Err(anyhow!(
+            "{} discrepancies found and {} slots couldn't be verified between slots {from_slot} and {to_slot}",
+            summary.discrepancies,
+            summary.failed_slots
+        ));
+    }
+
+    Ok(())
+}
+
+async fn verify_range(
+    context: &Context,
+    from_slot: u32,
+    to_slot: u32,
+    output: &mut impl Write,
+) -> AnyhowResult<VerificationSummary> {
+    let mut summary = VerificationSummary::default();
+
+    for slot in from_slot..=to_slot {
+        match verify_slot(context, slot).await {
+            Ok(discrepancies) => {
+                for discrepancy in discrepancies {
+                    writeln!(output, "{}", serde_json::to_string(&discrepancy)?)?;
+
+                    summary.discrepancies += 1;
+                }
+            }
+            Err(error) => {
+                let failure = VerificationFailure {
+                    slot,
+                    error: redact_error(error.as_ref()),
+                };
+
+                writeln!(output, "{}", serde_json::to_string(&failure)?)?;
+
+                summary.failed_slots += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+async fn verify_slot(context: &Context, slot: u32) -> AnyhowResult<Vec<Discrepancy>> {
+    let Some(beacon_block) = context
+        .beacon_client()
+        .get_block(&BlockId::Slot(slot))
+        .await?
+    else {
+        return Ok(vec![]);
+    };
+    let Some(execution_payload) = beacon_block.message.body.execution_payload else {
+        return Ok(vec![]);
+    };
+    let expected_versioned_hashes = beacon_block
+        .message
+        .body
+        .blob_kzg_commitments
+        .unwrap_or_default()
+        .iter()
+        .map(|commitment| calculate_versioned_hash(commitment))
+        .collect::<AnyhowResult<HashSet<_>>>()?;
+    let block_number = execution_payload.block_number;
+
+    let Some(indexed_block) = context.blobscan_client().get_block(block_number).await? else {
+        // Blocks without blobs may have been skipped on purpose
+        if expected_versioned_hashes.is_empty() {
+            return Ok(vec![]);
+        }
+
+        return Ok(vec![Discrepancy::MissingBlock { slot, block_number }]);
+    };
+
+    let mut discrepancies = vec![];
+
+    if indexed_block.hash != execution_payload.block_hash {
+        discrepancies.push(Discrepancy::BlockHashMismatch {
+            slot,
+            expected: execution_payload.block_hash,
+            found: indexed_block.hash,
+        });
+
+        // Whatever blobscan holds belongs to another block, so comparing its contents says
+        // nothing more
+        return Ok(discrepancies);
+    }
+
+    let indexed_versioned_hashes = indexed_block
+        .transactions
+        .iter()
+        .flat_map(|tx| tx.blobs.iter().map(|blob| blob.versioned_hash))
+        .collect::<HashSet<_>>();
+
+    for versioned_hash in expected_versioned_hashes.difference(&indexed_versioned_hashes) {
+        discrepancies.push(Discrepancy::MissingBlob {
+            slot,
+            versioned_hash: *versioned_hash,
+        });
+    }
+
+    for versioned_hash in indexed_versioned_hashes.difference(&expected_versioned_hashes) {
+        discrepancies.push(Discrepancy::ExtraBlob {
+            slot,
+            versioned_hash: *versioned_hash,
+        });
+    }
+
+    // Transactions can only be checked against the execution block
+    let Some(provider) = context.provider() else {
+        return Ok(discrepancies);
+    };
+
+    let execution_block = provider
+        .get_block_with_txs(execution_payload.block_hash)
+        .await?
+        .with_context(|| format!("Execution block {} not found", execution_payload.block_hash))?;
+    let indexed_tx_hashes = indexed_block
+        .transactions
+        .iter()
+        .map(|tx| tx.hash)
+        .collect::<HashSet<_>>();
+    let execution_tx_hashes = execution_block
+        .transactions
+        .iter()
+        .map(|tx| tx.hash)
+        .collect::<HashSet<_>>();
+
+    // Only blob transactions are always indexed, whatever the index mode
+    for tx in &execution_block.transactions {
+        let carries_blobs = is_blob_tx(tx) || get_tx_versioned_hashes(tx)?.is_some();
+
+        if carries_blobs && !indexed_tx_hashes.contains(&tx.hash) {
+            discrepancies.push(Discrepancy::MissingTransaction {
+                slot,
+                tx_hash: tx.hash,
+            });
+        }
+    }
+
+    for tx_hash in indexed_tx_hashes.difference(&execution_tx_hashes) {
+        discrepancies.push(Discrepancy::ExtraTransaction {
+            slot,
+            tx_hash: *tx_hash,
+        });
+    }
+
+    Ok(discrepancies)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ethers::types::H256;
+    use serde_json::{json, Value};
+
+    use super::{verify_range, verify_slot, Discrepancy, VerificationSummary};
+    use crate::{
+        clients::test_server::{self, Reply},
+        context::{Config as ContextConfig, Context},
+        utils::web3::calculate_versioned_hash,
+    };
+
+    const BLOCK_HASH: &str = "0x2222222222222222222222222222222222222222222222222222222222222222";
+
+    fn commitment(byte: u8) -> String {
+        format!("0x{}", hex::encode([byte; 48]))
+    }
+
+    fn versioned_hash(byte: u8) -> H256 {
+        calculate_versioned_hash(&commitment(byte)).unwrap()
+    }
+
+    fn tx_hash(byte: u8) -> H256 {
+        H256::repeat_byte(byte)
+    }
+
+    fn beacon_block(slot: u32, commitments: &[String]) -> Reply {
+        Reply::Respond(
+            "200 OK",
+            json!({
+                "data": {
+                    "message": {
+                        "slot": slot.to_string(),
+                        "parent_root": format!("0x{}", "11".repeat(32)),
+                        "body": {
+                            "execution_payload": {
+                                "block_hash": BLOCK_HASH,
+                                "parent_hash": format!("0x{}", "33".repeat(32)),
+                                "block_number": "100",
+                                "timestamp": "1700000000"
+                            },
+                            "blob_kzg_commitments": commitments
+                        }
+                    }
+                }
+            })
+            .to_string(),
+        )
+    }
+
+    /// Blobscan's block, with its transactions as (hash, blob versioned hashes)
+    fn indexed_block(hash: &str, transactions: &[(H256, Vec<H256>)]) -> Reply {
+        let transactions = transactions
+            .iter()
+            .map(|(hash, versioned_hashes)| {
+                json!({
+                    "hash": hash,
+                    "blobs": versioned_hashes
+                        .iter()
+                        .map(|versioned_hash| json!({ "versionedHash": versioned_hash }))
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Reply::Respond(
+            "200 OK",
+            json!({ "hash": hash, "slot": 10, "transactions": transactions }).to_string(),
+        )
+    }
+
+    /// A blob transaction carrying the given versioned hashes
+    fn blob_transaction(hash: H256, versioned_hashes: &[H256]) -> Value {
+        json!({
+            "hash": hash,
+            "nonce": "0x0",
+            "blockHash": BLOCK_HASH,
+            "blockNumber": "0x64",
+            "transactionIndex": "0x0",
+            "from": format!("0x{}", "55".repeat(20)),
+            "to": format!("0x{}", "66".repeat(20)),
+            "value": "0x0",
+            "gas": "0x5208",
+            "input": "0x",
+            "type": "0x3",
+            "blobVersionedHashes": versioned_hashes,
+            "v": "0x1",
+            "r": "0x1",
+            "s": "0x1"
+        })
+    }
+
+    fn execution_block(transactions: Vec<Value>) -> Reply {
+        Reply::Respond(
+            "200 OK",
+            json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": {
+                    "hash": BLOCK_HASH,
+                    "parentHash": format!("0x{}", "33".repeat(32)),
+                    "number": "0x64",
+                    "timestamp": "0x6553f100",
+                    "transactions": transactions
+                }
+            })
+            .to_string(),
+        )
+    }
+
+    async fn context(
+        beacon_replies: Vec<Reply>,
+        blobscan_replies: Vec<Reply>,
+        execution_replies: Option<Vec<Reply>>,
+    ) -> Context {
+        let (beacon_url, _) = test_server::serve(beacon_replies).await;
+        let (blobscan_url, _) = test_server::serve(blobscan_replies).await;
+        let execution_url = match execution_replies {
+            Some(replies) => Some(test_server::serve(replies).await.0),
+            None => None,
+        };
+
+        Context::try_new(ContextConfig {
+            blobscan_api_endpoint: blobscan_url,
+            beacon_node_url: beacon_url,
+            execution_node_endpoint: execution_url,
+            secret_key: "test-secret".to_string(),
+            disable_retries: true,
+            beacon_http_version: Default::default(),
+            blobscan_http_version: Default::default(),
+            beacon_body_trace: Default::default(),
+            blobscan_body_trace: Default::default(),
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn reports_a_missing_block_carrying_blobs() {
+        let context = context(
+            vec![beacon_block(10, &[commitment(1)])],
+            vec![Reply::Respond("404 Not Found", String::new())],
+            None,
+        )
+        .await;
+
+        assert_eq!(
+            verify_slot(&context, 10).await.unwrap(),
+            [Discrepancy::MissingBlock {
+                slot: 10,
+                block_number: 100
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn ignores_a_missing_blobless_block() {
+        let context = context(
+            vec![beacon_block(10, &[])],
+            vec![Reply::Respond("404 Not Found", String::new())],
+            None,
+        )
+        .await;
+
+        assert_eq!(verify_slot(&context, 10).await.unwrap(), []);
+    }
+
+    #[tokio::test]
+    async fn reports_a_block_hash_mismatch() {
+        let other_hash = format!("0x{}", "99".repeat(32));
+        let context = context(
+            vec![beacon_block(10, &[commitment(1)])],
+            vec![indexed_block(&other_hash, &[])],
+            None,
+        )
+        .await;
+
+        assert_eq!(
+            verify_slot(&context, 10).await.unwrap(),
+            [Discrepancy::BlockHashMismatch {
+                slot: 10,
+                expected: H256::from_str(BLOCK_HASH).unwrap(),
+                found: H256::from_str(&other_hash).unwrap(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_missing_and_extra_blobs() {
+        let context = context(
+            vec![beacon_block(10, &[commitment(1)])],
+            vec![indexed_block(
+                BLOCK_HASH,
+                &[(tx_hash(1), vec![versioned_hash(2)])],
+            )],
+            None,
+        )
+        .await;
+
+        assert_eq!(
+            verify_slot(&context, 10).await.unwrap(),
+            [
+                Discrepancy::MissingBlob {
+                    slot: 10,
+                    versioned_hash: versioned_hash(1),
+                },
+                Discrepancy::ExtraBlob {
+                    slot: 10,
+                    versioned_hash: versioned_hash(2),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_missing_and_extra_transactions() {
+        let context = context(
+            vec![beacon_block(10, &[commitment(1)])],
+            vec![indexed_block(
+                BLOCK_HASH,
+                &[(tx_hash(2), vec![versioned_hash(1)])],
+            )],
+            Some(vec![execution_block(vec![blob_transaction(
+                tx_hash(1),
+                &[versioned_hash(1)],
+            )])]),
+        )
+        .await;
+
+        assert_eq!(
+            verify_slot(&context, 10).await.unwrap(),
+            [
+                Discrepancy::MissingTransaction {
+                    slot: 10,
+                    tx_hash: tx_hash(1),
+                },
+                Discrepancy::ExtraTransaction {
+                    slot: 10,
+                    tx_hash: tx_hash(2),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_verifying_after_a_slot_that_cant_be_fetched() {
+        let context = context(
+            vec![
+                Reply::Respond("500 Internal Server Error", String::new()),
+                beacon_block(11, &[commitment(1)]),
+            ],
+            vec![Reply::Respond("404 Not Found", String::new())],
+            None,
+        )
+        .await;
+        let mut output = vec![];
+
+        let summary = verify_range(&context, 10, 11, &mut output).await.unwrap();
+
+        let lines = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            summary,
+            VerificationSummary {
+                discrepancies: 1,
+                failed_slots: 1,
+            }
+        );
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["kind"], "verification_failure");
+        assert_eq!(lines[0]["slot"], 10);
+        assert_eq!(
+            lines[1],
+            json!({ "kind": "missing_block", "slot": 11, "block_number": 100 })
+        );
+    }
+}